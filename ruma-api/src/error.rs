@@ -59,6 +59,10 @@ pub enum IntoHttpError {
     /// HTTP request construction failed.
     #[error("HTTP request construction failed: {0}")]
     Http(#[from] http::Error),
+
+    /// Computing the `Authorization` header of a federation request failed.
+    #[error("Signing the request failed: {0}")]
+    Signing(Box<dyn StdError + Send + Sync>),
 }
 
 /// An error when converting a http request to one of ruma's endpoint-specific request types.
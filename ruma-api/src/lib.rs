@@ -20,6 +20,7 @@ use std::{
 };
 
 use http::Method;
+use ruma_identifiers::ServerName;
 
 /// Generates a `ruma_api::Endpoint` from a concise definition.
 ///
@@ -260,6 +261,50 @@ pub trait OutgoingRequest {
         base_url: &str,
         access_token: Option<&str>,
     ) -> Result<http::Request<Vec<u8>>, IntoHttpError>;
+
+    /// Tries to convert this request into an `http::Request` to the homeserver `destination`,
+    /// signed by `signer` if the endpoint uses `AuthScheme::ServerSignatures`.
+    ///
+    /// This is used to send requests of the federation API. The `base_url` is handled like in
+    /// `try_into_http_request`.
+    fn try_into_signed_http_request<S>(
+        self,
+        base_url: &str,
+        destination: &ServerName,
+        signer: &S,
+    ) -> Result<http::Request<Vec<u8>>, IntoHttpError>
+    where
+        Self: Sized,
+        S: RequestSigner + ?Sized,
+    {
+        let mut request = self.try_into_http_request(base_url, None)?;
+
+        if Self::METADATA.authentication == AuthScheme::ServerSignatures {
+            let authorization = signer
+                .authorization_header(destination, &request)
+                .map_err(IntoHttpError::Signing)?;
+            request
+                .headers_mut()
+                .insert(http::header::AUTHORIZATION, http::HeaderValue::from_str(&authorization)?);
+        }
+
+        Ok(request)
+    }
+}
+
+/// Computes the `Authorization` header for requests to endpoints that use
+/// `AuthScheme::ServerSignatures`. (used when sending requests)
+///
+/// With its `ruma-api` feature, `ruma-signatures` provides an implementation that signs requests
+/// using the `X-Matrix` authorization scheme.
+pub trait RequestSigner {
+    /// Computes the value of the `Authorization` header of `request`, which is sent to the
+    /// homeserver `destination`.
+    fn authorization_header(
+        &self,
+        destination: &ServerName,
+        request: &http::Request<Vec<u8>>,
+    ) -> Result<String, Box<dyn StdError + Send + Sync>>;
 }
 
 /// A request type for a Matrix API endpoint. (trait used for receiving requests)
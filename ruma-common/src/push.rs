@@ -4,14 +4,20 @@
 
 use serde::{Deserialize, Serialize};
 
+use crate::Raw;
+
 mod action;
 mod any_push_rule;
 mod condition;
+mod predefined;
 
 pub use self::{
     action::{Action, Tweak},
-    any_push_rule::{AnyPushRule, MissingConditionsError, MissingPatternError},
-    condition::{ComparisonOperator, PushCondition, RoomMemberCountIs},
+    any_push_rule::{AnyPushRule, AnyPushRuleRef, MissingConditionsError, MissingPatternError},
+    condition::{
+        glob_matches, ComparisonOperator, FlattenedJson, PushCondition, PushConditionRoomCtx,
+        RoomMemberCountIs,
+    },
 };
 
 /// A push ruleset scopes a set of rules according to some criteria.
@@ -47,6 +53,53 @@ impl Ruleset {
     pub fn new() -> Self {
         Default::default()
     }
+
+    /// Iterates over all the push rules, in the order of priority defined by the specification:
+    /// `override`, `content`, `room`, `sender` and `underride`.
+    pub fn iter(&self) -> impl Iterator<Item = AnyPushRuleRef<'_>> {
+        self.override_
+            .iter()
+            .map(AnyPushRuleRef::Override)
+            .chain(self.content.iter().map(AnyPushRuleRef::Content))
+            .chain(self.room.iter().map(AnyPushRuleRef::Room))
+            .chain(self.sender.iter().map(AnyPushRuleRef::Sender))
+            .chain(self.underride.iter().map(AnyPushRuleRef::Underride))
+    }
+
+    /// Get the first enabled push rule that applies to this event, if any.
+    ///
+    /// Events sent by the owner of the push rules (`context.user_id`) never match, since
+    /// homeservers must not notify users about their own events.
+    ///
+    /// # Arguments
+    ///
+    /// * `event` - The raw JSON of a room message event.
+    /// * `context` - The context of the room at the time of the event.
+    pub fn get_match<T>(
+        &self,
+        event: &Raw<T>,
+        context: &PushConditionRoomCtx,
+    ) -> Option<AnyPushRuleRef<'_>> {
+        let event = FlattenedJson::from_raw(event);
+
+        if event.get("sender") == Some(context.user_id.as_str()) {
+            return None;
+        }
+
+        self.iter().find(|rule| rule.enabled() && rule.applies(&event, context))
+    }
+
+    /// Get the push actions for the given event.
+    ///
+    /// Returns an empty slice if no push rule applies.
+    ///
+    /// # Arguments
+    ///
+    /// * `event` - The raw JSON of a room message event.
+    /// * `context` - The context of the room at the time of the event.
+    pub fn get_actions<T>(&self, event: &Raw<T>, context: &PushConditionRoomCtx) -> &[Action] {
+        self.get_match(event, context).map(AnyPushRuleRef::actions).unwrap_or(&[])
+    }
 }
 
 /// A push rule is a single rule that states under what conditions an event should be passed onto a
@@ -213,6 +266,20 @@ impl From<PatternedPushRuleInit> for PatternedPushRule {
     }
 }
 
+impl PatternedPushRule {
+    /// Check if the push rule's pattern matches the body of the event.
+    ///
+    /// Like for `event_match` conditions on `content.body`, the pattern matches any part of the
+    /// body surrounded by word boundaries, ignoring case.
+    ///
+    /// # Arguments
+    ///
+    /// * `event` - The flattened JSON representation of a room message event.
+    pub fn applies(&self, event: &FlattenedJson) -> bool {
+        condition::event_match(event, "content.body", &self.pattern)
+    }
+}
+
 /// Information for the pusher implementation itself.
 #[derive(Clone, Debug, Default, Serialize, Deserialize)]
 #[cfg_attr(not(feature = "unstable-exhaustive-types"), non_exhaustive)]
@@ -244,3 +311,169 @@ pub enum PushFormat {
     /// Require the homeserver to only send a reduced set of fields in the push.
     EventIdOnly,
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use js_int::{int, uint};
+    use matches::assert_matches;
+    use ruma_identifiers::{room_id, user_id};
+    use serde_json::{json, value::to_raw_value as to_raw_json_value};
+
+    use super::{
+        Action, AnyPushRuleRef, ConditionalPushRuleInit, PatternedPushRuleInit, PushCondition,
+        PushConditionRoomCtx, PushRuleInit, Ruleset, Tweak,
+    };
+    use crate::Raw;
+
+    fn context() -> PushConditionRoomCtx {
+        PushConditionRoomCtx {
+            room_id: room_id!("!dm:server.name"),
+            user_id: user_id!("@carl:server.name"),
+            user_display_name: "Carl".into(),
+            member_count: uint!(2),
+            sender_power_level: int!(0),
+            notification_power_levels: BTreeMap::new(),
+        }
+    }
+
+    fn message(sender: &str, body: &str) -> Raw<()> {
+        Raw::from_json(
+            to_raw_json_value(&json!({
+                "sender": sender,
+                "type": "m.room.message",
+                "room_id": "!dm:server.name",
+                "content": {
+                    "msgtype": "m.text",
+                    "body": body
+                }
+            }))
+            .unwrap(),
+        )
+    }
+
+    fn ruleset() -> Ruleset {
+        let mut set = Ruleset::new();
+
+        set.override_.push(
+            ConditionalPushRuleInit {
+                actions: vec![Action::DontNotify],
+                default: true,
+                enabled: true,
+                rule_id: ".m.rule.suppress_notices".into(),
+                conditions: vec![PushCondition::EventMatch {
+                    key: "content.msgtype".into(),
+                    pattern: "m.notice".into(),
+                }],
+            }
+            .into(),
+        );
+        set.content.push(
+            PatternedPushRuleInit {
+                actions: vec![Action::Notify, Action::SetTweak(Tweak::Highlight(true))],
+                default: true,
+                enabled: true,
+                rule_id: ".m.rule.contains_user_name".into(),
+                pattern: "*carl*".into(),
+            }
+            .into(),
+        );
+        set.room.push(
+            PushRuleInit {
+                actions: vec![Action::DontNotify],
+                default: false,
+                enabled: false,
+                rule_id: "!dm:server.name".into(),
+            }
+            .into(),
+        );
+        set.sender.push(
+            PushRuleInit {
+                actions: vec![Action::Notify, Action::SetTweak(Tweak::Sound("default".into()))],
+                default: false,
+                enabled: true,
+                rule_id: "@friend:server.name".into(),
+            }
+            .into(),
+        );
+        set.underride.push(
+            ConditionalPushRuleInit {
+                actions: vec![Action::Notify],
+                default: true,
+                enabled: true,
+                rule_id: ".m.rule.message".into(),
+                conditions: vec![PushCondition::EventMatch {
+                    key: "type".into(),
+                    pattern: "m.room.message".into(),
+                }],
+            }
+            .into(),
+        );
+
+        set
+    }
+
+    #[test]
+    fn iter_in_priority_order() {
+        let set = ruleset();
+        let ids: Vec<_> = set.iter().map(AnyPushRuleRef::rule_id).collect();
+
+        assert_eq!(
+            ids,
+            vec![
+                ".m.rule.suppress_notices",
+                ".m.rule.contains_user_name",
+                "!dm:server.name",
+                "@friend:server.name",
+                ".m.rule.message",
+            ]
+        );
+    }
+
+    #[test]
+    fn content_rule_before_sender_rule() {
+        let set = ruleset();
+        let event = message("@friend:server.name", "Hi Carl!");
+
+        assert_matches!(
+            set.get_match(&event, &context()),
+            Some(AnyPushRuleRef::Content(rule)) if rule.rule_id == ".m.rule.contains_user_name"
+        );
+        assert_matches!(
+            set.get_actions(&event, &context()),
+            [Action::Notify, Action::SetTweak(Tweak::Highlight(true))]
+        );
+    }
+
+    #[test]
+    fn sender_rule_matches() {
+        let set = ruleset();
+        let event = message("@friend:server.name", "Hello!");
+
+        assert_matches!(
+            set.get_actions(&event, &context()),
+            [Action::Notify, Action::SetTweak(Tweak::Sound(sound))] if sound == "default"
+        );
+    }
+
+    #[test]
+    fn disabled_rule_is_skipped() {
+        let set = ruleset();
+        let event = message("@stranger:server.name", "Hello!");
+
+        assert_matches!(
+            set.get_match(&event, &context()),
+            Some(AnyPushRuleRef::Underride(rule)) if rule.rule_id == ".m.rule.message"
+        );
+    }
+
+    #[test]
+    fn own_events_never_match() {
+        let set = ruleset();
+        let event = message("@carl:server.name", "Hello!");
+
+        assert_matches!(set.get_match(&event, &context()), None);
+        assert!(set.get_actions(&event, &context()).is_empty());
+    }
+}
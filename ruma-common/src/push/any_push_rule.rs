@@ -7,8 +7,8 @@ use std::{
 use serde::{Deserialize, Serialize};
 
 use super::{
-    Action, ConditionalPushRule, ConditionalPushRuleInit, PatternedPushRule, PatternedPushRuleInit,
-    PushCondition, PushRule, PushRuleInit,
+    Action, ConditionalPushRule, ConditionalPushRuleInit, FlattenedJson, PatternedPushRule,
+    PatternedPushRuleInit, PushCondition, PushConditionRoomCtx, PushRule, PushRuleInit,
 };
 
 /// Like `PushRule`, but may represent any kind of push rule
@@ -91,6 +91,85 @@ impl From<AnyPushRule> for PushRule {
     }
 }
 
+/// A reference to a push rule of any kind, as stored in a `Ruleset`.
+///
+/// The variant indicates the kind of the rule, which determines how it is matched against events.
+#[derive(Clone, Copy, Debug)]
+pub enum AnyPushRuleRef<'a> {
+    /// Rule from the `override` kind.
+    Override(&'a ConditionalPushRule),
+
+    /// Rule from the `content` kind.
+    Content(&'a PatternedPushRule),
+
+    /// Rule from the `room` kind.
+    Room(&'a PushRule),
+
+    /// Rule from the `sender` kind.
+    Sender(&'a PushRule),
+
+    /// Rule from the `underride` kind.
+    Underride(&'a ConditionalPushRule),
+}
+
+impl<'a> AnyPushRuleRef<'a> {
+    /// The actions to perform when this rule is matched.
+    pub fn actions(self) -> &'a [Action] {
+        match self {
+            Self::Override(rule) | Self::Underride(rule) => &rule.actions,
+            Self::Content(rule) => &rule.actions,
+            Self::Room(rule) | Self::Sender(rule) => &rule.actions,
+        }
+    }
+
+    /// Whether the push rule is enabled or not.
+    pub fn enabled(self) -> bool {
+        match self {
+            Self::Override(rule) | Self::Underride(rule) => rule.enabled,
+            Self::Content(rule) => rule.enabled,
+            Self::Room(rule) | Self::Sender(rule) => rule.enabled,
+        }
+    }
+
+    /// The ID of this rule.
+    pub fn rule_id(self) -> &'a str {
+        match self {
+            Self::Override(rule) | Self::Underride(rule) => &rule.rule_id,
+            Self::Content(rule) => &rule.rule_id,
+            Self::Room(rule) | Self::Sender(rule) => &rule.rule_id,
+        }
+    }
+
+    /// Check if this push rule applies to the event.
+    ///
+    /// This doesn't take into account whether the rule is enabled.
+    ///
+    /// # Arguments
+    ///
+    /// * `event` - The flattened JSON representation of a room message event.
+    /// * `context` - The context of the room at the time of the event.
+    pub fn applies(self, event: &FlattenedJson, context: &PushConditionRoomCtx) -> bool {
+        match self {
+            Self::Override(rule) | Self::Underride(rule) => {
+                rule.conditions.iter().all(|cond| cond.applies(event, context))
+            }
+            Self::Content(rule) => rule.applies(event),
+            Self::Room(rule) => rule.rule_id == context.room_id.as_str(),
+            Self::Sender(rule) => event.get("sender") == Some(rule.rule_id.as_str()),
+        }
+    }
+}
+
+impl From<AnyPushRuleRef<'_>> for AnyPushRule {
+    fn from(push_rule: AnyPushRuleRef<'_>) -> Self {
+        match push_rule {
+            AnyPushRuleRef::Override(rule) | AnyPushRuleRef::Underride(rule) => rule.clone().into(),
+            AnyPushRuleRef::Content(rule) => rule.clone().into(),
+            AnyPushRuleRef::Room(rule) | AnyPushRuleRef::Sender(rule) => rule.clone().into(),
+        }
+    }
+}
+
 /// An error that happens when `AnyPushRule` cannot
 /// be converted into `PatternedPushRule`
 #[derive(Debug)]
@@ -1,8 +1,19 @@
+use std::{collections::BTreeMap, ops::RangeBounds};
+
+use js_int::{int, Int, UInt};
+use ruma_identifiers::{RoomId, UserId};
 use serde::{Deserialize, Serialize};
 
+mod flattened_json;
+mod glob;
 mod room_member_count_is;
 
-pub use room_member_count_is::{ComparisonOperator, RoomMemberCountIs};
+pub(crate) use self::glob::event_match;
+pub use self::{
+    flattened_json::FlattenedJson,
+    glob::glob_matches,
+    room_member_count_is::{ComparisonOperator, RoomMemberCountIs},
+};
 
 /// A condition that must apply for an associated push rule's action to be taken.
 #[derive(Clone, Debug, Deserialize, Serialize)]
@@ -16,8 +27,9 @@ pub enum PushCondition {
 
         /// The glob-style pattern to match against.
         ///
-        /// Patterns with no special glob characters should be treated as having asterisks prepended
-        /// and appended when testing the condition.
+        /// `*` matches any number of characters and `?` matches exactly one character. Matching is
+        /// case-insensitive. If `key` is `content.body`, the pattern matches any part of the body
+        /// surrounded by word boundaries, otherwise it must match the whole value.
         pattern: String,
     },
 
@@ -42,13 +54,94 @@ pub enum PushCondition {
     },
 }
 
+impl PushCondition {
+    /// Check if this condition applies to the event.
+    ///
+    /// # Arguments
+    ///
+    /// * `event` - The flattened JSON representation of a room message event.
+    /// * `context` - The context of the room at the time of the event.
+    pub fn applies(&self, event: &FlattenedJson, context: &PushConditionRoomCtx) -> bool {
+        match self {
+            Self::EventMatch { key, pattern } => glob::event_match(event, key, pattern),
+            Self::ContainsDisplayName => match event.get("content.body") {
+                Some(body) => glob::contains_display_name(body, &context.user_display_name),
+                None => false,
+            },
+            Self::RoomMemberCount { is } => is.contains(&context.member_count),
+            Self::SenderNotificationPermission { key } => {
+                let required_level = match context.notification_power_levels.get(key) {
+                    Some(level) => *level,
+                    // The only notification power level defined in the spec, with its default.
+                    None if key == "room" => int!(50),
+                    None => return false,
+                };
+
+                context.sender_power_level >= required_level
+            }
+        }
+    }
+}
+
+/// The context of the room associated to an event to be able to test all push conditions.
+#[derive(Clone, Debug)]
+pub struct PushConditionRoomCtx {
+    /// The ID of the room.
+    pub room_id: RoomId,
+
+    /// The ID of the user the push rules belong to.
+    pub user_id: UserId,
+
+    /// The display name of that user in the room.
+    ///
+    /// If this is empty, `contains_display_name` conditions never apply.
+    pub user_display_name: String,
+
+    /// The number of members in the room.
+    pub member_count: UInt,
+
+    /// The power level of the sender of the event.
+    pub sender_power_level: Int,
+
+    /// The power levels required to trigger the different kinds of notifications, from the
+    /// `notifications` property of the room's `m.room.power_levels` event.
+    ///
+    /// The `room` key defaults to 50 if missing.
+    pub notification_power_levels: BTreeMap<String, Int>,
+}
+
 #[cfg(test)]
 mod tests {
-    use js_int::uint;
+    use std::collections::BTreeMap;
+
+    use js_int::{int, uint};
     use matches::assert_matches;
+    use ruma_identifiers::{room_id, user_id};
     use serde_json::{from_value as from_json_value, json, to_value as to_json_value};
 
-    use super::{PushCondition, RoomMemberCountIs};
+    use super::{FlattenedJson, PushCondition, PushConditionRoomCtx, RoomMemberCountIs};
+
+    fn context() -> PushConditionRoomCtx {
+        PushConditionRoomCtx {
+            room_id: room_id!("!room:server.name"),
+            user_id: user_id!("@carl:server.name"),
+            user_display_name: "Carl".into(),
+            member_count: uint!(3),
+            sender_power_level: int!(25),
+            notification_power_levels: BTreeMap::new(),
+        }
+    }
+
+    fn message(body: &str) -> FlattenedJson {
+        FlattenedJson::from_value(json!({
+            "sender": "@worthy_whale:server.name",
+            "type": "m.room.message",
+            "content": {
+                "msgtype": "m.text",
+                "body": body
+            }
+        }))
+    }
 
     #[test]
     fn serialize_event_match_condition() {
@@ -151,4 +244,72 @@ mod tests {
             } if key == "room"
         );
     }
+
+    #[test]
+    fn event_match_applies() {
+        let event = message("Hello World!");
+        let context = context();
+
+        let exact =
+            PushCondition::EventMatch { key: "content.msgtype".into(), pattern: "m.text".into() };
+        assert!(exact.applies(&event, &context));
+
+        let glob = PushCondition::EventMatch {
+            key: "content.body".into(),
+            pattern: "hello w?rld*".into(),
+        };
+        assert!(glob.applies(&event, &context));
+
+        let other_value =
+            PushCondition::EventMatch { key: "content.msgtype".into(), pattern: "m.notice".into() };
+        assert!(!other_value.applies(&event, &context));
+
+        let missing_key =
+            PushCondition::EventMatch { key: "content.format".into(), pattern: "*".into() };
+        assert!(!missing_key.applies(&event, &context));
+    }
+
+    #[test]
+    fn contains_display_name_applies() {
+        let context = context();
+
+        assert!(PushCondition::ContainsDisplayName.applies(&message("Hi carl!"), &context));
+        assert!(PushCondition::ContainsDisplayName.applies(&message("CARL"), &context));
+        assert!(!PushCondition::ContainsDisplayName.applies(&message("Hi Carlos"), &context));
+        assert!(!PushCondition::ContainsDisplayName.applies(&message("Hi there"), &context));
+
+        let no_display_name = PushConditionRoomCtx { user_display_name: String::new(), ..context };
+        assert!(!PushCondition::ContainsDisplayName.applies(&message("Hi carl!"), &no_display_name));
+    }
+
+    #[test]
+    fn room_member_count_applies() {
+        let event = message("Hello World!");
+        let context = context();
+
+        let eq_3 = PushCondition::RoomMemberCount { is: RoomMemberCountIs::from(uint!(3)) };
+        assert!(eq_3.applies(&event, &context));
+
+        let lt_3 = PushCondition::RoomMemberCount { is: RoomMemberCountIs::from(..uint!(3)) };
+        assert!(!lt_3.applies(&event, &context));
+    }
+
+    #[test]
+    fn sender_notification_permission_applies() {
+        let event = message("@room Hello World!");
+        let mut context = context();
+        let room = PushCondition::SenderNotificationPermission { key: "room".into() };
+
+        // The default required power level is 50.
+        assert!(!room.applies(&event, &context));
+
+        context.sender_power_level = int!(50);
+        assert!(room.applies(&event, &context));
+
+        context.notification_power_levels.insert("room".into(), int!(100));
+        assert!(!room.applies(&event, &context));
+
+        let unknown = PushCondition::SenderNotificationPermission { key: "unknown".into() };
+        assert!(!unknown.applies(&event, &context));
+    }
 }
@@ -0,0 +1,111 @@
+use std::collections::BTreeMap;
+
+use serde_json::Value as JsonValue;
+
+use crate::Raw;
+
+/// The flattened representation of a JSON object.
+///
+/// Nested objects are flattened into their dot-separated paths, e.g. the body of an
+/// `m.room.message` event can be accessed with the key `content.body`. Only string values are
+/// kept, since those are the only ones `event_match` conditions can match against.
+#[derive(Clone, Debug, Default)]
+pub struct FlattenedJson {
+    /// The internal map containing the flattened JSON as a pair path, value.
+    map: BTreeMap<String, String>,
+}
+
+impl FlattenedJson {
+    /// Creates a `FlattenedJson` from `Raw`.
+    ///
+    /// If the raw JSON is not an object, the resulting `FlattenedJson` is empty.
+    pub fn from_raw<T>(raw: &Raw<T>) -> Self {
+        match serde_json::from_str(raw.json().get()) {
+            Ok(value) => Self::from_value(value),
+            Err(_) => Self::default(),
+        }
+    }
+
+    /// Creates a `FlattenedJson` from a JSON value.
+    ///
+    /// If the value is not an object, the resulting `FlattenedJson` is empty.
+    pub fn from_value(value: JsonValue) -> Self {
+        let mut flattened = Self::default();
+
+        if let JsonValue::Object(fields) = value {
+            for (key, value) in fields {
+                flattened.flatten_value(value, key);
+            }
+        }
+
+        flattened
+    }
+
+    /// Flattens a value recursively.
+    fn flatten_value(&mut self, value: JsonValue, path: String) {
+        match value {
+            JsonValue::Object(fields) => {
+                for (key, value) in fields {
+                    self.flatten_value(value, format!("{}.{}", path, key));
+                }
+            }
+            JsonValue::String(s) => {
+                self.map.insert(path, s);
+            }
+            _ => {}
+        }
+    }
+
+    /// Value associated with the given `path`.
+    pub fn get(&self, path: &str) -> Option<&str> {
+        self.map.get(path).map(|s| s.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::FlattenedJson;
+
+    #[test]
+    fn flattened_json_values() {
+        let flattened = FlattenedJson::from_value(json!({
+            "string": "Hello World",
+            "number": 10,
+            "array": [1, 2],
+            "boolean": true,
+            "null": null
+        }));
+
+        assert_eq!(flattened.get("string"), Some("Hello World"));
+        assert_eq!(flattened.get("number"), None);
+        assert_eq!(flattened.get("array"), None);
+        assert_eq!(flattened.get("boolean"), None);
+        assert_eq!(flattened.get("null"), None);
+    }
+
+    #[test]
+    fn flattened_json_nested() {
+        let flattened = FlattenedJson::from_value(json!({
+            "desc": "Level 0",
+            "up": {
+                "desc": "Level 1",
+                "up": {
+                    "desc": "Level 2"
+                }
+            }
+        }));
+
+        assert_eq!(flattened.get("desc"), Some("Level 0"));
+        assert_eq!(flattened.get("up.desc"), Some("Level 1"));
+        assert_eq!(flattened.get("up.up.desc"), Some("Level 2"));
+        assert_eq!(flattened.get("up"), None);
+    }
+
+    #[test]
+    fn flattened_json_not_an_object() {
+        let flattened = FlattenedJson::from_value(json!("m.room.message"));
+        assert_eq!(flattened.get(""), None);
+    }
+}
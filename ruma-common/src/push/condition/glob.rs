@@ -0,0 +1,343 @@
+//! Glob matching for `event_match` conditions, following the behaviour of Synapse.
+
+use std::{collections::BTreeMap, sync::Mutex};
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use super::FlattenedJson;
+
+/// The maximum number of compiled patterns kept in the cache.
+///
+/// When this number is reached, the least recently used pattern is evicted.
+const CACHE_CAPACITY: usize = 1024;
+
+/// A pattern, whether it is a glob and whether it should only match at word boundaries.
+type CacheKey = (String, bool, bool);
+
+static REGEX_CACHE: Lazy<Mutex<RegexCache>> = Lazy::new(Default::default);
+
+/// A bounded cache of compiled patterns that evicts the least recently used entry when full.
+///
+/// Patterns that failed to compile are cached as `None`, so they aren't compiled again.
+#[derive(Default)]
+struct RegexCache {
+    /// The compiled patterns and the tick at which they were last used.
+    entries: BTreeMap<CacheKey, (Option<Regex>, u64)>,
+
+    /// The keys of `entries`, ordered by the tick at which they were last used.
+    recency: BTreeMap<u64, CacheKey>,
+
+    /// Incremented on every access.
+    tick: u64,
+}
+
+impl RegexCache {
+    /// Returns the compiled pattern for `key`, compiling and inserting it if necessary.
+    fn get_or_compile(&mut self, key: CacheKey) -> Option<Regex> {
+        self.tick += 1;
+        let tick = self.tick;
+
+        if let Some((regex, last_used)) = self.entries.get_mut(&key) {
+            let key = self.recency.remove(last_used).expect("cache entry has a recency entry");
+            *last_used = tick;
+            self.recency.insert(tick, key);
+
+            return regex.clone();
+        }
+
+        let regex = compile(&key.0, key.1, key.2);
+
+        if self.entries.len() >= CACHE_CAPACITY {
+            let oldest = *self.recency.keys().next().expect("full cache is not empty");
+            let evicted = self.recency.remove(&oldest).expect("key was just looked up");
+            self.entries.remove(&evicted);
+        }
+        self.recency.insert(tick, key.clone());
+        self.entries.insert(key, (regex.clone(), tick));
+
+        regex
+    }
+}
+
+/// Checks whether the value at `key` in `event` matches the glob-style `pattern`.
+///
+/// The key `content.body` is special-cased: the pattern only has to match a part of the body that
+/// is surrounded by word boundaries. For any other key, the pattern must match the whole value.
+pub(crate) fn event_match(event: &FlattenedJson, key: &str, pattern: &str) -> bool {
+    match event.get(key) {
+        Some(value) => matches(pattern, value, true, key == "content.body"),
+        None => false,
+    }
+}
+
+/// Checks whether the whole `value` matches the glob-style `pattern`, ignoring case.
+///
+/// `*` matches any number of characters, `?` matches exactly one character, and character classes
+/// like `[abc]`, `[a-z]` or `[!a-z]` match a single character. This is the matching used for
+/// `event_match` push conditions. It is not suitable for the server names of `m.room.server_acl`
+/// events, whose patterns only support `*` and `?` and must match `[` and `]` literally.
+pub fn glob_matches(pattern: &str, value: &str) -> bool {
+    matches(pattern, value, true, false)
+}
+
+/// Checks whether `body` contains `display_name`, surrounded by word boundaries.
+pub(crate) fn contains_display_name(body: &str, display_name: &str) -> bool {
+    !display_name.is_empty() && matches(display_name, body, false, true)
+}
+
+/// Checks whether `value` matches `pattern`, ignoring case.
+///
+/// If `is_glob` is `false`, every character of the pattern is matched literally. Patterns that
+/// can't be compiled never match.
+fn matches(pattern: &str, value: &str, is_glob: bool, word_boundary: bool) -> bool {
+    let regex =
+        REGEX_CACHE.lock().unwrap().get_or_compile((pattern.to_owned(), is_glob, word_boundary));
+
+    match regex {
+        Some(regex) => regex.is_match(value),
+        None => false,
+    }
+}
+
+/// Compiles a pattern to a case-insensitive regular expression.
+fn compile(pattern: &str, is_glob: bool, word_boundary: bool) -> Option<Regex> {
+    let regex = if is_glob { glob_to_regex(pattern) } else { regex::escape(pattern) };
+
+    let regex = if word_boundary {
+        format!(r"(?i)(^|\W){}(\W|$)", regex)
+    } else {
+        format!(r"(?i)^{}$", regex)
+    };
+
+    Regex::new(&regex).ok()
+}
+
+/// Converts a glob to the equivalent regular expression.
+///
+/// Supports `*` (any number of characters), `?` (exactly one character), and character classes
+/// like `[abc]`, `[a-z]` or `[!a-z]`. Everything else is matched literally, including
+/// backslashes.
+fn glob_to_regex(glob: &str) -> String {
+    let mut regex = String::new();
+    let mut chars = glob.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '*' => regex.push_str(".*?"),
+            '?' => regex.push('.'),
+            '[' => {
+                let class: String = chars.clone().take_while(|&c| c != ']').collect();
+
+                // Without a closing bracket, `[` is a literal character.
+                if class.is_empty() || chars.clone().nth(class.chars().count()) != Some(']') {
+                    regex.push_str(r"\[");
+                    continue;
+                }
+
+                // Skip the class and the closing bracket.
+                for _ in 0..=class.chars().count() {
+                    chars.next();
+                }
+
+                let (negated, class) = match class.strip_prefix('!') {
+                    Some(class) => (true, class),
+                    None => (false, class.as_str()),
+                };
+
+                regex.push('[');
+                if negated {
+                    regex.push('^');
+                }
+                for c in class.chars() {
+                    if c == '-' {
+                        regex.push(c);
+                    } else {
+                        regex.push_str(&regex::escape(c.encode_utf8(&mut [0; 4])));
+                    }
+                }
+                regex.push(']');
+            }
+            c => regex.push_str(&regex::escape(c.encode_utf8(&mut [0; 4]))),
+        }
+    }
+
+    regex
+}
+
+#[cfg(test)]
+mod tests {
+    //! Conformance tests derived from Synapse's push rule evaluator tests.
+
+    use serde_json::json;
+
+    use super::{
+        contains_display_name, event_match, glob_matches, FlattenedJson, RegexCache, CACHE_CAPACITY,
+    };
+
+    fn body_matches(pattern: &str, body: &str) -> bool {
+        let event = FlattenedJson::from_value(json!({ "content": { "body": body } }));
+        event_match(&event, "content.body", pattern)
+    }
+
+    fn value_matches(pattern: &str, value: &str) -> bool {
+        let event = FlattenedJson::from_value(json!({ "content": { "value": value } }));
+        event_match(&event, "content.value", pattern)
+    }
+
+    #[test]
+    fn body_literal() {
+        // Patterns should match and be case-insensitive.
+        assert!(body_matches("foobaz", "aaa FoobaZ zzz"));
+        // Patterns should only match at word boundaries.
+        assert!(!body_matches("foobaz", "aa xFoobaZ yy"));
+        assert!(!body_matches("foobaz", "aa foobazx yy"));
+        // The start and end of the body are word boundaries.
+        assert!(body_matches("foobaz", "foobaz"));
+        assert!(body_matches("foobaz", "foobaz!"));
+    }
+
+    #[test]
+    fn body_wildcards() {
+        // `*` should match any string.
+        assert!(body_matches("f?o*baz", "aaa FoobarbaZ zzz"));
+        // `*` should match 0 characters.
+        assert!(body_matches("f?o*baz", "aa foobaz yy"));
+        // `?` should not match 0 characters.
+        assert!(!body_matches("f?o*baz", "aa fobbaz yy"));
+        // `?` should not match 2 characters.
+        assert!(!body_matches("f?o*baz", "aa fiiobaz yy"));
+        // Patterns should only match at word boundaries.
+        assert!(!body_matches("f?o*baz", "aa xfooxbaz yy"));
+        assert!(!body_matches("f?o*baz", "aa fooxbazx yy"));
+    }
+
+    #[test]
+    fn body_special_characters() {
+        assert!(body_matches("@room", "@room"));
+        assert!(body_matches("@room", "Hey @room, lunch!"));
+        assert!(!body_matches("@room", "@roomba"));
+        // Regex syntax is matched literally.
+        assert!(body_matches("a.b", "a.b"));
+        assert!(!body_matches("a.b", "axb"));
+        assert!(body_matches("(hi)+", "say (hi)+ now"));
+    }
+
+    #[test]
+    fn body_backslashes() {
+        // A backslash should match itself.
+        assert!(body_matches(r"f\oobaz", r"F\oobaz"));
+        // A `?` after a backslash should match any character.
+        assert!(body_matches(r"f\?obaz", r"F\oobaz"));
+    }
+
+    #[test]
+    fn non_body_literal() {
+        // Patterns should match and be case-insensitive.
+        assert!(value_matches("FoobaZ", "foobaz"));
+        // Patterns should match the entire value.
+        assert!(!value_matches("FoobaZ", "xx foobaz"));
+        assert!(!value_matches("FoobaZ", "foobaz yy"));
+    }
+
+    #[test]
+    fn non_body_wildcards() {
+        // `*` should match any string.
+        assert!(value_matches("f?o*baz", "FoobarbaZ"));
+        // `*` should match 0 characters.
+        assert!(value_matches("f?o*baz", "foobaz"));
+        // `?` should not match 0 characters.
+        assert!(!value_matches("f?o*baz", "fobbaz"));
+        // `?` should not match 2 characters.
+        assert!(!value_matches("f?o*baz", "fiiobaz"));
+        // Patterns should match the entire value.
+        assert!(!value_matches("f?o*baz", "xxfoobarbaz"));
+        assert!(!value_matches("f?o*baz", "foobarbazyy"));
+        // `*` should not match a newline.
+        assert!(!value_matches("f?o*baz", "foobar\nbaz"));
+    }
+
+    #[test]
+    fn non_body_character_ranges() {
+        // `[a-z]` should match a single character in the range, case-insensitively.
+        assert!(value_matches("[a-z]", "b"));
+        assert!(value_matches("[a-z]", "B"));
+        assert!(!value_matches("[a-z]", "0"));
+        assert!(!value_matches("[a-z]", "bb"));
+
+        // `[!a-z]` should match a single character outside of the range.
+        assert!(value_matches("[!a-z]", "0"));
+        assert!(!value_matches("[!a-z]", "b"));
+        assert!(!value_matches("[!a-z]", "B"));
+        assert!(!value_matches("[!a-z]", "00"));
+
+        // `[abc]` should match one of the characters.
+        assert!(value_matches("ba[rz]", "bar"));
+        assert!(value_matches("ba[rz]", "baz"));
+        assert!(!value_matches("ba[rz]", "bat"));
+    }
+
+    #[test]
+    fn non_body_pathological_patterns() {
+        // Unclosed brackets are literal characters.
+        assert!(value_matches("[abc", "[abc"));
+        assert!(value_matches("[]", "[]"));
+        // Invalid ranges never match.
+        assert!(!value_matches("[z-a]", "b"));
+        // The empty pattern matches the empty string only.
+        assert!(value_matches("", ""));
+        assert!(!value_matches("", "a"));
+    }
+
+    #[test]
+    fn missing_key() {
+        let event = FlattenedJson::from_value(json!({ "content": { "body": "foobaz" } }));
+        assert!(!event_match(&event, "content.value", "*"));
+    }
+
+    #[test]
+    fn glob_matches_whole_value() {
+        assert!(glob_matches("*.example.org", "matrix.EXAMPLE.org"));
+        assert!(!glob_matches("*.example.org", "example.org"));
+        assert!(!glob_matches("example.org", "example.org.evil"));
+    }
+
+    #[test]
+    fn display_name() {
+        assert!(contains_display_name("Hello Carl", "carl"));
+        assert!(contains_display_name("carl: hi", "Carl"));
+        assert!(!contains_display_name("Hello Carlos", "Carl"));
+        // Glob characters in display names are matched literally.
+        assert!(contains_display_name("Hi Dr. *Who*!", "Dr. *Who*"));
+        assert!(!contains_display_name("Hi Dr. Who", "Dr. *Who*"));
+        assert!(!contains_display_name("Hello", ""));
+    }
+
+    #[test]
+    fn cache_evicts_least_recently_used() {
+        let mut cache = RegexCache::default();
+        let key = |i: usize| (format!("pattern{}", i), true, false);
+
+        for i in 0..CACHE_CAPACITY {
+            cache.get_or_compile(key(i));
+        }
+        // Use the oldest entry again, so the second one becomes the least recently used.
+        cache.get_or_compile(key(0));
+        cache.get_or_compile(key(CACHE_CAPACITY));
+
+        assert_eq!(cache.entries.len(), CACHE_CAPACITY);
+        assert_eq!(cache.recency.len(), CACHE_CAPACITY);
+        assert!(cache.entries.contains_key(&key(0)));
+        assert!(!cache.entries.contains_key(&key(1)));
+        assert!(cache.entries.contains_key(&key(CACHE_CAPACITY)));
+    }
+
+    #[test]
+    fn cache_keeps_failed_compiles() {
+        let mut cache = RegexCache::default();
+        let key = ("[z-a]".to_owned(), true, false);
+
+        assert!(cache.get_or_compile(key.clone()).is_none());
+        assert!(matches!(cache.entries.get(&key), Some((None, _))));
+    }
+}
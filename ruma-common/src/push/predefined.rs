@@ -0,0 +1,441 @@
+//! Constructors for [predefined push rules].
+//!
+//! [predefined push rules]: https://matrix.org/docs/spec/client_server/r0.6.1#predefined-rules
+
+use js_int::uint;
+use ruma_identifiers::UserId;
+
+use super::{
+    Action::*, ConditionalPushRule, PatternedPushRule, PushCondition::*, RoomMemberCountIs,
+    Ruleset, Tweak,
+};
+
+impl Ruleset {
+    /// The list of all [predefined push rules].
+    ///
+    /// [predefined push rules]: https://matrix.org/docs/spec/client_server/r0.6.1#predefined-rules
+    ///
+    /// # Parameters
+    ///
+    /// - `user_id`: the user for which to generate the default rules. Some rules depend on the
+    ///   user's ID (for instance those to send notifications when they are mentioned).
+    pub fn server_default(user_id: &UserId) -> Self {
+        Self {
+            content: vec![PatternedPushRule::contains_user_name(user_id)],
+            override_: vec![
+                ConditionalPushRule::master(),
+                ConditionalPushRule::suppress_notices(),
+                ConditionalPushRule::invite_for_me(user_id),
+                ConditionalPushRule::member_event(),
+                ConditionalPushRule::contains_display_name(),
+                ConditionalPushRule::tombstone(),
+                ConditionalPushRule::roomnotif(),
+            ],
+            room: vec![],
+            sender: vec![],
+            underride: vec![
+                ConditionalPushRule::call(),
+                ConditionalPushRule::encrypted_room_one_to_one(),
+                ConditionalPushRule::room_one_to_one(),
+                ConditionalPushRule::message(),
+                ConditionalPushRule::encrypted(),
+            ],
+        }
+    }
+}
+
+/// Default override push rules
+impl ConditionalPushRule {
+    /// Matches all events, this can be enabled to turn off all push notifications other than those
+    /// generated by override rules set by the user.
+    pub fn master() -> Self {
+        Self {
+            actions: vec![DontNotify],
+            default: true,
+            enabled: false,
+            rule_id: ".m.rule.master".into(),
+            conditions: vec![],
+        }
+    }
+
+    /// Matches messages with a `msgtype` of `notice`.
+    pub fn suppress_notices() -> Self {
+        Self {
+            actions: vec![DontNotify],
+            default: true,
+            enabled: true,
+            rule_id: ".m.rule.suppress_notices".into(),
+            conditions: vec![EventMatch {
+                key: "content.msgtype".into(),
+                pattern: "m.notice".into(),
+            }],
+        }
+    }
+
+    /// Matches any invites to a new room for this user.
+    pub fn invite_for_me(user_id: &UserId) -> Self {
+        Self {
+            actions: vec![
+                Notify,
+                SetTweak(Tweak::Sound("default".into())),
+                SetTweak(Tweak::Highlight(false)),
+            ],
+            default: true,
+            enabled: true,
+            rule_id: ".m.rule.invite_for_me".into(),
+            conditions: vec![
+                EventMatch { key: "type".into(), pattern: "m.room.member".into() },
+                EventMatch { key: "content.membership".into(), pattern: "invite".into() },
+                EventMatch { key: "state_key".into(), pattern: user_id.to_string() },
+            ],
+        }
+    }
+
+    /// Matches any `m.room.member` event.
+    pub fn member_event() -> Self {
+        Self {
+            actions: vec![DontNotify],
+            default: true,
+            enabled: true,
+            rule_id: ".m.rule.member_event".into(),
+            conditions: vec![EventMatch { key: "type".into(), pattern: "m.room.member".into() }],
+        }
+    }
+
+    /// Matches any message whose content is unencrypted and contains the user's current display
+    /// name in the room in which it was sent.
+    pub fn contains_display_name() -> Self {
+        Self {
+            actions: vec![
+                Notify,
+                SetTweak(Tweak::Sound("default".into())),
+                SetTweak(Tweak::Highlight(true)),
+            ],
+            default: true,
+            enabled: true,
+            rule_id: ".m.rule.contains_display_name".into(),
+            conditions: vec![ContainsDisplayName],
+        }
+    }
+
+    /// Matches any state event whose type is `m.room.tombstone`. This is intended to notify users
+    /// of a room when it is upgraded, similar to what an `@room` notification would accomplish.
+    pub fn tombstone() -> Self {
+        Self {
+            actions: vec![Notify, SetTweak(Tweak::Highlight(true))],
+            default: true,
+            enabled: true,
+            rule_id: ".m.rule.tombstone".into(),
+            conditions: vec![
+                EventMatch { key: "type".into(), pattern: "m.room.tombstone".into() },
+                EventMatch { key: "state_key".into(), pattern: "".into() },
+            ],
+        }
+    }
+
+    /// Matches any message whose content is unencrypted and contains the text `@room`, signifying
+    /// the whole room should be notified of the event.
+    pub fn roomnotif() -> Self {
+        Self {
+            actions: vec![Notify, SetTweak(Tweak::Highlight(true))],
+            default: true,
+            enabled: true,
+            rule_id: ".m.rule.roomnotif".into(),
+            conditions: vec![
+                EventMatch { key: "content.body".into(), pattern: "@room".into() },
+                SenderNotificationPermission { key: "room".into() },
+            ],
+        }
+    }
+}
+
+/// Default content push rules
+impl PatternedPushRule {
+    /// Matches any message whose content is unencrypted and contains the local part of the user's
+    /// Matrix ID, separated by word boundaries.
+    pub fn contains_user_name(user_id: &UserId) -> Self {
+        Self {
+            actions: vec![
+                Notify,
+                SetTweak(Tweak::Sound("default".into())),
+                SetTweak(Tweak::Highlight(true)),
+            ],
+            default: true,
+            enabled: true,
+            rule_id: ".m.rule.contains_user_name".into(),
+            pattern: user_id.localpart().into(),
+        }
+    }
+}
+
+/// Default underrides push rules
+impl ConditionalPushRule {
+    /// Matches any incoming VOIP call.
+    pub fn call() -> Self {
+        Self {
+            actions: vec![
+                Notify,
+                SetTweak(Tweak::Sound("ring".into())),
+                SetTweak(Tweak::Highlight(false)),
+            ],
+            default: true,
+            enabled: true,
+            rule_id: ".m.rule.call".into(),
+            conditions: vec![EventMatch { key: "type".into(), pattern: "m.call.invite".into() }],
+        }
+    }
+
+    /// Matches any encrypted event sent in a room with exactly two members.
+    ///
+    /// Unlike other push rules, this rule cannot be matched against the content of the event by
+    /// nature of it being encrypted. This causes the rule to be an "all or nothing" match where it
+    /// either matches all events that are encrypted (in 1:1 rooms) or none.
+    pub fn encrypted_room_one_to_one() -> Self {
+        Self {
+            actions: vec![
+                Notify,
+                SetTweak(Tweak::Sound("default".into())),
+                SetTweak(Tweak::Highlight(false)),
+            ],
+            default: true,
+            enabled: true,
+            rule_id: ".m.rule.encrypted_room_one_to_one".into(),
+            conditions: vec![
+                RoomMemberCount { is: RoomMemberCountIs::from(uint!(2)) },
+                EventMatch { key: "type".into(), pattern: "m.room.encrypted".into() },
+            ],
+        }
+    }
+
+    /// Matches any message sent in a room with exactly two members.
+    pub fn room_one_to_one() -> Self {
+        Self {
+            actions: vec![
+                Notify,
+                SetTweak(Tweak::Sound("default".into())),
+                SetTweak(Tweak::Highlight(false)),
+            ],
+            default: true,
+            enabled: true,
+            rule_id: ".m.rule.room_one_to_one".into(),
+            conditions: vec![
+                RoomMemberCount { is: RoomMemberCountIs::from(uint!(2)) },
+                EventMatch { key: "type".into(), pattern: "m.room.message".into() },
+            ],
+        }
+    }
+
+    /// Matches all chat messages.
+    pub fn message() -> Self {
+        Self {
+            actions: vec![Notify, SetTweak(Tweak::Highlight(false))],
+            default: true,
+            enabled: true,
+            rule_id: ".m.rule.message".into(),
+            conditions: vec![EventMatch { key: "type".into(), pattern: "m.room.message".into() }],
+        }
+    }
+
+    /// Matches all encrypted events.
+    ///
+    /// Unlike other push rules, this rule cannot be matched against the content of the event by
+    /// nature of it being encrypted. This causes the rule to be an "all or nothing" match where it
+    /// either matches all events that are encrypted (in group rooms) or none.
+    pub fn encrypted() -> Self {
+        Self {
+            actions: vec![Notify, SetTweak(Tweak::Highlight(false))],
+            default: true,
+            enabled: true,
+            rule_id: ".m.rule.encrypted".into(),
+            conditions: vec![EventMatch { key: "type".into(), pattern: "m.room.encrypted".into() }],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use js_int::int;
+    use matches::assert_matches;
+    use ruma_identifiers::{room_id, user_id};
+    use serde_json::{json, value::to_raw_value as to_raw_json_value};
+
+    use super::super::{Action, PushConditionRoomCtx, Ruleset, Tweak};
+    use crate::Raw;
+
+    fn context(member_count: u32) -> PushConditionRoomCtx {
+        PushConditionRoomCtx {
+            room_id: room_id!("!room:server.name"),
+            user_id: user_id!("@carl:server.name"),
+            user_display_name: "Carl".into(),
+            member_count: member_count.into(),
+            sender_power_level: int!(0),
+            notification_power_levels: BTreeMap::new(),
+        }
+    }
+
+    fn event(json: serde_json::Value) -> Raw<()> {
+        Raw::from_json(to_raw_json_value(&json).unwrap())
+    }
+
+    #[test]
+    fn server_default_rule_ids() {
+        let set = Ruleset::server_default(&user_id!("@carl:server.name"));
+        let ids: Vec<_> = set.iter().map(|rule| rule.rule_id()).collect();
+
+        assert_eq!(
+            ids,
+            vec![
+                ".m.rule.master",
+                ".m.rule.suppress_notices",
+                ".m.rule.invite_for_me",
+                ".m.rule.member_event",
+                ".m.rule.contains_display_name",
+                ".m.rule.tombstone",
+                ".m.rule.roomnotif",
+                ".m.rule.contains_user_name",
+                ".m.rule.call",
+                ".m.rule.encrypted_room_one_to_one",
+                ".m.rule.room_one_to_one",
+                ".m.rule.message",
+                ".m.rule.encrypted",
+            ]
+        );
+        assert!(set.iter().all(|rule| rule.enabled() || rule.rule_id() == ".m.rule.master"));
+    }
+
+    #[test]
+    fn server_default_serialization() {
+        let set = Ruleset::server_default(&user_id!("@carl:server.name"));
+        let json = serde_json::to_value(&set).unwrap();
+
+        assert_eq!(
+            json["content"],
+            json!([{
+                "rule_id": ".m.rule.contains_user_name",
+                "default": true,
+                "enabled": true,
+                "pattern": "carl",
+                "actions": [
+                    "notify",
+                    { "set_tweak": "sound", "value": "default" },
+                    { "set_tweak": "highlight" }
+                ]
+            }])
+        );
+        assert_eq!(
+            json["override"][2],
+            json!({
+                "rule_id": ".m.rule.invite_for_me",
+                "default": true,
+                "enabled": true,
+                "conditions": [
+                    { "kind": "event_match", "key": "type", "pattern": "m.room.member" },
+                    { "kind": "event_match", "key": "content.membership", "pattern": "invite" },
+                    { "kind": "event_match", "key": "state_key", "pattern": "@carl:server.name" }
+                ],
+                "actions": [
+                    "notify",
+                    { "set_tweak": "sound", "value": "default" },
+                    { "set_tweak": "highlight", "value": false }
+                ]
+            })
+        );
+    }
+
+    #[test]
+    fn server_default_actions() {
+        let set = Ruleset::server_default(&user_id!("@carl:server.name"));
+
+        let dm_message = event(json!({
+            "sender": "@worthy_whale:server.name",
+            "type": "m.room.message",
+            "content": { "msgtype": "m.text", "body": "Hello!" }
+        }));
+        assert_matches!(
+            set.get_actions(&dm_message, &context(2)),
+            [
+                Action::Notify,
+                Action::SetTweak(Tweak::Sound(_)),
+                Action::SetTweak(Tweak::Highlight(false))
+            ]
+        );
+        assert_matches!(
+            set.get_actions(&dm_message, &context(10)),
+            [Action::Notify, Action::SetTweak(Tweak::Highlight(false))]
+        );
+
+        let notice = event(json!({
+            "sender": "@bot:server.name",
+            "type": "m.room.message",
+            "content": { "msgtype": "m.notice", "body": "Carl, beep boop" }
+        }));
+        assert_matches!(set.get_actions(&notice, &context(2)), [Action::DontNotify]);
+
+        let mention = event(json!({
+            "sender": "@worthy_whale:server.name",
+            "type": "m.room.message",
+            "content": { "msgtype": "m.text", "body": "carl: lunch?" }
+        }));
+        assert_matches!(
+            set.get_match(&mention, &context(10)),
+            Some(rule) if rule.rule_id() == ".m.rule.contains_display_name"
+        );
+
+        let room_mention = event(json!({
+            "sender": "@worthy_whale:server.name",
+            "type": "m.room.message",
+            "content": { "msgtype": "m.text", "body": "Lunch, @room?" }
+        }));
+        assert_matches!(
+            set.get_match(&room_mention, &context(10)),
+            Some(rule) if rule.rule_id() == ".m.rule.message"
+        );
+        let moderator = PushConditionRoomCtx { sender_power_level: int!(50), ..context(10) };
+        assert_matches!(
+            set.get_match(&room_mention, &moderator),
+            Some(rule) if rule.rule_id() == ".m.rule.roomnotif"
+        );
+
+        let invite = event(json!({
+            "sender": "@worthy_whale:server.name",
+            "type": "m.room.member",
+            "state_key": "@carl:server.name",
+            "content": { "membership": "invite" }
+        }));
+        assert_matches!(
+            set.get_match(&invite, &context(2)),
+            Some(rule) if rule.rule_id() == ".m.rule.invite_for_me"
+        );
+
+        let join = event(json!({
+            "sender": "@worthy_whale:server.name",
+            "type": "m.room.member",
+            "state_key": "@worthy_whale:server.name",
+            "content": { "membership": "join" }
+        }));
+        assert_matches!(set.get_actions(&join, &context(2)), [Action::DontNotify]);
+
+        let call = event(json!({
+            "sender": "@worthy_whale:server.name",
+            "type": "m.call.invite",
+            "content": {}
+        }));
+        assert_matches!(
+            set.get_actions(&call, &context(2)),
+            [Action::Notify, Action::SetTweak(Tweak::Sound(sound)), _] if sound == "ring"
+        );
+
+        let tombstone = event(json!({
+            "sender": "@worthy_whale:server.name",
+            "type": "m.room.tombstone",
+            "state_key": "",
+            "content": { "body": "This room was upgraded" }
+        }));
+        assert_matches!(
+            set.get_actions(&tombstone, &context(10)),
+            [Action::Notify, Action::SetTweak(Tweak::Highlight(true))]
+        );
+    }
+}
@@ -175,9 +175,13 @@ pub fn expand_event_content(
 
         quote! {
             // this is the non redacted event content's impl
-            impl #ident {
-                /// Transforms the full event content into a redacted content according to spec.
-                pub fn redact(self, version: #import_path::exports::ruma_identifiers::RoomVersionId) -> #redacted_ident {
+            impl #import_path::RedactContent for #ident {
+                type Redacted = #redacted_ident;
+
+                fn redact(
+                    self,
+                    _version: #import_path::exports::ruma_identifiers::RoomVersionId,
+                ) -> #redacted_ident {
                     #redacted_ident { #( #redaction_struct_fields: self.#redaction_struct_fields, )* }
                 }
             }
@@ -1,10 +1,10 @@
 //! Implementation of event enum and event content enum macros.
 
 use proc_macro2::{Span, TokenStream};
-use quote::{format_ident, quote};
+use quote::{format_ident, quote, ToTokens};
 use syn::{Attribute, Ident, LitStr};
 
-use crate::event_parse::{EventEnumInput, EventKind, EventKindVariation};
+use crate::event_parse::{EventEnumEntry, EventEnumInput, EventKind, EventKindVariation};
 
 fn is_non_stripped_room_event(kind: &EventKind, var: &EventKindVariation) -> bool {
     matches!(kind, EventKind::Message | EventKind::State)
@@ -47,9 +47,11 @@ pub fn expand_event_enum(input: EventEnumInput) -> syn::Result<TokenStream> {
     let import_path = crate::import_ruma_events();
 
     let name = &input.name;
-    let events = &input.events;
+    let events = input.events.iter().map(|entry| entry.ev_type.clone()).collect::<Vec<_>>();
+    let events = &events;
     let attrs = &input.attrs;
-    let variants = events.iter().map(to_camel_case).collect::<syn::Result<Vec<_>>>()?;
+    let variants =
+        input.events.iter().map(EventEnumVariant::from_entry).collect::<syn::Result<Vec<_>>>()?;
 
     let event_enum = expand_any_with_deser(
         name,
@@ -110,7 +112,7 @@ fn expand_any_with_deser(
     kind: &EventKind,
     events: &[LitStr],
     attrs: &[Attribute],
-    variants: &[Ident],
+    variants: &[EventEnumVariant],
     var: &EventKindVariation,
     import_path: &TokenStream,
 ) -> Option<TokenStream> {
@@ -121,12 +123,20 @@ fn expand_any_with_deser(
 
     let content = events
         .iter()
-        .map(|event| to_event_path(event, &event_struct, import_path))
+        .zip(variants)
+        .map(|(event, variant)| {
+            to_event_path(event, variant.content.as_ref(), &event_struct, import_path)
+        })
         .collect::<Vec<_>>();
 
     let (custom_variant, custom_deserialize) =
         generate_custom_variant(&event_struct, var, import_path);
 
+    let variant_decls = variants.iter().map(|v| v.decl());
+    let variant_attrs = variants.iter().map(|v| &v.attrs);
+    let self_variants = variants.iter().map(|v| v.ctor(quote!(Self)));
+    let event_patterns = events.iter().map(event_type_pattern);
+
     let any_enum = quote! {
         #( #attrs )*
         #[derive(Clone, Debug, #import_path::exports::serde::Serialize)]
@@ -135,7 +145,7 @@ fn expand_any_with_deser(
         pub enum #ident {
             #(
                 #[doc = #events]
-                #variants(#content),
+                #variant_decls(#content),
             )*
             #custom_variant
         }
@@ -155,10 +165,11 @@ fn expand_any_with_deser(
 
                 match ev_type.as_str() {
                     #(
-                        #events => {
+                        #( #variant_attrs )*
+                        #event_patterns => {
                             let event = #import_path::exports::serde_json::from_str::<#content>(json.get())
                                 .map_err(D::Error::custom)?;
-                            Ok(Self::#variants(event))
+                            Ok(#self_variants(event))
                         },
                     )*
                     #custom_deserialize
@@ -167,13 +178,13 @@ fn expand_any_with_deser(
         }
     };
 
-    let event_enum_to_from_sync = expand_conversion_impl(kind, var, &variants, import_path);
+    let event_enum_to_from_sync = expand_conversion_impl(kind, var, variants, import_path);
 
     let redacted_enum = expand_redacted_enum(kind, var, import_path);
 
-    let field_accessor_impl = accessor_methods(kind, var, &variants, import_path);
+    let field_accessor_impl = accessor_methods(kind, var, variants, import_path);
 
-    let redact_impl = expand_redact(&ident, kind, var, &variants, import_path);
+    let redact_impl = expand_redact(&ident, kind, var, variants, import_path);
 
     Some(quote! {
         #any_enum
@@ -193,17 +204,17 @@ fn expand_any_with_deser(
 fn expand_conversion_impl(
     kind: &EventKind,
     var: &EventKindVariation,
-    variants: &[Ident],
+    variants: &[EventEnumVariant],
     import_path: &TokenStream,
 ) -> Option<TokenStream> {
     let ident = kind.to_event_enum_ident(var)?;
     let variants = &variants
         .iter()
-        .filter(|id| {
+        .filter(|v| {
             // We filter this variant out only for non redacted events.
             // The type of the struct held in the enum variant is different in this case
             // so we construct the variant manually.
-            !(id.to_string().as_str() == "RoomRedaction"
+            !(v.ident == "RoomRedaction"
                 && matches!(var, EventKindVariation::Full | EventKindVariation::Sync))
         })
         .collect::<Vec<_>>();
@@ -230,13 +241,16 @@ fn expand_conversion_impl(
                 TokenStream::new()
             };
 
+            let ident_variants = variants.iter().map(|v| v.match_arm(&ident));
+            let self_variants = variants.iter().map(|v| v.ctor(quote!(Self)));
+
             Some(quote! {
                 impl From<#ident> for #sync {
                     fn from(event: #ident) -> Self {
                         match event {
                             #(
-                                #ident::#variants(event) => {
-                                    Self::#variants(#import_path::#sync_struct::from(event))
+                                #ident_variants(event) => {
+                                    #self_variants(#import_path::#sync_struct::from(event))
                                 },
                             )*
                             #redaction
@@ -266,14 +280,17 @@ fn expand_conversion_impl(
                 TokenStream::new()
             };
 
+            let self_variants = variants.iter().map(|v| v.match_arm(quote!(Self)));
+            let full_variants = variants.iter().map(|v| v.ctor(&full));
+
             Some(quote! {
                 impl #ident {
                     /// Convert this sync event into a full event, one with a room_id field.
                     pub fn into_full_event(self, room_id: #import_path::exports::ruma_identifiers::RoomId) -> #full {
                         match self {
                             #(
-                                Self::#variants(event) => {
-                                    #full::#variants(event.into_full_event(room_id))
+                                #self_variants(event) => {
+                                    #full_variants(event.into_full_event(room_id))
                                 },
                             )*
                             #redaction
@@ -298,7 +315,7 @@ fn expand_any_redacted(
     kind: &EventKind,
     events: &[LitStr],
     attrs: &[Attribute],
-    variants: &[Ident],
+    variants: &[EventEnumVariant],
     import_path: &TokenStream,
 ) -> TokenStream {
     use EventKindVariation as V;
@@ -339,14 +356,23 @@ fn expand_content_enum(
     kind: &EventKind,
     events: &[LitStr],
     attrs: &[Attribute],
-    variants: &[Ident],
+    variants: &[EventEnumVariant],
     import_path: &TokenStream,
 ) -> TokenStream {
     let ident = kind.to_content_enum();
     let event_type_str = events;
 
-    let content =
-        events.iter().map(|ev| to_event_content_path(ev, import_path)).collect::<Vec<_>>();
+    let content = events
+        .iter()
+        .zip(variants)
+        .map(|(ev, variant)| to_event_content_path(ev, variant.content.as_ref(), import_path))
+        .collect::<Vec<_>>();
+
+    let variant_decls = variants.iter().map(|v| v.decl());
+    let variant_arms = variants.iter().map(|v| v.match_arm(quote!(Self)));
+    let variant_attrs = variants.iter().map(|v| &v.attrs);
+    let variant_ctors = variants.iter().map(|v| v.ctor(quote!(Self)));
+    let event_type_patterns = events.iter().map(event_type_pattern);
 
     let content_enum = quote! {
         #( #attrs )*
@@ -356,7 +382,7 @@ fn expand_content_enum(
         pub enum #ident {
             #(
                 #[doc = #event_type_str]
-                #variants(#content),
+                #variant_decls(#content),
             )*
             /// Content of an event not defined by the Matrix specification.
             Custom(#import_path::custom::CustomEventContent),
@@ -367,7 +393,7 @@ fn expand_content_enum(
         impl #import_path::EventContent for #ident {
             fn event_type(&self) -> &str {
                 match self {
-                    #( Self::#variants(content) => content.event_type(), )*
+                    #( #variant_arms(content) => content.event_type(), )*
                     Self::Custom(content) => content.event_type(),
                 }
             }
@@ -377,9 +403,10 @@ fn expand_content_enum(
             ) -> Result<Self, #import_path::exports::serde_json::Error> {
                 match event_type {
                     #(
-                        #event_type_str => {
+                        #( #variant_attrs )*
+                        #event_type_patterns => {
                             let content = #content::from_parts(event_type, input)?;
-                            Ok(Self::#variants(content))
+                            Ok(#variant_ctors(content))
                         },
                     )*
                     ev_type => {
@@ -407,77 +434,46 @@ fn expand_redact(
     ident: &Ident,
     kind: &EventKind,
     var: &EventKindVariation,
-    variants: &[Ident],
+    variants: &[EventEnumVariant],
     import_path: &TokenStream,
 ) -> Option<TokenStream> {
-    if let EventKindVariation::Full | EventKindVariation::Sync | EventKindVariation::Stripped = var
-    {
-        let (param, redaction_type, redaction_enum) = match var {
-            EventKindVariation::Full => {
-                let struct_id = kind.to_event_ident(&EventKindVariation::Redacted)?;
-                (
-                    quote! { #import_path::room::redaction::RedactionEvent },
-                    quote! { #import_path::#struct_id },
-                    kind.to_event_enum_ident(&EventKindVariation::Redacted)?,
-                )
-            }
-            EventKindVariation::Sync => {
-                let struct_id = kind.to_event_ident(&EventKindVariation::RedactedSync)?;
-                (
-                    quote! { #import_path::room::redaction::SyncRedactionEvent },
-                    quote! { #import_path::#struct_id },
-                    kind.to_event_enum_ident(&EventKindVariation::RedactedSync)?,
-                )
-            }
-            EventKindVariation::Stripped => {
-                let struct_id = kind.to_event_ident(&EventKindVariation::RedactedStripped)?;
-                (
-                    quote! { #import_path::room::redaction::SyncRedactionEvent },
-                    quote! { #import_path::#struct_id },
-                    kind.to_event_enum_ident(&EventKindVariation::RedactedStripped)?,
-                )
-            }
-            _ => return None,
-        };
-
-        let fields = EVENT_FIELDS.iter().map(|(name, has_field)| {
-            generate_redacted_fields(name, kind, var, *has_field, import_path)
-        });
-
-        let fields = quote! { #( #fields )* };
+    let redacted_var = match var {
+        EventKindVariation::Full => EventKindVariation::Redacted,
+        EventKindVariation::Sync => EventKindVariation::RedactedSync,
+        EventKindVariation::Stripped => EventKindVariation::RedactedStripped,
+        _ => return None,
+    };
+    let redaction_enum = kind.to_event_enum_ident(&redacted_var)?;
+    let redaction_type = match var {
+        EventKindVariation::Full => quote! { #import_path::room::redaction::RedactionEvent },
+        _ => quote! { #import_path::room::redaction::SyncRedactionEvent },
+    };
+    let self_variants = variants.iter().map(|v| v.match_arm(quote!(Self)));
+    let redaction_variants = variants.iter().map(|v| v.ctor(&redaction_enum));
 
-        Some(quote! {
-            impl #ident {
-                /// Redacts `Self` given a valid `Redaction[Sync]Event`.
-                pub fn redact(
-                    self,
-                    redaction: #param,
-                    version: #import_path::exports::ruma_identifiers::RoomVersionId,
-                ) -> #redaction_enum {
-                    match self {
-                        #(
-                            Self::#variants(event) => {
-                                let content = event.content.redact(version);
-                                #redaction_enum::#variants(#redaction_type {
-                                    content,
-                                    #fields
-                                })
-                            }
-                        )*
-                        Self::Custom(event) => {
-                            let content = event.content.redact(version);
-                            #redaction_enum::Custom(#redaction_type {
-                                content,
-                                #fields
-                            })
-                        }
-                    }
+    Some(quote! {
+        impl #import_path::Redact for #ident {
+            type Redacted = #redaction_enum;
+            type Redaction = #redaction_type;
+
+            fn redact(
+                self,
+                redaction: #redaction_type,
+                version: #import_path::exports::ruma_identifiers::RoomVersionId,
+            ) -> #redaction_enum {
+                match self {
+                    #(
+                        #self_variants(event) => #redaction_variants(
+                            #import_path::Redact::redact(event, redaction, version),
+                        ),
+                    )*
+                    Self::Custom(event) => #redaction_enum::Custom(
+                        #import_path::Redact::redact(event, redaction, version),
+                    ),
                 }
             }
-        })
-    } else {
-        None
-    }
+        }
+    })
 }
 
 fn expand_redacted_enum(
@@ -535,38 +531,6 @@ fn generate_event_idents(kind: &EventKind, var: &EventKindVariation) -> Option<(
     Some((kind.to_event_ident(var)?, kind.to_event_enum_ident(var)?))
 }
 
-fn generate_redacted_fields(
-    name: &str,
-    kind: &EventKind,
-    var: &EventKindVariation,
-    is_event_kind: EventKindFn,
-    import_path: &TokenStream,
-) -> TokenStream {
-    if is_event_kind(kind, var) {
-        let name = Ident::new(name, Span::call_site());
-
-        if name == "unsigned" {
-            let redaction_type = if let EventKindVariation::Sync = var {
-                quote! { RedactedSyncUnsigned }
-            } else {
-                quote! { RedactedUnsigned }
-            };
-
-            quote! {
-                unsigned: #import_path::#redaction_type {
-                    redacted_because: Some(::std::boxed::Box::new(redaction)),
-                },
-            }
-        } else {
-            quote! {
-                #name: event.#name,
-            }
-        }
-    } else {
-        TokenStream::new()
-    }
-}
-
 fn generate_custom_variant(
     event_struct: &Ident,
     var: &EventKindVariation,
@@ -636,7 +600,7 @@ fn marker_traits(kind: &EventKind, import_path: &TokenStream) -> TokenStream {
 fn accessor_methods(
     kind: &EventKind,
     var: &EventKindVariation,
-    variants: &[Ident],
+    variants: &[EventEnumVariant],
     import_path: &TokenStream,
 ) -> Option<TokenStream> {
     use EventKindVariation as V;
@@ -653,13 +617,15 @@ fn accessor_methods(
     });
 
     let content_enum = kind.to_content_enum();
+    let self_variants = variants.iter().map(|v| v.match_arm(quote!(Self))).collect::<Vec<_>>();
+    let content_variants = variants.iter().map(|v| v.ctor(&content_enum)).collect::<Vec<_>>();
 
     let content = quote! {
         /// Returns the any content enum for this event.
         pub fn content(&self) -> #content_enum {
             match self {
                 #(
-                    Self::#variants(event) => #content_enum::#variants(event.content.clone()),
+                    #self_variants(event) => #content_variants(event.content.clone()),
                 )*
                 Self::Custom(event) => #content_enum::Custom(event.content.clone()),
             }
@@ -672,8 +638,8 @@ fn accessor_methods(
             pub fn prev_content(&self) -> Option<#content_enum> {
                 match self {
                     #(
-                        Self::#variants(event) => {
-                            event.prev_content.as_ref().map(|c| #content_enum::#variants(c.clone()))
+                        #self_variants(event) => {
+                            event.prev_content.as_ref().map(|c| #content_variants(c.clone()))
                         },
                     )*
                     Self::Custom(event) => {
@@ -720,7 +686,7 @@ fn inner_enum_idents(kind: &EventKind, var: &EventKindVariation) -> (Option<Iden
 fn redacted_accessor_methods(
     kind: &EventKind,
     var: &EventKindVariation,
-    variants: &[Ident],
+    variants: &[EventEnumVariant],
     import_path: &TokenStream,
 ) -> Option<TokenStream> {
     // this will never fail as it is called in `expand_any_with_deser`.
@@ -736,9 +702,15 @@ fn redacted_accessor_methods(
     })
 }
 
-fn to_event_path(name: &LitStr, struct_name: &Ident, import_path: &TokenStream) -> TokenStream {
+fn to_event_path(
+    name: &LitStr,
+    content: Option<&Ident>,
+    struct_name: &Ident,
+    import_path: &TokenStream,
+) -> TokenStream {
     let span = name.span();
     let name = name.value();
+    let name = without_wildcard(&name);
 
     // There is no need to give a good compiler error as `to_camel_case` is called first.
     assert_eq!(&name[..2], "m.");
@@ -768,7 +740,7 @@ fn to_event_path(name: &LitStr, struct_name: &Ident, import_path: &TokenStream)
         | "InitialStateEvent"
         | "SyncMessageEvent"
         | "SyncEphemeralRoomEvent" => {
-            let content = format_ident!("{}EventContent", event);
+            let content = content_ident(content, &event);
             quote! { #import_path::#struct_name<#import_path::#( #path )::*::#content> }
         }
         struct_str if struct_str.contains("Redacted") => {
@@ -782,9 +754,14 @@ fn to_event_path(name: &LitStr, struct_name: &Ident, import_path: &TokenStream)
     }
 }
 
-fn to_event_content_path(name: &LitStr, import_path: &TokenStream) -> TokenStream {
+fn to_event_content_path(
+    name: &LitStr,
+    content: Option<&Ident>,
+    import_path: &TokenStream,
+) -> TokenStream {
     let span = name.span();
     let name = name.value();
+    let name = without_wildcard(&name);
 
     // There is no need to give a good compiler error as `to_camel_case` is called first.
     assert_eq!(&name[..2], "m.");
@@ -797,13 +774,82 @@ fn to_event_content_path(name: &LitStr, import_path: &TokenStream) -> TokenStrea
         .map(|s| s.chars().next().unwrap().to_uppercase().to_string() + &s[1..])
         .collect::<String>();
 
-    let content_str = format_ident!("{}EventContent", event);
+    let content_str = content_ident(content, &event);
     let path = path.iter().map(|s| Ident::new(s, span));
     quote! {
         #import_path::#( #path )::*::#content_str
     }
 }
 
+/// The name of the content type of an event whose camel case name is `event`.
+///
+/// This is the name set with `#[ruma_enum(content = "...")]` if there is one, e.g. for events that
+/// can be sent both as to-device and as message events, with different contents.
+fn content_ident(content: Option<&Ident>, event: &str) -> Ident {
+    content.cloned().unwrap_or_else(|| format_ident!("{}EventContent", event))
+}
+
+/// A variant of the generated event enums, corresponding to one entry of the `events` array.
+pub struct EventEnumVariant {
+    /// Attributes of the entry, such as `#[cfg]`, that are applied wherever the variant is used.
+    pub attrs: Vec<Attribute>,
+
+    /// The name of the variant.
+    pub ident: Ident,
+
+    /// The name of the content type, if it isn't named after the event type.
+    pub content: Option<Ident>,
+}
+
+impl EventEnumVariant {
+    fn from_entry(entry: &EventEnumEntry) -> syn::Result<Self> {
+        Ok(Self {
+            attrs: entry.attrs.clone(),
+            ident: to_camel_case(&entry.ev_type)?,
+            content: entry.content.clone(),
+        })
+    }
+
+    /// The variant as it appears in an enum declaration, with its attributes.
+    fn decl(&self) -> TokenStream {
+        let Self { attrs, ident, .. } = self;
+        quote! { #( #attrs )* #ident }
+    }
+
+    /// A path to the variant to be used as a pattern in a match arm, with its attributes.
+    fn match_arm(&self, parent: impl ToTokens) -> TokenStream {
+        let Self { attrs, ident, .. } = self;
+        quote! { #( #attrs )* #parent::#ident }
+    }
+
+    /// A path to the variant to be used as a constructor.
+    fn ctor(&self, parent: impl ToTokens) -> TokenStream {
+        let ident = &self.ident;
+        quote! { #parent::#ident }
+    }
+}
+
+/// The pattern matching the event type `name` in the generated `match` expressions.
+///
+/// Event types ending in `.*`, like `m.secret_storage.key.*`, match all event types starting with
+/// the part before the `*`.
+fn event_type_pattern(name: &LitStr) -> TokenStream {
+    let value = name.value();
+    if value.ends_with(".*") {
+        let prefix = &value[..value.len() - 1];
+        quote! { ev_type if ev_type.starts_with(#prefix) }
+    } else {
+        quote! { #name }
+    }
+}
+
+/// The given event type without a trailing `.*`.
+///
+/// The types and variants of event types ending in `.*` are named after the part before it.
+fn without_wildcard(name: &str) -> &str {
+    name.trim_end_matches(".*")
+}
+
 /// Splits the given `event_type` string on `.` and `_` removing the `m.room.` then
 /// camel casing to give the `Event` struct name.
 fn to_camel_case(name: &LitStr) -> syn::Result<Ident> {
@@ -817,7 +863,7 @@ fn to_camel_case(name: &LitStr) -> syn::Result<Ident> {
         ));
     }
 
-    let s = name[2..]
+    let s = without_wildcard(&name)[2..]
         .split(&['.', '_'] as &[char])
         .map(|s| s.chars().next().unwrap().to_uppercase().to_string() + &s[1..])
         .collect::<String>();
@@ -830,7 +876,7 @@ fn generate_accessor(
     kind: &EventKind,
     var: &EventKindVariation,
     is_event_kind: EventKindFn,
-    variants: &[Ident],
+    variants: &[EventEnumVariant],
     import_path: &TokenStream,
 ) -> TokenStream {
     if is_event_kind(kind, var) {
@@ -838,12 +884,14 @@ fn generate_accessor(
 
         let name = Ident::new(name, Span::call_site());
         let docs = format!("Returns this events {} field.", name);
+        let self_variants = variants.iter().map(|v| v.match_arm(quote!(Self)));
+
         quote! {
             #[doc = #docs]
             pub fn #name(&self) -> &#field_type {
                 match self {
                     #(
-                        Self::#variants(event) => &event.#name,
+                        #self_variants(event) => &event.#name,
                     )*
                     Self::Custom(event) => &event.#name,
                 }
@@ -6,13 +6,14 @@ use proc_macro2::Span;
 use quote::format_ident;
 use syn::{
     parse::{self, Parse, ParseStream},
-    Attribute, Expr, ExprLit, Ident, Lit, LitStr, Token,
+    Attribute, Ident, LitStr, Token,
 };
 
 /// Custom keywords for the `event_enum!` macro
 mod kw {
     syn::custom_keyword!(kind);
     syn::custom_keyword!(events);
+    syn::custom_keyword!(content);
 }
 
 // If the variants of this enum change `to_event_path` needs to be updated as well.
@@ -193,7 +194,48 @@ pub struct EventEnumInput {
     /// variant in `ruma_events::EventType` for this event (converted to a valid Rust-style type
     /// name by stripping `m.`, replacing the remaining dots by underscores and then converting from
     /// snake_case to CamelCase).
-    pub events: Vec<LitStr>,
+    pub events: Vec<EventEnumEntry>,
+}
+
+/// An entry of the `events` array of the `event_enum!` macro.
+pub struct EventEnumEntry {
+    /// Outer attributes on the entry, such as `#[cfg]`.
+    ///
+    /// These are applied to every item generated for the event.
+    pub attrs: Vec<Attribute>,
+
+    /// The matrix event type.
+    pub ev_type: LitStr,
+
+    /// The name of the content type, set with `#[ruma_enum(content = "FooEventContent")]`.
+    ///
+    /// If this is `None`, the content type is named after the event type, suffixed with
+    /// `EventContent`.
+    pub content: Option<Ident>,
+}
+
+impl Parse for EventEnumEntry {
+    fn parse(input: ParseStream<'_>) -> parse::Result<Self> {
+        let mut attrs = input.call(Attribute::parse_outer)?;
+        let ev_type = input.parse::<LitStr>().map_err(|e| {
+            let msg = "values of field `events` are required to be a string literal";
+            syn::Error::new(e.span(), msg)
+        })?;
+
+        let mut content = None;
+        for attr in attrs.iter().filter(|attr| attr.path.is_ident("ruma_enum")) {
+            content = Some(attr.parse_args_with(|input: ParseStream<'_>| {
+                input.parse::<kw::content>()?;
+                input.parse::<Token![=]>()?;
+                let name = input.parse::<LitStr>()?;
+                Ok(Ident::new(&name.value(), name.span()))
+            })?);
+        }
+        // don't re-emit our `ruma_enum` attributes
+        attrs.retain(|attr| !attr.path.is_ident("ruma_enum"));
+
+        Ok(Self { attrs, ev_type, content })
+    }
 }
 
 impl Parse for EventEnumInput {
@@ -211,20 +253,11 @@ impl Parse for EventEnumInput {
         input.parse::<kw::events>()?;
         input.parse::<Token![:]>()?;
 
-        // an array of event names `["m.room.whatever", ...]`
-        let ev_array = input.parse::<syn::ExprArray>()?;
-        let events = ev_array
-            .elems
-            .into_iter()
-            .map(|item| {
-                if let Expr::Lit(ExprLit { lit: Lit::Str(lit_str), .. }) = item {
-                    Ok(lit_str)
-                } else {
-                    let msg = "values of field `events` are required to be a string literal";
-                    Err(syn::Error::new_spanned(item, msg))
-                }
-            })
-            .collect::<syn::Result<_>>()?;
+        // an array of event names `["m.room.whatever", ...]`, optionally with attributes
+        let content;
+        syn::bracketed!(content in input);
+        let events =
+            content.parse_terminated::<_, Token![,]>(EventEnumEntry::parse)?.into_iter().collect();
 
         Ok(Self { attrs, name, events })
     }
@@ -32,6 +32,9 @@ mod event_parse;
 /// This macro also implements the necessary traits for the type to serialize and deserialize
 /// itself.
 ///
+/// The content type of each event is named after its event type, e.g. `OtherEventContent` for
+/// `m.other.event`. A different name can be given with `#[ruma_enum(content = "...")]`.
+///
 /// # Examples
 ///
 /// ```ignore
@@ -41,6 +44,7 @@ mod event_parse;
 ///     name: AnyBarEvent, // `BarEvent` has to be a valid type at `::ruma_events::BarEvent`
 ///     events: [
 ///         "m.any.event",
+///         #[ruma_enum(content = "OtherBarEventContent")]
 ///         "m.other.event",
 ///     ]
 /// }
@@ -6,7 +6,7 @@ use serde_json::{value::RawValue as RawJsonValue, Value as JsonValue};
 
 use crate::{
     BasicEventContent, EphemeralRoomEventContent, EventContent, HasDeserializeFields,
-    MessageEventContent, RedactedEventContent, RedactedMessageEventContent,
+    MessageEventContent, RedactContent, RedactedEventContent, RedactedMessageEventContent,
     RedactedStateEventContent, RoomEventContent, StateEventContent,
 };
 
@@ -22,9 +22,10 @@ pub struct CustomEventContent {
     pub json: JsonValue,
 }
 
-impl CustomEventContent {
-    /// Transforms the full event content into a redacted content according to spec.
-    pub fn redact(self, _: RoomVersionId) -> RedactedCustomEventContent {
+impl RedactContent for CustomEventContent {
+    type Redacted = RedactedCustomEventContent;
+
+    fn redact(self, _: RoomVersionId) -> RedactedCustomEventContent {
         RedactedCustomEventContent { event_type: self.event_type }
     }
 }
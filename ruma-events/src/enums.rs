@@ -14,6 +14,8 @@ event_enum! {
         "m.presence",
         "m.push_rules",
         "m.room_key",
+        "m.secret_storage.default_key",
+        "m.secret_storage.key.*",
         "m.tag",
     ]
 }
@@ -36,6 +38,22 @@ event_enum! {
         "m.call.invite",
         "m.call.hangup",
         "m.call.candidates",
+        #[cfg(feature = "unstable-pre-spec")]
+        "m.key.verification.ready",
+        #[cfg(feature = "unstable-pre-spec")]
+        "m.key.verification.start",
+        #[cfg(feature = "unstable-pre-spec")]
+        "m.key.verification.cancel",
+        #[cfg(feature = "unstable-pre-spec")]
+        "m.key.verification.accept",
+        #[cfg(feature = "unstable-pre-spec")]
+        "m.key.verification.key",
+        #[cfg(feature = "unstable-pre-spec")]
+        "m.key.verification.mac",
+        #[cfg(feature = "unstable-pre-spec")]
+        "m.key.verification.done",
+        #[cfg(feature = "unstable-pre-spec")]
+        "m.reaction",
         "m.room.encrypted",
         "m.room.message",
         "m.room.message.feedback",
@@ -79,12 +97,25 @@ event_enum! {
         "m.room_key_request",
         "m.forwarded_room_key",
         "m.key.verification.request",
+        #[cfg(feature = "unstable-pre-spec")]
+        #[ruma_enum(content = "ReadyToDeviceEventContent")]
+        "m.key.verification.ready",
+        #[ruma_enum(content = "StartToDeviceEventContent")]
         "m.key.verification.start",
+        #[ruma_enum(content = "CancelToDeviceEventContent")]
         "m.key.verification.cancel",
+        #[ruma_enum(content = "AcceptToDeviceEventContent")]
         "m.key.verification.accept",
+        #[ruma_enum(content = "KeyToDeviceEventContent")]
         "m.key.verification.key",
+        #[ruma_enum(content = "MacToDeviceEventContent")]
         "m.key.verification.mac",
+        #[cfg(feature = "unstable-pre-spec")]
+        #[ruma_enum(content = "DoneToDeviceEventContent")]
+        "m.key.verification.done",
         "m.room.encrypted",
+        "m.secret.request",
+        "m.secret.send",
     ]
 }
 
@@ -1,12 +1,13 @@
 use std::time::SystemTime;
 
 use ruma_events_macros::Event;
-use ruma_identifiers::{EventId, RoomId, UserId};
+use ruma_identifiers::{EventId, RoomId, RoomVersionId, UserId};
 
 use crate::{
-    BasicEventContent, EphemeralRoomEventContent, EventContent, MessageEventContent,
-    RedactedMessageEventContent, RedactedStateEventContent, RedactedSyncUnsigned, RedactedUnsigned,
-    StateEventContent, Unsigned,
+    room::redaction::{RedactionEvent, SyncRedactionEvent},
+    BasicEventContent, EphemeralRoomEventContent, EventContent, MessageEventContent, Redact,
+    RedactContent, RedactedMessageEventContent, RedactedStateEventContent, RedactedSyncUnsigned,
+    RedactedUnsigned, StateEventContent, Unsigned,
 };
 
 /// A basic event – one that consists only of it's type and the `content` object.
@@ -316,3 +317,101 @@ pub struct ToDeviceEvent<C: EventContent> {
     /// The fully-qualified ID of the user who sent this event.
     pub sender: UserId,
 }
+
+impl<C> Redact for MessageEvent<C>
+where
+    C: MessageEventContent + RedactContent,
+    C::Redacted: RedactedMessageEventContent,
+{
+    type Redacted = RedactedMessageEvent<C::Redacted>;
+    type Redaction = RedactionEvent;
+
+    fn redact(self, redaction: RedactionEvent, version: RoomVersionId) -> Self::Redacted {
+        RedactedMessageEvent {
+            content: self.content.redact(version),
+            event_id: self.event_id,
+            sender: self.sender,
+            origin_server_ts: self.origin_server_ts,
+            room_id: self.room_id,
+            unsigned: RedactedUnsigned { redacted_because: Some(Box::new(redaction)) },
+        }
+    }
+}
+
+impl<C> Redact for SyncMessageEvent<C>
+where
+    C: MessageEventContent + RedactContent,
+    C::Redacted: RedactedMessageEventContent,
+{
+    type Redacted = RedactedSyncMessageEvent<C::Redacted>;
+    type Redaction = SyncRedactionEvent;
+
+    fn redact(self, redaction: SyncRedactionEvent, version: RoomVersionId) -> Self::Redacted {
+        RedactedSyncMessageEvent {
+            content: self.content.redact(version),
+            event_id: self.event_id,
+            sender: self.sender,
+            origin_server_ts: self.origin_server_ts,
+            unsigned: RedactedSyncUnsigned { redacted_because: Some(Box::new(redaction)) },
+        }
+    }
+}
+
+impl<C> Redact for StateEvent<C>
+where
+    C: StateEventContent + RedactContent,
+    C::Redacted: RedactedStateEventContent,
+{
+    type Redacted = RedactedStateEvent<C::Redacted>;
+    type Redaction = RedactionEvent;
+
+    fn redact(self, redaction: RedactionEvent, version: RoomVersionId) -> Self::Redacted {
+        RedactedStateEvent {
+            content: self.content.redact(version),
+            event_id: self.event_id,
+            sender: self.sender,
+            origin_server_ts: self.origin_server_ts,
+            room_id: self.room_id,
+            state_key: self.state_key,
+            unsigned: RedactedUnsigned { redacted_because: Some(Box::new(redaction)) },
+        }
+    }
+}
+
+impl<C> Redact for SyncStateEvent<C>
+where
+    C: StateEventContent + RedactContent,
+    C::Redacted: RedactedStateEventContent,
+{
+    type Redacted = RedactedSyncStateEvent<C::Redacted>;
+    type Redaction = SyncRedactionEvent;
+
+    fn redact(self, redaction: SyncRedactionEvent, version: RoomVersionId) -> Self::Redacted {
+        RedactedSyncStateEvent {
+            content: self.content.redact(version),
+            event_id: self.event_id,
+            sender: self.sender,
+            origin_server_ts: self.origin_server_ts,
+            state_key: self.state_key,
+            unsigned: RedactedSyncUnsigned { redacted_because: Some(Box::new(redaction)) },
+        }
+    }
+}
+
+impl<C> Redact for StrippedStateEvent<C>
+where
+    C: StateEventContent + RedactContent,
+    C::Redacted: RedactedStateEventContent,
+{
+    type Redacted = RedactedStrippedStateEvent<C::Redacted>;
+    type Redaction = SyncRedactionEvent;
+
+    /// Redacts this event, ignoring `redaction` since stripped events have no unsigned data.
+    fn redact(self, _redaction: SyncRedactionEvent, version: RoomVersionId) -> Self::Redacted {
+        RedactedStrippedStateEvent {
+            content: self.content.redact(version),
+            sender: self.sender,
+            state_key: self.state_key,
+        }
+    }
+}
@@ -40,12 +40,20 @@ pub enum EventType {
     /// m.key.verification.cancel
     KeyVerificationCancel,
 
+    /// m.key.verification.done
+    #[cfg(feature = "unstable-pre-spec")]
+    KeyVerificationDone,
+
     /// m.key.verification.key
     KeyVerificationKey,
 
     /// m.key.verification.mac
     KeyVerificationMac,
 
+    /// m.key.verification.ready
+    #[cfg(feature = "unstable-pre-spec")]
+    KeyVerificationReady,
+
     /// m.key.verification.request
     KeyVerificationRequest,
 
@@ -70,6 +78,10 @@ pub enum EventType {
     /// m.push_rules
     PushRules,
 
+    /// m.reaction
+    #[cfg(feature = "unstable-pre-spec")]
+    Reaction,
+
     /// m.receipt
     Receipt,
 
@@ -139,6 +151,15 @@ pub enum EventType {
     /// m.room_key_request
     RoomKeyRequest,
 
+    /// m.secret.request
+    SecretRequest,
+
+    /// m.secret.send
+    SecretSend,
+
+    /// m.secret_storage.default_key
+    SecretStorageDefaultKey,
+
     /// m.sticker
     Sticker,
 
@@ -166,8 +187,12 @@ impl EventType {
             EventType::FullyRead => "m.fully_read",
             EventType::KeyVerificationAccept => "m.key.verification.accept",
             EventType::KeyVerificationCancel => "m.key.verification.cancel",
+            #[cfg(feature = "unstable-pre-spec")]
+            EventType::KeyVerificationDone => "m.key.verification.done",
             EventType::KeyVerificationKey => "m.key.verification.key",
             EventType::KeyVerificationMac => "m.key.verification.mac",
+            #[cfg(feature = "unstable-pre-spec")]
+            EventType::KeyVerificationReady => "m.key.verification.ready",
             EventType::KeyVerificationRequest => "m.key.verification.request",
             EventType::KeyVerificationStart => "m.key.verification.start",
             EventType::IgnoredUserList => "m.ignored_user_list",
@@ -176,6 +201,8 @@ impl EventType {
             EventType::PolicyRuleUser => "m.policy.rule.user",
             EventType::Presence => "m.presence",
             EventType::PushRules => "m.push_rules",
+            #[cfg(feature = "unstable-pre-spec")]
+            EventType::Reaction => "m.reaction",
             EventType::Receipt => "m.receipt",
             EventType::RoomAliases => "m.room.aliases",
             EventType::RoomAvatar => "m.room.avatar",
@@ -199,6 +226,9 @@ impl EventType {
             EventType::RoomTopic => "m.room.topic",
             EventType::RoomKey => "m.room_key",
             EventType::RoomKeyRequest => "m.room_key_request",
+            EventType::SecretRequest => "m.secret.request",
+            EventType::SecretSend => "m.secret.send",
+            EventType::SecretStorageDefaultKey => "m.secret_storage.default_key",
             EventType::Sticker => "m.sticker",
             EventType::Tag => "m.tag",
             EventType::Typing => "m.typing",
@@ -229,8 +259,12 @@ where
             "m.fully_read" => EventType::FullyRead,
             "m.key.verification.accept" => EventType::KeyVerificationAccept,
             "m.key.verification.cancel" => EventType::KeyVerificationCancel,
+            #[cfg(feature = "unstable-pre-spec")]
+            "m.key.verification.done" => EventType::KeyVerificationDone,
             "m.key.verification.key" => EventType::KeyVerificationKey,
             "m.key.verification.mac" => EventType::KeyVerificationMac,
+            #[cfg(feature = "unstable-pre-spec")]
+            "m.key.verification.ready" => EventType::KeyVerificationReady,
             "m.key.verification.request" => EventType::KeyVerificationRequest,
             "m.key.verification.start" => EventType::KeyVerificationStart,
             "m.ignored_user_list" => EventType::IgnoredUserList,
@@ -239,6 +273,8 @@ where
             "m.policy.rule.user" => EventType::PolicyRuleUser,
             "m.presence" => EventType::Presence,
             "m.push_rules" => EventType::PushRules,
+            #[cfg(feature = "unstable-pre-spec")]
+            "m.reaction" => EventType::Reaction,
             "m.receipt" => EventType::Receipt,
             "m.room.aliases" => EventType::RoomAliases,
             "m.room.avatar" => EventType::RoomAvatar,
@@ -262,6 +298,9 @@ where
             "m.room.topic" => EventType::RoomTopic,
             "m.room_key" => EventType::RoomKey,
             "m.room_key_request" => EventType::RoomKeyRequest,
+            "m.secret.request" => EventType::SecretRequest,
+            "m.secret.send" => EventType::SecretSend,
+            "m.secret_storage.default_key" => EventType::SecretStorageDefaultKey,
             "m.sticker" => EventType::Sticker,
             "m.tag" => EventType::Tag,
             "m.typing" => EventType::Typing,
@@ -296,8 +335,12 @@ mod tests {
         serde_json_eq(EventType::FullyRead, json!("m.fully_read"));
         serde_json_eq(EventType::KeyVerificationAccept, json!("m.key.verification.accept"));
         serde_json_eq(EventType::KeyVerificationCancel, json!("m.key.verification.cancel"));
+        #[cfg(feature = "unstable-pre-spec")]
+        serde_json_eq(EventType::KeyVerificationDone, json!("m.key.verification.done"));
         serde_json_eq(EventType::KeyVerificationKey, json!("m.key.verification.key"));
         serde_json_eq(EventType::KeyVerificationMac, json!("m.key.verification.mac"));
+        #[cfg(feature = "unstable-pre-spec")]
+        serde_json_eq(EventType::KeyVerificationReady, json!("m.key.verification.ready"));
         serde_json_eq(EventType::KeyVerificationRequest, json!("m.key.verification.request"));
         serde_json_eq(EventType::KeyVerificationStart, json!("m.key.verification.start"));
         serde_json_eq(EventType::IgnoredUserList, json!("m.ignored_user_list"));
@@ -306,6 +349,8 @@ mod tests {
         serde_json_eq(EventType::PolicyRuleUser, json!("m.policy.rule.user"));
         serde_json_eq(EventType::Presence, json!("m.presence"));
         serde_json_eq(EventType::PushRules, json!("m.push_rules"));
+        #[cfg(feature = "unstable-pre-spec")]
+        serde_json_eq(EventType::Reaction, json!("m.reaction"));
         serde_json_eq(EventType::Receipt, json!("m.receipt"));
         serde_json_eq(EventType::RoomAliases, json!("m.room.aliases"));
         serde_json_eq(EventType::RoomAvatar, json!("m.room.avatar"));
@@ -329,6 +374,9 @@ mod tests {
         serde_json_eq(EventType::RoomTopic, json!("m.room.topic"));
         serde_json_eq(EventType::RoomKey, json!("m.room_key"));
         serde_json_eq(EventType::RoomKeyRequest, json!("m.room_key_request"));
+        serde_json_eq(EventType::SecretRequest, json!("m.secret.request"));
+        serde_json_eq(EventType::SecretSend, json!("m.secret.send"));
+        serde_json_eq(EventType::SecretStorageDefaultKey, json!("m.secret_storage.default_key"));
         serde_json_eq(EventType::Sticker, json!("m.sticker"));
         serde_json_eq(EventType::Tag, json!("m.tag"));
         serde_json_eq(EventType::Typing, json!("m.typing"));
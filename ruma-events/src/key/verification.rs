@@ -1,15 +1,27 @@
 //! Modules for events in the *m.key.verification* namespace.
 //!
 //! This module also contains types shared by events in its child namespaces.
+//!
+//! Verifications can be done with to-device messages, identified by their `transaction_id`, or,
+//! with the `unstable-pre-spec` feature, in a room. In-room verifications are started with an
+//! *m.room.message* of type *m.key.verification.request*, and all following messages reference it
+//! in their `m.relates_to` field. The contents of to-device messages are suffixed with
+//! `ToDeviceEventContent`.
 
 use serde::{Deserialize, Serialize};
 use strum::{Display, EnumString};
 
 pub mod accept;
 pub mod cancel;
+#[cfg(feature = "unstable-pre-spec")]
+pub mod done;
 pub mod key;
 pub mod mac;
+#[cfg(feature = "unstable-pre-spec")]
+pub mod ready;
 pub mod request;
+#[cfg(feature = "sas")]
+pub mod sas;
 pub mod start;
 
 /// A hash algorithm.
@@ -3,23 +3,28 @@
 use std::collections::BTreeMap;
 
 use ruma_events_macros::BasicEventContent;
+#[cfg(feature = "unstable-pre-spec")]
+use ruma_events_macros::MessageEventContent;
 use serde::{Deserialize, Serialize};
 use serde_json::Value as JsonValue;
 
 use super::{
     HashAlgorithm, KeyAgreementProtocol, MessageAuthenticationCode, ShortAuthenticationString,
 };
-use crate::BasicEvent;
+#[cfg(feature = "unstable-pre-spec")]
+use crate::{relation::Reference, MessageEvent};
 
 /// Accepts a previously sent *m.key.verification.start* message.
 ///
-/// Typically sent as a to-device event.
-pub type AcceptEvent = BasicEvent<AcceptEventContent>;
+/// This is the in-room version of the event, sent in the room of the verification. The
+/// content of the to-device version is `AcceptToDeviceEventContent`.
+#[cfg(feature = "unstable-pre-spec")]
+pub type AcceptEvent = MessageEvent<AcceptEventContent>;
 
-/// The payload for `AcceptEvent`.
+/// The payload of a to-device *m.key.verification.accept* event.
 #[derive(Clone, Debug, Deserialize, Serialize, BasicEventContent)]
 #[ruma_event(type = "m.key.verification.accept")]
-pub struct AcceptEventContent {
+pub struct AcceptToDeviceEventContent {
     /// An opaque identifier for the verification process.
     ///
     /// Must be the same as the one used for the *m.key.verification.start*
@@ -31,6 +36,29 @@ pub struct AcceptEventContent {
     pub method: AcceptMethod,
 }
 
+/// The payload of an in-room *m.key.verification.accept* event.
+#[cfg(feature = "unstable-pre-spec")]
+#[derive(Clone, Debug, Deserialize, Serialize, MessageEventContent)]
+#[cfg_attr(not(feature = "unstable-exhaustive-types"), non_exhaustive)]
+#[ruma_event(type = "m.key.verification.accept")]
+pub struct AcceptEventContent {
+    /// The method specific content.
+    #[serde(flatten)]
+    pub method: AcceptMethod,
+
+    /// Information about the verification request this message belongs to.
+    #[serde(rename = "m.relates_to")]
+    pub relates_to: Reference,
+}
+
+#[cfg(feature = "unstable-pre-spec")]
+impl AcceptEventContent {
+    /// Creates a new `AcceptEventContent` with the given method and relation.
+    pub fn new(method: AcceptMethod, relates_to: Reference) -> Self {
+        Self { method, relates_to }
+    }
+}
+
 /// An enum representing the different method specific
 /// *m.key.verification.accept* content.
 #[derive(Clone, Debug, Deserialize, Serialize)]
@@ -135,14 +163,17 @@ mod tests {
     };
 
     use super::{
-        AcceptEvent, AcceptEventContent, AcceptMethod, CustomContent, HashAlgorithm,
+        AcceptMethod, AcceptToDeviceEventContent, CustomContent, HashAlgorithm,
         KeyAgreementProtocol, MSasV1Content, MessageAuthenticationCode, ShortAuthenticationString,
     };
     use ruma_common::Raw;
+    use ruma_identifiers::user_id;
+
+    use crate::ToDeviceEvent;
 
     #[test]
     fn serialization() {
-        let key_verification_accept_content = AcceptEventContent {
+        let key_verification_accept_content = AcceptToDeviceEventContent {
             transaction_id: "456".into(),
             method: AcceptMethod::MSasV1(MSasV1Content {
                 hash: HashAlgorithm::Sha256,
@@ -153,7 +184,10 @@ mod tests {
             }),
         };
 
-        let key_verification_accept = AcceptEvent { content: key_verification_accept_content };
+        let key_verification_accept = ToDeviceEvent {
+            content: key_verification_accept_content,
+            sender: user_id!("@example:localhost"),
+        };
 
         let json_data = json!({
             "content": {
@@ -165,6 +199,7 @@ mod tests {
                 "message_authentication_code": "hkdf-hmac-sha256",
                 "short_authentication_string": ["decimal"]
             },
+            "sender": "@example:localhost",
             "type": "m.key.verification.accept"
         });
 
@@ -176,10 +211,11 @@ mod tests {
                 "method": "m.sas.custom",
                 "test": "field",
             },
+            "sender": "@example:localhost",
             "type": "m.key.verification.accept"
         });
 
-        let key_verification_accept_content = AcceptEventContent {
+        let key_verification_accept_content = AcceptToDeviceEventContent {
             transaction_id: "456".into(),
             method: AcceptMethod::Custom(CustomContent {
                 method: "m.sas.custom".to_owned(),
@@ -189,7 +225,10 @@ mod tests {
             }),
         };
 
-        let key_verification_accept = AcceptEvent { content: key_verification_accept_content };
+        let key_verification_accept = ToDeviceEvent {
+            content: key_verification_accept_content,
+            sender: user_id!("@example:localhost"),
+        };
 
         assert_eq!(to_json_value(&key_verification_accept).unwrap(), json_data);
     }
@@ -208,11 +247,11 @@ mod tests {
 
         // Deserialize the content struct separately to verify `TryFromRaw` is implemented for it.
         assert_matches!(
-            from_json_value::<Raw<AcceptEventContent>>(json)
+            from_json_value::<Raw<AcceptToDeviceEventContent>>(json)
                 .unwrap()
                 .deserialize()
                 .unwrap(),
-            AcceptEventContent {
+            AcceptToDeviceEventContent {
                 transaction_id,
                 method: AcceptMethod::MSasV1(MSasV1Content {
                     commitment,
@@ -239,16 +278,17 @@ mod tests {
                 "message_authentication_code": "hkdf-hmac-sha256",
                 "short_authentication_string": ["decimal"]
             },
+            "sender": "@example:localhost",
             "type": "m.key.verification.accept"
         });
 
         assert_matches!(
-            from_json_value::<Raw<AcceptEvent>>(json)
+            from_json_value::<Raw<ToDeviceEvent<AcceptToDeviceEventContent>>>(json)
                 .unwrap()
                 .deserialize()
                 .unwrap(),
-            AcceptEvent {
-                content: AcceptEventContent {
+            ToDeviceEvent {
+                content: AcceptToDeviceEventContent {
                     transaction_id,
                     method: AcceptMethod::MSasV1(MSasV1Content {
                         commitment,
@@ -257,8 +297,10 @@ mod tests {
                         message_authentication_code,
                         short_authentication_string,
                     })
-                }
-            } if commitment == "test_commitment"
+                },
+                sender,
+            } if sender == user_id!("@example:localhost")
+                && commitment == "test_commitment"
                 && transaction_id == "456"
                 && hash == HashAlgorithm::Sha256
                 && key_agreement_protocol == KeyAgreementProtocol::Curve25519
@@ -273,23 +315,26 @@ mod tests {
                 "method": "m.sas.custom",
                 "test": "field",
             },
+            "sender": "@example:localhost",
             "type": "m.key.verification.accept"
         });
 
         assert_matches!(
-            from_json_value::<Raw<AcceptEvent>>(json)
+            from_json_value::<Raw<ToDeviceEvent<AcceptToDeviceEventContent>>>(json)
                 .unwrap()
                 .deserialize()
                 .unwrap(),
-            AcceptEvent {
-                content: AcceptEventContent {
+            ToDeviceEvent {
+                content: AcceptToDeviceEventContent {
                     transaction_id,
                     method: AcceptMethod::Custom(CustomContent {
                         method,
                         fields,
                     })
-                }
-            } if transaction_id == "456"
+                },
+                sender,
+            } if sender == user_id!("@example:localhost")
+                && transaction_id == "456"
                 && method == "m.sas.custom"
                 && fields.get("test").unwrap() == &JsonValue::from("field")
         );
@@ -3,19 +3,24 @@
 use std::fmt::{Display, Formatter, Result as FmtResult};
 
 use ruma_events_macros::BasicEventContent;
+#[cfg(feature = "unstable-pre-spec")]
+use ruma_events_macros::MessageEventContent;
 use serde::{Deserialize, Serialize};
 
-use crate::BasicEvent;
+#[cfg(feature = "unstable-pre-spec")]
+use crate::{relation::Reference, MessageEvent};
 
 /// Cancels a key verification process/request.
 ///
-/// Typically sent as a to-device event.
-pub type CancelEvent = BasicEvent<CancelEventContent>;
+/// This is the in-room version of the event, sent in the room of the verification. The
+/// content of the to-device version is `CancelToDeviceEventContent`.
+#[cfg(feature = "unstable-pre-spec")]
+pub type CancelEvent = MessageEvent<CancelEventContent>;
 
-/// The payload for `CancelEvent`.
+/// The payload of a to-device *m.key.verification.cancel* event.
 #[derive(Clone, Debug, Deserialize, Serialize, BasicEventContent)]
 #[ruma_event(type = "m.key.verification.cancel")]
-pub struct CancelEventContent {
+pub struct CancelToDeviceEventContent {
     /// The opaque identifier for the verification process/request.
     pub transaction_id: String,
 
@@ -28,6 +33,33 @@ pub struct CancelEventContent {
     pub code: CancelCode,
 }
 
+/// The payload of an in-room *m.key.verification.cancel* event.
+#[cfg(feature = "unstable-pre-spec")]
+#[derive(Clone, Debug, Deserialize, Serialize, MessageEventContent)]
+#[cfg_attr(not(feature = "unstable-exhaustive-types"), non_exhaustive)]
+#[ruma_event(type = "m.key.verification.cancel")]
+pub struct CancelEventContent {
+    /// A human readable description of the `code`.
+    ///
+    /// The client should only rely on this string if it does not understand the `code`.
+    pub reason: String,
+
+    /// The error code for why the process/request was cancelled by the user.
+    pub code: CancelCode,
+
+    /// Information about the verification request this message belongs to.
+    #[serde(rename = "m.relates_to")]
+    pub relates_to: Reference,
+}
+
+#[cfg(feature = "unstable-pre-spec")]
+impl CancelEventContent {
+    /// Creates a new `CancelEventContent` with the given reason, code and relation.
+    pub fn new(reason: String, code: CancelCode, relates_to: Reference) -> Self {
+        Self { reason, code, relates_to }
+    }
+}
+
 /// An error code for why the process/request was cancelled by the user.
 ///
 /// Custom error codes should use the Java package naming convention.
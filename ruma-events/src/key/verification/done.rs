@@ -0,0 +1,98 @@
+//! Types for the *m.key.verification.done* event.
+
+use ruma_events_macros::{BasicEventContent, MessageEventContent};
+use serde::{Deserialize, Serialize};
+
+use crate::{relation::Reference, MessageEvent};
+
+/// Signals that a key verification was completed successfully.
+///
+/// This is the in-room version of the event, sent in the room of the verification. The
+/// content of the to-device version is `DoneToDeviceEventContent`.
+pub type DoneEvent = MessageEvent<DoneEventContent>;
+
+/// The payload of a to-device *m.key.verification.done* event.
+#[derive(Clone, Debug, Deserialize, Serialize, BasicEventContent)]
+#[cfg_attr(not(feature = "unstable-exhaustive-types"), non_exhaustive)]
+#[ruma_event(type = "m.key.verification.done")]
+pub struct DoneToDeviceEventContent {
+    /// An opaque identifier for the verification process.
+    ///
+    /// Must be the same as the one used for the *m.key.verification.start* message.
+    pub transaction_id: String,
+}
+
+impl DoneToDeviceEventContent {
+    /// Creates a new `DoneToDeviceEventContent` with the given transaction ID.
+    pub fn new(transaction_id: String) -> Self {
+        Self { transaction_id }
+    }
+}
+
+/// The payload of an in-room *m.key.verification.done* event.
+#[derive(Clone, Debug, Deserialize, Serialize, MessageEventContent)]
+#[cfg_attr(not(feature = "unstable-exhaustive-types"), non_exhaustive)]
+#[ruma_event(type = "m.key.verification.done")]
+pub struct DoneEventContent {
+    /// Information about the verification request this message belongs to.
+    #[serde(rename = "m.relates_to")]
+    pub relates_to: Reference,
+}
+
+impl DoneEventContent {
+    /// Creates a new `DoneEventContent` with the given reference to the request message.
+    pub fn new(relates_to: Reference) -> Self {
+        Self { relates_to }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use matches::assert_matches;
+    use ruma_identifiers::event_id;
+    use serde_json::{from_value as from_json_value, json, to_value as to_json_value};
+
+    use super::{DoneEventContent, DoneToDeviceEventContent};
+    use crate::relation::Reference;
+
+    #[test]
+    fn serialization() {
+        let content = DoneEventContent::new(Reference::new(event_id!("$request:example.org")));
+
+        assert_eq!(
+            to_json_value(&content).unwrap(),
+            json!({
+                "m.relates_to": {
+                    "rel_type": "m.reference",
+                    "event_id": "$request:example.org",
+                },
+            })
+        );
+
+        assert_eq!(
+            to_json_value(DoneToDeviceEventContent::new("456".into())).unwrap(),
+            json!({ "transaction_id": "456" })
+        );
+    }
+
+    #[test]
+    fn deserialization() {
+        let json_data = json!({
+            "m.relates_to": {
+                "rel_type": "m.reference",
+                "event_id": "$request:example.org",
+            },
+        });
+
+        assert_matches!(
+            from_json_value::<DoneEventContent>(json_data).unwrap(),
+            DoneEventContent { relates_to: Reference { event_id }, .. }
+                if event_id == event_id!("$request:example.org")
+        );
+
+        assert_matches!(
+            from_json_value::<DoneToDeviceEventContent>(json!({ "transaction_id": "456" })).unwrap(),
+            DoneToDeviceEventContent { transaction_id, .. } if transaction_id == "456"
+        );
+    }
+}
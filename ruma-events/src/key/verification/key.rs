@@ -1,19 +1,24 @@
 //! Types for the *m.key.verification.key* event.
 
 use ruma_events_macros::BasicEventContent;
+#[cfg(feature = "unstable-pre-spec")]
+use ruma_events_macros::MessageEventContent;
 use serde::{Deserialize, Serialize};
 
-use crate::BasicEvent;
+#[cfg(feature = "unstable-pre-spec")]
+use crate::{relation::Reference, MessageEvent};
 
 /// Sends the ephemeral public key for a device to the partner device.
 ///
-/// Typically sent as a to-device event.
-pub type KeyEvent = BasicEvent<KeyEventContent>;
+/// This is the in-room version of the event, sent in the room of the verification. The
+/// content of the to-device version is `KeyToDeviceEventContent`.
+#[cfg(feature = "unstable-pre-spec")]
+pub type KeyEvent = MessageEvent<KeyEventContent>;
 
-/// The payload for `KeyEvent`.
+/// The payload of a to-device *m.key.verification.key* event.
 #[derive(Clone, Debug, Deserialize, Serialize, BasicEventContent)]
 #[ruma_event(type = "m.key.verification.key")]
-pub struct KeyEventContent {
+pub struct KeyToDeviceEventContent {
     /// An opaque identifier for the verification process.
     ///
     /// Must be the same as the one used for the *m.key.verification.start* message.
@@ -22,3 +27,25 @@ pub struct KeyEventContent {
     /// The device's ephemeral public key, encoded as unpadded Base64.
     pub key: String,
 }
+
+/// The payload of an in-room *m.key.verification.key* event.
+#[cfg(feature = "unstable-pre-spec")]
+#[derive(Clone, Debug, Deserialize, Serialize, MessageEventContent)]
+#[cfg_attr(not(feature = "unstable-exhaustive-types"), non_exhaustive)]
+#[ruma_event(type = "m.key.verification.key")]
+pub struct KeyEventContent {
+    /// The device's ephemeral public key, encoded as unpadded Base64.
+    pub key: String,
+
+    /// Information about the verification request this message belongs to.
+    #[serde(rename = "m.relates_to")]
+    pub relates_to: Reference,
+}
+
+#[cfg(feature = "unstable-pre-spec")]
+impl KeyEventContent {
+    /// Creates a new `KeyEventContent` with the given key and relation.
+    pub fn new(key: String, relates_to: Reference) -> Self {
+        Self { key, relates_to }
+    }
+}
@@ -3,19 +3,24 @@
 use std::collections::BTreeMap;
 
 use ruma_events_macros::BasicEventContent;
+#[cfg(feature = "unstable-pre-spec")]
+use ruma_events_macros::MessageEventContent;
 use serde::{Deserialize, Serialize};
 
-use crate::BasicEvent;
+#[cfg(feature = "unstable-pre-spec")]
+use crate::{relation::Reference, MessageEvent};
 
 /// Sends the MAC of a device's key to the partner device.
 ///
-/// Typically sent as a to-device event.
-pub type MacEvent = BasicEvent<MacEventContent>;
+/// This is the in-room version of the event, sent in the room of the verification. The
+/// content of the to-device version is `MacToDeviceEventContent`.
+#[cfg(feature = "unstable-pre-spec")]
+pub type MacEvent = MessageEvent<MacEventContent>;
 
-/// The payload for `MacEvent`.
+/// The payload of a to-device *m.key.verification.mac* event.
 #[derive(Clone, Debug, Deserialize, Serialize, BasicEventContent)]
 #[ruma_event(type = "m.key.verification.mac")]
-pub struct MacEventContent {
+pub struct MacToDeviceEventContent {
     /// An opaque identifier for the verification process.
     ///
     /// Must be the same as the one used for the *m.key.verification.start* message.
@@ -30,3 +35,31 @@ pub struct MacEventContent {
     /// as unpadded Base64.
     pub keys: String,
 }
+
+/// The payload of an in-room *m.key.verification.mac* event.
+#[cfg(feature = "unstable-pre-spec")]
+#[derive(Clone, Debug, Deserialize, Serialize, MessageEventContent)]
+#[cfg_attr(not(feature = "unstable-exhaustive-types"), non_exhaustive)]
+#[ruma_event(type = "m.key.verification.mac")]
+pub struct MacEventContent {
+    /// A map of the key ID to the MAC of the key, using the algorithm in the verification process.
+    ///
+    /// The MAC is encoded as unpadded Base64.
+    pub mac: BTreeMap<String, String>,
+
+    /// The MAC of the comma-separated, sorted, list of key IDs given in the `mac` property, encoded
+    /// as unpadded Base64.
+    pub keys: String,
+
+    /// Information about the verification request this message belongs to.
+    #[serde(rename = "m.relates_to")]
+    pub relates_to: Reference,
+}
+
+#[cfg(feature = "unstable-pre-spec")]
+impl MacEventContent {
+    /// Creates a new `MacEventContent` with the given MACs and relation.
+    pub fn new(mac: BTreeMap<String, String>, keys: String, relates_to: Reference) -> Self {
+        Self { mac, keys, relates_to }
+    }
+}
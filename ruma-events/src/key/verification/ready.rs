@@ -0,0 +1,154 @@
+//! Types for the *m.key.verification.ready* event.
+
+use ruma_events_macros::{BasicEventContent, MessageEventContent};
+use ruma_identifiers::DeviceIdBox;
+use serde::{Deserialize, Serialize};
+
+use super::VerificationMethod;
+use crate::{relation::Reference, MessageEvent};
+
+/// Accepts a key verification request.
+///
+/// This is the in-room version of the event, sent in the room of the verification. The
+/// content of the to-device version is `ReadyToDeviceEventContent`.
+pub type ReadyEvent = MessageEvent<ReadyEventContent>;
+
+/// The payload of a to-device *m.key.verification.ready* event.
+#[derive(Clone, Debug, Deserialize, Serialize, BasicEventContent)]
+#[cfg_attr(not(feature = "unstable-exhaustive-types"), non_exhaustive)]
+#[ruma_event(type = "m.key.verification.ready")]
+pub struct ReadyToDeviceEventContent {
+    /// The device ID which is accepting the request.
+    pub from_device: DeviceIdBox,
+
+    /// The verification methods supported by the sender.
+    pub methods: Vec<VerificationMethod>,
+
+    /// An opaque identifier for the verification process.
+    ///
+    /// Must be the same as the one used for the *m.key.verification.request* message.
+    pub transaction_id: String,
+}
+
+impl ReadyToDeviceEventContent {
+    /// Creates a new `ReadyToDeviceEventContent` with the given device ID, methods and
+    /// transaction ID.
+    pub fn new(
+        from_device: DeviceIdBox,
+        methods: Vec<VerificationMethod>,
+        transaction_id: String,
+    ) -> Self {
+        Self { from_device, methods, transaction_id }
+    }
+}
+
+/// The payload of an in-room *m.key.verification.ready* event.
+#[derive(Clone, Debug, Deserialize, Serialize, MessageEventContent)]
+#[cfg_attr(not(feature = "unstable-exhaustive-types"), non_exhaustive)]
+#[ruma_event(type = "m.key.verification.ready")]
+pub struct ReadyEventContent {
+    /// The device ID which is accepting the request.
+    pub from_device: DeviceIdBox,
+
+    /// The verification methods supported by the sender.
+    pub methods: Vec<VerificationMethod>,
+
+    /// Information about the verification request this message belongs to.
+    #[serde(rename = "m.relates_to")]
+    pub relates_to: Reference,
+}
+
+impl ReadyEventContent {
+    /// Creates a new `ReadyEventContent` with the given device ID, methods and relation.
+    pub fn new(
+        from_device: DeviceIdBox,
+        methods: Vec<VerificationMethod>,
+        relates_to: Reference,
+    ) -> Self {
+        Self { from_device, methods, relates_to }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use matches::assert_matches;
+    use ruma_identifiers::event_id;
+    use serde_json::{from_value as from_json_value, json, to_value as to_json_value};
+
+    use super::{ReadyEventContent, ReadyToDeviceEventContent};
+    use crate::{key::verification::VerificationMethod, relation::Reference};
+
+    #[test]
+    fn serialization() {
+        let content = ReadyEventContent::new(
+            "123".into(),
+            vec![VerificationMethod::MSasV1],
+            Reference::new(event_id!("$request:example.org")),
+        );
+
+        assert_eq!(
+            to_json_value(&content).unwrap(),
+            json!({
+                "from_device": "123",
+                "methods": ["m.sas.v1"],
+                "m.relates_to": {
+                    "rel_type": "m.reference",
+                    "event_id": "$request:example.org",
+                },
+            })
+        );
+
+        let content = ReadyToDeviceEventContent::new(
+            "123".into(),
+            vec![VerificationMethod::MSasV1],
+            "456".into(),
+        );
+
+        assert_eq!(
+            to_json_value(&content).unwrap(),
+            json!({
+                "from_device": "123",
+                "methods": ["m.sas.v1"],
+                "transaction_id": "456",
+            })
+        );
+    }
+
+    #[test]
+    fn deserialization() {
+        let json_data = json!({
+            "from_device": "123",
+            "methods": ["m.sas.v1"],
+            "m.relates_to": {
+                "rel_type": "m.reference",
+                "event_id": "$request:example.org",
+            },
+        });
+
+        assert_matches!(
+            from_json_value::<ReadyEventContent>(json_data).unwrap(),
+            ReadyEventContent {
+                from_device,
+                methods,
+                relates_to: Reference { event_id },
+                ..
+            } if from_device == "123"
+                && methods == [VerificationMethod::MSasV1]
+                && event_id == event_id!("$request:example.org")
+        );
+
+        let json_data = json!({
+            "from_device": "123",
+            "methods": ["m.sas.v1"],
+            "transaction_id": "456",
+        });
+
+        assert_matches!(
+            from_json_value::<ReadyToDeviceEventContent>(json_data).unwrap(),
+            ReadyToDeviceEventContent { from_device, methods, transaction_id, .. }
+                if from_device == "123"
+                    && methods == [VerificationMethod::MSasV1]
+                    && transaction_id == "456"
+        );
+    }
+}
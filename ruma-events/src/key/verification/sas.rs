@@ -0,0 +1,917 @@
+//! A transport-agnostic state machine for the
+//! [SAS verification](https://matrix.org/docs/spec/client_server/r0.6.1#short-authentication-string-sas-verification)
+//! method *m.sas.v1*.
+//!
+//! [`Sas`] consumes the contents of the *m.key.verification.\** events received from the other
+//! device and produces the contents that have to be sent to it; how they are sent is up to the
+//! user of this module.
+//!
+//! Only to-device verifications, identified by their transaction ID, are supported. In-room
+//! verifications, whose messages reference the *m.key.verification.request* message instead,
+//! can't be driven by [`Sas`] yet.
+//!
+//! This module is only available with the `sas` feature.
+
+use std::{collections::BTreeMap, convert::TryFrom, fmt, mem};
+
+use hkdf::Hkdf;
+use hmac::{Hmac, Mac, NewMac};
+use rand_core::OsRng;
+use ruma_identifiers::{DeviceIdBox, UserId};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use x25519_dalek::{EphemeralSecret, PublicKey};
+
+use super::{
+    accept::{self, AcceptMethod, AcceptToDeviceEventContent},
+    cancel::{CancelCode, CancelToDeviceEventContent},
+    key::KeyToDeviceEventContent,
+    mac::MacToDeviceEventContent,
+    start::{self, StartMethod, StartToDeviceEventContent},
+    HashAlgorithm, KeyAgreementProtocol, MessageAuthenticationCode, ShortAuthenticationString,
+};
+
+mod emoji;
+
+pub use emoji::Emoji;
+
+/// A device taking part in a SAS verification.
+#[derive(Clone, Debug)]
+pub struct SasDevice {
+    /// The user the device belongs to.
+    pub user_id: UserId,
+
+    /// The ID of the device.
+    pub device_id: DeviceIdBox,
+
+    /// The keys of the device that are verified by the verification, as a map from key ID (e.g.
+    /// `ed25519:DEVICEID`) to the unpadded base64 encoded key.
+    ///
+    /// For our own device, these are the keys the MACs are sent for. For the other device, these
+    /// are the keys the received MACs are checked against.
+    pub keys: BTreeMap<String, String>,
+}
+
+impl SasDevice {
+    /// Creates a new `SasDevice` with the given user ID, device ID and keys.
+    pub fn new(user_id: UserId, device_id: DeviceIdBox, keys: BTreeMap<String, String>) -> Self {
+        Self { user_id, device_id, keys }
+    }
+}
+
+/// A SAS verification between our own device and another device.
+///
+/// The device that sends the *m.key.verification.start* message creates the verification with
+/// [`Sas::start`], the other device with [`Sas::from_start`]. When a received message violates the
+/// protocol, the verification is cancelled and the method that received it returns the
+/// *m.key.verification.cancel* content that should be sent to the other device.
+#[derive(Debug)]
+pub struct Sas {
+    own: SasDevice,
+    other: SasDevice,
+    transaction_id: String,
+    we_started: bool,
+    start_json: String,
+    state: State,
+}
+
+#[derive(Debug)]
+enum State {
+    /// We sent the start message and wait for the other device to accept it.
+    Started { private_key: PrivateKey, public_key: String },
+
+    /// We received a start message that can be accepted.
+    Received { private_key: PrivateKey, public_key: String, methods: AgreedMethods },
+
+    /// We accepted the start message and wait for the key of the other device.
+    Accepted { private_key: PrivateKey, public_key: String, methods: AgreedMethods },
+
+    /// Our start message was accepted and we sent our key, the other device's key is outstanding.
+    KeySent {
+        private_key: PrivateKey,
+        public_key: String,
+        methods: AgreedMethods,
+        commitment: String,
+    },
+
+    /// Both keys were exchanged, the short authentication string can be presented to the user.
+    KeysExchanged {
+        secret: SharedSecret,
+        methods: AgreedMethods,
+        confirmed: bool,
+        verified_keys: Option<Vec<String>>,
+    },
+
+    /// The user confirmed the short authentication string and the MAC of the other device was
+    /// valid.
+    Done { verified_keys: Vec<String> },
+
+    /// The verification was cancelled by either device.
+    Cancelled(CancelCode),
+}
+
+/// Our ephemeral Curve25519 private key.
+struct PrivateKey(EphemeralSecret);
+
+impl fmt::Debug for PrivateKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("PrivateKey(..)")
+    }
+}
+
+/// The methods both devices agreed on.
+#[derive(Clone, Debug)]
+struct AgreedMethods {
+    key_agreement_protocol: KeyAgreementProtocol,
+    short_authentication_string: Vec<ShortAuthenticationString>,
+}
+
+/// The result of the key agreement.
+#[derive(Debug)]
+struct SharedSecret {
+    secret: Vec<u8>,
+    own_public_key: String,
+    other_public_key: String,
+}
+
+impl Sas {
+    /// Starts a new verification with the other device as the initiating device.
+    ///
+    /// Returns the verification and the *m.key.verification.start* content that has to be sent to
+    /// the other device.
+    pub fn start(
+        own: SasDevice,
+        other: SasDevice,
+        transaction_id: String,
+    ) -> (Self, StartToDeviceEventContent) {
+        let content = StartToDeviceEventContent {
+            from_device: own.device_id.clone(),
+            transaction_id: transaction_id.clone(),
+            method: StartMethod::MSasV1(start::MSasV1Content {
+                key_agreement_protocols: SUPPORTED_KEY_AGREEMENT_PROTOCOLS.to_vec(),
+                hashes: vec![HashAlgorithm::Sha256],
+                message_authentication_codes: vec![MessageAuthenticationCode::HkdfHmacSha256],
+                short_authentication_string: SUPPORTED_SAS_METHODS.to_vec(),
+            }),
+        };
+        let (private_key, public_key) = generate_key_pair();
+
+        let sas = Self {
+            own,
+            other,
+            transaction_id,
+            we_started: true,
+            start_json: canonical_json(&content),
+            state: State::Started { private_key, public_key },
+        };
+
+        (sas, content)
+    }
+
+    /// Creates a new verification from the *m.key.verification.start* content sent by the other
+    /// device.
+    ///
+    /// The verification can be accepted with [`Sas::accept`]. Returns the content of the
+    /// *m.key.verification.cancel* message to send if the start message can't be accepted.
+    pub fn from_start(
+        own: SasDevice,
+        other: SasDevice,
+        content: &StartToDeviceEventContent,
+    ) -> Result<Self, CancelToDeviceEventContent> {
+        let cancel = |code| cancel_content(content.transaction_id.clone(), code);
+
+        if content.from_device != other.device_id {
+            return Err(cancel(CancelCode::InvalidMessage));
+        }
+
+        let start = match &content.method {
+            StartMethod::MSasV1(start) => start,
+            _ => return Err(cancel(CancelCode::UnknownMethod)),
+        };
+
+        let key_agreement_protocol = match SUPPORTED_KEY_AGREEMENT_PROTOCOLS
+            .iter()
+            .rev()
+            .find(|protocol| start.key_agreement_protocols.contains(*protocol))
+        {
+            Some(protocol) => *protocol,
+            None => return Err(cancel(CancelCode::UnknownMethod)),
+        };
+        let short_authentication_string: Vec<_> = SUPPORTED_SAS_METHODS
+            .iter()
+            .filter(|method| start.short_authentication_string.contains(*method))
+            .copied()
+            .collect();
+
+        if !start.hashes.contains(&HashAlgorithm::Sha256)
+            || !start
+                .message_authentication_codes
+                .contains(&MessageAuthenticationCode::HkdfHmacSha256)
+            || short_authentication_string.is_empty()
+        {
+            return Err(cancel(CancelCode::UnknownMethod));
+        }
+
+        let (private_key, public_key) = generate_key_pair();
+
+        Ok(Self {
+            own,
+            other,
+            transaction_id: content.transaction_id.clone(),
+            we_started: false,
+            start_json: canonical_json(content),
+            state: State::Received {
+                private_key,
+                public_key,
+                methods: AgreedMethods { key_agreement_protocol, short_authentication_string },
+            },
+        })
+    }
+
+    /// The transaction ID of this verification.
+    pub fn transaction_id(&self) -> &str {
+        &self.transaction_id
+    }
+
+    /// Whether we sent the *m.key.verification.start* message of this verification.
+    pub fn we_started(&self) -> bool {
+        self.we_started
+    }
+
+    /// The device this verification is with.
+    pub fn other_device(&self) -> &SasDevice {
+        &self.other
+    }
+
+    /// Accepts a verification that was created with [`Sas::from_start`].
+    ///
+    /// Returns the *m.key.verification.accept* content that has to be sent to the other device, or
+    /// `None` if the verification is not waiting to be accepted.
+    pub fn accept(&mut self) -> Option<AcceptToDeviceEventContent> {
+        let (private_key, public_key, methods) =
+            match mem::replace(&mut self.state, State::Cancelled(CancelCode::UnexpectedMessage)) {
+                State::Received { private_key, public_key, methods } => {
+                    (private_key, public_key, methods)
+                }
+                state => {
+                    self.state = state;
+                    return None;
+                }
+            };
+
+        let content = AcceptToDeviceEventContent {
+            transaction_id: self.transaction_id.clone(),
+            method: AcceptMethod::MSasV1(accept::MSasV1Content {
+                key_agreement_protocol: methods.key_agreement_protocol,
+                hash: HashAlgorithm::Sha256,
+                message_authentication_code: MessageAuthenticationCode::HkdfHmacSha256,
+                short_authentication_string: methods.short_authentication_string.clone(),
+                commitment: commitment(&public_key, &self.start_json),
+            }),
+        };
+        self.state = State::Accepted { private_key, public_key, methods };
+
+        Some(content)
+    }
+
+    /// Handles the *m.key.verification.accept* content sent by the other device in response to our
+    /// start message.
+    ///
+    /// Returns the *m.key.verification.key* content that has to be sent to the other device.
+    pub fn receive_accept(
+        &mut self,
+        content: &AcceptToDeviceEventContent,
+    ) -> Result<KeyToDeviceEventContent, CancelToDeviceEventContent> {
+        self.check_transaction_id(&content.transaction_id)?;
+
+        let (private_key, public_key) =
+            match mem::replace(&mut self.state, State::Cancelled(CancelCode::UnexpectedMessage)) {
+                State::Started { private_key, public_key } => (private_key, public_key),
+                _ => return Err(self.cancel(CancelCode::UnexpectedMessage)),
+            };
+
+        let accept = match &content.method {
+            AcceptMethod::MSasV1(accept) => accept,
+            _ => return Err(self.cancel(CancelCode::UnknownMethod)),
+        };
+
+        if !SUPPORTED_KEY_AGREEMENT_PROTOCOLS.contains(&accept.key_agreement_protocol)
+            || accept.hash != HashAlgorithm::Sha256
+            || accept.message_authentication_code != MessageAuthenticationCode::HkdfHmacSha256
+            || accept.short_authentication_string.is_empty()
+            || !accept
+                .short_authentication_string
+                .iter()
+                .all(|method| SUPPORTED_SAS_METHODS.contains(method))
+        {
+            return Err(self.cancel(CancelCode::UnknownMethod));
+        }
+
+        let key = KeyToDeviceEventContent {
+            transaction_id: self.transaction_id.clone(),
+            key: public_key.clone(),
+        };
+        self.state = State::KeySent {
+            private_key,
+            public_key,
+            methods: AgreedMethods {
+                key_agreement_protocol: accept.key_agreement_protocol,
+                short_authentication_string: accept.short_authentication_string.clone(),
+            },
+            commitment: accept.commitment.clone(),
+        };
+
+        Ok(key)
+    }
+
+    /// Handles the *m.key.verification.key* content sent by the other device.
+    ///
+    /// If we accepted the verification, returns the *m.key.verification.key* content that has to
+    /// be sent to the other device in response. Afterwards, the short authentication string can be
+    /// presented to the user.
+    pub fn receive_key(
+        &mut self,
+        content: &KeyToDeviceEventContent,
+    ) -> Result<Option<KeyToDeviceEventContent>, CancelToDeviceEventContent> {
+        self.check_transaction_id(&content.transaction_id)?;
+
+        let (private_key, public_key, methods, expected_commitment) =
+            match mem::replace(&mut self.state, State::Cancelled(CancelCode::UnexpectedMessage)) {
+                State::Accepted { private_key, public_key, methods } => {
+                    (private_key, public_key, methods, None)
+                }
+                State::KeySent { private_key, public_key, methods, commitment } => {
+                    (private_key, public_key, methods, Some(commitment))
+                }
+                _ => return Err(self.cancel(CancelCode::UnexpectedMessage)),
+            };
+
+        if let Some(expected_commitment) = expected_commitment {
+            if commitment(&content.key, &self.start_json) != expected_commitment {
+                return Err(self.cancel(CancelCode::KeyMismatch));
+            }
+        }
+
+        let other_public_key = match base64::decode_config(&content.key, base64::STANDARD_NO_PAD)
+            .ok()
+            .and_then(|key| <[u8; 32]>::try_from(key.as_slice()).ok())
+        {
+            Some(key) => PublicKey::from(key),
+            None => return Err(self.cancel(CancelCode::InvalidMessage)),
+        };
+        let secret = private_key.0.diffie_hellman(&other_public_key).as_bytes().to_vec();
+        // Low-order public keys result in an all-zero secret that the other device doesn't
+        // contribute to.
+        if secret.iter().all(|&b| b == 0) {
+            return Err(self.cancel(CancelCode::InvalidMessage));
+        }
+
+        let response = if self.we_started {
+            None
+        } else {
+            Some(KeyToDeviceEventContent {
+                transaction_id: self.transaction_id.clone(),
+                key: public_key.clone(),
+            })
+        };
+        self.state = State::KeysExchanged {
+            secret: SharedSecret {
+                secret,
+                own_public_key: public_key,
+                other_public_key: content.key.clone(),
+            },
+            methods,
+            confirmed: false,
+            verified_keys: None,
+        };
+
+        Ok(response)
+    }
+
+    /// The short authentication string as seven emoji, if the keys have been exchanged and both
+    /// devices support the emoji method.
+    pub fn emoji(&self) -> Option<[Emoji; 7]> {
+        let (secret, methods) = self.shared_secret()?;
+        if !methods.short_authentication_string.contains(&ShortAuthenticationString::Emoji) {
+            return None;
+        }
+
+        Some(emoji::from_bytes(&self.sas_bytes(secret, methods, 6)))
+    }
+
+    /// The short authentication string as three numbers between 1000 and 9191, if the keys have
+    /// been exchanged.
+    pub fn decimals(&self) -> Option<(u16, u16, u16)> {
+        let (secret, methods) = self.shared_secret()?;
+        Some(decimals_from_bytes(&self.sas_bytes(secret, methods, 5)))
+    }
+
+    /// Confirms that the short authentication strings shown on both devices match.
+    ///
+    /// Returns the *m.key.verification.mac* content that has to be sent to the other device, or
+    /// `None` if the keys have not been exchanged yet or the verification was already confirmed.
+    pub fn confirm(&mut self) -> Option<MacToDeviceEventContent> {
+        let content = match &self.state {
+            State::KeysExchanged { secret, confirmed: false, .. } => {
+                let mac = self
+                    .own
+                    .keys
+                    .iter()
+                    .map(|(key_id, key)| {
+                        (key_id.clone(), self.mac(secret, &self.own, &self.other, key_id, key))
+                    })
+                    .collect::<BTreeMap<_, _>>();
+                let keys = self.mac(secret, &self.own, &self.other, "KEY_IDS", &key_ids(&mac));
+
+                MacToDeviceEventContent { transaction_id: self.transaction_id.clone(), mac, keys }
+            }
+            _ => return None,
+        };
+
+        match &mut self.state {
+            State::KeysExchanged { verified_keys: Some(verified_keys), .. } => {
+                self.state = State::Done { verified_keys: mem::take(verified_keys) };
+            }
+            State::KeysExchanged { confirmed, .. } => *confirmed = true,
+            _ => unreachable!(),
+        }
+
+        Some(content)
+    }
+
+    /// Handles the *m.key.verification.mac* content sent by the other device.
+    ///
+    /// The MACs are checked against the keys of the other device given when creating the
+    /// verification. MACs for keys that are not known are ignored. Once the MACs are valid and
+    /// the user has confirmed the short authentication string, the verification is done.
+    pub fn receive_mac(
+        &mut self,
+        content: &MacToDeviceEventContent,
+    ) -> Result<(), CancelToDeviceEventContent> {
+        self.check_transaction_id(&content.transaction_id)?;
+
+        let verified_keys = match &self.state {
+            State::KeysExchanged { secret, verified_keys: None, .. } => {
+                self.verify_macs(secret, content)
+            }
+            _ => Err(CancelCode::UnexpectedMessage),
+        }
+        .map_err(|code| self.cancel(code))?;
+
+        match &mut self.state {
+            State::KeysExchanged { confirmed: true, .. } => {
+                self.state = State::Done { verified_keys };
+            }
+            State::KeysExchanged { verified_keys: keys, .. } => *keys = Some(verified_keys),
+            _ => unreachable!(),
+        }
+
+        Ok(())
+    }
+
+    /// Handles the *m.key.verification.cancel* content sent by the other device.
+    ///
+    /// Cancel messages for other transactions are ignored.
+    pub fn receive_cancel(&mut self, content: &CancelToDeviceEventContent) {
+        if content.transaction_id == self.transaction_id {
+            self.state = State::Cancelled(content.code.clone());
+        }
+    }
+
+    /// Cancels the verification, e.g. with `CancelCode::User` if the user aborted it or with
+    /// `CancelCode::KeyMismatch` if the short authentication strings don't match.
+    ///
+    /// Returns the *m.key.verification.cancel* content that has to be sent to the other device.
+    pub fn cancel(&mut self, code: CancelCode) -> CancelToDeviceEventContent {
+        self.state = State::Cancelled(code.clone());
+        cancel_content(self.transaction_id.clone(), code)
+    }
+
+    /// Whether the verification completed successfully.
+    pub fn is_done(&self) -> bool {
+        matches!(self.state, State::Done { .. })
+    }
+
+    /// Whether the verification was cancelled by either device.
+    pub fn is_cancelled(&self) -> bool {
+        matches!(self.state, State::Cancelled(_))
+    }
+
+    /// The code the verification was cancelled with, if it was cancelled.
+    pub fn cancel_code(&self) -> Option<&CancelCode> {
+        match &self.state {
+            State::Cancelled(code) => Some(code),
+            _ => None,
+        }
+    }
+
+    /// The IDs of the keys of the other device that were verified, once the verification is done.
+    pub fn verified_keys(&self) -> Option<&[String]> {
+        match &self.state {
+            State::Done { verified_keys } => Some(verified_keys),
+            _ => None,
+        }
+    }
+
+    /// Checks the MACs sent by the other device, returning the IDs of the verified keys.
+    fn verify_macs(
+        &self,
+        secret: &SharedSecret,
+        content: &MacToDeviceEventContent,
+    ) -> Result<Vec<String>, CancelCode> {
+        if !self.mac_is_valid(secret, "KEY_IDS", &key_ids(&content.mac), &content.keys) {
+            return Err(CancelCode::KeyMismatch);
+        }
+
+        let mut verified_keys = Vec::new();
+        for (key_id, mac) in &content.mac {
+            let key = match self.other.keys.get(key_id) {
+                Some(key) => key,
+                None => continue,
+            };
+
+            if !self.mac_is_valid(secret, key_id, key, mac) {
+                return Err(CancelCode::KeyMismatch);
+            }
+            verified_keys.push(key_id.clone());
+        }
+
+        if verified_keys.is_empty() {
+            Err(CancelCode::KeyMismatch)
+        } else {
+            Ok(verified_keys)
+        }
+    }
+
+    fn check_transaction_id(&self, transaction_id: &str) -> Result<(), CancelToDeviceEventContent> {
+        if transaction_id == self.transaction_id {
+            Ok(())
+        } else {
+            Err(cancel_content(transaction_id.to_owned(), CancelCode::UnknownTransaction))
+        }
+    }
+
+    fn shared_secret(&self) -> Option<(&SharedSecret, &AgreedMethods)> {
+        match &self.state {
+            State::KeysExchanged { secret, methods, .. } => Some((secret, methods)),
+            _ => None,
+        }
+    }
+
+    /// Derives `len` bytes for the short authentication string from the shared secret.
+    fn sas_bytes(&self, secret: &SharedSecret, methods: &AgreedMethods, len: usize) -> Vec<u8> {
+        let (start_device, start_key, accept_device, accept_key) = if self.we_started {
+            (&self.own, &secret.own_public_key, &self.other, &secret.other_public_key)
+        } else {
+            (&self.other, &secret.other_public_key, &self.own, &secret.own_public_key)
+        };
+
+        let info = match methods.key_agreement_protocol {
+            KeyAgreementProtocol::Curve25519 => format!(
+                "MATRIX_KEY_VERIFICATION_SAS{}{}{}{}{}",
+                start_device.user_id,
+                start_device.device_id,
+                accept_device.user_id,
+                accept_device.device_id,
+                self.transaction_id,
+            ),
+            _ => format!(
+                "MATRIX_KEY_VERIFICATION_SAS|{}|{}|{}|{}|{}|{}|{}",
+                start_device.user_id,
+                start_device.device_id,
+                start_key,
+                accept_device.user_id,
+                accept_device.device_id,
+                accept_key,
+                self.transaction_id,
+            ),
+        };
+
+        hkdf_sha256(&secret.secret, &info, len)
+    }
+
+    /// Calculates the MAC of `input` sent from `sender` to `receiver`.
+    fn mac(
+        &self,
+        secret: &SharedSecret,
+        sender: &SasDevice,
+        receiver: &SasDevice,
+        key_id: &str,
+        input: &str,
+    ) -> String {
+        let mut mac = self.mac_key(secret, sender, receiver, key_id);
+        mac.update(input.as_bytes());
+
+        base64::encode_config(mac.finalize().into_bytes(), base64::STANDARD_NO_PAD)
+    }
+
+    /// Checks the MAC of `input` sent from the other device to us.
+    fn mac_is_valid(&self, secret: &SharedSecret, key_id: &str, input: &str, mac: &str) -> bool {
+        let mac = match base64::decode_config(mac, base64::STANDARD_NO_PAD) {
+            Ok(mac) => mac,
+            Err(_) => return false,
+        };
+        let mut expected = self.mac_key(secret, &self.other, &self.own, key_id);
+        expected.update(input.as_bytes());
+
+        expected.verify(&mac).is_ok()
+    }
+
+    fn mac_key(
+        &self,
+        secret: &SharedSecret,
+        sender: &SasDevice,
+        receiver: &SasDevice,
+        key_id: &str,
+    ) -> Hmac<Sha256> {
+        let info = format!(
+            "MATRIX_KEY_VERIFICATION_MAC{}{}{}{}{}{}",
+            sender.user_id,
+            sender.device_id,
+            receiver.user_id,
+            receiver.device_id,
+            self.transaction_id,
+            key_id,
+        );
+
+        Hmac::new_varkey(&hkdf_sha256(&secret.secret, &info, 32))
+            .expect("HMAC takes keys of any size")
+    }
+}
+
+/// The key agreement protocols we support, in ascending order of preference.
+const SUPPORTED_KEY_AGREEMENT_PROTOCOLS: &[KeyAgreementProtocol] =
+    &[KeyAgreementProtocol::Curve25519, KeyAgreementProtocol::Curve25519HkdfSha256];
+
+/// The short authentication string methods we support.
+const SUPPORTED_SAS_METHODS: &[ShortAuthenticationString] =
+    &[ShortAuthenticationString::Decimal, ShortAuthenticationString::Emoji];
+
+fn generate_key_pair() -> (PrivateKey, String) {
+    let private_key = EphemeralSecret::new(OsRng);
+    let public_key = PublicKey::from(&private_key);
+
+    (PrivateKey(private_key), base64::encode_config(public_key.as_bytes(), base64::STANDARD_NO_PAD))
+}
+
+fn canonical_json<T: Serialize>(content: &T) -> String {
+    let value = ruma_serde::to_canonical_value(content)
+        .expect("verification event contents are valid canonical JSON");
+    ruma_serde::to_canonical_json_string(&value)
+        .expect("verification event contents are valid canonical JSON")
+}
+
+/// The commitment of the accepting device to its public key.
+fn commitment(public_key: &str, start_json: &str) -> String {
+    let hash = Sha256::digest(format!("{}{}", public_key, start_json).as_bytes());
+    base64::encode_config(hash, base64::STANDARD_NO_PAD)
+}
+
+fn hkdf_sha256(secret: &[u8], info: &str, len: usize) -> Vec<u8> {
+    let mut output = vec![0; len];
+    Hkdf::<Sha256>::new(None, secret)
+        .expand(info.as_bytes(), &mut output)
+        .expect("HKDF output length is valid");
+
+    output
+}
+
+/// The comma-separated, sorted list of the key IDs of the given MACs.
+fn key_ids(mac: &BTreeMap<String, String>) -> String {
+    mac.keys().map(String::as_str).collect::<Vec<_>>().join(",")
+}
+
+/// Splits the first 39 bits of `bytes` into three numbers of 13 bits each, offset by 1000.
+fn decimals_from_bytes(bytes: &[u8]) -> (u16, u16, u16) {
+    let bytes: Vec<u16> = bytes.iter().map(|&b| u16::from(b)).collect();
+
+    (
+        (bytes[0] << 5 | bytes[1] >> 3) + 1000,
+        ((bytes[1] & 0x7) << 10 | bytes[2] << 2 | bytes[3] >> 6) + 1000,
+        ((bytes[3] & 0x3F) << 7 | bytes[4] >> 1) + 1000,
+    )
+}
+
+fn cancel_content(transaction_id: String, code: CancelCode) -> CancelToDeviceEventContent {
+    let reason = match code {
+        CancelCode::User => "The user cancelled the verification.",
+        CancelCode::UnknownTransaction => "The transaction ID is not known.",
+        CancelCode::UnknownMethod => "None of the offered verification methods is supported.",
+        CancelCode::UnexpectedMessage => "The message was not expected at this point.",
+        CancelCode::KeyMismatch => "The keys could not be verified.",
+        CancelCode::InvalidMessage => "The message was invalid.",
+        _ => "The verification was cancelled.",
+    };
+
+    CancelToDeviceEventContent { transaction_id, reason: reason.to_owned(), code }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use maplit::btreemap;
+    use matches::assert_matches;
+    use ruma_identifiers::user_id;
+
+    use super::{commitment, decimals_from_bytes, emoji, hkdf_sha256, Sas, SasDevice};
+    use crate::key::verification::{
+        accept::AcceptMethod,
+        cancel::CancelCode,
+        start::{StartMethod, StartToDeviceEventContent},
+        KeyAgreementProtocol, ShortAuthenticationString,
+    };
+
+    fn alice() -> SasDevice {
+        SasDevice::new(
+            user_id!("@alice:example.org"),
+            "ALICEDEVICE".into(),
+            btreemap! {
+                "ed25519:ALICEDEVICE".to_owned() => "alice+ed25519+key".to_owned(),
+            },
+        )
+    }
+
+    fn bob() -> SasDevice {
+        SasDevice::new(
+            user_id!("@bob:example.org"),
+            "BOBDEVICE".into(),
+            btreemap! {
+                "ed25519:BOBDEVICE".to_owned() => "bob+ed25519+key".to_owned(),
+                "ed25519:BOBMASTERKEY".to_owned() => "bob+master+key".to_owned(),
+            },
+        )
+    }
+
+    /// Runs a verification up to the point where the keys are exchanged.
+    fn exchange_keys(start: StartToDeviceEventContent) -> (Sas, Sas) {
+        let (mut alice_sas, _) = Sas::start(alice(), bob(), start.transaction_id.clone());
+        let mut bob_sas = Sas::from_start(bob(), alice(), &start).unwrap();
+        // Use the start content under test for the commitment on Alice's side too.
+        alice_sas.start_json = bob_sas.start_json.clone();
+
+        let accept = bob_sas.accept().unwrap();
+        let alice_key = alice_sas.receive_accept(&accept).unwrap();
+        let bob_key = bob_sas.receive_key(&alice_key).unwrap().unwrap();
+        assert!(alice_sas.receive_key(&bob_key).unwrap().is_none());
+
+        (alice_sas, bob_sas)
+    }
+
+    #[test]
+    fn full_verification() {
+        let (mut alice_sas, start) = Sas::start(alice(), bob(), "txn".into());
+        let mut bob_sas = Sas::from_start(bob(), alice(), &start).unwrap();
+        assert!(alice_sas.we_started());
+        assert!(!bob_sas.we_started());
+
+        let accept = bob_sas.accept().unwrap();
+        assert_matches!(
+            &accept.method,
+            AcceptMethod::MSasV1(method)
+                if method.key_agreement_protocol == KeyAgreementProtocol::Curve25519HkdfSha256
+        );
+        assert!(bob_sas.accept().is_none());
+
+        let alice_key = alice_sas.receive_accept(&accept).unwrap();
+        assert!(alice_sas.decimals().is_none());
+        let bob_key = bob_sas.receive_key(&alice_key).unwrap().unwrap();
+        assert!(alice_sas.receive_key(&bob_key).unwrap().is_none());
+
+        assert!(alice_sas.decimals().is_some());
+        assert_eq!(alice_sas.decimals(), bob_sas.decimals());
+        assert!(alice_sas.emoji().is_some());
+        assert_eq!(alice_sas.emoji(), bob_sas.emoji());
+
+        let alice_mac = alice_sas.confirm().unwrap();
+        assert!(alice_sas.confirm().is_none());
+        bob_sas.receive_mac(&alice_mac).unwrap();
+        assert!(!bob_sas.is_done());
+
+        let bob_mac = bob_sas.confirm().unwrap();
+        assert!(bob_sas.is_done());
+        assert_eq!(bob_sas.verified_keys().unwrap(), ["ed25519:ALICEDEVICE"]);
+
+        alice_sas.receive_mac(&bob_mac).unwrap();
+        assert!(alice_sas.is_done());
+        assert_eq!(
+            alice_sas.verified_keys().unwrap(),
+            ["ed25519:BOBDEVICE", "ed25519:BOBMASTERKEY"]
+        );
+    }
+
+    #[test]
+    fn legacy_key_agreement_and_decimal_only() {
+        let (_, mut start) = Sas::start(alice(), bob(), "txn".into());
+        match &mut start.method {
+            StartMethod::MSasV1(method) => {
+                method.key_agreement_protocols = vec![KeyAgreementProtocol::Curve25519];
+                method.short_authentication_string = vec![ShortAuthenticationString::Decimal];
+            }
+            _ => unreachable!(),
+        }
+
+        let (alice_sas, bob_sas) = exchange_keys(start);
+        assert_eq!(alice_sas.decimals(), bob_sas.decimals());
+        assert!(alice_sas.decimals().is_some());
+        assert!(alice_sas.emoji().is_none());
+        assert!(bob_sas.emoji().is_none());
+    }
+
+    #[test]
+    fn unknown_method() {
+        let (_, mut start) = Sas::start(alice(), bob(), "txn".into());
+        if let StartMethod::MSasV1(method) = &mut start.method {
+            method.short_authentication_string.clear();
+        }
+
+        let cancel = Sas::from_start(bob(), alice(), &start).unwrap_err();
+        assert_eq!(cancel.code, CancelCode::UnknownMethod);
+        assert_eq!(cancel.transaction_id, "txn");
+    }
+
+    #[test]
+    fn commitment_mismatch() {
+        let (mut alice_sas, start) = Sas::start(alice(), bob(), "txn".into());
+        let mut bob_sas = Sas::from_start(bob(), alice(), &start).unwrap();
+        let mut accept = bob_sas.accept().unwrap();
+        if let AcceptMethod::MSasV1(method) = &mut accept.method {
+            method.commitment = "bogus".into();
+        }
+
+        let alice_key = alice_sas.receive_accept(&accept).unwrap();
+        let bob_key = bob_sas.receive_key(&alice_key).unwrap().unwrap();
+        let cancel = alice_sas.receive_key(&bob_key).unwrap_err();
+        assert_eq!(cancel.code, CancelCode::KeyMismatch);
+        assert_eq!(alice_sas.cancel_code(), Some(&CancelCode::KeyMismatch));
+    }
+
+    #[test]
+    fn mac_mismatch() {
+        let (_, start) = Sas::start(alice(), bob(), "txn".into());
+        let (mut alice_sas, mut bob_sas) = exchange_keys(start);
+
+        let mut bob_mac = bob_sas.confirm().unwrap();
+        bob_mac.mac.insert("ed25519:BOBDEVICE".into(), alice_sas.confirm().unwrap().keys);
+
+        let cancel = alice_sas.receive_mac(&bob_mac).unwrap_err();
+        assert_eq!(cancel.code, CancelCode::KeyMismatch);
+        assert!(alice_sas.is_cancelled());
+        assert!(alice_sas.verified_keys().is_none());
+    }
+
+    #[test]
+    fn unexpected_message_and_unknown_transaction() {
+        let (mut alice_sas, start) = Sas::start(alice(), bob(), "txn".into());
+        let mut bob_sas = Sas::from_start(bob(), alice(), &start).unwrap();
+        let mut accept = bob_sas.accept().unwrap();
+
+        accept.transaction_id = "other_txn".into();
+        let cancel = alice_sas.receive_accept(&accept).unwrap_err();
+        assert_eq!(cancel.code, CancelCode::UnknownTransaction);
+        assert_eq!(cancel.transaction_id, "other_txn");
+        assert!(!alice_sas.is_cancelled());
+
+        let mac = super::MacToDeviceEventContent {
+            transaction_id: "txn".into(),
+            mac: BTreeMap::new(),
+            keys: String::new(),
+        };
+        let cancel = alice_sas.receive_mac(&mac).unwrap_err();
+        assert_eq!(cancel.code, CancelCode::UnexpectedMessage);
+        assert_eq!(alice_sas.cancel_code(), Some(&CancelCode::UnexpectedMessage));
+    }
+
+    #[test]
+    fn cancellation() {
+        let (mut alice_sas, start) = Sas::start(alice(), bob(), "txn".into());
+        let mut bob_sas = Sas::from_start(bob(), alice(), &start).unwrap();
+
+        let cancel = bob_sas.cancel(CancelCode::User);
+        assert!(bob_sas.is_cancelled());
+        alice_sas.receive_cancel(&cancel);
+        assert_eq!(alice_sas.cancel_code(), Some(&CancelCode::User));
+    }
+
+    #[test]
+    fn short_authentication_strings_from_bytes() {
+        assert_eq!(decimals_from_bytes(&[0, 0, 0, 0, 0]), (1000, 1000, 1000));
+        assert_eq!(decimals_from_bytes(&[0xFF; 5]), (9191, 9191, 9191));
+        assert_eq!(decimals_from_bytes(&[0x00, 0x08, 0x00, 0x40, 0x02]), (1001, 1001, 1001));
+
+        let emoji = emoji::from_bytes(&[0x00, 0x10, 0x83, 0x10, 0x5F, 0xC0]);
+        let symbols: Vec<_> = emoji.iter().map(|emoji| emoji.symbol).collect();
+        assert_eq!(symbols, ["🐶", "🐱", "🦁", "🐎", "🦄", "🐷", "📌"]);
+        assert_eq!(emoji[6].description, "Pin");
+    }
+
+    #[test]
+    fn hkdf_and_commitment() {
+        // RFC 5869, test case 3.
+        let okm = hkdf_sha256(&[0x0b; 22], "", 42);
+        assert_eq!(base64::encode(okm), "jaTndaVjwY9xX4AqBjxaMbihH1xe4Yeew0VOXzxzjS2dIBOV+qS2GpbI");
+
+        // The SHA-256 hash of "abc".
+        assert_eq!(commitment("a", "bc"), "ungWv48Bz+pBQUDeXa4iI7ADYaOWF3qctBD/YfIAFa0");
+    }
+}
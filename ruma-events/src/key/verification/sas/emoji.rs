@@ -0,0 +1,90 @@
+//! The emoji representation of the short authentication string.
+
+/// An emoji of the short authentication string, together with its English description.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Emoji {
+    /// The emoji itself.
+    pub symbol: &'static str,
+
+    /// The English description of the emoji, as given in the specification.
+    pub description: &'static str,
+}
+
+/// Splits the first 42 bits of `bytes` into seven numbers of 6 bits each and maps them to emoji.
+pub fn from_bytes(bytes: &[u8]) -> [Emoji; 7] {
+    let bits = bytes[..6].iter().fold(0u64, |bits, &byte| bits << 8 | u64::from(byte));
+    let mut emoji = [EMOJI[0]; 7];
+    for (i, emoji) in emoji.iter_mut().enumerate() {
+        *emoji = EMOJI[(bits >> (42 - 6 * i) & 0x3F) as usize];
+    }
+
+    emoji
+}
+
+/// The emoji table from the specification, indexed by their number.
+const EMOJI: [Emoji; 64] = [
+    Emoji { symbol: "🐶", description: "Dog" },
+    Emoji { symbol: "🐱", description: "Cat" },
+    Emoji { symbol: "🦁", description: "Lion" },
+    Emoji { symbol: "🐎", description: "Horse" },
+    Emoji { symbol: "🦄", description: "Unicorn" },
+    Emoji { symbol: "🐷", description: "Pig" },
+    Emoji { symbol: "🐘", description: "Elephant" },
+    Emoji { symbol: "🐰", description: "Rabbit" },
+    Emoji { symbol: "🐼", description: "Panda" },
+    Emoji { symbol: "🐓", description: "Rooster" },
+    Emoji { symbol: "🐧", description: "Penguin" },
+    Emoji { symbol: "🐢", description: "Turtle" },
+    Emoji { symbol: "🐟", description: "Fish" },
+    Emoji { symbol: "🐙", description: "Octopus" },
+    Emoji { symbol: "🦋", description: "Butterfly" },
+    Emoji { symbol: "🌷", description: "Flower" },
+    Emoji { symbol: "🌳", description: "Tree" },
+    Emoji { symbol: "🌵", description: "Cactus" },
+    Emoji { symbol: "🍄", description: "Mushroom" },
+    Emoji { symbol: "🌏", description: "Globe" },
+    Emoji { symbol: "🌙", description: "Moon" },
+    Emoji { symbol: "☁️", description: "Cloud" },
+    Emoji { symbol: "🔥", description: "Fire" },
+    Emoji { symbol: "🍌", description: "Banana" },
+    Emoji { symbol: "🍎", description: "Apple" },
+    Emoji { symbol: "🍓", description: "Strawberry" },
+    Emoji { symbol: "🌽", description: "Corn" },
+    Emoji { symbol: "🍕", description: "Pizza" },
+    Emoji { symbol: "🎂", description: "Cake" },
+    Emoji { symbol: "❤️", description: "Heart" },
+    Emoji { symbol: "😀", description: "Smiley" },
+    Emoji { symbol: "🤖", description: "Robot" },
+    Emoji { symbol: "🎩", description: "Hat" },
+    Emoji { symbol: "👓", description: "Glasses" },
+    Emoji { symbol: "🔧", description: "Spanner" },
+    Emoji { symbol: "🎅", description: "Santa" },
+    Emoji { symbol: "👍", description: "Thumbs Up" },
+    Emoji { symbol: "☂️", description: "Umbrella" },
+    Emoji { symbol: "⌛", description: "Hourglass" },
+    Emoji { symbol: "⏰", description: "Clock" },
+    Emoji { symbol: "🎁", description: "Gift" },
+    Emoji { symbol: "💡", description: "Light Bulb" },
+    Emoji { symbol: "📕", description: "Book" },
+    Emoji { symbol: "✏️", description: "Pencil" },
+    Emoji { symbol: "📎", description: "Paperclip" },
+    Emoji { symbol: "✂️", description: "Scissors" },
+    Emoji { symbol: "🔒", description: "Lock" },
+    Emoji { symbol: "🔑", description: "Key" },
+    Emoji { symbol: "🔨", description: "Hammer" },
+    Emoji { symbol: "☎️", description: "Telephone" },
+    Emoji { symbol: "🏁", description: "Flag" },
+    Emoji { symbol: "🚂", description: "Train" },
+    Emoji { symbol: "🚲", description: "Bicycle" },
+    Emoji { symbol: "✈️", description: "Aeroplane" },
+    Emoji { symbol: "🚀", description: "Rocket" },
+    Emoji { symbol: "🏆", description: "Trophy" },
+    Emoji { symbol: "⚽", description: "Ball" },
+    Emoji { symbol: "🎸", description: "Guitar" },
+    Emoji { symbol: "🎺", description: "Trumpet" },
+    Emoji { symbol: "🔔", description: "Bell" },
+    Emoji { symbol: "⚓", description: "Anchor" },
+    Emoji { symbol: "🎧", description: "Headphones" },
+    Emoji { symbol: "📁", description: "Folder" },
+    Emoji { symbol: "📌", description: "Pin" },
+];
@@ -3,6 +3,8 @@
 use std::{collections::BTreeMap, convert::TryFrom};
 
 use ruma_events_macros::BasicEventContent;
+#[cfg(feature = "unstable-pre-spec")]
+use ruma_events_macros::MessageEventContent;
 use ruma_identifiers::DeviceIdBox;
 use serde::{Deserialize, Serialize};
 use serde_json::Value as JsonValue;
@@ -10,17 +12,21 @@ use serde_json::Value as JsonValue;
 use super::{
     HashAlgorithm, KeyAgreementProtocol, MessageAuthenticationCode, ShortAuthenticationString,
 };
-use crate::{BasicEvent, InvalidInput};
+use crate::InvalidInput;
+#[cfg(feature = "unstable-pre-spec")]
+use crate::{relation::Reference, MessageEvent};
 
 /// Begins an SAS key verification process.
 ///
-/// Typically sent as a to-device event.
-pub type StartEvent = BasicEvent<StartEventContent>;
+/// This is the in-room version of the event, sent in the room of the verification. The
+/// content of the to-device version is `StartToDeviceEventContent`.
+#[cfg(feature = "unstable-pre-spec")]
+pub type StartEvent = MessageEvent<StartEventContent>;
 
-/// The payload of an *m.key.verification.start* event.
+/// The payload of a to-device *m.key.verification.start* event.
 #[derive(Clone, Debug, Deserialize, Serialize, BasicEventContent)]
 #[ruma_event(type = "m.key.verification.start")]
-pub struct StartEventContent {
+pub struct StartToDeviceEventContent {
     /// The device ID which is initiating the process.
     pub from_device: DeviceIdBox,
 
@@ -36,6 +42,32 @@ pub struct StartEventContent {
     pub method: StartMethod,
 }
 
+/// The payload of an in-room *m.key.verification.start* event.
+#[cfg(feature = "unstable-pre-spec")]
+#[derive(Clone, Debug, Deserialize, Serialize, MessageEventContent)]
+#[cfg_attr(not(feature = "unstable-exhaustive-types"), non_exhaustive)]
+#[ruma_event(type = "m.key.verification.start")]
+pub struct StartEventContent {
+    /// The device ID which is initiating the process.
+    pub from_device: DeviceIdBox,
+
+    /// Method specific content.
+    #[serde(flatten)]
+    pub method: StartMethod,
+
+    /// Information about the verification request this message belongs to.
+    #[serde(rename = "m.relates_to")]
+    pub relates_to: Reference,
+}
+
+#[cfg(feature = "unstable-pre-spec")]
+impl StartEventContent {
+    /// Creates a new `StartEventContent` with the given device ID, method and relation.
+    pub fn new(from_device: DeviceIdBox, method: StartMethod, relates_to: Reference) -> Self {
+        Self { from_device, method, relates_to }
+    }
+}
+
 /// An enum representing the different method specific
 /// *m.key.verification.start* content.
 #[derive(Clone, Debug, Deserialize, Serialize)]
@@ -185,10 +217,13 @@ mod tests {
 
     use super::{
         CustomContent, HashAlgorithm, KeyAgreementProtocol, MSasV1Content, MSasV1ContentInit,
-        MessageAuthenticationCode, ShortAuthenticationString, StartEvent, StartEventContent,
-        StartMethod,
+        MessageAuthenticationCode, ShortAuthenticationString, StartMethod,
+        StartToDeviceEventContent,
     };
     use ruma_common::Raw;
+    use ruma_identifiers::user_id;
+
+    use crate::ToDeviceEvent;
 
     #[test]
     fn invalid_m_sas_v1_content_missing_required_key_agreement_protocols() {
@@ -248,7 +283,7 @@ mod tests {
 
     #[test]
     fn serialization() {
-        let key_verification_start_content = StartEventContent {
+        let key_verification_start_content = StartToDeviceEventContent {
             from_device: "123".into(),
             transaction_id: "456".into(),
             method: StartMethod::MSasV1(
@@ -262,7 +297,10 @@ mod tests {
             ),
         };
 
-        let key_verification_start = StartEvent { content: key_verification_start_content };
+        let key_verification_start = ToDeviceEvent {
+            content: key_verification_start_content,
+            sender: user_id!("@example:localhost"),
+        };
 
         let json_data = json!({
             "content": {
@@ -274,6 +312,7 @@ mod tests {
                 "message_authentication_codes": ["hkdf-hmac-sha256"],
                 "short_authentication_string": ["decimal"]
             },
+            "sender": "@example:localhost",
             "type": "m.key.verification.start"
         });
 
@@ -286,10 +325,11 @@ mod tests {
                 "method": "m.sas.custom",
                 "test": "field",
             },
+            "sender": "@example:localhost",
             "type": "m.key.verification.start"
         });
 
-        let key_verification_start_content = StartEventContent {
+        let key_verification_start_content = StartToDeviceEventContent {
             from_device: "123".into(),
             transaction_id: "456".into(),
             method: StartMethod::Custom(CustomContent {
@@ -300,7 +340,10 @@ mod tests {
             }),
         };
 
-        let key_verification_start = StartEvent { content: key_verification_start_content };
+        let key_verification_start = ToDeviceEvent {
+            content: key_verification_start_content,
+            sender: user_id!("@example:localhost"),
+        };
 
         assert_eq!(to_json_value(&key_verification_start).unwrap(), json_data);
     }
@@ -319,11 +362,11 @@ mod tests {
 
         // Deserialize the content struct separately to verify `TryFromRaw` is implemented for it.
         assert_matches!(
-            from_json_value::<Raw<StartEventContent>>(json)
+            from_json_value::<Raw<StartToDeviceEventContent>>(json)
                 .unwrap()
                 .deserialize()
                 .unwrap(),
-            StartEventContent {
+            StartToDeviceEventContent {
                 from_device,
                 transaction_id,
                 method: StartMethod::MSasV1(MSasV1Content {
@@ -350,16 +393,17 @@ mod tests {
                 "message_authentication_codes": ["hkdf-hmac-sha256"],
                 "short_authentication_string": ["decimal"]
             },
+            "sender": "@example:localhost",
             "type": "m.key.verification.start"
         });
 
         assert_matches!(
-            from_json_value::<Raw<StartEvent>>(json)
+            from_json_value::<Raw<ToDeviceEvent<StartToDeviceEventContent>>>(json)
                 .unwrap()
                 .deserialize()
                 .unwrap(),
-            StartEvent {
-                content: StartEventContent {
+            ToDeviceEvent {
+                content: StartToDeviceEventContent {
                     from_device,
                     transaction_id,
                     method: StartMethod::MSasV1(MSasV1Content {
@@ -368,8 +412,10 @@ mod tests {
                         message_authentication_codes,
                         short_authentication_string,
                     })
-                }
-            } if from_device == "123"
+                },
+                sender,
+            } if sender == user_id!("@example:localhost")
+                && from_device == "123"
                 && transaction_id == "456"
                 && hashes == vec![HashAlgorithm::Sha256]
                 && key_agreement_protocols == vec![KeyAgreementProtocol::Curve25519]
@@ -384,24 +430,27 @@ mod tests {
                 "method": "m.sas.custom",
                 "test": "field",
             },
+            "sender": "@example:localhost",
             "type": "m.key.verification.start"
         });
 
         assert_matches!(
-            from_json_value::<Raw<StartEvent>>(json)
+            from_json_value::<Raw<ToDeviceEvent<StartToDeviceEventContent>>>(json)
                 .unwrap()
                 .deserialize()
                 .unwrap(),
-            StartEvent {
-                content: StartEventContent {
+            ToDeviceEvent {
+                content: StartToDeviceEventContent {
                     from_device,
                     transaction_id,
                     method: StartMethod::Custom(CustomContent {
                         method,
                         fields,
                     })
-                }
-            } if from_device == "123"
+                },
+                sender,
+            } if sender == user_id!("@example:localhost")
+                && from_device == "123"
                 && transaction_id == "456"
                 && method == "m.sas.custom"
                 && fields.get("test").unwrap() == &JsonValue::from("field")
@@ -411,7 +460,7 @@ mod tests {
     #[test]
     fn deserialization_failure() {
         // Ensure that invalid JSON  creates a `serde_json::Error` and not `InvalidEvent`
-        assert!(serde_json::from_str::<Raw<StartEventContent>>("{").is_err());
+        assert!(serde_json::from_str::<Raw<StartToDeviceEventContent>>("{").is_err());
     }
 
     // TODO this fails because the error is a Validation error not deserialization?
@@ -420,7 +469,7 @@ mod tests {
     fn deserialization_structure_mismatch() {
         // Missing several required fields.
         let error =
-            from_json_value::<Raw<StartEventContent>>(json!({ "from_device": "123" }))
+            from_json_value::<Raw<StartToDeviceEventContent>>(json!({ "from_device": "123" }))
                 .unwrap()
                 .deserialize()
                 .unwrap_err();
@@ -444,7 +493,7 @@ mod tests {
             "short_authentication_string": ["decimal"]
         });
 
-        let error = from_json_value::<Raw<StartEventContent>>(json_data)
+        let error = from_json_value::<Raw<StartToDeviceEventContent>>(json_data)
             .unwrap()
             .deserialize()
             .unwrap_err();
@@ -467,7 +516,7 @@ mod tests {
             "message_authentication_codes": ["hkdf-hmac-sha256"],
             "short_authentication_string": ["decimal"]
         });
-        let error = from_json_value::<Raw<StartEventContent>>(json_data)
+        let error = from_json_value::<Raw<StartToDeviceEventContent>>(json_data)
             .unwrap()
             .deserialize()
             .unwrap_err();
@@ -490,7 +539,7 @@ mod tests {
             "message_authentication_codes": [],
             "short_authentication_string": ["decimal"]
         });
-        let error = from_json_value::<Raw<StartEventContent>>(json_data)
+        let error = from_json_value::<Raw<StartToDeviceEventContent>>(json_data)
             .unwrap()
             .deserialize()
             .unwrap_err();
@@ -512,7 +561,7 @@ mod tests {
             "message_authentication_codes": ["hkdf-hmac-sha256"],
             "short_authentication_string": []
         });
-        let error = from_json_value::<Raw<StartEventContent>>(json_data)
+        let error = from_json_value::<Raw<StartToDeviceEventContent>>(json_data)
             .unwrap()
             .deserialize()
             .unwrap_err();
@@ -537,9 +586,10 @@ mod tests {
                 "message_authentication_codes": ["hkdf-hmac-sha256"],
                 "short_authentication_string": ["decimal"]
             },
+            "sender": "@example:localhost",
             "type": "m.key.verification.start"
         });
-        let error = from_json_value::<Raw<StartEvent>>(json_data)
+        let error = from_json_value::<Raw<ToDeviceEvent<StartToDeviceEventContent>>>(json_data)
             .unwrap()
             .deserialize()
             .unwrap_err();
@@ -121,7 +121,7 @@ use std::fmt::Debug;
 
 use js_int::Int;
 use ruma_common::Raw;
-use ruma_identifiers::{EventEncryptionAlgorithm, RoomId};
+use ruma_identifiers::{EventEncryptionAlgorithm, RoomId, RoomVersionId};
 use serde::{
     de::{self, IgnoredAny},
     Deserialize, Serialize,
@@ -170,10 +170,16 @@ pub mod pdu;
 pub mod policy;
 pub mod presence;
 pub mod push_rules;
+#[cfg(feature = "unstable-pre-spec")]
+pub mod reaction;
 pub mod receipt;
+pub mod relation;
 pub mod room;
 pub mod room_key;
 pub mod room_key_request;
+pub mod room_state;
+pub mod secret;
+pub mod secret_storage;
 pub mod sticker;
 pub mod tag;
 pub mod typing;
@@ -368,8 +374,8 @@ pub trait StateEventContent: RoomEventContent {}
 /// The base trait that all redacted event content types implement.
 ///
 /// This trait's associated functions and methods should not be used to build
-/// redacted events, prefer the `redact` method on `AnyStateEvent` and
-/// `AnyMessageEvent` and their "sync" and "stripped" counterparts. The
+/// redacted events, prefer the `Redact` trait implemented by the event types and
+/// `AnyStateEvent`, `AnyMessageEvent` and their "sync" and "stripped" counterparts. The
 /// `RedactedEventContent` trait is an implementation detail, ruma makes no
 /// API guarantees.
 pub trait RedactedEventContent: EventContent {
@@ -396,6 +402,37 @@ pub trait RedactedMessageEventContent: RedactedEventContent {}
 /// Marker trait for the content of a redacted state event.
 pub trait RedactedStateEventContent: RedactedEventContent {}
 
+/// Trait to define the behavior of redacting an event.
+pub trait Redact {
+    /// The redacted form of the event.
+    type Redacted;
+
+    /// The redaction event that caused the redaction.
+    ///
+    /// This is `RedactionEvent` for events with a `room_id` and `SyncRedactionEvent` for their
+    /// "sync" and "stripped" counterparts.
+    type Redaction;
+
+    /// Transforms `self` into a redacted form (removing most fields) according to the spec.
+    ///
+    /// `redaction` becomes the `redacted_because` of the redacted event's unsigned data, except
+    /// for stripped events which have no unsigned data. A small number of events have
+    /// room-version specific redaction behavior, so a version has to be specified.
+    fn redact(self, redaction: Self::Redaction, version: RoomVersionId) -> Self::Redacted;
+}
+
+/// Trait to define the behavior of redacting an event's content.
+pub trait RedactContent {
+    /// The redacted form of the event's content.
+    type Redacted;
+
+    /// Transforms `self` into a redacted form (removing most fields) according to the spec.
+    ///
+    /// A small number of events have room-version specific redaction behavior, so a version has
+    /// to be specified.
+    fn redact(self, version: RoomVersionId) -> Self::Redacted;
+}
+
 /// `HasDeserializeFields` is used in the code generated by the `Event` derive
 /// to aid in deserializing redacted events.
 #[doc(hidden)]
@@ -27,6 +27,100 @@ pub enum Pdu {
     RoomV3Pdu(RoomV3Pdu),
 }
 
+impl Pdu {
+    /// The event ID of the PDU, if it is part of the PDU (room versions 1 and 2).
+    ///
+    /// For later room versions, the event ID is derived from the reference hash of the event.
+    pub fn event_id(&self) -> Option<&EventId> {
+        match self {
+            Pdu::RoomV1Pdu(pdu) => Some(&pdu.event_id),
+            Pdu::RoomV3Pdu(_) => None,
+        }
+    }
+
+    /// The room this event belongs to.
+    pub fn room_id(&self) -> &RoomId {
+        match self {
+            Pdu::RoomV1Pdu(pdu) => &pdu.room_id,
+            Pdu::RoomV3Pdu(pdu) => &pdu.room_id,
+        }
+    }
+
+    /// The user id of the user who sent this event.
+    pub fn sender(&self) -> &UserId {
+        match self {
+            Pdu::RoomV1Pdu(pdu) => &pdu.sender,
+            Pdu::RoomV3Pdu(pdu) => &pdu.sender,
+        }
+    }
+
+    /// Timestamp (milliseconds since the UNIX epoch) on originating homeserver of when this event
+    /// was created.
+    pub fn origin_server_ts(&self) -> SystemTime {
+        match self {
+            Pdu::RoomV1Pdu(pdu) => pdu.origin_server_ts,
+            Pdu::RoomV3Pdu(pdu) => pdu.origin_server_ts,
+        }
+    }
+
+    /// The event's type.
+    pub fn kind(&self) -> &EventType {
+        match self {
+            Pdu::RoomV1Pdu(pdu) => &pdu.kind,
+            Pdu::RoomV3Pdu(pdu) => &pdu.kind,
+        }
+    }
+
+    /// The event's content.
+    pub fn content(&self) -> &JsonValue {
+        match self {
+            Pdu::RoomV1Pdu(pdu) => &pdu.content,
+            Pdu::RoomV3Pdu(pdu) => &pdu.content,
+        }
+    }
+
+    /// A key that determines which piece of room state the event represents.
+    pub fn state_key(&self) -> Option<&str> {
+        match self {
+            Pdu::RoomV1Pdu(pdu) => pdu.state_key.as_deref(),
+            Pdu::RoomV3Pdu(pdu) => pdu.state_key.as_deref(),
+        }
+    }
+
+    /// Event IDs for the most recent events in the room that the homeserver was aware of when it
+    /// created this event.
+    pub fn prev_event_ids(&self) -> Vec<&EventId> {
+        match self {
+            Pdu::RoomV1Pdu(pdu) => pdu.prev_events.iter().map(|(id, _)| id).collect(),
+            Pdu::RoomV3Pdu(pdu) => pdu.prev_events.iter().collect(),
+        }
+    }
+
+    /// The maximum depth of the `prev_events`, plus one.
+    pub fn depth(&self) -> UInt {
+        match self {
+            Pdu::RoomV1Pdu(pdu) => pdu.depth,
+            Pdu::RoomV3Pdu(pdu) => pdu.depth,
+        }
+    }
+
+    /// Event IDs for the authorization events that would allow this event to be in the room.
+    pub fn auth_event_ids(&self) -> Vec<&EventId> {
+        match self {
+            Pdu::RoomV1Pdu(pdu) => pdu.auth_events.iter().map(|(id, _)| id).collect(),
+            Pdu::RoomV3Pdu(pdu) => pdu.auth_events.iter().collect(),
+        }
+    }
+
+    /// For redaction events, the ID of the event being redacted.
+    pub fn redacts(&self) -> Option<&EventId> {
+        match self {
+            Pdu::RoomV1Pdu(pdu) => pdu.redacts.as_ref(),
+            Pdu::RoomV3Pdu(pdu) => pdu.redacts.as_ref(),
+        }
+    }
+}
+
 /// A 'persistent data unit' (event) for room versions 1 and 2.
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct RoomV1Pdu {
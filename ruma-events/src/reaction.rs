@@ -0,0 +1,80 @@
+//! Types for the *m.reaction* event.
+
+use ruma_events_macros::MessageEventContent;
+use serde::{Deserialize, Serialize};
+
+use crate::{relation::Annotation, MessageEvent};
+
+/// A reaction to another event.
+pub type ReactionEvent = MessageEvent<ReactionEventContent>;
+
+/// The payload for a `ReactionEvent`.
+#[derive(Clone, Debug, Deserialize, Serialize, MessageEventContent)]
+#[cfg_attr(not(feature = "unstable-exhaustive-types"), non_exhaustive)]
+#[ruma_event(type = "m.reaction")]
+pub struct ReactionEventContent {
+    /// Information about the related event.
+    #[serde(rename = "m.relates_to")]
+    pub relates_to: Annotation,
+}
+
+impl ReactionEventContent {
+    /// Creates a new `ReactionEventContent` from the given annotation.
+    ///
+    /// You can also construct a `ReactionEventContent` from an annotation using `From` / `Into`.
+    pub fn new(relates_to: Annotation) -> Self {
+        Self { relates_to }
+    }
+}
+
+impl From<Annotation> for ReactionEventContent {
+    fn from(relates_to: Annotation) -> Self {
+        Self::new(relates_to)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use matches::assert_matches;
+    use ruma_identifiers::event_id;
+    use serde_json::{from_value as from_json_value, json, to_value as to_json_value};
+
+    use super::ReactionEventContent;
+    use crate::relation::Annotation;
+
+    #[test]
+    fn serialize_reaction() {
+        let content = ReactionEventContent::new(Annotation::new(
+            event_id!("$1598361704261elfgc:localhost"),
+            "👍".to_owned(),
+        ));
+
+        assert_eq!(
+            to_json_value(&content).unwrap(),
+            json!({
+                "m.relates_to": {
+                    "rel_type": "m.annotation",
+                    "event_id": "$1598361704261elfgc:localhost",
+                    "key": "👍",
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn deserialize_reaction() {
+        let json_data = json!({
+            "m.relates_to": {
+                "rel_type": "m.annotation",
+                "event_id": "$1598361704261elfgc:localhost",
+                "key": "🦛",
+            }
+        });
+
+        assert_matches!(
+            from_json_value::<ReactionEventContent>(json_data).unwrap(),
+            ReactionEventContent { relates_to: Annotation { event_id, key } }
+                if event_id == event_id!("$1598361704261elfgc:localhost") && key == "🦛"
+        );
+    }
+}
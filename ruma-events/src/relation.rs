@@ -0,0 +1,380 @@
+//! Types describing the relationship of an event to other events, expressed in the
+//! `m.relates_to` field of event contents.
+
+use std::collections::BTreeMap;
+
+use ruma_identifiers::EventId;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+
+#[cfg(feature = "unstable-pre-spec")]
+use crate::room::message::MessageEventContent;
+
+pub(crate) mod relation_serde;
+
+/// Enum modeling the different ways relationships can be expressed in a `m.relates_to` field of
+/// an event.
+#[derive(Clone, Debug)]
+#[cfg_attr(not(feature = "unstable-exhaustive-types"), non_exhaustive)]
+pub enum Relation {
+    /// A reply to another event, see
+    /// [rich replies](https://matrix.org/docs/spec/client_server/r0.6.1#rich-replies).
+    Reply {
+        /// Information about another message being replied to.
+        in_reply_to: InReplyTo,
+    },
+
+    /// An event that replaces another event.
+    #[cfg(feature = "unstable-pre-spec")]
+    Replacement(Replacement),
+
+    /// An annotation to another event, such as a reaction.
+    #[cfg(feature = "unstable-pre-spec")]
+    Annotation(Annotation),
+
+    /// A reference to another event.
+    #[cfg(feature = "unstable-pre-spec")]
+    Reference(Reference),
+
+    /// An event that is part of a thread.
+    #[cfg(feature = "unstable-pre-spec")]
+    Thread(Thread),
+
+    /// A relation with a `rel_type` that is not known to this crate.
+    ///
+    /// Without the `unstable-pre-spec` feature, this is also used for the relation types that are
+    /// not part of the Matrix specification yet, like `m.replace` and `m.annotation`.
+    Custom(CustomRelation),
+}
+
+impl Relation {
+    /// The `rel_type` of this relation, if any.
+    ///
+    /// Replies are the only relations without a `rel_type`.
+    pub fn rel_type(&self) -> Option<&str> {
+        match self {
+            Self::Reply { .. } => None,
+            #[cfg(feature = "unstable-pre-spec")]
+            Self::Replacement(_) => Some("m.replace"),
+            #[cfg(feature = "unstable-pre-spec")]
+            Self::Annotation(_) => Some("m.annotation"),
+            #[cfg(feature = "unstable-pre-spec")]
+            Self::Reference(_) => Some("m.reference"),
+            #[cfg(feature = "unstable-pre-spec")]
+            Self::Thread(_) => Some("m.thread"),
+            Self::Custom(relation) => Some(&relation.rel_type),
+        }
+    }
+
+    /// The ID of the event this relation points to.
+    ///
+    /// Returns `None` for custom relations, whose fields are not known to this crate.
+    pub fn event_id(&self) -> Option<&EventId> {
+        match self {
+            Self::Reply { in_reply_to } => Some(&in_reply_to.event_id),
+            #[cfg(feature = "unstable-pre-spec")]
+            Self::Replacement(replacement) => Some(&replacement.event_id),
+            #[cfg(feature = "unstable-pre-spec")]
+            Self::Annotation(annotation) => Some(&annotation.event_id),
+            #[cfg(feature = "unstable-pre-spec")]
+            Self::Reference(reference) => Some(&reference.event_id),
+            #[cfg(feature = "unstable-pre-spec")]
+            Self::Thread(thread) => Some(&thread.event_id),
+            Self::Custom(_) => None,
+        }
+    }
+}
+
+/// Information about the event a "rich reply" is replying to.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct InReplyTo {
+    /// The event being replied to.
+    pub event_id: EventId,
+}
+
+impl InReplyTo {
+    /// Creates a new `InReplyTo` with the given event ID.
+    pub fn new(event_id: EventId) -> Self {
+        Self { event_id }
+    }
+}
+
+/// The event this relation belongs to replaces another event.
+///
+/// In addition to the `m.relates_to` field, the replacement is represented by the
+/// `m.new_content` field of the event content.
+#[cfg(feature = "unstable-pre-spec")]
+#[derive(Clone, Debug)]
+pub struct Replacement {
+    /// The ID of the event being replaced.
+    pub event_id: EventId,
+
+    /// New content.
+    ///
+    /// This is `None` if the replacing event doesn't carry the new content in cleartext, e.g. if
+    /// it is encrypted.
+    pub new_content: Option<Box<MessageEventContent>>,
+}
+
+#[cfg(feature = "unstable-pre-spec")]
+impl Replacement {
+    /// Creates a new `Replacement` with the given event ID and new content.
+    pub fn new(event_id: EventId, new_content: Option<Box<MessageEventContent>>) -> Self {
+        Self { event_id, new_content }
+    }
+}
+
+/// The event this relation belongs to annotates another event, e.g. with a reaction.
+#[cfg(feature = "unstable-pre-spec")]
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(tag = "rel_type", rename = "m.annotation")]
+pub struct Annotation {
+    /// The ID of the event being annotated.
+    pub event_id: EventId,
+
+    /// The annotation, e.g. the emoji of a reaction.
+    pub key: String,
+}
+
+#[cfg(feature = "unstable-pre-spec")]
+impl Annotation {
+    /// Creates a new `Annotation` with the given event ID and key.
+    pub fn new(event_id: EventId, key: String) -> Self {
+        Self { event_id, key }
+    }
+}
+
+/// The event this relation belongs to references another event.
+///
+/// In-room key verification messages reference the *m.key.verification.request* message that
+/// started the verification.
+#[cfg(feature = "unstable-pre-spec")]
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(tag = "rel_type", rename = "m.reference")]
+pub struct Reference {
+    /// The ID of the event being referenced.
+    pub event_id: EventId,
+}
+
+#[cfg(feature = "unstable-pre-spec")]
+impl Reference {
+    /// Creates a new `Reference` with the given event ID.
+    pub fn new(event_id: EventId) -> Self {
+        Self { event_id }
+    }
+}
+
+/// The event this relation belongs to is part of a thread.
+#[cfg(feature = "unstable-pre-spec")]
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[cfg_attr(not(feature = "unstable-exhaustive-types"), non_exhaustive)]
+pub struct Thread {
+    /// The ID of the root event of the thread.
+    pub event_id: EventId,
+
+    /// The event this event replies to, for clients that don't support threads.
+    #[serde(rename = "m.in_reply_to", skip_serializing_if = "Option::is_none")]
+    pub in_reply_to: Option<InReplyTo>,
+
+    /// Whether `in_reply_to` is only a fallback for clients that don't support threads, rather
+    /// than a real reply.
+    #[serde(default, skip_serializing_if = "ruma_serde::is_default")]
+    pub is_falling_back: bool,
+}
+
+#[cfg(feature = "unstable-pre-spec")]
+impl Thread {
+    /// Creates a new `Thread` with the given root event ID and no reply fallback.
+    pub fn new(event_id: EventId) -> Self {
+        Self { event_id, in_reply_to: None, is_falling_back: false }
+    }
+}
+
+/// A relation with a `rel_type` that is not known to this crate.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CustomRelation {
+    /// The type of the relation.
+    pub rel_type: String,
+
+    /// The remaining fields of the `m.relates_to` object.
+    #[serde(flatten)]
+    pub data: BTreeMap<String, JsonValue>,
+}
+
+impl CustomRelation {
+    /// Creates a new `CustomRelation` with the given type and fields.
+    pub fn new(rel_type: String, data: BTreeMap<String, JsonValue>) -> Self {
+        Self { rel_type, data }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use matches::assert_matches;
+    use ruma_identifiers::event_id;
+    use serde_json::{from_value as from_json_value, json, to_value as to_json_value};
+
+    use super::Relation;
+    use crate::{room::encrypted::EncryptedEventContent, sticker::StickerEventContent};
+
+    fn megolm_content(relates_to: serde_json::Value) -> serde_json::Value {
+        json!({
+            "algorithm": "m.megolm.v1.aes-sha2",
+            "ciphertext": "ciphertext",
+            "sender_key": "sender_key",
+            "device_id": "device_id",
+            "session_id": "session_id",
+            "m.relates_to": relates_to,
+        })
+    }
+
+    #[test]
+    fn encrypted_reply() {
+        let json_data = megolm_content(json!({
+            "m.in_reply_to": { "event_id": "$original:example.com" },
+        }));
+
+        let content = from_json_value::<EncryptedEventContent>(json_data.clone()).unwrap();
+        assert_matches!(
+            content.relates_to(),
+            Some(Relation::Reply { in_reply_to })
+                if in_reply_to.event_id == event_id!("$original:example.com")
+        );
+        assert_eq!(to_json_value(&content).unwrap(), json_data);
+    }
+
+    #[test]
+    fn sticker_without_relation() {
+        let json_data = json!({
+            "body": "Hello",
+            "info": {},
+            "url": "mxc://example.com/sticker",
+            "m.relates_to": {},
+        });
+
+        let content = from_json_value::<StickerEventContent>(json_data).unwrap();
+        assert!(content.relates_to.is_none());
+    }
+
+    #[test]
+    #[cfg(not(feature = "unstable-pre-spec"))]
+    fn unstable_relations_are_custom() {
+        let json_data = megolm_content(json!({
+            "rel_type": "m.replace",
+            "event_id": "$original:example.com",
+        }));
+
+        let content = from_json_value::<EncryptedEventContent>(json_data.clone()).unwrap();
+        let relation = content.relates_to().unwrap();
+        assert_eq!(relation.rel_type(), Some("m.replace"));
+        assert_matches!(relation, Relation::Custom(_));
+        assert_eq!(to_json_value(&content).unwrap(), json_data);
+    }
+
+    #[test]
+    #[cfg(feature = "unstable-pre-spec")]
+    fn encrypted_replacement_without_new_content() {
+        use super::Replacement;
+
+        let json_data = megolm_content(json!({
+            "rel_type": "m.replace",
+            "event_id": "$original:example.com",
+        }));
+
+        let content = from_json_value::<EncryptedEventContent>(json_data.clone()).unwrap();
+        assert_matches!(
+            content.relates_to(),
+            Some(Relation::Replacement(Replacement { event_id, new_content: None }))
+                if *event_id == event_id!("$original:example.com")
+        );
+        assert_eq!(to_json_value(&content).unwrap(), json_data);
+    }
+
+    #[test]
+    #[cfg(feature = "unstable-pre-spec")]
+    fn sticker_annotation() {
+        use super::Annotation;
+
+        let json_data = json!({
+            "body": "Hello",
+            "info": {},
+            "url": "mxc://example.com/sticker",
+            "m.relates_to": {
+                "rel_type": "m.annotation",
+                "event_id": "$original:example.com",
+                "key": "🦛",
+            },
+        });
+
+        let content = from_json_value::<StickerEventContent>(json_data.clone()).unwrap();
+        assert_matches!(
+            &content.relates_to,
+            Some(Relation::Annotation(Annotation { event_id, key }))
+                if *event_id == event_id!("$original:example.com") && key == "🦛"
+        );
+        assert_eq!(to_json_value(&content).unwrap(), json_data);
+    }
+
+    #[test]
+    #[cfg(feature = "unstable-pre-spec")]
+    fn annotation_ignores_new_content() {
+        use super::Annotation;
+
+        let mut json_data = megolm_content(json!({
+            "rel_type": "m.annotation",
+            "event_id": "$original:example.com",
+            "key": "🦛",
+        }));
+        json_data["m.new_content"] = json!({ "msgtype": "m.text" });
+
+        let content = from_json_value::<EncryptedEventContent>(json_data).unwrap();
+        assert_matches!(
+            content.relates_to(),
+            Some(Relation::Annotation(Annotation { event_id, key }))
+                if *event_id == event_id!("$original:example.com") && key == "🦛"
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "unstable-pre-spec")]
+    fn reference() {
+        use super::Reference;
+
+        let json_data = megolm_content(json!({
+            "rel_type": "m.reference",
+            "event_id": "$original:example.com",
+        }));
+
+        let content = from_json_value::<EncryptedEventContent>(json_data.clone()).unwrap();
+        assert_matches!(
+            content.relates_to(),
+            Some(Relation::Reference(Reference { event_id }))
+                if *event_id == event_id!("$original:example.com")
+        );
+        assert_eq!(to_json_value(&content).unwrap(), json_data);
+    }
+
+    #[test]
+    #[cfg(feature = "unstable-pre-spec")]
+    fn thread_with_reply_fallback() {
+        let json_data = megolm_content(json!({
+            "rel_type": "m.thread",
+            "event_id": "$root:example.com",
+            "m.in_reply_to": { "event_id": "$latest:example.com" },
+            "is_falling_back": true,
+        }));
+
+        let content = from_json_value::<EncryptedEventContent>(json_data.clone()).unwrap();
+        let thread = match content.relates_to() {
+            Some(Relation::Thread(thread)) => thread,
+            other => panic!("expected a thread relation, found {:?}", other),
+        };
+        assert_eq!(thread.event_id, event_id!("$root:example.com"));
+        assert_eq!(
+            thread.in_reply_to.as_ref().map(|r| &r.event_id),
+            Some(&event_id!("$latest:example.com"))
+        );
+        assert!(thread.is_falling_back);
+        assert_eq!(to_json_value(&content).unwrap(), json_data);
+    }
+}
@@ -0,0 +1,182 @@
+//! `Serialize` and `Deserialize` implementations for the `relates_to` field of event contents,
+//! which is represented as the `m.relates_to` field and, for replacements, the `m.new_content`
+//! field of the content.
+//!
+//! This module is meant to be used with `#[serde(flatten, with = "...")]`.
+
+#[cfg(feature = "unstable-pre-spec")]
+use ruma_identifiers::EventId;
+use serde::{
+    de::DeserializeOwned, ser::SerializeStruct as _, Deserialize, Deserializer, Serialize,
+    Serializer,
+};
+use serde_json::{Map as JsonObject, Value as JsonValue};
+
+#[cfg(feature = "unstable-pre-spec")]
+use super::{Annotation, Reference, Replacement, Thread};
+use super::{CustomRelation, InReplyTo, Relation};
+#[cfg(feature = "unstable-pre-spec")]
+use crate::room::message::MessageEventContent;
+
+/// Deserializes the `m.relates_to` (and `m.new_content`) fields of an event content.
+///
+/// Relations with an unknown `rel_type` are deserialized as `Relation::Custom`.
+pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<Relation>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let ev = EventWithRelatesToDeHelper::deserialize(deserializer)?;
+
+    let relates_to = match ev.relates_to {
+        Some(relates_to) => relates_to,
+        None => return Ok(None),
+    };
+    let rel_type = relates_to.get("rel_type").and_then(JsonValue::as_str).map(ToOwned::to_owned);
+
+    let relation = match rel_type.as_deref() {
+        None => {
+            if !relates_to.contains_key("m.in_reply_to") {
+                return Ok(None);
+            }
+
+            let ReplyJsonRepr { in_reply_to } = from_json_object(relates_to)?;
+            Relation::Reply { in_reply_to }
+        }
+        #[cfg(feature = "unstable-pre-spec")]
+        Some("m.replace") => {
+            let ReplacementJsonRepr { event_id } = from_json_object(relates_to)?;
+            let new_content = ev.new_content.map(from_json_value).transpose()?;
+
+            Relation::Replacement(Replacement { event_id, new_content })
+        }
+        #[cfg(feature = "unstable-pre-spec")]
+        Some("m.annotation") => Relation::Annotation(from_json_object(relates_to)?),
+        #[cfg(feature = "unstable-pre-spec")]
+        Some("m.reference") => Relation::Reference(from_json_object(relates_to)?),
+        #[cfg(feature = "unstable-pre-spec")]
+        Some("m.thread") => Relation::Thread(from_json_object(relates_to)?),
+        Some(_) => Relation::Custom(from_json_object(relates_to)?),
+    };
+
+    Ok(Some(relation))
+}
+
+/// Serializes the `relates_to` field of an event content to the `m.relates_to` (and
+/// `m.new_content`) fields.
+pub fn serialize<S>(relation: &Option<Relation>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    let relation = match relation {
+        Some(relation) => relation,
+        // This code path is not reachable if the `relates_to` field is skipped when it is `None`,
+        // which it is for all event contents.
+        None => return serializer.serialize_struct("NoRelation", 0)?.end(),
+    };
+
+    let relates_to = match relation {
+        Relation::Reply { in_reply_to } => RelatesToSerHelper::Reply { in_reply_to },
+        #[cfg(feature = "unstable-pre-spec")]
+        Relation::Replacement(Replacement { event_id, new_content }) => {
+            let relates_to = RelationSerHelper::Replacement { event_id };
+            let helper = EventWithRelatesToSerHelper {
+                relates_to: RelatesToSerHelper::Typed(relates_to),
+                new_content: new_content.as_deref(),
+            };
+
+            return helper.serialize(serializer);
+        }
+        #[cfg(feature = "unstable-pre-spec")]
+        Relation::Annotation(annotation) => RelatesToSerHelper::Annotation(annotation),
+        #[cfg(feature = "unstable-pre-spec")]
+        Relation::Reference(reference) => RelatesToSerHelper::Reference(reference),
+        #[cfg(feature = "unstable-pre-spec")]
+        Relation::Thread(thread) => RelatesToSerHelper::Typed(RelationSerHelper::Thread(thread)),
+        Relation::Custom(custom) => RelatesToSerHelper::Custom(custom),
+    };
+
+    EventWithRelatesToSerHelper {
+        relates_to,
+        #[cfg(feature = "unstable-pre-spec")]
+        new_content: None,
+    }
+    .serialize(serializer)
+}
+
+fn from_json_object<T: DeserializeOwned, E: serde::de::Error>(
+    object: JsonObject<String, JsonValue>,
+) -> Result<T, E> {
+    from_json_value(JsonValue::Object(object))
+}
+
+fn from_json_value<T: DeserializeOwned, E: serde::de::Error>(value: JsonValue) -> Result<T, E> {
+    serde_json::from_value(value).map_err(E::custom)
+}
+
+#[derive(Deserialize)]
+struct EventWithRelatesToDeHelper {
+    #[serde(rename = "m.relates_to")]
+    relates_to: Option<JsonObject<String, JsonValue>>,
+
+    /// Only deserialized to a `MessageEventContent` for `m.replace` relations.
+    #[cfg(feature = "unstable-pre-spec")]
+    #[serde(rename = "m.new_content")]
+    new_content: Option<JsonValue>,
+}
+
+#[derive(Serialize)]
+struct EventWithRelatesToSerHelper<'a> {
+    #[serde(rename = "m.relates_to")]
+    relates_to: RelatesToSerHelper<'a>,
+
+    #[cfg(feature = "unstable-pre-spec")]
+    #[serde(rename = "m.new_content", skip_serializing_if = "Option::is_none")]
+    new_content: Option<&'a MessageEventContent>,
+}
+
+/// The contents of the `m.relates_to` field.
+#[derive(Serialize)]
+#[serde(untagged)]
+enum RelatesToSerHelper<'a> {
+    Reply {
+        #[serde(rename = "m.in_reply_to")]
+        in_reply_to: &'a InReplyTo,
+    },
+
+    #[cfg(feature = "unstable-pre-spec")]
+    Typed(RelationSerHelper<'a>),
+
+    /// Serialized with its own `rel_type`.
+    #[cfg(feature = "unstable-pre-spec")]
+    Annotation(&'a Annotation),
+
+    /// Serialized with its own `rel_type`.
+    #[cfg(feature = "unstable-pre-spec")]
+    Reference(&'a Reference),
+
+    Custom(&'a CustomRelation),
+}
+
+/// A relation with a `rel_type` known to this crate.
+#[cfg(feature = "unstable-pre-spec")]
+#[derive(Serialize)]
+#[serde(tag = "rel_type")]
+enum RelationSerHelper<'a> {
+    #[serde(rename = "m.replace")]
+    Replacement { event_id: &'a EventId },
+
+    #[serde(rename = "m.thread")]
+    Thread(&'a Thread),
+}
+
+#[derive(Deserialize)]
+struct ReplyJsonRepr {
+    #[serde(rename = "m.in_reply_to")]
+    in_reply_to: InReplyTo,
+}
+
+#[cfg(feature = "unstable-pre-spec")]
+#[derive(Deserialize)]
+struct ReplacementJsonRepr {
+    event_id: EventId,
+}
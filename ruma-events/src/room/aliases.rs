@@ -1,12 +1,13 @@
 //! Types for the *m.room.aliases* event.
 
 use ruma_events_macros::StateEventContent;
-use ruma_identifiers::{RoomAliasId, RoomVersionId};
+use ruma_identifiers::{RedactionAlgorithm, RoomAliasId, RoomVersionId};
 use serde::{Deserialize, Serialize};
 use serde_json::value::RawValue as RawJsonValue;
 
 use crate::{
-    EventContent, HasDeserializeFields, RedactedEventContent, RedactedStateEventContent, StateEvent,
+    EventContent, HasDeserializeFields, RedactContent, RedactedEventContent,
+    RedactedStateEventContent, StateEvent,
 };
 
 /// Informs the room about what room aliases it has been given.
@@ -26,17 +27,16 @@ impl AliasesEventContent {
     pub fn new(aliases: Vec<RoomAliasId>) -> Self {
         Self { aliases }
     }
+}
+
+impl RedactContent for AliasesEventContent {
+    type Redacted = RedactedAliasesEventContent;
 
-    /// Redact an `AliasesEventContent` according to current Matrix spec.
-    pub fn redact(self, version: RoomVersionId) -> RedactedAliasesEventContent {
-        // We compare the long way to avoid pre version 6 behavior if/when
+    fn redact(self, version: RoomVersionId) -> RedactedAliasesEventContent {
+        // Unknown room versions don't keep the aliases, to avoid pre version 6 behavior if/when
         // a new room version is introduced.
-        let aliases = match version {
-            RoomVersionId::Version1
-            | RoomVersionId::Version2
-            | RoomVersionId::Version3
-            | RoomVersionId::Version4
-            | RoomVersionId::Version5 => Some(self.aliases),
+        let aliases = match version.rules().map(|rules| rules.redaction_algorithm) {
+            Some(RedactionAlgorithm::V1) => Some(self.aliases),
             _ => None,
         };
 
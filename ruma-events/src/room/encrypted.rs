@@ -7,7 +7,7 @@ use ruma_events_macros::MessageEventContent;
 use ruma_identifiers::DeviceIdBox;
 use serde::{Deserialize, Serialize};
 
-use crate::MessageEvent;
+use crate::{relation::Relation, MessageEvent};
 
 /// An event that defines how messages sent in this room should be encrypted.
 pub type EncryptedEvent = MessageEvent<EncryptedEventContent>;
@@ -27,6 +27,18 @@ pub enum EncryptedEventContent {
     MegolmV1AesSha2(MegolmV1AesSha2Content),
 }
 
+impl EncryptedEventContent {
+    /// Information about related events.
+    ///
+    /// Relations are not encrypted, so they are available without decrypting the event.
+    pub fn relates_to(&self) -> Option<&Relation> {
+        match self {
+            Self::OlmV1Curve25519AesSha2(content) => content.relates_to.as_ref(),
+            Self::MegolmV1AesSha2(content) => content.relates_to.as_ref(),
+        }
+    }
+}
+
 /// The payload for `EncryptedEvent` using the *m.olm.v1.curve25519-aes-sha2* algorithm.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 #[cfg_attr(not(feature = "unstable-exhaustive-types"), non_exhaustive)]
@@ -36,12 +48,20 @@ pub struct OlmV1Curve25519AesSha2Content {
 
     /// The Curve25519 key of the sender.
     pub sender_key: String,
+
+    /// Information about related events.
+    #[serde(
+        flatten,
+        with = "crate::relation::relation_serde",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub relates_to: Option<Relation>,
 }
 
 impl OlmV1Curve25519AesSha2Content {
     /// Creates a new `OlmV1Curve25519AesSha2Content` with the given ciphertext and sender key.
     pub fn new(ciphertext: BTreeMap<String, CiphertextInfo>, sender_key: String) -> Self {
-        Self { ciphertext, sender_key }
+        Self { ciphertext, sender_key, relates_to: None }
     }
 }
 
@@ -84,6 +104,14 @@ pub struct MegolmV1AesSha2Content {
 
     /// The ID of the session used to encrypt the message.
     pub session_id: String,
+
+    /// Information about related events.
+    #[serde(
+        flatten,
+        with = "crate::relation::relation_serde",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub relates_to: Option<Relation>,
 }
 
 /// Mandatory initial set of fields of `MegolmV1AesSha2Content`.
@@ -109,7 +137,7 @@ impl From<MegolmV1AesSha2ContentInit> for MegolmV1AesSha2Content {
     /// Creates a new `MegolmV1AesSha2Content` from the given init struct.
     fn from(init: MegolmV1AesSha2ContentInit) -> Self {
         let MegolmV1AesSha2ContentInit { ciphertext, sender_key, device_id, session_id } = init;
-        Self { ciphertext, sender_key, device_id, session_id }
+        Self { ciphertext, sender_key, device_id, session_id, relates_to: None }
     }
 }
 
@@ -129,6 +157,7 @@ mod tests {
                 sender_key: "sender_key".into(),
                 device_id: "device_id".into(),
                 session_id: "session_id".into(),
+                relates_to: None,
             });
 
         let json_data = json!({
@@ -162,6 +191,7 @@ mod tests {
                 sender_key,
                 device_id,
                 session_id,
+                relates_to: None,
             }) if ciphertext == "ciphertext"
                 && sender_key == "sender_key"
                 && device_id == "device_id"
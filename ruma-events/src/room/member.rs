@@ -61,6 +61,29 @@ pub struct MemberEventContent {
     /// contain information about that invitation.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub third_party_invite: Option<ThirdPartyInvite>,
+
+    /// User-supplied text for why their membership has changed.
+    ///
+    /// For kicks and bans, this is typically the reason for the kick or ban. For other membership
+    /// changes, this is a way for the user to communicate their intent without having to send a
+    /// message to the room, such as in a case where Bob rejects an invite from Alice about an
+    /// upcoming concert, but can't make it that day.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+impl MemberEventContent {
+    /// Creates a new `MemberEventContent` with the given membership state.
+    pub fn new(membership: MembershipState) -> Self {
+        Self {
+            avatar_url: None,
+            displayname: None,
+            is_direct: None,
+            membership,
+            third_party_invite: None,
+            reason: None,
+        }
+    }
 }
 
 /// The membership state of a user.
@@ -115,9 +138,9 @@ pub struct SignedContent {
 }
 
 /// Translation of the membership change in `m.room.member` event.
-#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 #[cfg_attr(not(feature = "unstable-exhaustive-types"), non_exhaustive)]
-pub enum MembershipChange {
+pub enum MembershipChange<'a> {
     /// No change.
     None,
 
@@ -128,91 +151,153 @@ pub enum MembershipChange {
     Joined,
 
     /// User left the room.
-    Left,
+    Left {
+        /// Why the user left the room, if they gave a reason.
+        reason: Option<&'a str>,
+    },
 
     /// User was banned.
-    Banned,
+    Banned {
+        /// The user who banned them.
+        by: &'a UserId,
+
+        /// Why the user was banned, if a reason was given.
+        reason: Option<&'a str>,
+    },
 
     /// User was unbanned.
-    Unbanned,
+    Unbanned {
+        /// The user who unbanned them.
+        by: &'a UserId,
+
+        /// Why the user was unbanned, if a reason was given.
+        reason: Option<&'a str>,
+    },
 
     /// User was kicked.
-    Kicked,
+    Kicked {
+        /// The user who kicked them.
+        by: &'a UserId,
+
+        /// Why the user was kicked, if a reason was given.
+        reason: Option<&'a str>,
+    },
 
     /// User was invited.
-    Invited,
+    Invited {
+        /// The user who invited them.
+        by: &'a UserId,
+
+        /// Why the user was invited, if a reason was given.
+        reason: Option<&'a str>,
+    },
 
     /// User was kicked and banned.
-    KickedAndBanned,
+    KickedAndBanned {
+        /// The user who kicked and banned them.
+        by: &'a UserId,
+
+        /// Why the user was kicked and banned, if a reason was given.
+        reason: Option<&'a str>,
+    },
 
     /// User rejected the invite.
-    InvitationRejected,
+    InvitationRejected {
+        /// Why the user rejected the invite, if they gave a reason.
+        reason: Option<&'a str>,
+    },
 
     /// User had their invite revoked.
-    InvitationRevoked,
+    InvitationRevoked {
+        /// The user who revoked the invite.
+        by: &'a UserId,
+
+        /// Why the invite was revoked, if a reason was given.
+        reason: Option<&'a str>,
+    },
 
     /// `displayname` or `avatar_url` changed.
     ProfileChanged {
-        /// Whether the `displayname` changed.
-        displayname_changed: bool,
+        /// The `displayname` before and after the change, if it changed.
+        displayname_change: Option<Change<Option<&'a str>>>,
 
-        /// Whether the `avatar_url` changed.
-        avatar_url_changed: bool,
+        /// The `avatar_url` before and after the change, if it changed.
+        avatar_url_change: Option<Change<Option<&'a str>>>,
     },
 
     /// Not implemented.
     NotImplemented,
 }
 
+/// A change of a value, e.g. of a user's display name.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Change<T> {
+    /// The old value.
+    pub old: T,
+
+    /// The new value.
+    pub new: T,
+}
+
+impl<T: PartialEq> Change<T> {
+    /// Creates a `Change` from the given values, if they differ.
+    fn new(old: T, new: T) -> Option<Self> {
+        if old == new {
+            None
+        } else {
+            Some(Self { old, new })
+        }
+    }
+}
+
 /// Internal function so all `MemberEventContent` state event kinds can share the same
 /// implementation.
-fn membership_change(
-    content: &MemberEventContent,
-    prev_content: Option<&MemberEventContent>,
-    sender: &UserId,
+fn membership_change<'a>(
+    content: &'a MemberEventContent,
+    prev_content: Option<&'a MemberEventContent>,
+    sender: &'a UserId,
     state_key: &str,
-) -> MembershipChange {
+) -> MembershipChange<'a> {
     use MembershipChange as Ch;
     use MembershipState as St;
 
-    let prev_content = if let Some(prev_content) = &prev_content {
-        prev_content
-    } else {
-        &MemberEventContent {
-            avatar_url: None,
-            displayname: None,
-            is_direct: None,
-            membership: St::Leave,
-            third_party_invite: None,
-        }
-    };
+    // If there is no previous content, the user's previous membership must be assumed as leave.
+    let prev_membership = prev_content.map_or(St::Leave, |c| c.membership);
+    let reason = content.reason.as_deref();
+    let by = sender;
 
-    match (prev_content.membership, &content.membership) {
+    match (prev_membership, &content.membership) {
         (St::Invite, St::Invite) | (St::Leave, St::Leave) | (St::Ban, St::Ban) => Ch::None,
         (St::Invite, St::Join) | (St::Leave, St::Join) => Ch::Joined,
         (St::Invite, St::Leave) => {
             if sender == state_key {
-                Ch::InvitationRevoked
+                Ch::InvitationRejected { reason }
             } else {
-                Ch::InvitationRejected
+                Ch::InvitationRevoked { by, reason }
             }
         }
-        (St::Invite, St::Ban) | (St::Leave, St::Ban) => Ch::Banned,
+        (St::Invite, St::Ban) | (St::Leave, St::Ban) => Ch::Banned { by, reason },
         (St::Join, St::Invite) | (St::Ban, St::Invite) | (St::Ban, St::Join) => Ch::Error,
         (St::Join, St::Join) => Ch::ProfileChanged {
-            displayname_changed: prev_content.displayname != content.displayname,
-            avatar_url_changed: prev_content.avatar_url != content.avatar_url,
+            displayname_change: Change::new(
+                prev_content.and_then(|c| c.displayname.as_deref()),
+                content.displayname.as_deref(),
+            ),
+            avatar_url_change: Change::new(
+                prev_content.and_then(|c| c.avatar_url.as_deref()),
+                content.avatar_url.as_deref(),
+            ),
         },
         (St::Join, St::Leave) => {
             if sender == state_key {
-                Ch::Left
+                Ch::Left { reason }
             } else {
-                Ch::Kicked
+                Ch::Kicked { by, reason }
             }
         }
-        (St::Join, St::Ban) => Ch::KickedAndBanned,
-        (St::Leave, St::Invite) => Ch::Invited,
-        (St::Ban, St::Leave) => Ch::Unbanned,
+        (St::Join, St::Ban) => Ch::KickedAndBanned { by, reason },
+        (St::Leave, St::Invite) => Ch::Invited { by, reason },
+        (St::Ban, St::Leave) => Ch::Unbanned { by, reason },
         (St::Knock, _) | (_, St::Knock) => Ch::NotImplemented,
     }
 }
@@ -221,7 +306,7 @@ impl MemberEvent {
     /// Helper function for membership change. Check [the specification][spec] for details.
     ///
     /// [spec]: https://matrix.org/docs/spec/client_server/latest#m-room-member
-    pub fn membership_change(&self) -> MembershipChange {
+    pub fn membership_change(&self) -> MembershipChange<'_> {
         membership_change(&self.content, self.prev_content.as_ref(), &self.sender, &self.state_key)
     }
 }
@@ -230,7 +315,7 @@ impl SyncStateEvent<MemberEventContent> {
     /// Helper function for membership change. Check [the specification][spec] for details.
     ///
     /// [spec]: https://matrix.org/docs/spec/client_server/latest#m-room-member
-    pub fn membership_change(&self) -> MembershipChange {
+    pub fn membership_change(&self) -> MembershipChange<'_> {
         membership_change(&self.content, self.prev_content.as_ref(), &self.sender, &self.state_key)
     }
 }
@@ -239,7 +324,7 @@ impl StrippedStateEvent<MemberEventContent> {
     /// Helper function for membership change. Check [the specification][spec] for details.
     ///
     /// [spec]: https://matrix.org/docs/spec/client_server/latest#m-room-member
-    pub fn membership_change(&self) -> MembershipChange {
+    pub fn membership_change(&self) -> MembershipChange<'_> {
         membership_change(&self.content, None, &self.sender, &self.state_key)
     }
 }
@@ -252,11 +337,31 @@ mod tests {
     use matches::assert_matches;
     use ruma_common::Raw;
     use ruma_identifiers::{server_key_id, server_name};
-    use serde_json::{from_value as from_json_value, json};
+    use serde_json::{from_value as from_json_value, json, Value as JsonValue};
 
-    use super::{MemberEventContent, MembershipState, SignedContent, ThirdPartyInvite};
+    use super::{
+        Change, MemberEventContent, MembershipChange, MembershipState, SignedContent,
+        ThirdPartyInvite,
+    };
     use crate::StateEvent;
 
+    fn member_event(content: JsonValue, prev_content: Option<JsonValue>) -> JsonValue {
+        let mut json = json!({
+            "type": "m.room.member",
+            "content": content,
+            "event_id": "$h29iv0s8:example.com",
+            "origin_server_ts": 1,
+            "room_id": "!n8f893n9:example.com",
+            "sender": "@alice:example.com",
+            "state_key": "@bob:example.com"
+        });
+        if let Some(prev_content) = prev_content {
+            json["prev_content"] = prev_content;
+        }
+
+        json
+    }
+
     #[test]
     fn serde_with_no_prev_content() {
         let json = json!({
@@ -283,6 +388,7 @@ mod tests {
                     is_direct: None,
                     membership: MembershipState::Join,
                     third_party_invite: None,
+                    reason: None,
                 },
                 event_id,
                 origin_server_ts,
@@ -329,6 +435,7 @@ mod tests {
                     is_direct: None,
                     membership: MembershipState::Join,
                     third_party_invite: None,
+                    reason: None,
                 },
                 event_id,
                 origin_server_ts,
@@ -342,6 +449,7 @@ mod tests {
                     is_direct: None,
                     membership: MembershipState::Join,
                     third_party_invite: None,
+                    reason: None,
                 }),
             } if event_id == "$h29iv0s8:example.com"
                 && origin_server_ts == UNIX_EPOCH + Duration::from_millis(1)
@@ -396,6 +504,7 @@ mod tests {
                         display_name: third_party_displayname,
                         signed: SignedContent { mxid, signatures, token },
                     }),
+                    reason: None,
                 },
                 event_id,
                 origin_server_ts,
@@ -467,6 +576,7 @@ mod tests {
                     is_direct: None,
                     membership: MembershipState::Join,
                     third_party_invite: None,
+                    reason: None,
                 },
                 event_id,
                 origin_server_ts,
@@ -483,6 +593,7 @@ mod tests {
                         display_name: third_party_displayname,
                         signed: SignedContent { mxid, signatures, token },
                     }),
+                    reason: None,
                 }),
             } if event_id == "$143273582443PhrSn:example.org"
                 && origin_server_ts == UNIX_EPOCH + Duration::from_millis(233)
@@ -502,4 +613,74 @@ mod tests {
                 && token == "abc123"
         );
     }
+
+    #[test]
+    fn membership_change_kick_with_reason() {
+        let json = member_event(
+            json!({ "membership": "leave", "reason": "spamming" }),
+            Some(json!({ "membership": "join" })),
+        );
+        let event = from_json_value::<StateEvent<MemberEventContent>>(json).unwrap();
+
+        assert_eq!(event.content.reason.as_deref(), Some("spamming"));
+        assert_matches!(
+            event.membership_change(),
+            MembershipChange::Kicked { by, reason: Some("spamming") }
+                if by == "@alice:example.com"
+        );
+    }
+
+    #[test]
+    fn membership_change_invitation_revoked_and_rejected() {
+        let json =
+            member_event(json!({ "membership": "leave" }), Some(json!({ "membership": "invite" })));
+        let event = from_json_value::<StateEvent<MemberEventContent>>(json).unwrap();
+        assert_matches!(
+            event.membership_change(),
+            MembershipChange::InvitationRevoked { by, reason: None } if by == "@alice:example.com"
+        );
+
+        let mut json = member_event(
+            json!({ "membership": "leave", "reason": "can't make it" }),
+            Some(json!({ "membership": "invite" })),
+        );
+        json["sender"] = json!("@bob:example.com");
+        let event = from_json_value::<StateEvent<MemberEventContent>>(json).unwrap();
+        assert_eq!(
+            event.membership_change(),
+            MembershipChange::InvitationRejected { reason: Some("can't make it") }
+        );
+    }
+
+    #[test]
+    fn membership_change_profile_changed() {
+        let json = member_event(
+            json!({
+                "membership": "join",
+                "displayname": "Bob",
+                "avatar_url": "mxc://example.com/avatar"
+            }),
+            Some(json!({ "membership": "join", "avatar_url": "mxc://example.com/avatar" })),
+        );
+        let event = from_json_value::<StateEvent<MemberEventContent>>(json).unwrap();
+
+        assert_eq!(
+            event.membership_change(),
+            MembershipChange::ProfileChanged {
+                displayname_change: Some(Change { old: None, new: Some("Bob") }),
+                avatar_url_change: None,
+            }
+        );
+    }
+
+    #[test]
+    fn membership_change_without_prev_content() {
+        let json = member_event(json!({ "membership": "invite" }), None);
+        let event = from_json_value::<StateEvent<MemberEventContent>>(json).unwrap();
+
+        assert_matches!(
+            event.membership_change(),
+            MembershipChange::Invited { by, reason: None } if by == "@alice:example.com"
+        );
+    }
 }
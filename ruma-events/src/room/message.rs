@@ -4,13 +4,21 @@ use std::fmt;
 
 use js_int::UInt;
 use ruma_events_macros::MessageEventContent;
-use ruma_identifiers::EventId;
+#[cfg(feature = "unstable-pre-spec")]
+use ruma_identifiers::{DeviceIdBox, EventId, UserId};
 use serde::{Deserialize, Serialize};
 
 use super::{EncryptedFile, ImageInfo, ThumbnailInfo};
 
 pub mod feedback;
 
+mod reply;
+
+#[cfg(feature = "unstable-pre-spec")]
+use crate::key::verification::VerificationMethod;
+#[cfg(feature = "unstable-pre-spec")]
+pub use crate::relation::Replacement;
+pub use crate::relation::{InReplyTo, Relation};
 use crate::MessageEvent as OuterMessageEvent;
 
 /// This event is used when sending messages in a room.
@@ -59,6 +67,11 @@ pub enum MessageEventContent {
     /// A video message.
     #[serde(rename = "m.video")]
     Video(VideoMessageEventContent),
+
+    /// A request to start an in-room key verification.
+    #[cfg(feature = "unstable-pre-spec")]
+    #[serde(rename = "m.key.verification.request")]
+    VerificationRequest(KeyVerificationRequestEventContent),
 }
 
 impl MessageEventContent {
@@ -81,6 +94,170 @@ impl MessageEventContent {
     pub fn notice_html(body: impl Into<String>, html_body: impl Into<String>) -> Self {
         Self::Notice(NoticeMessageEventContent::html(body, html_body))
     }
+
+    /// Creates a message that replaces the content of the message with the ID `original` with
+    /// `new_content`.
+    ///
+    /// The body of the new content, prefixed with `* `, is used as a fallback for clients that
+    /// don't support edits. The relation of `new_content` is discarded.
+    #[cfg(feature = "unstable-pre-spec")]
+    pub fn edit(original: EventId, mut new_content: MessageEventContent) -> Self {
+        *new_content.relates_to_mut() = None;
+
+        let mut content = new_content.clone();
+        *content.body_mut() = format!("* {}", new_content.body());
+        if let Some(Some(formatted)) = content.formatted_mut() {
+            formatted.body = format!("* {}", formatted.body);
+        }
+        *content.relates_to_mut() = Some(Relation::Replacement(Replacement {
+            event_id: original,
+            new_content: Some(Box::new(new_content)),
+        }));
+
+        content
+    }
+
+    /// Creates a [rich reply](https://matrix.org/docs/spec/client_server/r0.6.1#rich-replies) to
+    /// `original` with the given content.
+    ///
+    /// If `new_content` is a text, notice or emote, plain text and HTML fallbacks quoting
+    /// `original`, for clients that don't support rich replies, are prepended to its body and
+    /// formatted body. Reply fallbacks in `original` itself are not quoted. If `new_content` has
+    /// no HTML body, one is created from its plain text body. The body of other message types,
+    /// e.g. the file name of an image, is left untouched. The relation of `new_content` is
+    /// replaced by the reply relation.
+    pub fn reply_to(original: &MessageEvent, mut new_content: MessageEventContent) -> Self {
+        if new_content.has_reply_fallback() {
+            let html_body = match new_content.formatted() {
+                Some(FormattedBody { format: MessageFormat::Html, body }) => body.clone(),
+                _ => reply::plain_to_html(new_content.body()),
+            };
+
+            if let Some(formatted) = new_content.formatted_mut() {
+                let html_fallback = reply::html_fallback(original);
+                *formatted = Some(FormattedBody::html(html_fallback + &html_body));
+            }
+
+            let body = new_content.body_mut();
+            *body = reply::plain_fallback(original) + body;
+        }
+
+        *new_content.relates_to_mut() =
+            Some(Relation::Reply { in_reply_to: InReplyTo::new(original.event_id.clone()) });
+
+        new_content
+    }
+
+    /// Removes the fallbacks of a
+    /// [rich reply](https://matrix.org/docs/spec/client_server/r0.6.1#rich-replies) from the body
+    /// and formatted body of this message, leaving only the reply itself.
+    ///
+    /// Does nothing if this message is not a reply, or not a text, notice or emote.
+    pub fn strip_reply_fallback(&mut self) {
+        let is_reply = matches!(self.relates_to(), Some(Relation::Reply { .. }));
+        if !is_reply || !self.has_reply_fallback() {
+            return;
+        }
+
+        let body = self.body_mut();
+        *body = reply::strip_plain_fallback(body).to_owned();
+
+        if let Some(Some(FormattedBody { format: MessageFormat::Html, body })) =
+            self.formatted_mut()
+        {
+            *body = reply::strip_html_fallback(body).to_owned();
+        }
+    }
+
+    /// The textual representation of this message.
+    pub fn body(&self) -> &str {
+        match self {
+            Self::Audio(content) => &content.body,
+            Self::Emote(content) => &content.body,
+            Self::File(content) => &content.body,
+            Self::Image(content) => &content.body,
+            Self::Location(content) => &content.body,
+            Self::Notice(content) => &content.body,
+            Self::ServerNotice(content) => &content.body,
+            Self::Text(content) => &content.body,
+            Self::Video(content) => &content.body,
+            #[cfg(feature = "unstable-pre-spec")]
+            Self::VerificationRequest(content) => &content.body,
+        }
+    }
+
+    /// Information about related messages, if any.
+    pub fn relates_to(&self) -> Option<&Relation> {
+        match self {
+            Self::Audio(content) => content.relates_to.as_ref(),
+            Self::Emote(content) => content.relates_to.as_ref(),
+            Self::File(content) => content.relates_to.as_ref(),
+            Self::Image(content) => content.relates_to.as_ref(),
+            Self::Location(content) => content.relates_to.as_ref(),
+            Self::Notice(content) => content.relates_to.as_ref(),
+            Self::ServerNotice(content) => content.relates_to.as_ref(),
+            Self::Text(content) => content.relates_to.as_ref(),
+            Self::Video(content) => content.relates_to.as_ref(),
+            #[cfg(feature = "unstable-pre-spec")]
+            Self::VerificationRequest(content) => content.relates_to.as_ref(),
+        }
+    }
+
+    fn body_mut(&mut self) -> &mut String {
+        match self {
+            Self::Audio(content) => &mut content.body,
+            Self::Emote(content) => &mut content.body,
+            Self::File(content) => &mut content.body,
+            Self::Image(content) => &mut content.body,
+            Self::Location(content) => &mut content.body,
+            Self::Notice(content) => &mut content.body,
+            Self::ServerNotice(content) => &mut content.body,
+            Self::Text(content) => &mut content.body,
+            Self::Video(content) => &mut content.body,
+            #[cfg(feature = "unstable-pre-spec")]
+            Self::VerificationRequest(content) => &mut content.body,
+        }
+    }
+
+    /// Whether replies of this message type carry a fallback in their body.
+    fn has_reply_fallback(&self) -> bool {
+        matches!(self, Self::Emote(_) | Self::Notice(_) | Self::Text(_))
+    }
+
+    fn formatted(&self) -> Option<&FormattedBody> {
+        match self {
+            Self::Emote(content) => content.formatted.as_ref(),
+            Self::Notice(content) => content.formatted.as_ref(),
+            Self::Text(content) => content.formatted.as_ref(),
+            _ => None,
+        }
+    }
+
+    /// The formatted body of this message, if its type supports one.
+    fn formatted_mut(&mut self) -> Option<&mut Option<FormattedBody>> {
+        match self {
+            Self::Emote(content) => Some(&mut content.formatted),
+            Self::Notice(content) => Some(&mut content.formatted),
+            Self::Text(content) => Some(&mut content.formatted),
+            _ => None,
+        }
+    }
+
+    fn relates_to_mut(&mut self) -> &mut Option<Relation> {
+        match self {
+            Self::Audio(content) => &mut content.relates_to,
+            Self::Emote(content) => &mut content.relates_to,
+            Self::File(content) => &mut content.relates_to,
+            Self::Image(content) => &mut content.relates_to,
+            Self::Location(content) => &mut content.relates_to,
+            Self::Notice(content) => &mut content.relates_to,
+            Self::ServerNotice(content) => &mut content.relates_to,
+            Self::Text(content) => &mut content.relates_to,
+            Self::Video(content) => &mut content.relates_to,
+            #[cfg(feature = "unstable-pre-spec")]
+            Self::VerificationRequest(content) => &mut content.relates_to,
+        }
+    }
 }
 
 /// The payload for an audio message.
@@ -101,6 +278,15 @@ pub struct AudioMessageEventContent {
     /// Required if the audio clip is encrypted. Information on the encrypted audio clip.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub file: Option<Box<EncryptedFile>>,
+
+    /// Information about related messages for
+    /// [rich replies](https://matrix.org/docs/spec/client_server/r0.6.1#rich-replies) and edits.
+    #[serde(
+        flatten,
+        with = "crate::relation::relation_serde",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub relates_to: Option<Relation>,
 }
 
 /// Metadata about an audio clip.
@@ -128,6 +314,15 @@ pub struct EmoteMessageEventContent {
     /// Formatted form of the message `body`.
     #[serde(flatten)]
     pub formatted: Option<FormattedBody>,
+
+    /// Information about related messages for
+    /// [rich replies](https://matrix.org/docs/spec/client_server/r0.6.1#rich-replies) and edits.
+    #[serde(
+        flatten,
+        with = "crate::relation::relation_serde",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub relates_to: Option<Relation>,
 }
 
 /// The payload for a file message.
@@ -153,6 +348,15 @@ pub struct FileMessageEventContent {
     /// Required if file is encrypted. Information on the encrypted file.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub file: Option<Box<EncryptedFile>>,
+
+    /// Information about related messages for
+    /// [rich replies](https://matrix.org/docs/spec/client_server/r0.6.1#rich-replies) and edits.
+    #[serde(
+        flatten,
+        with = "crate::relation::relation_serde",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub relates_to: Option<Relation>,
 }
 
 /// Metadata about a file.
@@ -198,6 +402,15 @@ pub struct ImageMessageEventContent {
     /// Required if image is encrypted. Information on the encrypted image.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub file: Option<Box<EncryptedFile>>,
+
+    /// Information about related messages for
+    /// [rich replies](https://matrix.org/docs/spec/client_server/r0.6.1#rich-replies) and edits.
+    #[serde(
+        flatten,
+        with = "crate::relation::relation_serde",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub relates_to: Option<Relation>,
 }
 
 /// The payload for a location message.
@@ -213,6 +426,15 @@ pub struct LocationMessageEventContent {
     /// Info about the location being represented.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub info: Option<Box<LocationInfo>>,
+
+    /// Information about related messages for
+    /// [rich replies](https://matrix.org/docs/spec/client_server/r0.6.1#rich-replies) and edits.
+    #[serde(
+        flatten,
+        with = "crate::relation::relation_serde",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub relates_to: Option<Relation>,
 }
 
 /// Thumbnail info associated with a location.
@@ -244,9 +466,13 @@ pub struct NoticeMessageEventContent {
     pub formatted: Option<FormattedBody>,
 
     /// Information about related messages for
-    /// [rich replies](https://matrix.org/docs/spec/client_server/r0.6.1#rich-replies).
-    #[serde(rename = "m.relates_to", skip_serializing_if = "Option::is_none")]
-    pub relates_to: Option<RelatesTo>,
+    /// [rich replies](https://matrix.org/docs/spec/client_server/r0.6.1#rich-replies) and edits.
+    #[serde(
+        flatten,
+        with = "crate::relation::relation_serde",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub relates_to: Option<Relation>,
 }
 
 impl NoticeMessageEventContent {
@@ -285,6 +511,15 @@ pub struct ServerNoticeMessageEventContent {
     /// Required if the notice type is `m.server_notice.usage_limit_reached`.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub limit_type: Option<LimitType>,
+
+    /// Information about related messages for
+    /// [rich replies](https://matrix.org/docs/spec/client_server/r0.6.1#rich-replies) and edits.
+    #[serde(
+        flatten,
+        with = "crate::relation::relation_serde",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub relates_to: Option<Relation>,
 }
 
 /// Types of server notices.
@@ -370,9 +605,13 @@ pub struct TextMessageEventContent {
     pub formatted: Option<FormattedBody>,
 
     /// Information about related messages for
-    /// [rich replies](https://matrix.org/docs/spec/client_server/r0.6.1#rich-replies).
-    #[serde(rename = "m.relates_to", skip_serializing_if = "Option::is_none")]
-    pub relates_to: Option<RelatesTo>,
+    /// [rich replies](https://matrix.org/docs/spec/client_server/r0.6.1#rich-replies) and edits.
+    #[serde(
+        flatten,
+        with = "crate::relation::relation_serde",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub relates_to: Option<Relation>,
 }
 
 impl TextMessageEventContent {
@@ -416,6 +655,15 @@ pub struct VideoMessageEventContent {
     /// Required if video clip is encrypted. Information on the encrypted video clip.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub file: Option<Box<EncryptedFile>>,
+
+    /// Information about related messages for
+    /// [rich replies](https://matrix.org/docs/spec/client_server/r0.6.1#rich-replies) and edits.
+    #[serde(
+        flatten,
+        with = "crate::relation::relation_serde",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub relates_to: Option<Relation>,
 }
 
 /// Metadata about a video.
@@ -457,20 +705,48 @@ pub struct VideoInfo {
     pub thumbnail_file: Option<Box<EncryptedFile>>,
 }
 
-/// Information about related messages for
-/// [rich replies](https://matrix.org/docs/spec/client_server/r0.6.1#rich-replies).
+/// The payload for a key verification request message.
+#[cfg(feature = "unstable-pre-spec")]
 #[derive(Clone, Debug, Deserialize, Serialize)]
-pub struct RelatesTo {
-    /// Information about another message being replied to.
-    #[serde(rename = "m.in_reply_to")]
-    pub in_reply_to: Option<InReplyTo>,
+#[cfg_attr(not(feature = "unstable-exhaustive-types"), non_exhaustive)]
+pub struct KeyVerificationRequestEventContent {
+    /// A fallback message to alert users that their client does not support the key verification
+    /// framework.
+    pub body: String,
+
+    /// The verification methods supported by the sender.
+    pub methods: Vec<VerificationMethod>,
+
+    /// The device ID which is initiating the request.
+    pub from_device: DeviceIdBox,
+
+    /// The user ID which should receive the request.
+    ///
+    /// Users should only respond to verification requests if they are named in this field.
+    pub to: UserId,
+
+    /// Information about related messages for
+    /// [rich replies](https://matrix.org/docs/spec/client_server/r0.6.1#rich-replies) and edits.
+    #[serde(
+        flatten,
+        with = "crate::relation::relation_serde",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub relates_to: Option<Relation>,
 }
 
-/// Information about the event a "rich reply" is replying to.
-#[derive(Clone, Debug, Deserialize, Serialize)]
-pub struct InReplyTo {
-    /// The event being replied to.
-    pub event_id: EventId,
+#[cfg(feature = "unstable-pre-spec")]
+impl KeyVerificationRequestEventContent {
+    /// Creates a new `KeyVerificationRequestEventContent` with the given body, methods, device ID
+    /// and recipient.
+    pub fn new(
+        body: String,
+        methods: Vec<VerificationMethod>,
+        from_device: DeviceIdBox,
+        to: UserId,
+    ) -> Self {
+        Self { body, methods, from_device, to, relates_to: None }
+    }
 }
 
 #[cfg(test)]
@@ -482,9 +758,12 @@ mod tests {
     use ruma_identifiers::{event_id, room_id, user_id};
     use serde_json::{from_value as from_json_value, json, to_value as to_json_value};
 
-    use super::{AudioMessageEventContent, FormattedBody, MessageEventContent, MessageFormat};
+    use super::{
+        AudioMessageEventContent, EmoteMessageEventContent, FormattedBody, MessageEventContent,
+        MessageFormat,
+    };
     use crate::{
-        room::message::{InReplyTo, RelatesTo, TextMessageEventContent},
+        room::message::{InReplyTo, Relation, TextMessageEventContent},
         MessageEvent, Unsigned,
     };
 
@@ -496,6 +775,7 @@ mod tests {
                 info: None,
                 url: Some("http://example.com/audio.mp3".into()),
                 file: None,
+                relates_to: None,
             }),
             event_id: event_id!("$143273582443PhrSn:example.org"),
             origin_server_ts: UNIX_EPOCH + Duration::from_millis(10_000),
@@ -528,6 +808,7 @@ mod tests {
             info: None,
             url: Some("http://example.com/audio.mp3".into()),
             file: None,
+            relates_to: None,
         });
 
         assert_eq!(
@@ -582,10 +863,8 @@ mod tests {
         let message_event_content = MessageEventContent::Text(TextMessageEventContent {
             body: "> <@test:example.com> test\n\ntest reply".to_owned(),
             formatted: None,
-            relates_to: Some(RelatesTo {
-                in_reply_to: Some(InReplyTo {
-                    event_id: event_id!("$15827405538098VGFWH:example.com"),
-                }),
+            relates_to: Some(Relation::Reply {
+                in_reply_to: InReplyTo { event_id: event_id!("$15827405538098VGFWH:example.com") },
             }),
         });
 
@@ -620,6 +899,7 @@ mod tests {
                 info: None,
                 url: Some(url),
                 file: None,
+                relates_to: None,
             }) if body == "test" && url == "http://example.com/audio.mp3"
         );
     }
@@ -635,4 +915,265 @@ mod tests {
             .deserialize()
             .is_err());
     }
+
+    #[test]
+    #[cfg(feature = "unstable-pre-spec")]
+    fn edit_serialization() {
+        let content = MessageEventContent::edit(
+            event_id!("$original:example.com"),
+            MessageEventContent::text_html("new body", "<em>new</em> body"),
+        );
+
+        assert_eq!(
+            to_json_value(&content).unwrap(),
+            json!({
+                "body": "* new body",
+                "msgtype": "m.text",
+                "format": "org.matrix.custom.html",
+                "formatted_body": "* <em>new</em> body",
+                "m.new_content": {
+                    "body": "new body",
+                    "msgtype": "m.text",
+                    "format": "org.matrix.custom.html",
+                    "formatted_body": "<em>new</em> body",
+                },
+                "m.relates_to": {
+                    "rel_type": "m.replace",
+                    "event_id": "$original:example.com",
+                },
+            })
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "unstable-pre-spec")]
+    fn edit_deserialization() {
+        use super::Replacement;
+
+        let json_data = json!({
+            "body": "* new body",
+            "msgtype": "m.notice",
+            "m.new_content": {
+                "body": "new body",
+                "msgtype": "m.notice",
+            },
+            "m.relates_to": {
+                "rel_type": "m.replace",
+                "event_id": "$original:example.com",
+            },
+        });
+
+        let content = from_json_value::<MessageEventContent>(json_data).unwrap();
+
+        assert_eq!(content.body(), "* new body");
+        assert_matches!(
+            content.relates_to(),
+            Some(Relation::Replacement(Replacement { event_id, new_content: Some(new_content) }))
+                if *event_id == event_id!("$original:example.com")
+                    && new_content.body() == "new body"
+                    && new_content.relates_to().is_none()
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "unstable-pre-spec")]
+    fn new_content_round_trips_for_all_msgtypes() {
+        let contents = vec![
+            json!({ "body": "audio", "msgtype": "m.audio", "url": "mxc://example.com/audio" }),
+            json!({ "body": "emote", "msgtype": "m.emote" }),
+            json!({ "body": "file", "msgtype": "m.file", "url": "mxc://example.com/file" }),
+            json!({ "body": "image", "msgtype": "m.image", "url": "mxc://example.com/image" }),
+            json!({ "body": "location", "msgtype": "m.location", "geo_uri": "geo:0,0" }),
+            json!({ "body": "notice", "msgtype": "m.notice" }),
+            json!({
+                "body": "server notice",
+                "msgtype": "m.server_notice",
+                "server_notice_type": "m.server_notice.usage_limit_reached",
+            }),
+            json!({ "body": "text", "msgtype": "m.text" }),
+            json!({ "body": "video", "msgtype": "m.video", "url": "mxc://example.com/video" }),
+        ];
+
+        for new_content in contents {
+            let mut json_data = new_content.clone();
+            json_data["m.new_content"] = new_content;
+            json_data["m.relates_to"] =
+                json!({ "rel_type": "m.replace", "event_id": "$original:example.com" });
+
+            let content = from_json_value::<MessageEventContent>(json_data.clone()).unwrap();
+            assert!(content.relates_to().is_some());
+            assert_eq!(to_json_value(&content).unwrap(), json_data);
+        }
+    }
+
+    #[test]
+    fn unknown_relation_round_trips() {
+        let json_data = json!({
+            "body": "text",
+            "msgtype": "m.text",
+            "m.relates_to": {
+                "rel_type": "io.ruma.unknown",
+                "event_id": "$original:example.com",
+            },
+        });
+
+        let content = from_json_value::<MessageEventContent>(json_data.clone()).unwrap();
+        assert_matches!(
+            content.relates_to(),
+            Some(Relation::Custom(relation)) if relation.rel_type == "io.ruma.unknown"
+        );
+        assert_eq!(to_json_value(&content).unwrap(), json_data);
+    }
+
+    fn original_message(content: MessageEventContent) -> MessageEvent<MessageEventContent> {
+        MessageEvent {
+            content,
+            event_id: event_id!("$original:example.org"),
+            origin_server_ts: UNIX_EPOCH + Duration::from_millis(10_000),
+            room_id: room_id!("!room:example.org"),
+            sender: user_id!("@alice:example.org"),
+            unsigned: Unsigned::default(),
+        }
+    }
+
+    #[test]
+    fn reply_to_plain_text() {
+        let original = original_message(MessageEventContent::text_plain("multi\nline <text>"));
+        let reply =
+            MessageEventContent::reply_to(&original, MessageEventContent::text_plain("reply"));
+
+        assert_eq!(
+            to_json_value(&reply).unwrap(),
+            json!({
+                "msgtype": "m.text",
+                "body": "> <@alice:example.org> multi\n> line <text>\n\nreply",
+                "format": "org.matrix.custom.html",
+                "formatted_body": "<mx-reply><blockquote>\
+                    <a href=\"https://matrix.to/#/!room:example.org/$original:example.org\">In reply to</a> \
+                    <a href=\"https://matrix.to/#/@alice:example.org\">@alice:example.org</a>\
+                    <br />multi<br />line &lt;text&gt;\
+                    </blockquote></mx-reply>reply",
+                "m.relates_to": {
+                    "m.in_reply_to": {
+                        "event_id": "$original:example.org",
+                    },
+                },
+            })
+        );
+    }
+
+    #[test]
+    fn reply_to_emote_and_image() {
+        let emote = original_message(MessageEventContent::Emote(EmoteMessageEventContent {
+            body: "waves".into(),
+            formatted: Some(FormattedBody::html("<em>waves</em>")),
+            relates_to: None,
+        }));
+        let reply = MessageEventContent::reply_to(
+            &emote,
+            MessageEventContent::notice_html("reply", "<b>reply</b>"),
+        );
+
+        assert_eq!(reply.body(), "> * <@alice:example.org> waves\n\nreply");
+        assert_matches!(
+            reply.formatted(),
+            Some(FormattedBody { body, .. }) if body.ends_with(
+                "* <a href=\"https://matrix.to/#/@alice:example.org\">@alice:example.org</a>\
+                <br /><em>waves</em></blockquote></mx-reply><b>reply</b>"
+            )
+        );
+
+        let image = original_message(
+            from_json_value(json!({
+                "body": "cat.png",
+                "msgtype": "m.image",
+                "url": "mxc://example.org/cat",
+            }))
+            .unwrap(),
+        );
+        let reply = MessageEventContent::reply_to(&image, MessageEventContent::text_plain("nice"));
+
+        assert_eq!(reply.body(), "> <@alice:example.org> sent an image.\n\nnice");
+    }
+
+    #[test]
+    fn reply_escapes_identifiers() {
+        let mut original = original_message(MessageEventContent::text_plain("text"));
+        original.room_id = room_id!("!a\"><b>x</b>:example.org");
+        original.event_id = event_id!("$acR1l0raoZnm60CBwAVgqbZqoO/mYU81xysh+u7XcJk");
+        let reply =
+            MessageEventContent::reply_to(&original, MessageEventContent::text_plain("reply"));
+
+        assert_matches!(
+            reply.formatted(),
+            Some(FormattedBody { body, .. }) if body.starts_with(
+                "<mx-reply><blockquote>\
+                <a href=\"https://matrix.to/#/!a%22%3E%3Cb%3Ex%3C%2Fb%3E:example.org/\
+                $acR1l0raoZnm60CBwAVgqbZqoO%2FmYU81xysh%2Bu7XcJk\">In reply to</a> "
+            )
+        );
+    }
+
+    #[test]
+    fn reply_with_image() {
+        let original = original_message(MessageEventContent::text_plain("send a cat"));
+        let image: MessageEventContent = from_json_value(json!({
+            "body": "> cat.png",
+            "msgtype": "m.image",
+            "url": "mxc://example.org/cat",
+        }))
+        .unwrap();
+        let mut reply = MessageEventContent::reply_to(&original, image);
+
+        assert_eq!(
+            to_json_value(&reply).unwrap(),
+            json!({
+                "body": "> cat.png",
+                "msgtype": "m.image",
+                "url": "mxc://example.org/cat",
+                "m.relates_to": {
+                    "m.in_reply_to": {
+                        "event_id": "$original:example.org",
+                    },
+                },
+            })
+        );
+
+        reply.strip_reply_fallback();
+        assert_eq!(reply.body(), "> cat.png");
+    }
+
+    #[test]
+    fn reply_to_reply_and_strip_fallback() {
+        let first_reply = MessageEventContent::reply_to(
+            &original_message(MessageEventContent::text_plain("original")),
+            MessageEventContent::text_html("first", "<b>first</b>"),
+        );
+        let mut second_reply = MessageEventContent::reply_to(
+            &original_message(first_reply),
+            MessageEventContent::text_plain("second"),
+        );
+
+        assert_eq!(second_reply.body(), "> <@alice:example.org> first\n\nsecond");
+        assert_matches!(
+            second_reply.formatted(),
+            Some(FormattedBody { body, .. })
+                if body.ends_with("<br /><b>first</b></blockquote></mx-reply>second")
+                    && body.matches("<mx-reply>").count() == 1
+        );
+
+        second_reply.strip_reply_fallback();
+        assert_eq!(second_reply.body(), "second");
+        assert_matches!(
+            second_reply.formatted(),
+            Some(FormattedBody { body, .. }) if body == "second"
+        );
+    }
+
+    #[test]
+    fn strip_reply_fallback_ignores_non_replies() {
+        let mut content = MessageEventContent::text_plain("> quote\n\ntext");
+        content.strip_reply_fallback();
+        assert_eq!(content.body(), "> quote\n\ntext");
+    }
 }
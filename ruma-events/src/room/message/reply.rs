@@ -0,0 +1,209 @@
+//! Generation and removal of the fallbacks of
+//! [rich replies](https://matrix.org/docs/spec/client_server/r0.6.1#rich-replies).
+
+use std::fmt::Write as _;
+
+use percent_encoding::{utf8_percent_encode, AsciiSet, CONTROLS};
+
+use super::{FormattedBody, MessageEvent, MessageEventContent, MessageFormat};
+
+/// The characters that are percent-encoded in the identifiers of a matrix.to link.
+///
+/// This includes the characters with a special meaning in URIs and in HTML, but not the sigils
+/// and the `:` separating the server name.
+const MATRIX_TO_ID: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'&')
+    .add(b'\'')
+    .add(b'+')
+    .add(b'/')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?')
+    .add(b'[')
+    .add(b'\\')
+    .add(b']')
+    .add(b'^')
+    .add(b'`')
+    .add(b'{')
+    .add(b'|')
+    .add(b'}');
+
+/// Creates the plain text fallback of a reply to `original`, without the reply itself.
+///
+/// Every line of the fallback is prefixed with `> `, and it is followed by an empty line.
+pub fn plain_fallback(original: &MessageEvent) -> String {
+    let sender = &original.sender;
+    let (emote_prefix, body) = match &original.content {
+        MessageEventContent::Audio(_) => ("", "sent an audio file."),
+        MessageEventContent::Emote(content) => ("* ", strip_plain_fallback(&content.body)),
+        MessageEventContent::File(_) => ("", "sent a file."),
+        MessageEventContent::Image(_) => ("", "sent an image."),
+        MessageEventContent::Location(_) => ("", "sent a location."),
+        MessageEventContent::Notice(content) => ("", strip_plain_fallback(&content.body)),
+        MessageEventContent::ServerNotice(content) => ("", strip_plain_fallback(&content.body)),
+        MessageEventContent::Text(content) => ("", strip_plain_fallback(&content.body)),
+        MessageEventContent::Video(_) => ("", "sent a video."),
+        #[cfg(feature = "unstable-pre-spec")]
+        MessageEventContent::VerificationRequest(content) => {
+            ("", strip_plain_fallback(&content.body))
+        }
+    };
+
+    let mut lines = body.lines();
+    let mut fallback =
+        format!("> {}<{}> {}\n", emote_prefix, sender, lines.next().unwrap_or_default());
+    for line in lines {
+        writeln!(fallback, "> {}", line).expect("writing to a String can't fail");
+    }
+    fallback.push('\n');
+
+    fallback
+}
+
+/// Creates the HTML fallback of a reply to `original`, without the reply itself.
+pub fn html_fallback(original: &MessageEvent) -> String {
+    let quote = match &original.content {
+        MessageEventContent::Audio(_) => "sent an audio file.".to_owned(),
+        MessageEventContent::Emote(content) => {
+            formatted_or_plain_body(&content.formatted, &content.body)
+        }
+        MessageEventContent::File(_) => "sent a file.".to_owned(),
+        MessageEventContent::Image(_) => "sent an image.".to_owned(),
+        MessageEventContent::Location(_) => "sent a location.".to_owned(),
+        MessageEventContent::Notice(content) => {
+            formatted_or_plain_body(&content.formatted, &content.body)
+        }
+        MessageEventContent::ServerNotice(content) => formatted_or_plain_body(&None, &content.body),
+        MessageEventContent::Text(content) => {
+            formatted_or_plain_body(&content.formatted, &content.body)
+        }
+        MessageEventContent::Video(_) => "sent a video.".to_owned(),
+        #[cfg(feature = "unstable-pre-spec")]
+        MessageEventContent::VerificationRequest(content) => {
+            formatted_or_plain_body(&None, &content.body)
+        }
+    };
+    let emote_prefix = match original.content {
+        MessageEventContent::Emote(_) => "* ",
+        _ => "",
+    };
+
+    format!(
+        "<mx-reply>\
+            <blockquote>\
+                <a href=\"https://matrix.to/#/{room_id}/{event_id}\">In reply to</a> \
+                {emote_prefix}<a href=\"https://matrix.to/#/{sender_id}\">{sender}</a>\
+                <br />\
+                {quote}\
+            </blockquote>\
+        </mx-reply>",
+        room_id = matrix_to_id(original.room_id.as_str()),
+        event_id = matrix_to_id(original.event_id.as_str()),
+        emote_prefix = emote_prefix,
+        sender_id = matrix_to_id(original.sender.as_str()),
+        sender = escape_html(original.sender.as_str()),
+        quote = quote,
+    )
+}
+
+/// Converts the given plain text body to HTML.
+pub fn plain_to_html(body: &str) -> String {
+    escape_html(body).replace('\n', "<br />")
+}
+
+/// Removes the plain text fallback of a rich reply from the given body, if it has one.
+///
+/// The fallback consists of all lines at the start of the body that start with `>`, and the
+/// empty line that follows them.
+pub fn strip_plain_fallback(body: &str) -> &str {
+    if !body.starts_with("> ") {
+        return body;
+    }
+
+    let mut fallback_len = 0;
+    for line in body.split('\n') {
+        if line.starts_with('>') {
+            fallback_len += line.len() + 1;
+        } else {
+            if line.is_empty() {
+                fallback_len += 1;
+            }
+            break;
+        }
+    }
+
+    body.get(fallback_len..).unwrap_or_default()
+}
+
+/// Removes the HTML fallback of a rich reply, the `<mx-reply>` element, from the given formatted
+/// body, if it has one.
+pub fn strip_html_fallback(body: &str) -> &str {
+    const CLOSING_TAG: &str = "</mx-reply>";
+
+    if !body.starts_with("<mx-reply>") {
+        return body;
+    }
+
+    match body.find(CLOSING_TAG) {
+        Some(idx) => &body[idx + CLOSING_TAG.len()..],
+        None => body,
+    }
+}
+
+/// The HTML version of a quoted message, without its own reply fallback.
+fn formatted_or_plain_body(formatted: &Option<FormattedBody>, body: &str) -> String {
+    match formatted {
+        Some(FormattedBody { format: MessageFormat::Html, body }) => {
+            strip_html_fallback(body).to_owned()
+        }
+        _ => plain_to_html(strip_plain_fallback(body)),
+    }
+}
+
+/// Encodes an identifier for a matrix.to link in an HTML attribute.
+fn matrix_to_id(id: &str) -> String {
+    escape_html(&utf8_percent_encode(id, MATRIX_TO_ID).to_string())
+}
+
+fn escape_html(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{strip_html_fallback, strip_plain_fallback};
+
+    #[test]
+    fn strip_plain() {
+        assert_eq!(strip_plain_fallback("> <@alice:example.com> hi\n> there\n\nhello"), "hello");
+        assert_eq!(strip_plain_fallback("> <@alice:example.com> hi\n\n"), "");
+        assert_eq!(strip_plain_fallback("no fallback\n> quote"), "no fallback\n> quote");
+    }
+
+    #[test]
+    fn strip_html() {
+        assert_eq!(
+            strip_html_fallback("<mx-reply><blockquote>hi</blockquote></mx-reply><b>hello</b>"),
+            "<b>hello</b>"
+        );
+        assert_eq!(strip_html_fallback("<b>hello</b>"), "<b>hello</b>");
+        assert_eq!(strip_html_fallback("<mx-reply>unclosed"), "<mx-reply>unclosed");
+    }
+}
@@ -90,6 +90,89 @@ impl Default for PowerLevelsEventContent {
     }
 }
 
+impl PowerLevelsEventContent {
+    /// Creates the power levels that apply to a room without an `m.room.power_levels` event.
+    ///
+    /// The creator of the room has power level 100 and every other user power level 0. Every
+    /// user can send state events and invite other users, the other default levels are the same
+    /// as for `PowerLevelsEventContent::default()`.
+    pub fn without_power_levels_event(creator: UserId) -> Self {
+        let mut users = BTreeMap::new();
+        users.insert(creator, Int::from(100));
+
+        Self { invite: Int::default(), state_default: Int::default(), users, ..Self::default() }
+    }
+
+    /// The power level of the given user.
+    ///
+    /// This is the user's level in `users`, or `users_default` if the user isn't listed.
+    pub fn user_power(&self, user_id: &UserId) -> Int {
+        self.users.get(user_id).copied().unwrap_or(self.users_default)
+    }
+
+    /// The power level required to send a message event of the given type.
+    pub fn message_power(&self, event_type: &EventType) -> Int {
+        self.events.get(event_type).copied().unwrap_or(self.events_default)
+    }
+
+    /// The power level required to send a state event of the given type.
+    pub fn state_power(&self, event_type: &EventType) -> Int {
+        self.events.get(event_type).copied().unwrap_or(self.state_default)
+    }
+
+    /// Whether the given user can send message events of the given type.
+    pub fn user_can_send_message(&self, user_id: &UserId, event_type: EventType) -> bool {
+        self.user_power(user_id) >= self.message_power(&event_type)
+    }
+
+    /// Whether the given user can send state events of the given type.
+    pub fn user_can_send_state(&self, user_id: &UserId, event_type: EventType) -> bool {
+        self.user_power(user_id) >= self.state_power(&event_type)
+    }
+
+    /// Whether the given user can ban other users.
+    pub fn user_can_ban(&self, user_id: &UserId) -> bool {
+        self.user_power(user_id) >= self.ban
+    }
+
+    /// Whether the given user can kick other users.
+    pub fn user_can_kick(&self, user_id: &UserId) -> bool {
+        self.user_power(user_id) >= self.kick
+    }
+
+    /// Whether the given user can invite other users.
+    pub fn user_can_invite(&self, user_id: &UserId) -> bool {
+        self.user_power(user_id) >= self.invite
+    }
+
+    /// Whether the given user can redact events sent by other users.
+    ///
+    /// Every user can redact their own events, as long as they are allowed to send
+    /// `m.room.redaction` events.
+    pub fn user_can_redact(&self, user_id: &UserId) -> bool {
+        self.user_power(user_id) >= self.redact
+    }
+
+    /// Whether the given user can trigger an `@room` notification.
+    pub fn user_can_trigger_room_notification(&self, user_id: &UserId) -> bool {
+        self.user_power(user_id) >= self.notifications.room
+    }
+
+    /// Whether `actor` can change the power level of `target` to `new_level`.
+    ///
+    /// This requires `actor` to be allowed to send `m.room.power_levels` events and `new_level`
+    /// to be no higher than the power level of `actor`. Users can always lower their own power
+    /// level, but the power level of other users can only be changed if it is lower than the one
+    /// of `actor`.
+    pub fn can_change_power_level(&self, actor: &UserId, target: &UserId, new_level: Int) -> bool {
+        let actor_power = self.user_power(actor);
+
+        self.user_can_send_state(actor, EventType::RoomPowerLevels)
+            && new_level <= actor_power
+            && (actor == target || self.user_power(target) < actor_power)
+    }
+}
+
 /// The power level requirements for specific notification types.
 #[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
 pub struct NotificationPowerLevels {
@@ -266,4 +349,72 @@ mod tests {
 
         assert_eq!(actual, expected);
     }
+
+    #[test]
+    fn user_power_levels() {
+        let alice = user_id!("@alice:example.com");
+        let bob = user_id!("@bob:example.com");
+        let content = PowerLevelsEventContent {
+            events: btreemap! {
+                EventType::RoomName => Int::from(10),
+                EventType::Sticker => Int::from(10),
+            },
+            users: btreemap! { alice.clone() => Int::from(50) },
+            ..PowerLevelsEventContent::default()
+        };
+
+        assert_eq!(content.user_power(&alice), Int::from(50));
+        assert_eq!(content.user_power(&bob), Int::from(0));
+
+        assert!(content.user_can_send_message(&bob, EventType::RoomMessage));
+        assert!(!content.user_can_send_message(&bob, EventType::Sticker));
+        assert!(content.user_can_send_state(&alice, EventType::RoomTopic));
+        assert!(!content.user_can_send_state(&bob, EventType::RoomTopic));
+        assert!(!content.user_can_send_state(&bob, EventType::RoomName));
+
+        assert!(content.user_can_ban(&alice));
+        assert!(content.user_can_kick(&alice));
+        assert!(content.user_can_redact(&alice));
+        assert!(content.user_can_trigger_room_notification(&alice));
+        assert!(!content.user_can_invite(&bob));
+        assert!(!content.user_can_trigger_room_notification(&bob));
+    }
+
+    #[test]
+    fn change_power_level() {
+        let alice = user_id!("@alice:example.com");
+        let bob = user_id!("@bob:example.com");
+        let carl = user_id!("@carl:example.com");
+        let dan = user_id!("@dan:example.com");
+        let content = PowerLevelsEventContent {
+            users: btreemap! {
+                alice.clone() => Int::from(100),
+                bob.clone() => Int::from(100),
+                carl.clone() => Int::from(50),
+            },
+            ..PowerLevelsEventContent::default()
+        };
+
+        assert!(content.can_change_power_level(&alice, &carl, Int::from(100)));
+        assert!(!content.can_change_power_level(&alice, &carl, Int::from(101)));
+        assert!(!content.can_change_power_level(&alice, &bob, Int::from(50)));
+        assert!(content.can_change_power_level(&alice, &alice, Int::from(50)));
+        assert!(content.can_change_power_level(&carl, &carl, Int::from(0)));
+        assert!(content.can_change_power_level(&carl, &dan, Int::from(50)));
+        assert!(!content.can_change_power_level(&dan, &dan, Int::from(0)));
+    }
+
+    #[test]
+    fn without_power_levels_event() {
+        let creator = user_id!("@creator:example.com");
+        let other = user_id!("@other:example.com");
+        let content = PowerLevelsEventContent::without_power_levels_event(creator.clone());
+
+        assert_eq!(content.user_power(&creator), Int::from(100));
+        assert_eq!(content.user_power(&other), Int::from(0));
+        assert!(content.user_can_send_state(&other, EventType::RoomTopic));
+        assert!(content.user_can_invite(&other));
+        assert!(!content.user_can_ban(&other));
+        assert!(content.user_can_ban(&creator));
+    }
 }
@@ -3,12 +3,13 @@
 use std::time::SystemTime;
 
 use ruma_events_macros::{Event, EventContent};
-use ruma_identifiers::{EventId, RoomId, UserId};
+use ruma_identifiers::{EventId, RoomId, RoomVersionId, UserId};
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    MessageEventContent, RedactedMessageEventContent, RedactedStateEventContent, RoomEventContent,
-    Unsigned,
+    MessageEventContent, Redact, RedactContent, RedactedMessageEvent, RedactedMessageEventContent,
+    RedactedStateEventContent, RedactedSyncMessageEvent, RedactedSyncUnsigned, RedactedUnsigned,
+    RoomEventContent, Unsigned,
 };
 
 /// Redaction event.
@@ -58,6 +59,37 @@ pub struct SyncRedactionEvent {
     pub unsigned: Unsigned,
 }
 
+impl Redact for RedactionEvent {
+    type Redacted = RedactedMessageEvent<RedactedRedactionEventContent>;
+    type Redaction = RedactionEvent;
+
+    fn redact(self, redaction: RedactionEvent, version: RoomVersionId) -> Self::Redacted {
+        RedactedMessageEvent {
+            content: self.content.redact(version),
+            event_id: self.event_id,
+            sender: self.sender,
+            origin_server_ts: self.origin_server_ts,
+            room_id: self.room_id,
+            unsigned: RedactedUnsigned { redacted_because: Some(Box::new(redaction)) },
+        }
+    }
+}
+
+impl Redact for SyncRedactionEvent {
+    type Redacted = RedactedSyncMessageEvent<RedactedRedactionEventContent>;
+    type Redaction = SyncRedactionEvent;
+
+    fn redact(self, redaction: SyncRedactionEvent, version: RoomVersionId) -> Self::Redacted {
+        RedactedSyncMessageEvent {
+            content: self.content.redact(version),
+            event_id: self.event_id,
+            sender: self.sender,
+            origin_server_ts: self.origin_server_ts,
+            unsigned: RedactedSyncUnsigned { redacted_because: Some(Box::new(redaction)) },
+        }
+    }
+}
+
 /// A redaction of an event.
 #[derive(Clone, Debug, Deserialize, Serialize, EventContent)]
 #[ruma_event(type = "m.room.redaction")]
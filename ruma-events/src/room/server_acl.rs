@@ -1,6 +1,9 @@
 //! Types for the *m.room.server_acl* event.
 
+use std::net::Ipv4Addr;
+
 use ruma_events_macros::StateEventContent;
+use ruma_identifiers::ServerName;
 use serde::{Deserialize, Serialize};
 
 use crate::StateEvent;
@@ -38,8 +41,88 @@ pub struct ServerAclEventContent {
     pub deny: Vec<String>,
 }
 
+impl ServerAclEventContent {
+    /// Returns true if and only if the server is allowed by the ACL rules.
+    ///
+    /// The port of the server name is ignored. IP literals are denied unless `allow_ip_literals`
+    /// is set, and the `deny` list takes precedence over the `allow` list.
+    pub fn is_allowed(&self, server_name: &ServerName) -> bool {
+        let host = host(server_name.as_str());
+
+        if !self.allow_ip_literals && is_ip_literal(host) {
+            return false;
+        }
+
+        if self.deny.iter().any(|pattern| glob_matches(pattern, host)) {
+            return false;
+        }
+
+        self.allow.iter().any(|pattern| glob_matches(pattern, host))
+    }
+}
+
+/// Returns the host of the given server name, without the port.
+fn host(server_name: &str) -> &str {
+    if server_name.starts_with('[') {
+        match server_name.find(']') {
+            Some(end) => &server_name[..=end],
+            None => server_name,
+        }
+    } else {
+        match server_name.rfind(':') {
+            Some(colon) => &server_name[..colon],
+            None => server_name,
+        }
+    }
+}
+
+/// Whether the given host is an IPv4 address or a bracketed IPv6 address.
+fn is_ip_literal(host: &str) -> bool {
+    host.starts_with('[') || host.parse::<Ipv4Addr>().is_ok()
+}
+
+/// Checks whether `host` matches `pattern`, ignoring ASCII case.
+///
+/// In the pattern, `*` matches zero or more characters and `?` matches exactly one character.
+/// Every other character, including `[` and `]`, is matched literally.
+fn glob_matches(pattern: &str, host: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let host: Vec<char> = host.chars().collect();
+
+    let (mut p, mut h) = (0, 0);
+    // The position of the last `*` in the pattern and the position in the host it was matched at.
+    let mut backtrack = None;
+
+    while h < host.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, h));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c.eq_ignore_ascii_case(&host[h]) => {
+                p += 1;
+                h += 1;
+            }
+            _ => match backtrack {
+                // Let the last `*` match one more character.
+                Some((star, star_h)) => {
+                    backtrack = Some((star, star_h + 1));
+                    p = star + 1;
+                    h = star_h + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
 #[cfg(test)]
 mod tests {
+    use std::convert::TryFrom;
+
+    use ruma_identifiers::ServerName;
     use serde_json::{from_value as from_json_value, json};
 
     use super::ServerAclEventContent;
@@ -67,4 +150,79 @@ mod tests {
         assert!(server_acl_event.content.allow.is_empty());
         assert!(server_acl_event.content.deny.is_empty());
     }
+
+    fn acl(allow: &[&str], deny: &[&str], allow_ip_literals: bool) -> ServerAclEventContent {
+        ServerAclEventContent {
+            allow_ip_literals,
+            allow: allow.iter().map(|&s| s.to_owned()).collect(),
+            deny: deny.iter().map(|&s| s.to_owned()).collect(),
+        }
+    }
+
+    fn server_name(name: &str) -> &ServerName {
+        <&ServerName>::try_from(name).unwrap()
+    }
+
+    #[test]
+    fn empty_allow_list_denies_everything() {
+        let acl = acl(&[], &[], true);
+
+        assert!(!acl.is_allowed(server_name("matrix.org")));
+    }
+
+    #[test]
+    fn glob_patterns() {
+        let acl = acl(&["*.example.org", "matrix.org", "server?.net"], &[], true);
+
+        assert!(acl.is_allowed(server_name("chat.example.org")));
+        assert!(acl.is_allowed(server_name("a.b.example.org:8448")));
+        assert!(acl.is_allowed(server_name("MATRIX.org")));
+        assert!(acl.is_allowed(server_name("server1.net")));
+        assert!(!acl.is_allowed(server_name("example.org")));
+        assert!(!acl.is_allowed(server_name("server10.net")));
+        assert!(!acl.is_allowed(server_name("matrix.org.evil.com")));
+    }
+
+    #[test]
+    fn deny_takes_precedence() {
+        let acl = acl(&["*"], &["evil.com", "*.evil.com"], true);
+
+        assert!(acl.is_allowed(server_name("matrix.org")));
+        assert!(!acl.is_allowed(server_name("evil.com")));
+        assert!(!acl.is_allowed(server_name("sub.evil.com:443")));
+    }
+
+    #[test]
+    fn ip_literals() {
+        let allowed = acl(&["*"], &[], true);
+        let denied = acl(&["*"], &[], false);
+
+        for name in &["1.2.3.4", "1.2.3.4:8448", "[::1]", "[2001:db8::1]:8448"] {
+            assert!(allowed.is_allowed(server_name(name)));
+            assert!(!denied.is_allowed(server_name(name)));
+        }
+        assert!(denied.is_allowed(server_name("1.2.3.4.example.org")));
+    }
+
+    #[test]
+    fn deny_ipv6_literals() {
+        let acl = acl(&["*"], &["[::1]", "[2001:db8::1]"], true);
+
+        assert!(!acl.is_allowed(server_name("[::1]")));
+        assert!(!acl.is_allowed(server_name("[::1]:8448")));
+        assert!(!acl.is_allowed(server_name("[2001:db8::1]:8448")));
+        assert!(acl.is_allowed(server_name("[2001:db8::2]")));
+        assert!(acl.is_allowed(server_name("1.2.3.4")));
+    }
+
+    #[test]
+    fn allow_only_ipv6_literal() {
+        let acl = acl(&["[2001:db8::1]"], &[], true);
+
+        assert!(acl.is_allowed(server_name("[2001:db8::1]")));
+        assert!(acl.is_allowed(server_name("[2001:db8::1]:8448")));
+        assert!(!acl.is_allowed(server_name("[::1]")));
+        assert!(!acl.is_allowed(server_name("2")));
+        assert!(!acl.is_allowed(server_name("matrix.org")));
+    }
 }
@@ -0,0 +1,513 @@
+//! A container for the current state of a room, built from its state events.
+
+use std::{collections::BTreeMap, convert::TryFrom};
+
+use js_int::UInt;
+use ruma_identifiers::{RoomAliasId, UserId};
+
+use crate::{
+    room::{
+        create::CreateEventContent,
+        encryption::EncryptionEventContent,
+        guest_access::GuestAccess,
+        history_visibility::HistoryVisibility,
+        join_rules::JoinRule,
+        member::{MemberEventContent, MembershipState},
+        power_levels::PowerLevelsEventContent,
+        tombstone::TombstoneEventContent,
+    },
+    AnyStateEventContent, AnyStrippedStateEvent, AnySyncStateEvent, EventContent, EventType,
+};
+
+/// The number of heroes used to calculate the display name of a room if the homeserver didn't
+/// send any.
+const MAX_HEROES: usize = 5;
+
+/// The current state of a room.
+///
+/// The contents of the state events are stored by their event type and state key, later events
+/// replacing earlier ones with the same type and state key.
+#[derive(Clone, Debug, Default)]
+pub struct RoomState {
+    events: BTreeMap<EventType, BTreeMap<String, AnyStateEventContent>>,
+}
+
+impl RoomState {
+    /// Creates an empty `RoomState`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the content of a state event with the given state key, replacing the content of the
+    /// previous state event with the same type and state key, if any.
+    pub fn insert(&mut self, state_key: String, content: AnyStateEventContent) {
+        let event_type = EventType::from(content.event_type());
+        self.events.entry(event_type).or_default().insert(state_key, content);
+    }
+
+    /// Adds the content of the given state event from a sync response.
+    pub fn insert_sync_event(&mut self, event: &AnySyncStateEvent) {
+        self.insert(event.state_key().to_owned(), event.content());
+    }
+
+    /// Adds the content of the given stripped state event, e.g. from an invite.
+    pub fn insert_stripped_event(&mut self, event: &AnyStrippedStateEvent) {
+        self.insert(event.state_key().to_owned(), event.content());
+    }
+
+    /// The content of the state event with the given type and state key, if any.
+    pub fn get(&self, event_type: &EventType, state_key: &str) -> Option<&AnyStateEventContent> {
+        self.events.get(event_type)?.get(state_key)
+    }
+
+    /// The content of the *m.room.create* event, if any.
+    pub fn create(&self) -> Option<&CreateEventContent> {
+        match self.get(&EventType::RoomCreate, "") {
+            Some(AnyStateEventContent::RoomCreate(content)) => Some(content),
+            _ => None,
+        }
+    }
+
+    /// The name of the room, if any.
+    pub fn name(&self) -> Option<&str> {
+        match self.get(&EventType::RoomName, "") {
+            Some(AnyStateEventContent::RoomName(content)) => content.name(),
+            _ => None,
+        }
+    }
+
+    /// The topic of the room, if any.
+    pub fn topic(&self) -> Option<&str> {
+        match self.get(&EventType::RoomTopic, "") {
+            Some(AnyStateEventContent::RoomTopic(content)) => Some(&content.topic),
+            _ => None,
+        }
+    }
+
+    /// The URL of the avatar of the room, if any.
+    pub fn avatar_url(&self) -> Option<&str> {
+        match self.get(&EventType::RoomAvatar, "") {
+            Some(AnyStateEventContent::RoomAvatar(content)) if !content.url.is_empty() => {
+                Some(&content.url)
+            }
+            _ => None,
+        }
+    }
+
+    /// The canonical alias of the room, if any.
+    pub fn canonical_alias(&self) -> Option<&RoomAliasId> {
+        match self.get(&EventType::RoomCanonicalAlias, "") {
+            Some(AnyStateEventContent::RoomCanonicalAlias(content)) => content.alias.as_ref(),
+            _ => None,
+        }
+    }
+
+    /// The alternative aliases of the room.
+    pub fn alt_aliases(&self) -> &[RoomAliasId] {
+        match self.get(&EventType::RoomCanonicalAlias, "") {
+            Some(AnyStateEventContent::RoomCanonicalAlias(content)) => &content.alt_aliases,
+            _ => &[],
+        }
+    }
+
+    /// The join rule of the room, if it is known.
+    pub fn join_rule(&self) -> Option<JoinRule> {
+        match self.get(&EventType::RoomJoinRules, "") {
+            Some(AnyStateEventContent::RoomJoinRules(content)) => Some(content.join_rule),
+            _ => None,
+        }
+    }
+
+    /// The history visibility of the room, if it is known.
+    pub fn history_visibility(&self) -> Option<HistoryVisibility> {
+        match self.get(&EventType::RoomHistoryVisibility, "") {
+            Some(AnyStateEventContent::RoomHistoryVisibility(content)) => {
+                Some(content.history_visibility)
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether guests can join the room, if it is known.
+    pub fn guest_access(&self) -> Option<GuestAccess> {
+        match self.get(&EventType::RoomGuestAccess, "") {
+            Some(AnyStateEventContent::RoomGuestAccess(content)) => Some(content.guest_access),
+            _ => None,
+        }
+    }
+
+    /// The content of the *m.room.power_levels* event, if any.
+    pub fn power_levels(&self) -> Option<&PowerLevelsEventContent> {
+        match self.get(&EventType::RoomPowerLevels, "") {
+            Some(AnyStateEventContent::RoomPowerLevels(content)) => Some(content),
+            _ => None,
+        }
+    }
+
+    /// The encryption settings of the room, if it is encrypted.
+    pub fn encryption(&self) -> Option<&EncryptionEventContent> {
+        match self.get(&EventType::RoomEncryption, "") {
+            Some(AnyStateEventContent::RoomEncryption(content)) => Some(content),
+            _ => None,
+        }
+    }
+
+    /// Whether the room is encrypted.
+    pub fn is_encrypted(&self) -> bool {
+        self.encryption().is_some()
+    }
+
+    /// The content of the *m.room.tombstone* event, if the room has been replaced by another one.
+    pub fn tombstone(&self) -> Option<&TombstoneEventContent> {
+        match self.get(&EventType::RoomTombstone, "") {
+            Some(AnyStateEventContent::RoomTombstone(content)) => Some(content),
+            _ => None,
+        }
+    }
+
+    /// The content of the *m.room.member* event of the given user, if any.
+    pub fn member(&self, user_id: &UserId) -> Option<&MemberEventContent> {
+        match self.get(&EventType::RoomMember, user_id.as_str()) {
+            Some(AnyStateEventContent::RoomMember(content)) => Some(content),
+            _ => None,
+        }
+    }
+
+    /// The users with an *m.room.member* event, regardless of their membership, and the contents
+    /// of these events.
+    pub fn members(&self) -> impl Iterator<Item = (UserId, &MemberEventContent)> {
+        let events = self.events.get(&EventType::RoomMember).into_iter().flatten();
+        events.filter_map(|(state_key, content)| match content {
+            AnyStateEventContent::RoomMember(content) => {
+                UserId::try_from(state_key.as_str()).ok().map(|user_id| (user_id, content))
+            }
+            _ => None,
+        })
+    }
+
+    /// The users that have joined the room.
+    pub fn joined_members(&self) -> impl Iterator<Item = (UserId, &MemberEventContent)> {
+        self.members().filter(|(_, content)| content.membership == MembershipState::Join)
+    }
+
+    /// The users that have been invited to the room.
+    pub fn invited_members(&self) -> impl Iterator<Item = (UserId, &MemberEventContent)> {
+        self.members().filter(|(_, content)| content.membership == MembershipState::Invite)
+    }
+
+    /// The name to display for the given member of the room.
+    ///
+    /// This is the member's display name, followed by their user ID in parentheses if another
+    /// joined or invited member uses the same display name. If the member has no display name,
+    /// their user ID is used.
+    pub fn member_display_name(&self, user_id: &UserId) -> String {
+        let displayname = match self.member(user_id).and_then(|c| c.displayname.as_deref()) {
+            Some(displayname) if !displayname.is_empty() => displayname,
+            _ => return user_id.to_string(),
+        };
+
+        let is_ambiguous = self.members().any(|(other_id, content)| {
+            other_id != *user_id
+                && is_joined_or_invited(content)
+                && content.displayname.as_deref() == Some(displayname)
+        });
+
+        if is_ambiguous {
+            format!("{} ({})", displayname, user_id)
+        } else {
+            displayname.to_owned()
+        }
+    }
+
+    /// Calculates the name to display for the room, as described in [the specification][spec].
+    ///
+    /// `own_user_id` is the user the room is displayed to. The heroes and member counts of the
+    /// given summary are used if present, otherwise they are determined from the *m.room.member*
+    /// events of this `RoomState`.
+    ///
+    /// [spec]: https://matrix.org/docs/spec/client_server/r0.6.1#calculating-the-display-name-for-a-room
+    pub fn display_name(&self, own_user_id: &UserId, summary: &RoomSummary) -> String {
+        if let Some(name) = self.name() {
+            return name.to_owned();
+        }
+
+        if let Some(alias) = self.canonical_alias() {
+            return alias.to_string();
+        }
+
+        let heroes = if summary.heroes.is_empty() {
+            self.default_heroes(own_user_id)
+        } else {
+            summary.heroes.clone()
+        };
+        let hero_names: Vec<_> = heroes.iter().map(|id| self.member_display_name(id)).collect();
+
+        let joined_member_count = summary
+            .joined_member_count
+            .map_or_else(|| self.joined_members().count() as u64, u64::from);
+        let invited_member_count = summary
+            .invited_member_count
+            .map_or_else(|| self.invited_members().count() as u64, u64::from);
+        let member_count = joined_member_count + invited_member_count;
+
+        if member_count <= 1 {
+            if hero_names.is_empty() {
+                "Empty Room".to_owned()
+            } else {
+                format!("Empty Room (was {})", join_names(&hero_names))
+            }
+        } else if hero_names.len() as u64 >= member_count - 1 {
+            join_names(&hero_names)
+        } else {
+            format!(
+                "{} and {} others",
+                hero_names.join(", "),
+                member_count - 1 - hero_names.len() as u64
+            )
+        }
+    }
+
+    /// The heroes of the room if the homeserver didn't send any: joined and invited members
+    /// other than the own user, ordered by user ID.
+    ///
+    /// Only if there are no such members, members that left or were banned are used instead.
+    fn default_heroes(&self, own_user_id: &UserId) -> Vec<UserId> {
+        let (heroes, former_members): (Vec<_>, Vec<_>) = self
+            .members()
+            .filter(|(user_id, _)| user_id != own_user_id)
+            .partition(|(_, content)| is_joined_or_invited(content));
+        let heroes = if heroes.is_empty() { former_members } else { heroes };
+
+        heroes.into_iter().take(MAX_HEROES).map(|(user_id, _)| user_id).collect()
+    }
+}
+
+/// Information about the members of a room, usually sent by the homeserver with the room's state,
+/// used to calculate the display name of the room.
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(not(feature = "unstable-exhaustive-types"), non_exhaustive)]
+pub struct RoomSummary {
+    /// The users to use to calculate the room name if it has no name or canonical alias.
+    pub heroes: Vec<UserId>,
+
+    /// The number of users whose membership is `join`.
+    pub joined_member_count: Option<UInt>,
+
+    /// The number of users whose membership is `invite`.
+    pub invited_member_count: Option<UInt>,
+}
+
+impl RoomSummary {
+    /// Creates an empty `RoomSummary`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+fn is_joined_or_invited(content: &MemberEventContent) -> bool {
+    matches!(content.membership, MembershipState::Join | MembershipState::Invite)
+}
+
+/// Joins the given names as "Alice", "Alice and Bob" or "Alice, Bob and Charlie".
+fn join_names(names: &[String]) -> String {
+    match names.split_last() {
+        None => String::new(),
+        Some((last, [])) => last.clone(),
+        Some((last, rest)) => format!("{} and {}", rest.join(", "), last),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use js_int::uint;
+    use ruma_identifiers::{room_alias_id, user_id};
+    use serde_json::{from_value as from_json_value, json, Value as JsonValue};
+
+    use super::{RoomState, RoomSummary};
+    use crate::{
+        room::join_rules::JoinRule, AnyStateEventContent, AnyStrippedStateEvent, AnySyncStateEvent,
+        EventType,
+    };
+
+    fn sync_event(event_type: &str, state_key: &str, content: JsonValue) -> AnySyncStateEvent {
+        from_json_value(json!({
+            "type": event_type,
+            "state_key": state_key,
+            "content": content,
+            "event_id": "$h29iv0s8:example.com",
+            "origin_server_ts": 1,
+            "sender": "@alice:example.com"
+        }))
+        .unwrap()
+    }
+
+    fn room_with_members(members: &[(&str, &str, Option<&str>)]) -> RoomState {
+        let mut state = RoomState::new();
+        for (user_id, membership, displayname) in members {
+            state.insert_sync_event(&sync_event(
+                "m.room.member",
+                user_id,
+                json!({ "membership": membership, "displayname": displayname }),
+            ));
+        }
+
+        state
+    }
+
+    #[test]
+    fn typed_getters() {
+        let mut state = RoomState::new();
+        state.insert_sync_event(&sync_event("m.room.name", "", json!({ "name": "Room" })));
+        state.insert_sync_event(&sync_event("m.room.topic", "", json!({ "topic": "Stuff" })));
+        state.insert_sync_event(&sync_event(
+            "m.room.canonical_alias",
+            "",
+            json!({ "alias": "#room:example.com", "alt_aliases": ["#alt:example.com"] }),
+        ));
+        state.insert_sync_event(&sync_event(
+            "m.room.join_rules",
+            "",
+            json!({ "join_rule": "public" }),
+        ));
+        state.insert_sync_event(&sync_event(
+            "m.room.encryption",
+            "",
+            json!({ "algorithm": "m.megolm.v1.aes-sha2" }),
+        ));
+
+        assert_eq!(state.name(), Some("Room"));
+        assert_eq!(state.topic(), Some("Stuff"));
+        assert_eq!(state.canonical_alias(), Some(&room_alias_id!("#room:example.com")));
+        assert_eq!(state.alt_aliases(), &[room_alias_id!("#alt:example.com")]);
+        assert_eq!(state.join_rule(), Some(JoinRule::Public));
+        assert!(state.is_encrypted());
+        assert!(state.avatar_url().is_none());
+        assert!(state.tombstone().is_none());
+        assert!(state.power_levels().is_none());
+    }
+
+    #[test]
+    fn later_events_replace_earlier_ones() {
+        let mut state = RoomState::new();
+        state.insert_sync_event(&sync_event("m.room.topic", "", json!({ "topic": "Old" })));
+        state.insert_sync_event(&sync_event("m.room.topic", "", json!({ "topic": "New" })));
+
+        assert_eq!(state.topic(), Some("New"));
+        match state.get(&EventType::RoomTopic, "") {
+            Some(AnyStateEventContent::RoomTopic(content)) => assert_eq!(content.topic, "New"),
+            _ => panic!("expected a topic"),
+        }
+    }
+
+    #[test]
+    fn stripped_events() {
+        let event = from_json_value::<AnyStrippedStateEvent>(json!({
+            "type": "m.room.member",
+            "state_key": "@bob:example.com",
+            "content": { "membership": "invite", "displayname": "Bob" },
+            "sender": "@alice:example.com"
+        }))
+        .unwrap();
+
+        let mut state = RoomState::new();
+        state.insert_stripped_event(&event);
+
+        assert_eq!(state.invited_members().count(), 1);
+        assert_eq!(state.member_display_name(&user_id!("@bob:example.com")), "Bob");
+    }
+
+    #[test]
+    fn display_name_from_name_and_alias() {
+        let mut state = RoomState::new();
+        let own_user_id = user_id!("@me:example.com");
+
+        state.insert_sync_event(&sync_event(
+            "m.room.canonical_alias",
+            "",
+            json!({ "alias": "#room:example.com" }),
+        ));
+        assert_eq!(state.display_name(&own_user_id, &RoomSummary::new()), "#room:example.com");
+
+        state.insert_sync_event(&sync_event("m.room.name", "", json!({ "name": "Room" })));
+        assert_eq!(state.display_name(&own_user_id, &RoomSummary::new()), "Room");
+    }
+
+    #[test]
+    fn display_name_from_members() {
+        let own_user_id = user_id!("@me:example.com");
+        let state = room_with_members(&[
+            ("@me:example.com", "join", Some("Me")),
+            ("@alice:example.com", "join", Some("Alice")),
+            ("@bob:example.com", "invite", Some("Bob")),
+            ("@carol:example.com", "join", None),
+        ]);
+
+        assert_eq!(
+            state.display_name(&own_user_id, &RoomSummary::new()),
+            "Alice, Bob and @carol:example.com"
+        );
+    }
+
+    #[test]
+    fn display_name_from_summary() {
+        let own_user_id = user_id!("@me:example.com");
+        let state = room_with_members(&[
+            ("@alice:example.com", "join", Some("Alice")),
+            ("@bob:example.com", "join", Some("Bob")),
+        ]);
+
+        let mut summary = RoomSummary::new();
+        summary.heroes = vec![user_id!("@alice:example.com"), user_id!("@bob:example.com")];
+        summary.joined_member_count = Some(uint!(10));
+        summary.invited_member_count = Some(uint!(2));
+
+        assert_eq!(state.display_name(&own_user_id, &summary), "Alice, Bob and 9 others");
+    }
+
+    #[test]
+    fn display_name_of_empty_room() {
+        let own_user_id = user_id!("@me:example.com");
+
+        let state = room_with_members(&[("@me:example.com", "join", None)]);
+        assert_eq!(state.display_name(&own_user_id, &RoomSummary::new()), "Empty Room");
+
+        let state = room_with_members(&[
+            ("@me:example.com", "join", None),
+            ("@alice:example.com", "leave", Some("Alice")),
+            ("@bob:example.com", "leave", Some("Bob")),
+        ]);
+        assert_eq!(
+            state.display_name(&own_user_id, &RoomSummary::new()),
+            "Empty Room (was Alice and Bob)"
+        );
+    }
+
+    #[test]
+    fn display_name_ignores_former_members_if_there_are_active_ones() {
+        let own_user_id = user_id!("@me:example.com");
+        let state = room_with_members(&[
+            ("@me:example.com", "join", None),
+            ("@alice:example.com", "leave", Some("Alice")),
+            ("@bob:example.com", "join", Some("Bob")),
+            ("@carol:example.com", "ban", Some("Carol")),
+        ]);
+
+        assert_eq!(state.display_name(&own_user_id, &RoomSummary::new()), "Bob");
+    }
+
+    #[test]
+    fn ambiguous_member_display_names() {
+        let state = room_with_members(&[
+            ("@alice:example.com", "join", Some("Alice")),
+            ("@alice:example.org", "invite", Some("Alice")),
+            ("@bob:example.com", "join", Some("Bob")),
+            ("@bob:example.org", "leave", Some("Bob")),
+        ]);
+
+        assert_eq!(
+            state.member_display_name(&user_id!("@alice:example.com")),
+            "Alice (@alice:example.com)"
+        );
+        assert_eq!(state.member_display_name(&user_id!("@bob:example.com")), "Bob");
+    }
+}
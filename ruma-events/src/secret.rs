@@ -0,0 +1,4 @@
+//! Modules for events in the *m.secret* namespace.
+
+pub mod request;
+pub mod send;
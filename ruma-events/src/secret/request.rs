@@ -0,0 +1,266 @@
+//! Types for the *m.secret.request* event.
+
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+use ruma_events_macros::BasicEventContent;
+use ruma_identifiers::DeviceIdBox;
+use serde::{ser::SerializeStruct as _, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::ToDeviceEvent;
+
+/// Sent by a client to request a secret from another device or to cancel a previous request.
+///
+/// It is sent as an unencrypted to-device event.
+pub type RequestEvent = ToDeviceEvent<RequestEventContent>;
+
+/// The payload for `RequestEvent`.
+#[derive(Clone, Debug, Deserialize, Serialize, BasicEventContent)]
+#[cfg_attr(not(feature = "unstable-exhaustive-types"), non_exhaustive)]
+#[ruma_event(type = "m.secret.request")]
+pub struct RequestEventContent {
+    /// The action for the request.
+    #[serde(flatten)]
+    pub action: RequestAction,
+
+    /// The ID of the device requesting the event.
+    pub requesting_device_id: DeviceIdBox,
+
+    /// A random string uniquely identifying (with respect to the requester and the target) the
+    /// target for a secret.
+    ///
+    /// If the secret is requested from multiple devices at the same time, the same ID may be used
+    /// for every target. The same ID is also used in order to cancel a previous request.
+    pub request_id: String,
+}
+
+impl RequestEventContent {
+    /// Creates a new `RequestEventContent` with the given action, requesting device ID and
+    /// request ID.
+    pub fn new(
+        action: RequestAction,
+        requesting_device_id: DeviceIdBox,
+        request_id: String,
+    ) -> Self {
+        Self { action, requesting_device_id, request_id }
+    }
+}
+
+/// Action for an *m.secret.request* event.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(not(feature = "unstable-exhaustive-types"), non_exhaustive)]
+pub enum RequestAction {
+    /// Request a secret by its name.
+    Request(SecretName),
+
+    /// Cancel a request for a secret.
+    RequestCancellation,
+
+    #[doc(hidden)]
+    _Custom(String),
+}
+
+impl RequestAction {
+    /// Creates a string slice from the `action` of this `RequestAction`.
+    pub fn as_str(&self) -> &str {
+        match self {
+            RequestAction::Request(_) => "request",
+            RequestAction::RequestCancellation => "request_cancellation",
+            RequestAction::_Custom(action) => action,
+        }
+    }
+}
+
+impl Serialize for RequestAction {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            RequestAction::Request(name) => {
+                let mut st = serializer.serialize_struct("RequestAction", 2)?;
+                st.serialize_field("action", self.as_str())?;
+                st.serialize_field("name", name)?;
+                st.end()
+            }
+            _ => {
+                let mut st = serializer.serialize_struct("RequestAction", 1)?;
+                st.serialize_field("action", self.as_str())?;
+                st.end()
+            }
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for RequestAction {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        struct RequestActionDeHelper {
+            action: String,
+            name: Option<SecretName>,
+        }
+
+        let RequestActionDeHelper { action, name } =
+            RequestActionDeHelper::deserialize(deserializer)?;
+
+        Ok(match action.as_str() {
+            "request" => {
+                RequestAction::Request(name.ok_or_else(|| serde::de::Error::missing_field("name"))?)
+            }
+            "request_cancellation" => RequestAction::RequestCancellation,
+            _ => RequestAction::_Custom(action),
+        })
+    }
+}
+
+/// The name of a secret.
+///
+/// This type can hold an arbitrary string. To check for secrets that are not available as a
+/// documented variant here, use its string representation, obtained through `.as_str()`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(not(feature = "unstable-exhaustive-types"), non_exhaustive)]
+#[serde(from = "String", into = "String")]
+pub enum SecretName {
+    /// The private key of the cross-signing master key.
+    CrossSigningMasterKey,
+
+    /// The private key of the cross-signing user-signing key.
+    CrossSigningUserSigningKey,
+
+    /// The private key of the cross-signing self-signing key.
+    CrossSigningSelfSigningKey,
+
+    /// The recovery key of the server-side key backup.
+    RecoveryKey,
+
+    #[doc(hidden)]
+    _Custom(String),
+}
+
+impl SecretName {
+    /// Creates a string slice from this `SecretName`.
+    pub fn as_str(&self) -> &str {
+        match *self {
+            SecretName::CrossSigningMasterKey => "m.cross_signing.master",
+            SecretName::CrossSigningUserSigningKey => "m.cross_signing.user_signing",
+            SecretName::CrossSigningSelfSigningKey => "m.cross_signing.self_signing",
+            SecretName::RecoveryKey => "m.megolm_backup.v1",
+            SecretName::_Custom(ref name) => name,
+        }
+    }
+}
+
+impl Display for SecretName {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.write_str(self.as_str())
+    }
+}
+
+impl<T> From<T> for SecretName
+where
+    T: Into<String> + AsRef<str>,
+{
+    fn from(s: T) -> SecretName {
+        match s.as_ref() {
+            "m.cross_signing.master" => SecretName::CrossSigningMasterKey,
+            "m.cross_signing.user_signing" => SecretName::CrossSigningUserSigningKey,
+            "m.cross_signing.self_signing" => SecretName::CrossSigningSelfSigningKey,
+            "m.megolm_backup.v1" => SecretName::RecoveryKey,
+            _ => SecretName::_Custom(s.into()),
+        }
+    }
+}
+
+impl From<SecretName> for String {
+    fn from(name: SecretName) -> String {
+        name.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use matches::assert_matches;
+    use serde_json::{from_value as from_json_value, json, to_value as to_json_value};
+
+    use super::{RequestAction, RequestEventContent, SecretName};
+
+    #[test]
+    fn secret_request_serialization() {
+        let content = RequestEventContent::new(
+            RequestAction::Request(SecretName::CrossSigningMasterKey),
+            "ABCDEFG".into(),
+            "randomly_generated_id_9573".into(),
+        );
+
+        let json = json!({
+            "name": "m.cross_signing.master",
+            "action": "request",
+            "requesting_device_id": "ABCDEFG",
+            "request_id": "randomly_generated_id_9573"
+        });
+
+        assert_eq!(to_json_value(&content).unwrap(), json);
+    }
+
+    #[test]
+    fn secret_request_cancellation_serialization() {
+        let content = RequestEventContent::new(
+            RequestAction::RequestCancellation,
+            "ABCDEFG".into(),
+            "randomly_generated_id_9573".into(),
+        );
+
+        let json = json!({
+            "action": "request_cancellation",
+            "requesting_device_id": "ABCDEFG",
+            "request_id": "randomly_generated_id_9573"
+        });
+
+        assert_eq!(to_json_value(&content).unwrap(), json);
+    }
+
+    #[test]
+    fn secret_request_deserialization() {
+        let json = json!({
+            "name": "m.megolm_backup.v1",
+            "action": "request",
+            "requesting_device_id": "ABCDEFG",
+            "request_id": "this_is_a_request_id"
+        });
+
+        let content = from_json_value::<RequestEventContent>(json).unwrap();
+        assert_eq!(content.action, RequestAction::Request(SecretName::RecoveryKey));
+        assert_eq!(content.requesting_device_id, "ABCDEFG");
+        assert_eq!(content.request_id, "this_is_a_request_id");
+    }
+
+    #[test]
+    fn secret_request_with_custom_name() {
+        let json = json!({
+            "name": "org.example.some.secret",
+            "action": "request",
+            "requesting_device_id": "ABCDEFG",
+            "request_id": "this_is_a_request_id"
+        });
+
+        let content = from_json_value::<RequestEventContent>(json.clone()).unwrap();
+        assert_matches!(
+            &content.action,
+            RequestAction::Request(name) if name.as_str() == "org.example.some.secret"
+        );
+        assert_eq!(to_json_value(&content).unwrap(), json);
+    }
+
+    #[test]
+    fn secret_request_without_name() {
+        let json = json!({
+            "action": "request",
+            "requesting_device_id": "ABCDEFG",
+            "request_id": "this_is_a_request_id"
+        });
+
+        assert!(from_json_value::<RequestEventContent>(json).is_err());
+    }
+}
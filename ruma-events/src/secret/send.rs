@@ -0,0 +1,31 @@
+//! Types for the *m.secret.send* event.
+
+use ruma_events_macros::BasicEventContent;
+use serde::{Deserialize, Serialize};
+
+use crate::ToDeviceEvent;
+
+/// Sent by a client to share a secret with another device, in response to an *m.secret.request*
+/// event.
+///
+/// It must be encrypted as an *m.room.encrypted* event, then sent as a to-device event.
+pub type SendEvent = ToDeviceEvent<SendEventContent>;
+
+/// The payload for `SendEvent`.
+#[derive(Clone, Debug, Deserialize, Serialize, BasicEventContent)]
+#[cfg_attr(not(feature = "unstable-exhaustive-types"), non_exhaustive)]
+#[ruma_event(type = "m.secret.send")]
+pub struct SendEventContent {
+    /// The ID of the request that this is a response to.
+    pub request_id: String,
+
+    /// The contents of the secret.
+    pub secret: String,
+}
+
+impl SendEventContent {
+    /// Creates a new `SendEventContent` with the given request ID and secret.
+    pub fn new(request_id: String, secret: String) -> Self {
+        Self { request_id, secret }
+    }
+}
@@ -0,0 +1,25 @@
+//! Types for the *m.secret_storage.default_key* event.
+
+use ruma_events_macros::BasicEventContent;
+use serde::{Deserialize, Serialize};
+
+use crate::BasicEvent;
+
+/// The ID of the default key used for secret storage, stored in the user's account data.
+pub type DefaultKeyEvent = BasicEvent<DefaultKeyEventContent>;
+
+/// The payload for `DefaultKeyEvent`.
+#[derive(Clone, Debug, Deserialize, Serialize, BasicEventContent)]
+#[cfg_attr(not(feature = "unstable-exhaustive-types"), non_exhaustive)]
+#[ruma_event(type = "m.secret_storage.default_key")]
+pub struct DefaultKeyEventContent {
+    /// The ID of the default key.
+    pub key: String,
+}
+
+impl DefaultKeyEventContent {
+    /// Creates a new `DefaultKeyEventContent` with the given key ID.
+    pub fn new(key: String) -> Self {
+        Self { key }
+    }
+}
@@ -0,0 +1,321 @@
+//! Types for the *m.secret_storage.key.** events.
+
+use js_int::{uint, UInt};
+use serde::{
+    de::Error as _, ser::SerializeStruct as _, Deserialize, Deserializer, Serialize, Serializer,
+};
+use serde_json::{
+    from_value as from_json_value, value::RawValue as RawJsonValue, Map as JsonObject,
+    Value as JsonValue,
+};
+
+use crate::{BasicEvent, BasicEventContent, EventContent};
+
+const EVENT_TYPE_PREFIX: &str = "m.secret_storage.key.";
+
+/// The description of a key used for secret storage, stored in the user's account data.
+///
+/// The event type is *m.secret_storage.key.[key_id]*.
+pub type KeyEvent = BasicEvent<KeyEventContent>;
+
+/// The payload for `KeyEvent`.
+///
+/// The key ID is part of the event type, so this can only be deserialized together with it,
+/// through `EventContent::from_parts`.
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(not(feature = "unstable-exhaustive-types"), non_exhaustive)]
+pub struct KeyEventContent {
+    /// The full event type, including the key ID.
+    #[serde(skip)]
+    event_type: String,
+
+    /// The name of the key.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+
+    /// The encryption algorithm used for this key, and its properties.
+    #[serde(flatten)]
+    pub algorithm: SecretEncryptionAlgorithm,
+
+    /// Information on how to generate the key from a passphrase.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub passphrase: Option<PassPhrase>,
+}
+
+impl KeyEventContent {
+    /// Creates a new `KeyEventContent` for the key with the given ID and algorithm.
+    pub fn new(key_id: &str, algorithm: SecretEncryptionAlgorithm) -> Self {
+        Self {
+            event_type: format!("{}{}", EVENT_TYPE_PREFIX, key_id),
+            name: None,
+            algorithm,
+            passphrase: None,
+        }
+    }
+
+    /// The ID of the key this content describes.
+    pub fn key_id(&self) -> &str {
+        &self.event_type[EVENT_TYPE_PREFIX.len()..]
+    }
+}
+
+impl EventContent for KeyEventContent {
+    fn event_type(&self) -> &str {
+        &self.event_type
+    }
+
+    fn from_parts(event_type: &str, content: Box<RawJsonValue>) -> Result<Self, serde_json::Error> {
+        if !event_type.starts_with(EVENT_TYPE_PREFIX) {
+            return Err(::serde::de::Error::custom(format!(
+                "expected event type `{}*`, found `{}`",
+                EVENT_TYPE_PREFIX, event_type
+            )));
+        }
+
+        let KeyEventContentDeHelper { name, algorithm, passphrase } =
+            serde_json::from_str(content.get())?;
+
+        Ok(Self { event_type: event_type.to_owned(), name, algorithm, passphrase })
+    }
+}
+
+#[derive(Deserialize)]
+struct KeyEventContentDeHelper {
+    name: Option<String>,
+
+    #[serde(flatten)]
+    algorithm: SecretEncryptionAlgorithm,
+
+    passphrase: Option<PassPhrase>,
+}
+
+impl BasicEventContent for KeyEventContent {}
+
+/// An encryption algorithm to be used to encrypt secrets.
+#[derive(Clone, Debug)]
+#[cfg_attr(not(feature = "unstable-exhaustive-types"), non_exhaustive)]
+pub enum SecretEncryptionAlgorithm {
+    /// Encrypting using AES-CTR, with the MAC computed with HMAC-SHA-256.
+    SecretStorageV1AesHmacSha2 {
+        /// The 16-byte initialization vector, encoded as base64.
+        iv: String,
+
+        /// The MAC of the result of encrypting 32 bytes of 0, encoded as base64.
+        mac: String,
+    },
+
+    #[doc(hidden)]
+    _Custom(CustomSecretEncryptionAlgorithm),
+}
+
+impl SecretEncryptionAlgorithm {
+    /// The identifier of this algorithm, like `m.secret_storage.v1.aes-hmac-sha2`.
+    pub fn algorithm(&self) -> &str {
+        match self {
+            Self::SecretStorageV1AesHmacSha2 { .. } => AES_HMAC_SHA2,
+            Self::_Custom(custom) => &custom.algorithm,
+        }
+    }
+}
+
+const AES_HMAC_SHA2: &str = "m.secret_storage.v1.aes-hmac-sha2";
+
+/// An encryption algorithm not known to this crate, with its properties as raw JSON.
+#[doc(hidden)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CustomSecretEncryptionAlgorithm {
+    algorithm: String,
+
+    #[serde(flatten)]
+    properties: JsonObject<String, JsonValue>,
+}
+
+impl Serialize for SecretEncryptionAlgorithm {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            Self::SecretStorageV1AesHmacSha2 { iv, mac } => {
+                let mut st = serializer.serialize_struct("SecretEncryptionAlgorithm", 3)?;
+                st.serialize_field("algorithm", AES_HMAC_SHA2)?;
+                st.serialize_field("iv", iv)?;
+                st.serialize_field("mac", mac)?;
+                st.end()
+            }
+            Self::_Custom(custom) => custom.serialize(serializer),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for SecretEncryptionAlgorithm {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        struct AesHmacSha2Properties {
+            iv: String,
+            mac: String,
+        }
+
+        let custom = CustomSecretEncryptionAlgorithm::deserialize(deserializer)?;
+
+        match custom.algorithm.as_str() {
+            AES_HMAC_SHA2 => {
+                let AesHmacSha2Properties { iv, mac } =
+                    from_json_value(JsonValue::Object(custom.properties))
+                        .map_err(D::Error::custom)?;
+                Ok(Self::SecretStorageV1AesHmacSha2 { iv, mac })
+            }
+            _ => Ok(Self::_Custom(custom)),
+        }
+    }
+}
+
+/// Information on how to generate a key from a passphrase.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[cfg_attr(not(feature = "unstable-exhaustive-types"), non_exhaustive)]
+pub struct PassPhrase {
+    /// The algorithm to use to generate the key from the passphrase.
+    pub algorithm: KeyDerivationAlgorithm,
+
+    /// The salt used in the key derivation.
+    pub salt: String,
+
+    /// The number of iterations to use in the key derivation.
+    pub iterations: UInt,
+
+    /// The number of bits to generate for the key.
+    ///
+    /// Defaults to 256.
+    #[serde(default = "default_bits")]
+    pub bits: UInt,
+}
+
+impl PassPhrase {
+    /// Creates a new `PassPhrase` with the given algorithm, salt and number of iterations, and
+    /// the default number of bits.
+    pub fn new(algorithm: KeyDerivationAlgorithm, salt: String, iterations: UInt) -> Self {
+        Self { algorithm, salt, iterations, bits: default_bits() }
+    }
+}
+
+fn default_bits() -> UInt {
+    uint!(256)
+}
+
+/// An algorithm to derive a key from a passphrase.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+#[cfg_attr(not(feature = "unstable-exhaustive-types"), non_exhaustive)]
+pub enum KeyDerivationAlgorithm {
+    /// PBKDF2 with SHA-512 as the hash function.
+    #[serde(rename = "m.pbkdf2")]
+    Pbkdf2,
+}
+
+#[cfg(test)]
+mod tests {
+    use js_int::uint;
+    use matches::assert_matches;
+    use serde_json::{json, to_value as to_json_value, value::RawValue as RawJsonValue};
+
+    use super::{KeyDerivationAlgorithm, KeyEventContent, PassPhrase, SecretEncryptionAlgorithm};
+    use crate::EventContent;
+
+    #[test]
+    fn key_serialization() {
+        let mut content = KeyEventContent::new(
+            "my_key",
+            SecretEncryptionAlgorithm::SecretStorageV1AesHmacSha2 {
+                iv: "YWJjZGVmZ2hpamtsbW5vcA".into(),
+                mac: "aWRvbnRrbm93d2hhdGFtYWNsb29rc2xpa2U".into(),
+            },
+        );
+        content.name = Some("my_key".into());
+        content.passphrase =
+            Some(PassPhrase::new(KeyDerivationAlgorithm::Pbkdf2, "rocksalt".into(), uint!(8)));
+
+        let json = json!({
+            "name": "my_key",
+            "algorithm": "m.secret_storage.v1.aes-hmac-sha2",
+            "iv": "YWJjZGVmZ2hpamtsbW5vcA",
+            "mac": "aWRvbnRrbm93d2hhdGFtYWNsb29rc2xpa2U",
+            "passphrase": {
+                "algorithm": "m.pbkdf2",
+                "salt": "rocksalt",
+                "iterations": 8,
+                "bits": 256
+            }
+        });
+
+        assert_eq!(content.event_type(), "m.secret_storage.key.my_key");
+        assert_eq!(content.key_id(), "my_key");
+        assert_eq!(to_json_value(&content).unwrap(), json);
+    }
+
+    #[test]
+    fn key_from_parts() {
+        let json = json!({
+            "algorithm": "m.secret_storage.v1.aes-hmac-sha2",
+            "iv": "YWJjZGVmZ2hpamtsbW5vcA",
+            "mac": "aWRvbnRrbm93d2hhdGFtYWNsb29rc2xpa2U",
+            "passphrase": {
+                "algorithm": "m.pbkdf2",
+                "salt": "rocksalt",
+                "iterations": 8
+            }
+        });
+        let raw = RawJsonValue::from_string(json.to_string()).unwrap();
+
+        let content = KeyEventContent::from_parts("m.secret_storage.key.my_key", raw).unwrap();
+        assert_eq!(content.key_id(), "my_key");
+        assert!(content.name.is_none());
+        assert_matches!(
+            &content.algorithm,
+            SecretEncryptionAlgorithm::SecretStorageV1AesHmacSha2 { iv, mac }
+                if iv == "YWJjZGVmZ2hpamtsbW5vcA" && mac == "aWRvbnRrbm93d2hhdGFtYWNsb29rc2xpa2U"
+        );
+
+        let passphrase = content.passphrase.unwrap();
+        assert_eq!(passphrase.algorithm, KeyDerivationAlgorithm::Pbkdf2);
+        assert_eq!(passphrase.salt, "rocksalt");
+        assert_eq!(passphrase.iterations, uint!(8));
+        assert_eq!(passphrase.bits, uint!(256));
+    }
+
+    #[test]
+    fn key_from_parts_with_wrong_event_type() {
+        let json = json!({
+            "algorithm": "m.secret_storage.v1.aes-hmac-sha2",
+            "iv": "YWJjZGVmZ2hpamtsbW5vcA",
+            "mac": "aWRvbnRrbm93d2hhdGFtYWNsb29rc2xpa2U"
+        });
+        let raw = RawJsonValue::from_string(json.to_string()).unwrap();
+
+        assert!(KeyEventContent::from_parts("m.secret_storage.default_key", raw).is_err());
+    }
+
+    #[test]
+    fn key_with_unknown_algorithm() {
+        let json = json!({
+            "name": "my_key",
+            "algorithm": "org.example.unknown",
+            "salt": "rocksalt",
+        });
+        let raw = RawJsonValue::from_string(json.to_string()).unwrap();
+
+        let content = KeyEventContent::from_parts("m.secret_storage.key.my_key", raw).unwrap();
+        assert_eq!(content.name.as_deref(), Some("my_key"));
+        assert_eq!(content.algorithm.algorithm(), "org.example.unknown");
+        assert_eq!(to_json_value(&content).unwrap(), json);
+    }
+
+    #[test]
+    fn key_with_invalid_known_algorithm() {
+        let json = json!({ "algorithm": "m.secret_storage.v1.aes-hmac-sha2", "iv": "abc" });
+        let raw = RawJsonValue::from_string(json.to_string()).unwrap();
+
+        assert!(KeyEventContent::from_parts("m.secret_storage.key.my_key", raw).is_err());
+    }
+}
@@ -3,7 +3,7 @@
 use ruma_events_macros::MessageEventContent;
 use serde::{Deserialize, Serialize};
 
-use crate::{room::ImageInfo, MessageEvent};
+use crate::{relation::Relation, room::ImageInfo, MessageEvent};
 
 /// A sticker message.
 pub type StickerEvent = MessageEvent<StickerEventContent>;
@@ -22,4 +22,12 @@ pub struct StickerEventContent {
 
     /// The URL to the sticker image. This must be a valid `mxc://` URI.
     pub url: String,
+
+    /// Information about related events.
+    #[serde(
+        flatten,
+        with = "crate::relation::relation_serde",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub relates_to: Option<Relation>,
 }
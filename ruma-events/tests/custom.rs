@@ -202,7 +202,7 @@ fn deserialize_custom_message_sync_event() {
         "origin_server_ts": 10,
         "room_id": "!room:room.com",
         "sender": "@carl:example.com",
-        "type": "m.reaction",
+        "type": "io.ruma.reaction",
         "unsigned": {
             "age": 85
         }
@@ -227,7 +227,7 @@ fn deserialize_custom_message_sync_event() {
             origin_server_ts,
             sender,
             unsigned,
-        })) if json == expected_content && event_type == "m.reaction"
+        })) if json == expected_content && event_type == "io.ruma.reaction"
             && event_id == event_id!("$h29iv0s8:example.com")
             && origin_server_ts == UNIX_EPOCH + Duration::from_millis(10)
             && sender == user_id!("@carl:example.com")
@@ -267,3 +267,61 @@ fn alias_event_field_access() {
         panic!("the `Any*Event` enum's accessor methods may have been altered")
     }
 }
+
+#[test]
+fn secret_storage_key_event_deserialization() {
+    use ruma_events::{
+        secret_storage::key::SecretEncryptionAlgorithm, AnyBasicEvent, AnyBasicEventContent,
+        EventContent,
+    };
+
+    let json_data = json!({
+        "content": {
+            "name": "Recovery key",
+            "algorithm": "m.secret_storage.v1.aes-hmac-sha2",
+            "iv": "YWJjZGVmZ2hpamtsbW5vcA",
+            "mac": "aWRvbnRrbm93d2hhdGFtYWNsb29rc2xpa2U"
+        },
+        "type": "m.secret_storage.key.abc"
+    });
+
+    let event = from_json_value::<AnyBasicEvent>(json_data.clone()).unwrap();
+    let content = event.content();
+    assert_eq!(content.event_type(), "m.secret_storage.key.abc");
+
+    let content = match content {
+        AnyBasicEventContent::SecretStorageKey(content) => content,
+        _ => panic!("expected an m.secret_storage.key.* event"),
+    };
+    assert_eq!(content.key_id(), "abc");
+    assert_eq!(content.name.as_deref(), Some("Recovery key"));
+    assert_matches!(
+        content.algorithm,
+        SecretEncryptionAlgorithm::SecretStorageV1AesHmacSha2 { .. }
+    );
+
+    assert_eq!(serde_json::to_value(&event).unwrap(), json_data);
+}
+
+#[test]
+fn secret_storage_key_event_with_unknown_algorithm() {
+    use ruma_events::{AnyBasicEvent, AnyBasicEventContent};
+
+    let json_data = json!({
+        "content": {
+            "algorithm": "org.example.unknown",
+            "salt": "rocksalt"
+        },
+        "type": "m.secret_storage.key.abc"
+    });
+
+    let event = from_json_value::<AnyBasicEvent>(json_data.clone()).unwrap();
+    let content = match event.content() {
+        AnyBasicEventContent::SecretStorageKey(content) => content,
+        _ => panic!("expected an m.secret_storage.key.* event"),
+    };
+    assert_eq!(content.key_id(), "abc");
+    assert_eq!(content.algorithm.algorithm(), "org.example.unknown");
+
+    assert_eq!(serde_json::to_value(&event).unwrap(), json_data);
+}
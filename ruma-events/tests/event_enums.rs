@@ -86,6 +86,7 @@ fn serialize_message_event() {
                 thumbnail_file: None,
             },
             url: "http://www.matrix.org".into(),
+            relates_to: None,
         },
         event_id: event_id!("$h29iv0s8:example.com"),
         origin_server_ts: UNIX_EPOCH + Duration::from_millis(1),
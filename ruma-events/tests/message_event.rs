@@ -32,6 +32,7 @@ fn message_serialize_sticker() {
                 thumbnail_file: None,
             },
             url: "http://www.matrix.org".into(),
+            relates_to: None,
         }),
         event_id: event_id!("$h29iv0s8:example.com"),
         origin_server_ts: UNIX_EPOCH + Duration::from_millis(1),
@@ -191,6 +192,7 @@ fn deserialize_message_sticker() {
                     thumbnail_file: None,
                 },
                 url,
+                relates_to: None,
             }),
             event_id,
             origin_server_ts,
@@ -280,3 +282,132 @@ fn deserialize_message_then_convert_to_full() {
             && unsigned.is_empty()
     );
 }
+
+#[cfg(feature = "unstable-pre-spec")]
+#[test]
+fn deserialize_message_reaction() {
+    use ruma_events::{reaction::ReactionEventContent, relation::Annotation, AnyMessageEvent};
+
+    let json_data = json!({
+        "content": {
+            "m.relates_to": {
+                "rel_type": "m.annotation",
+                "event_id": "$h29iv0s8:example.com",
+                "key": "👍"
+            }
+        },
+        "event_id": "$reaction:example.com",
+        "origin_server_ts": 1,
+        "room_id": "!roomid:room.com",
+        "sender": "@carl:example.com",
+        "type": "m.reaction"
+    });
+
+    assert_matches!(
+        from_json_value::<Raw<AnyMessageEvent>>(json_data).unwrap().deserialize().unwrap(),
+        AnyMessageEvent::Reaction(MessageEvent {
+            content: ReactionEventContent { relates_to: Annotation { event_id, key }, .. },
+            room_id,
+            ..
+        }) if event_id == event_id!("$h29iv0s8:example.com")
+            && key == "👍"
+            && room_id == room_id!("!roomid:room.com")
+    );
+}
+
+#[cfg(feature = "unstable-pre-spec")]
+#[test]
+fn deserialize_in_room_key_verification() {
+    use ruma_events::{
+        key::verification::{
+            ready::ReadyEventContent, start::StartEventContent, VerificationMethod,
+        },
+        relation::Reference,
+        room::message::MessageEventContent,
+        AnyMessageEvent,
+    };
+
+    let json_data = json!({
+        "content": {
+            "body": "@carl:example.com is requesting to verify your key, but your client does not support in-chat key verification.",
+            "msgtype": "m.key.verification.request",
+            "from_device": "CARLDEVICE",
+            "methods": ["m.sas.v1"],
+            "to": "@dana:example.com"
+        },
+        "event_id": "$request:example.com",
+        "origin_server_ts": 1,
+        "room_id": "!roomid:room.com",
+        "sender": "@carl:example.com",
+        "type": "m.room.message"
+    });
+
+    assert_matches!(
+        from_json_value::<Raw<AnyMessageEvent>>(json_data).unwrap().deserialize().unwrap(),
+        AnyMessageEvent::RoomMessage(MessageEvent {
+            content: MessageEventContent::VerificationRequest(request),
+            ..
+        }) if request.from_device == "CARLDEVICE"
+            && request.methods == [VerificationMethod::MSasV1]
+            && request.to == user_id!("@dana:example.com")
+    );
+
+    let json_data = json!({
+        "content": {
+            "from_device": "DANADEVICE",
+            "methods": ["m.sas.v1"],
+            "m.relates_to": {
+                "rel_type": "m.reference",
+                "event_id": "$request:example.com"
+            }
+        },
+        "event_id": "$ready:example.com",
+        "origin_server_ts": 1,
+        "room_id": "!roomid:room.com",
+        "sender": "@dana:example.com",
+        "type": "m.key.verification.ready"
+    });
+
+    assert_matches!(
+        from_json_value::<Raw<AnyMessageEvent>>(json_data).unwrap().deserialize().unwrap(),
+        AnyMessageEvent::KeyVerificationReady(MessageEvent {
+            content: ReadyEventContent {
+                from_device,
+                relates_to: Reference { event_id },
+                ..
+            },
+            ..
+        }) if from_device == "DANADEVICE" && event_id == event_id!("$request:example.com")
+    );
+
+    let json_data = json!({
+        "content": {
+            "from_device": "CARLDEVICE",
+            "method": "m.sas.v1",
+            "key_agreement_protocols": ["curve25519-hkdf-sha256"],
+            "hashes": ["sha256"],
+            "message_authentication_codes": ["hkdf-hmac-sha256"],
+            "short_authentication_string": ["decimal", "emoji"],
+            "m.relates_to": {
+                "rel_type": "m.reference",
+                "event_id": "$request:example.com"
+            }
+        },
+        "event_id": "$start:example.com",
+        "origin_server_ts": 1,
+        "room_id": "!roomid:room.com",
+        "sender": "@carl:example.com",
+        "type": "m.key.verification.start"
+    });
+
+    let event =
+        from_json_value::<Raw<AnyMessageEvent>>(json_data.clone()).unwrap().deserialize().unwrap();
+    assert_matches!(
+        &event,
+        AnyMessageEvent::KeyVerificationStart(MessageEvent {
+            content: StartEventContent { from_device, relates_to: Reference { event_id }, .. },
+            ..
+        }) if from_device == "CARLDEVICE" && *event_id == event_id!("$request:example.com")
+    );
+    assert_eq!(to_json_value(&event).unwrap(), json_data);
+}
@@ -5,17 +5,17 @@ use ruma_common::Raw;
 use ruma_events::{
     custom::RedactedCustomEventContent,
     room::{
-        aliases::RedactedAliasesEventContent,
+        aliases::{AliasesEventContent, RedactedAliasesEventContent},
         create::RedactedCreateEventContent,
         message::RedactedMessageEventContent,
         redaction::{RedactionEvent, RedactionEventContent, SyncRedactionEvent},
     },
     AnyMessageEvent, AnyRedactedMessageEvent, AnyRedactedSyncMessageEvent,
-    AnyRedactedSyncStateEvent, AnyRoomEvent, AnySyncRoomEvent, RedactedMessageEvent,
+    AnyRedactedSyncStateEvent, AnyRoomEvent, AnySyncRoomEvent, Redact, RedactedMessageEvent,
     RedactedSyncMessageEvent, RedactedSyncStateEvent, RedactedSyncUnsigned, RedactedUnsigned,
-    Unsigned,
+    SyncStateEvent, Unsigned,
 };
-use ruma_identifiers::{event_id, room_id, user_id, RoomVersionId};
+use ruma_identifiers::{event_id, room_alias_id, room_id, user_id, RoomVersionId};
 use serde_json::{from_value as from_json_value, json, to_value as to_json_value};
 
 fn sync_unsigned() -> RedactedSyncUnsigned {
@@ -361,3 +361,34 @@ fn redact_method_properly_redacts() {
             && origin_server_ts == UNIX_EPOCH + Duration::from_millis(1)
     );
 }
+
+#[test]
+fn redact_keeps_aliases_depending_on_room_version() {
+    let event = SyncStateEvent {
+        content: AliasesEventContent::new(vec![room_alias_id!("#somewhere:localhost")]),
+        event_id: event_id!("$h29iv0s8:example.com"),
+        sender: user_id!("@carl:example.com"),
+        origin_server_ts: UNIX_EPOCH + Duration::from_millis(1),
+        state_key: "example.com".into(),
+        prev_content: None,
+        unsigned: Unsigned::default(),
+    };
+    let redaction = SyncRedactionEvent {
+        content: RedactionEventContent { reason: None },
+        redacts: event_id!("$h29iv0s8:example.com"),
+        event_id: event_id!("$redaction:example.com"),
+        origin_server_ts: UNIX_EPOCH + Duration::from_millis(2),
+        sender: user_id!("@carl:example.com"),
+        unsigned: Unsigned::default(),
+    };
+
+    let redacted = event.clone().redact(redaction.clone(), RoomVersionId::Version5);
+    assert_eq!(redacted.content.aliases, Some(vec![room_alias_id!("#somewhere:localhost")]));
+    assert_matches!(
+        redacted.unsigned.redacted_because.as_deref(),
+        Some(SyncRedactionEvent { event_id, .. }) if *event_id == event_id!("$redaction:example.com")
+    );
+
+    let redacted = event.redact(redaction, RoomVersionId::Version6);
+    assert_eq!(redacted.content.aliases, None);
+}
@@ -28,3 +28,80 @@ fn serialization() {
         })
     );
 }
+
+#[test]
+fn deserialize_key_verification_key() {
+    use matches::assert_matches;
+    use ruma_common::Raw;
+    use ruma_events::{key::verification::key::KeyToDeviceEventContent, AnyToDeviceEvent};
+    use serde_json::from_value as from_json_value;
+
+    let json_data = json!({
+        "type": "m.key.verification.key",
+        "sender": "@example:example.org",
+        "content": {
+            "transaction_id": "456",
+            "key": "ephemeral_key",
+        },
+    });
+
+    assert_matches!(
+        from_json_value::<Raw<AnyToDeviceEvent>>(json_data).unwrap().deserialize().unwrap(),
+        AnyToDeviceEvent::KeyVerificationKey(ToDeviceEvent {
+            content: KeyToDeviceEventContent { transaction_id, key },
+            sender,
+        }) if transaction_id == "456"
+            && key == "ephemeral_key"
+            && sender == user_id!("@example:example.org")
+    );
+}
+
+#[cfg(feature = "unstable-pre-spec")]
+#[test]
+fn serialize_key_verification_done() {
+    use ruma_events::key::verification::done::DoneToDeviceEventContent;
+
+    let ev = ToDeviceEvent {
+        sender: user_id!("@example:example.org"),
+        content: AnyToDeviceEventContent::KeyVerificationDone(DoneToDeviceEventContent::new(
+            "456".into(),
+        )),
+    };
+
+    assert_eq!(
+        to_json_value(ev).unwrap(),
+        json!({
+            "type": "m.key.verification.done",
+            "sender": "@example:example.org",
+            "content": {
+                "transaction_id": "456",
+            },
+        })
+    );
+}
+
+#[test]
+fn deserialize_secret_send() {
+    use matches::assert_matches;
+    use ruma_events::{secret::send::SendEventContent, AnyToDeviceEvent};
+    use serde_json::from_value as from_json_value;
+
+    let json_data = json!({
+        "type": "m.secret.send",
+        "sender": "@example:example.org",
+        "content": {
+            "request_id": "randomly_generated_id_9573",
+            "secret": "ThisIsASecretDon'tTellAnyone",
+        },
+    });
+
+    assert_matches!(
+        from_json_value::<AnyToDeviceEvent>(json_data).unwrap(),
+        AnyToDeviceEvent::SecretSend(ToDeviceEvent {
+            content: SendEventContent { request_id, secret, .. },
+            sender,
+        }) if request_id == "randomly_generated_id_9573"
+            && secret == "ThisIsASecretDon'tTellAnyone"
+            && sender == user_id!("@example:example.org")
+    );
+}
@@ -27,6 +27,10 @@ pub use crate::{
     room_alias_id::RoomAliasId,
     room_id::RoomId,
     room_id_or_room_alias_id::RoomIdOrAliasId,
+    room_version::{
+        EventIdFormat, RedactionAlgorithm, RoomVersion, RoomVersionStability,
+        StateResolutionVersion,
+    },
     room_version_id::RoomVersionId,
     server_key_id::ServerKeyId,
     server_name::{ServerName, ServerNameBox},
@@ -49,6 +53,7 @@ mod event_id;
 mod room_alias_id;
 mod room_id;
 mod room_id_or_room_alias_id;
+mod room_version;
 mod room_version_id;
 mod server_key_id;
 mod server_name;
@@ -0,0 +1,183 @@
+//! The rules and features of Matrix room versions.
+
+/// The rules and features of a room version.
+///
+/// The rules of the room versions known to this crate can be obtained through
+/// [`RoomVersionId::rules`](enum.RoomVersionId.html#method.rules), or through the associated
+/// constants of this type.
+///
+/// ```
+/// # use ruma_identifiers::{EventIdFormat, RoomVersion, RoomVersionId};
+/// let rules = RoomVersionId::Version4.rules().unwrap();
+///
+/// assert_eq!(rules, RoomVersion::V4);
+/// assert_eq!(rules.event_id_format, EventIdFormat::UrlSafeReferenceHash);
+/// assert!(!rules.enforce_key_validity);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct RoomVersion {
+    /// Whether the room version is stable or unstable.
+    pub stability: RoomVersionStability,
+
+    /// The format of the IDs of events in the room.
+    pub event_id_format: EventIdFormat,
+
+    /// The redaction algorithm of the room version.
+    pub redaction_algorithm: RedactionAlgorithm,
+
+    /// The state resolution algorithm of the room version.
+    pub state_res: StateResolutionVersion,
+
+    /// Whether the `valid_until_ts` of server signing keys is enforced when verifying the
+    /// signatures of events (room versions 5 and later).
+    pub enforce_key_validity: bool,
+
+    /// Whether events have to be valid canonical JSON, i.e. not contain floats or integers outside
+    /// of the range `[-(2**53)+1, (2**53)-1]` (room version 6).
+    pub strict_canonical_json: bool,
+
+    /// Whether power levels have to be integers, instead of strings containing integers being
+    /// allowed too (room version 6).
+    pub integer_power_levels: bool,
+
+    /// Whether `m.room.aliases` events are only allowed with the sender's server name as state
+    /// key, and regardless of power levels (room versions 1 to 5).
+    pub special_case_aliases_auth: bool,
+
+    /// Whether changes to the `notifications` power levels are subject to the same checks as the
+    /// `events` and `users` power levels (room version 6).
+    pub limit_notifications_power_levels: bool,
+}
+
+impl RoomVersion {
+    /// The rules of room version 1.
+    pub const V1: Self = Self {
+        stability: RoomVersionStability::Stable,
+        event_id_format: EventIdFormat::Opaque,
+        redaction_algorithm: RedactionAlgorithm::V1,
+        state_res: StateResolutionVersion::V1,
+        enforce_key_validity: false,
+        strict_canonical_json: false,
+        integer_power_levels: false,
+        special_case_aliases_auth: true,
+        limit_notifications_power_levels: false,
+    };
+
+    /// The rules of room version 2.
+    pub const V2: Self = Self { state_res: StateResolutionVersion::V2, ..Self::V1 };
+
+    /// The rules of room version 3.
+    pub const V3: Self = Self { event_id_format: EventIdFormat::ReferenceHash, ..Self::V2 };
+
+    /// The rules of room version 4.
+    pub const V4: Self = Self { event_id_format: EventIdFormat::UrlSafeReferenceHash, ..Self::V3 };
+
+    /// The rules of room version 5.
+    pub const V5: Self = Self { enforce_key_validity: true, ..Self::V4 };
+
+    /// The rules of room version 6.
+    pub const V6: Self = Self {
+        redaction_algorithm: RedactionAlgorithm::V2,
+        strict_canonical_json: true,
+        integer_power_levels: true,
+        special_case_aliases_auth: false,
+        limit_notifications_power_levels: true,
+        ..Self::V5
+    };
+
+    /// Whether the IDs of events in the room are derived from their reference hash, instead of
+    /// being chosen by the server that created them.
+    pub fn event_ids_are_hashes(&self) -> bool {
+        self.event_id_format != EventIdFormat::Opaque
+    }
+}
+
+/// Whether a room version is stable or unstable.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum RoomVersionStability {
+    /// The room version is stable and can be used by default for new rooms.
+    Stable,
+
+    /// The room version is unstable, it may change or be removed and should only be used for
+    /// testing.
+    Unstable,
+}
+
+/// The format of the IDs of events in a room.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum EventIdFormat {
+    /// An opaque localpart chosen by the server that created the event, followed by its server
+    /// name, like `$abc123:example.com` (room versions 1 and 2).
+    Opaque,
+
+    /// The reference hash of the event, encoded with standard unpadded base64 (room version 3).
+    ReferenceHash,
+
+    /// The reference hash of the event, encoded with URL-safe unpadded base64 (room versions 4
+    /// and later).
+    UrlSafeReferenceHash,
+}
+
+/// A version of the algorithm to redact events.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum RedactionAlgorithm {
+    /// The original redaction algorithm, used by room versions 1 to 5.
+    V1,
+
+    /// The redaction algorithm of room version 6, which doesn't preserve the `aliases` of
+    /// `m.room.aliases` events.
+    V2,
+}
+
+/// A version of the state resolution algorithm.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum StateResolutionVersion {
+    /// The original state resolution algorithm, used by room version 1.
+    V1,
+
+    /// State resolution version 2, used by room versions 2 and later.
+    V2,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use super::{EventIdFormat, RedactionAlgorithm, RoomVersion, StateResolutionVersion};
+    use crate::RoomVersionId;
+
+    #[test]
+    fn rules_of_known_versions() {
+        assert_eq!(RoomVersionId::Version1.rules(), Some(RoomVersion::V1));
+        assert_eq!(RoomVersionId::Version6.rules(), Some(RoomVersion::V6));
+    }
+
+    #[test]
+    fn no_rules_for_custom_versions() {
+        assert_eq!(RoomVersionId::try_from("io.ruma.1").unwrap().rules(), None);
+    }
+
+    #[test]
+    fn event_id_format() {
+        assert!(!RoomVersion::V2.event_ids_are_hashes());
+        assert_eq!(RoomVersion::V3.event_id_format, EventIdFormat::ReferenceHash);
+        assert_eq!(RoomVersion::V6.event_id_format, EventIdFormat::UrlSafeReferenceHash);
+    }
+
+    #[test]
+    fn version_6_features() {
+        let rules = RoomVersion::V6;
+
+        assert_eq!(rules.redaction_algorithm, RedactionAlgorithm::V2);
+        assert_eq!(rules.state_res, StateResolutionVersion::V2);
+        assert!(rules.enforce_key_validity);
+        assert!(rules.strict_canonical_json);
+        assert!(rules.integer_power_levels);
+        assert!(!rules.special_case_aliases_auth);
+    }
+}
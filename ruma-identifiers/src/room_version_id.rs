@@ -10,7 +10,7 @@ use std::{
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
-use crate::Error;
+use crate::{Error, RoomVersion};
 
 /// A Matrix room version ID.
 ///
@@ -69,6 +69,22 @@ impl RoomVersionId {
     pub fn as_bytes(&self) -> &[u8] {
         self.as_str().as_bytes()
     }
+
+    /// Returns the rules and features of this room version.
+    ///
+    /// Returns `None` for custom room versions and ones that were introduced into the
+    /// specification after this code was written.
+    pub fn rules(&self) -> Option<RoomVersion> {
+        match self {
+            Self::Version1 => Some(RoomVersion::V1),
+            Self::Version2 => Some(RoomVersion::V2),
+            Self::Version3 => Some(RoomVersion::V3),
+            Self::Version4 => Some(RoomVersion::V4),
+            Self::Version5 => Some(RoomVersion::V5),
+            Self::Version6 => Some(RoomVersion::V6),
+            Self::_Custom(_) => None,
+        }
+    }
 }
 
 impl From<RoomVersionId> for String {
@@ -0,0 +1,296 @@
+//! Errors produced when ruma-signatures operations fail.
+
+use std::{
+    error::Error as StdError,
+    fmt::{Display, Formatter, Result as FmtResult},
+};
+
+use ruma_serde::CanonicalJsonError;
+
+/// An error produced when ruma-signatures operations fail.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// The JSON object or event has an unexpected shape.
+    Json(JsonError),
+
+    /// The signatures of a JSON object or event could not be verified.
+    Verification(VerificationError),
+
+    /// A string could not be parsed, e.g. a key ID or a Base64-encoded value.
+    Parse(ParseError),
+
+    /// The algorithm of a key or signature is not supported.
+    UnsupportedAlgorithm(String),
+
+    /// A key pair could not be created or generated.
+    KeyPair(String),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Self::Json(error) => write!(f, "invalid JSON: {}", error),
+            Self::Verification(error) => write!(f, "verification failed: {}", error),
+            Self::Parse(error) => write!(f, "parse error: {}", error),
+            Self::UnsupportedAlgorithm(algorithm) => {
+                write!(f, "unsupported algorithm `{}`", algorithm)
+            }
+            Self::KeyPair(message) => write!(f, "invalid key pair: {}", message),
+        }
+    }
+}
+
+impl StdError for Error {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            Self::Json(error) => Some(error),
+            Self::Verification(error) => Some(error),
+            Self::Parse(error) => Some(error),
+            Self::UnsupportedAlgorithm(_) | Self::KeyPair(_) => None,
+        }
+    }
+}
+
+impl From<JsonError> for Error {
+    fn from(error: JsonError) -> Self {
+        Self::Json(error)
+    }
+}
+
+impl From<VerificationError> for Error {
+    fn from(error: VerificationError) -> Self {
+        Self::Verification(error)
+    }
+}
+
+impl From<ParseError> for Error {
+    fn from(error: ParseError) -> Self {
+        Self::Parse(error)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(error: serde_json::Error) -> Self {
+        Self::Json(JsonError::Serde(error))
+    }
+}
+
+impl From<CanonicalJsonError> for Error {
+    fn from(error: CanonicalJsonError) -> Self {
+        Self::Json(JsonError::Canonical(error))
+    }
+}
+
+/// An error produced when a JSON object or event has an unexpected shape.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum JsonError {
+    /// A required field is missing.
+    ///
+    /// Fields of nested objects are separated by dots, e.g. `hashes.sha256`.
+    MissingField(String),
+
+    /// A field doesn't have the expected JSON type.
+    ///
+    /// Fields of nested objects are separated by dots, e.g. `signatures.example.com`.
+    InvalidType {
+        /// The path of the field.
+        field: String,
+
+        /// The type the field should have.
+        expected: JsonType,
+    },
+
+    /// A JSON (de)serialization error.
+    Serde(serde_json::Error),
+
+    /// The JSON can't be converted to its canonical form.
+    Canonical(CanonicalJsonError),
+}
+
+impl JsonError {
+    pub(crate) fn missing_field(field: impl Into<String>) -> Self {
+        Self::MissingField(field.into())
+    }
+
+    pub(crate) fn invalid_type(field: impl Into<String>, expected: JsonType) -> Self {
+        Self::InvalidType { field: field.into(), expected }
+    }
+}
+
+impl Display for JsonError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Self::MissingField(field) => write!(f, "field `{}` must be present", field),
+            Self::InvalidType { field, expected } => {
+                write!(f, "field `{}` must be a JSON {}", field, expected)
+            }
+            Self::Serde(error) => write!(f, "{}", error),
+            Self::Canonical(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl StdError for JsonError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            Self::Serde(error) => Some(error),
+            Self::Canonical(error) => Some(error),
+            Self::MissingField(_) | Self::InvalidType { .. } => None,
+        }
+    }
+}
+
+/// A JSON type, used in errors about fields with an unexpected type.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum JsonType {
+    /// A JSON object.
+    Object,
+
+    /// A JSON string.
+    String,
+
+    /// A JSON integer.
+    Integer,
+
+    /// A JSON array.
+    Array,
+}
+
+impl Display for JsonType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let name = match self {
+            Self::Object => "object",
+            Self::String => "string",
+            Self::Integer => "integer",
+            Self::Array => "array",
+        };
+
+        f.write_str(name)
+    }
+}
+
+/// An error produced when the signatures of a JSON object or event could not be verified.
+///
+/// A content hash that doesn't match the event is not an error, since it may indicate that the
+/// event was redacted. See [`Verified`](enum.Verified.html) for details.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum VerificationError {
+    /// The object has no signatures from the given entity.
+    SignatureNotFound {
+        /// The entity whose signatures are missing, e.g. a homeserver.
+        entity: String,
+    },
+
+    /// The object is not signed with any of the public keys given for an entity.
+    PublicKeyNotFound {
+        /// The entity whose keys did not sign the object.
+        entity: String,
+    },
+
+    /// The object is only signed with keys of an entity that were not valid at the time the
+    /// object was created, e.g. the `origin_server_ts` of an event.
+    KeyExpired {
+        /// The entity whose key has expired.
+        entity: String,
+
+        /// The ID of the expired key.
+        key_id: String,
+    },
+
+    /// A signature doesn't match the object and public key.
+    InvalidSignature,
+}
+
+impl Display for VerificationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Self::SignatureNotFound { entity } => {
+                write!(f, "no signatures found for entity `{}`", entity)
+            }
+            Self::PublicKeyNotFound { entity } => write!(
+                f,
+                "object is not signed with any of the given public keys of entity `{}`",
+                entity
+            ),
+            Self::KeyExpired { entity, key_id } => write!(
+                f,
+                "key `{}` of entity `{}` was not valid when the object was created",
+                key_id, entity
+            ),
+            Self::InvalidSignature => f.write_str("signature doesn't match"),
+        }
+    }
+}
+
+impl StdError for VerificationError {}
+
+/// An error produced when a string could not be parsed.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ParseError {
+    /// A key ID is malformed.
+    ///
+    /// Key IDs consist of an algorithm and a version containing only the characters
+    /// `[a-zA-Z0-9_]`, separated by a colon, e.g. `ed25519:1`.
+    InvalidKeyId(String),
+
+    /// A Base64-encoded value could not be decoded.
+    Base64 {
+        /// What was encoded, e.g. "signature" or "public key".
+        of_type: &'static str,
+
+        /// The decoding error.
+        source: base64::DecodeError,
+    },
+
+    /// A field of an event doesn't contain a valid Matrix identifier.
+    Identifier {
+        /// The path of the field.
+        field: String,
+
+        /// The parsing error.
+        source: ruma_identifiers::Error,
+    },
+
+    /// The `Authorization` header of a federation request is not valid `X-Matrix` credentials.
+    XMatrix(String),
+}
+
+impl ParseError {
+    pub(crate) fn base64(of_type: &'static str, source: base64::DecodeError) -> Self {
+        Self::Base64 { of_type, source }
+    }
+
+    pub(crate) fn identifier(field: impl Into<String>, source: ruma_identifiers::Error) -> Self {
+        Self::Identifier { field: field.into(), source }
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Self::InvalidKeyId(id) => write!(f, "malformed key ID `{}`", id),
+            Self::Base64 { of_type, source } => {
+                write!(f, "could not decode {} as Base64: {}", of_type, source)
+            }
+            Self::Identifier { field, source } => {
+                write!(f, "field `{}` is not a valid identifier: {}", field, source)
+            }
+            Self::XMatrix(reason) => write!(f, "invalid X-Matrix authorization: {}", reason),
+        }
+    }
+}
+
+impl StdError for ParseError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            Self::Base64 { source, .. } => Some(source),
+            Self::Identifier { source, .. } => Some(source),
+            Self::InvalidKeyId(_) | Self::XMatrix(_) => None,
+        }
+    }
+}
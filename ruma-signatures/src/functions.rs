@@ -1,18 +1,27 @@
 //! Functions for signing and verifying JSON and events.
 
-use std::{collections::BTreeMap, mem};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    convert::TryFrom,
+    mem,
+    str::FromStr,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
 use base64::{decode_config, encode_config, STANDARD_NO_PAD, URL_SAFE_NO_PAD};
 use ring::digest::{digest, SHA256};
-use ruma_identifiers::RoomVersionId;
+use ruma_identifiers::{
+    Error as IdentifierError, EventId, EventIdFormat, RedactionAlgorithm, RoomVersion,
+    RoomVersionId, ServerKeyId, ServerNameBox, UserId,
+};
 use ruma_serde::{to_canonical_json_string, CanonicalJsonObject, CanonicalJsonValue};
 use serde_json::from_str as from_json_str;
 
 use crate::{
-    keys::{KeyPair, PublicKeyMap},
+    keys::{KeyPair, PublicKeyMap, VerifyKeyMap},
     split_id,
     verification::{Ed25519Verifier, Verified, Verifier},
-    Error,
+    Error, JsonError, JsonType, ParseError, VerificationError,
 };
 
 /// The fields that are allowed to remain in an event during redaction.
@@ -34,7 +43,7 @@ static ALLOWED_KEYS: &[&str] = &[
     "membership",
 ];
 
-fn allowed_content_keys_for(event_type: &str, version: &RoomVersionId) -> &'static [&'static str] {
+fn allowed_content_keys_for(event_type: &str, rules: &RoomVersion) -> &'static [&'static str] {
     match event_type {
         "m.room.member" => &["membership"],
         "m.room.create" => &["creator"],
@@ -49,14 +58,8 @@ fn allowed_content_keys_for(event_type: &str, version: &RoomVersionId) -> &'stat
             "users",
             "users_default",
         ],
-        "m.room.aliases" => match version {
-            RoomVersionId::Version1
-            | RoomVersionId::Version2
-            | RoomVersionId::Version3
-            | RoomVersionId::Version4
-            | RoomVersionId::Version5 => &["join_rule"],
-            // All other room versions, including custom ones, are treated by version 6 rules.
-            // TODO: Should we return an error for unknown versions instead?
+        "m.room.aliases" => match rules.redaction_algorithm {
+            RedactionAlgorithm::V1 => &["aliases"],
             _ => &[],
         },
         "m.room.history_visibility" => &["history_visibility"],
@@ -64,6 +67,13 @@ fn allowed_content_keys_for(event_type: &str, version: &RoomVersionId) -> &'stat
     }
 }
 
+/// Returns the rules of the given room version.
+fn room_version_rules(version: &RoomVersionId) -> RoomVersion {
+    // All other room versions, including custom ones, are treated by version 6 rules.
+    // TODO: Should we return an error for unknown versions instead?
+    version.rules().unwrap_or(RoomVersion::V6)
+}
+
 /// The fields to remove from a JSON object when converting JSON into the "canonical" form.
 static CANONICAL_JSON_FIELDS_TO_REMOVE: &[&str] = &["signatures", "unsigned"];
 
@@ -140,7 +150,7 @@ where
     // FIXME: Once MSRV >= 1.45.0, use remove_key and don't allocate new `String`s below.
     signature_map = match object.remove("signatures") {
         Some(CanonicalJsonValue::Object(signatures)) => signatures,
-        Some(_) => return Err(Error::new("field `signatures` must be a JSON object")),
+        Some(_) => return Err(JsonError::invalid_type("signatures", JsonType::Object).into()),
         None => BTreeMap::new(),
     };
 
@@ -159,7 +169,10 @@ where
 
     let signature_set = match signature_set {
         CanonicalJsonValue::Object(obj) => obj,
-        _ => return Err(Error::new("fields in `signatures` must be objects")),
+        _ => {
+            let field = format!("signatures.{}", entity_id);
+            return Err(JsonError::invalid_type(field, JsonType::Object).into());
+        }
     };
 
     signature_set.insert(signature.id(), CanonicalJsonValue::String(signature.base64()));
@@ -247,19 +260,24 @@ pub fn verify_json(
 ) -> Result<(), Error> {
     let signature_map = match object.get("signatures") {
         Some(CanonicalJsonValue::Object(signatures)) => signatures.clone(),
-        Some(_) => return Err(Error::new("field `signatures` must be a JSON object")),
-        None => return Err(Error::new("JSON object must contain a `signatures` field.")),
+        Some(_) => return Err(JsonError::invalid_type("signatures", JsonType::Object).into()),
+        None => return Err(JsonError::missing_field("signatures").into()),
     };
 
     for (entity_id, public_keys) in public_key_map {
         let signature_set = match signature_map.get(entity_id) {
             Some(CanonicalJsonValue::Object(set)) => set,
-            Some(_) => return Err(Error::new("signature sets must be JSON objects")),
+            Some(_) => {
+                let field = format!("signatures.{}", entity_id);
+                return Err(JsonError::invalid_type(field, JsonType::Object).into());
+            }
             None => {
-                return Err(Error::new(format!("no signatures found for entity `{}`", entity_id)))
+                let entity = entity_id.clone();
+                return Err(VerificationError::SignatureNotFound { entity }.into());
             }
         };
 
+        let mut maybe_key_id = None;
         let mut maybe_signature = None;
         let mut maybe_public_key = None;
 
@@ -271,6 +289,7 @@ pub fn verify_json(
             }
 
             if let Some(signature) = signature_set.get(key_id) {
+                maybe_key_id = Some(key_id);
                 maybe_signature = Some(signature);
                 maybe_public_key = Some(public_key);
 
@@ -278,24 +297,30 @@ pub fn verify_json(
             }
         }
 
-        let signature = match maybe_signature {
-            Some(CanonicalJsonValue::String(signature)) => signature,
-            Some(_) => return Err(Error::new("signature must be a string")),
-            None => {
-                return Err(Error::new("event is not signed with any of the given public keys"))
-            }
-        };
+        let (key_id, signature, public_key) =
+            match (maybe_key_id, maybe_signature, maybe_public_key) {
+                (Some(key_id), Some(signature), Some(public_key)) => {
+                    (key_id, signature, public_key)
+                }
+                _ => {
+                    let entity = entity_id.clone();
+                    return Err(VerificationError::PublicKeyNotFound { entity }.into());
+                }
+            };
 
-        let public_key = match maybe_public_key {
-            Some(public_key) => public_key,
-            None => {
-                return Err(Error::new("event is not signed with any of the given public keys"))
+        let signature = match signature {
+            CanonicalJsonValue::String(signature) => signature,
+            _ => {
+                let field = format!("signatures.{}.{}", entity_id, key_id);
+                return Err(JsonError::invalid_type(field, JsonType::String).into());
             }
         };
 
-        let signature_bytes = decode_config(signature, STANDARD_NO_PAD)?;
+        let signature_bytes = decode_config(signature, STANDARD_NO_PAD)
+            .map_err(|e| ParseError::base64("signature", e))?;
 
-        let public_key_bytes = decode_config(&public_key, STANDARD_NO_PAD)?;
+        let public_key_bytes = decode_config(&public_key, STANDARD_NO_PAD)
+            .map_err(|e| ParseError::base64("public key", e))?;
 
         verify_json_with(&Ed25519Verifier, &public_key_bytes, &signature_bytes, object)?;
     }
@@ -372,11 +397,8 @@ pub fn reference_hash(
 
     Ok(encode_config(
         &hash,
-        match version {
-            RoomVersionId::Version1 | RoomVersionId::Version2 | RoomVersionId::Version3 => {
-                STANDARD_NO_PAD
-            }
-            // Room versions higher than version 3 are url safe base64 encoded
+        match room_version_rules(version).event_id_format {
+            EventIdFormat::Opaque | EventIdFormat::ReferenceHash => STANDARD_NO_PAD,
             _ => URL_SAFE_NO_PAD,
         },
     ))
@@ -494,7 +516,7 @@ where
         CanonicalJsonValue::Object(hashes) => {
             hashes.insert("sha256".into(), CanonicalJsonValue::String(hash))
         }
-        _ => return Err(Error::new("field `hashes` must be a JSON object")),
+        _ => return Err(JsonError::invalid_type("hashes", JsonType::Object).into()),
     };
 
     let mut redacted = redact(object, version)?;
@@ -513,24 +535,29 @@ where
 /// will require a valid signature. All known public keys for a homeserver should be provided. The
 /// first one found on the given event will be used.
 ///
+/// Room versions that enforce key validity (version 5 and later) only accept signatures from keys
+/// that were valid at the `origin_server_ts` of the event. If an event is only signed with expired
+/// keys of a homeserver, a `VerificationError::KeyExpired` error is returned.
+///
 /// If the `Ok` variant is returned by this function, it will contain a `Verified` value which
 /// distinguishes an event with valid signatures and a matching content hash with an event with
 /// only valid signatures. See the documentation for `Verified` for details.
 ///
 /// # Parameters
 ///
-/// * public_key_map: A map from entity identifiers to a map from key identifiers to public keys.
-/// Generally, entity identifiers are server names—the host/IP/port of a homeserver (e.g.
-/// "example.com") for which a signature must be verified. Key identifiers for each server (e.g.
-/// "ed25519:1") then map to their respective public keys.
+/// * verify_key_map: A map from entity identifiers to a map from key identifiers to public keys
+///   and their validity. Generally, entity identifiers are server names—the host/IP/port of a
+///   homeserver (e.g. "example.com") for which a signature must be verified. Key identifiers for
+///   each server (e.g. "ed25519:1") then map to their respective public keys.
 /// * object: The JSON object of the event that was signed.
+/// * version: The room version of the event.
 ///
 /// # Examples
 ///
 /// ```rust
 /// # use std::collections::BTreeMap;
 /// # use ruma_identifiers::RoomVersionId;
-/// # use ruma_signatures::verify_event;
+/// # use ruma_signatures::{verify_event, VerifyKey};
 /// #
 /// const PUBLIC_KEY: &str = "XGX0JRS2Af3be3knz2fBiRbApjm2Dh61gXDJA8kcJNI";
 ///
@@ -560,17 +587,17 @@ where
 ///     }"#
 /// ).unwrap();
 ///
-/// // Create the `PublicKeyMap` that will inform `verify_json` which signatures to verify.
-/// let mut public_key_set = BTreeMap::new();
-/// public_key_set.insert("ed25519:1".into(), PUBLIC_KEY.to_string());
-/// let mut public_key_map = BTreeMap::new();
-/// public_key_map.insert("domain".into(), public_key_set);
+/// // Create the `VerifyKeyMap` that will inform `verify_event` which signatures to verify.
+/// let mut verify_key_set = BTreeMap::new();
+/// verify_key_set.insert("ed25519:1".into(), VerifyKey::new(PUBLIC_KEY.to_string(), None));
+/// let mut verify_key_map = BTreeMap::new();
+/// verify_key_map.insert("domain".into(), verify_key_set);
 ///
-/// // Verify at least one signature for each entity in `public_key_map`.
-/// assert!(verify_event(&public_key_map, &object, &RoomVersionId::Version6).is_ok());
+/// // Verify at least one signature for each entity in `verify_key_map`.
+/// assert!(verify_event(&verify_key_map, &object, &RoomVersionId::Version6).is_ok());
 /// ```
 pub fn verify_event(
-    public_key_map: &PublicKeyMap,
+    verify_key_map: &VerifyKeyMap,
     object: &CanonicalJsonObject,
     version: &RoomVersionId,
 ) -> Result<Verified, Error> {
@@ -581,68 +608,100 @@ pub fn verify_event(
             CanonicalJsonValue::Object(hashes) => match hashes.get("sha256") {
                 Some(hash_value) => match hash_value {
                     CanonicalJsonValue::String(hash) => hash,
-                    _ => return Err(Error::new("sha256 hash must be a JSON string")),
+                    _ => {
+                        return Err(
+                            JsonError::invalid_type("hashes.sha256", JsonType::String).into()
+                        )
+                    }
                 },
-                None => return Err(Error::new("field `hashes` must be a JSON object")),
+                None => return Err(JsonError::missing_field("hashes.sha256").into()),
             },
-            _ => return Err(Error::new("event missing sha256 hash")),
+            _ => return Err(JsonError::invalid_type("hashes", JsonType::Object).into()),
         },
-        None => return Err(Error::new("field `hashes` must be present")),
+        None => return Err(JsonError::missing_field("hashes").into()),
     };
 
     let signature_map = match object.get("signatures") {
         Some(CanonicalJsonValue::Object(signatures)) => signatures,
-        Some(_) => return Err(Error::new("field `signatures` must be a JSON object")),
-        None => return Err(Error::new("JSON object must contain a `signatures` field.")),
+        Some(_) => return Err(JsonError::invalid_type("signatures", JsonType::Object).into()),
+        None => return Err(JsonError::missing_field("signatures").into()),
     };
 
-    for (entity_id, public_keys) in public_key_map {
+    let origin_server_ts = if room_version_rules(version).enforce_key_validity {
+        Some(origin_server_ts(object)?)
+    } else {
+        None
+    };
+
+    for (entity_id, verify_keys) in verify_key_map {
         let signature_set = match signature_map.get(entity_id) {
             Some(CanonicalJsonValue::Object(set)) => set,
-            Some(_) => return Err(Error::new("signatures sets must be JSON objects")),
+            Some(_) => {
+                let field = format!("signatures.{}", entity_id);
+                return Err(JsonError::invalid_type(field, JsonType::Object).into());
+            }
             None => {
-                return Err(Error::new(format!("no signatures found for entity `{}`", entity_id)))
+                let entity = entity_id.clone();
+                return Err(VerificationError::SignatureNotFound { entity }.into());
             }
         };
 
-        let mut maybe_signature = None;
-        let mut maybe_public_key = None;
+        let mut maybe_signed = None;
+        let mut maybe_expired_key_id = None;
 
-        for (key_id, public_key) in public_keys {
+        for (key_id, verify_key) in verify_keys {
             // Since only ed25519 is supported right now, we don't actually need to check what the
             // algorithm is. If it split successfully, it's ed25519.
             if split_id(key_id).is_err() {
                 break;
             }
 
-            if let Some(signature) = signature_set.get(key_id) {
-                maybe_signature = Some(signature);
-                maybe_public_key = Some(public_key);
+            let signature = match signature_set.get(key_id) {
+                Some(signature) => signature,
+                None => continue,
+            };
 
-                break;
+            match origin_server_ts {
+                Some(ts) if !verify_key.is_valid_at(ts) => {
+                    maybe_expired_key_id = maybe_expired_key_id.or(Some(key_id));
+                }
+                _ => {
+                    maybe_signed = Some((key_id, signature, verify_key));
+                    break;
+                }
             }
         }
 
-        let signature = match maybe_signature {
-            Some(CanonicalJsonValue::String(signature)) => signature,
-            Some(_) => return Err(Error::new("signature must be a string")),
+        let (key_id, signature, verify_key) = match maybe_signed {
+            Some(signed) => signed,
             None => {
-                return Err(Error::new("event is not signed with any of the given public keys"))
+                let entity = entity_id.clone();
+                let error = match maybe_expired_key_id {
+                    Some(key_id) => {
+                        VerificationError::KeyExpired { entity, key_id: key_id.clone() }
+                    }
+                    None => VerificationError::PublicKeyNotFound { entity },
+                };
+
+                return Err(error.into());
             }
         };
 
-        let public_key = match maybe_public_key {
-            Some(public_key) => public_key,
-            None => {
-                return Err(Error::new("event is not signed with any of the given public keys"))
+        let signature = match signature {
+            CanonicalJsonValue::String(signature) => signature,
+            _ => {
+                let field = format!("signatures.{}.{}", entity_id, key_id);
+                return Err(JsonError::invalid_type(field, JsonType::String).into());
             }
         };
 
         let canonical_json = from_json_str(&canonical_json(&redacted))?;
 
-        let signature_bytes = decode_config(signature, STANDARD_NO_PAD)?;
+        let signature_bytes = decode_config(signature, STANDARD_NO_PAD)
+            .map_err(|e| ParseError::base64("signature", e))?;
 
-        let public_key_bytes = decode_config(&public_key, STANDARD_NO_PAD)?;
+        let public_key_bytes = decode_config(&verify_key.key, STANDARD_NO_PAD)
+            .map_err(|e| ParseError::base64("public key", e))?;
 
         verify_json_with(&Ed25519Verifier, &public_key_bytes, &signature_bytes, &canonical_json)?;
     }
@@ -656,6 +715,127 @@ pub fn verify_event(
     }
 }
 
+/// Computes the servers that must have signed an event, and the keys they signed it with.
+///
+/// An event must be signed by:
+///
+/// * the server of its `sender`,
+/// * the server of its `event_id`, in room versions 1 and 2.
+///
+/// The `origin` of an event is not a required signer in any room version.
+///
+/// The identity servers that signed the `third_party_invite` of an *m.room.member* invite only
+/// sign its `signed` block, not the event, so their signatures are checked by the authorization
+/// rules instead.
+///
+/// For each of these servers, the returned map contains the IDs of the keys that were used to sign
+/// the event, which is empty if the event has no signatures of that server. Once the public keys
+/// for these IDs are fetched, the event can be passed to `verify_event`.
+///
+/// # Parameters
+///
+/// * object: The JSON object of the event.
+/// * version: The room version of the event.
+///
+/// # Errors
+///
+/// Returns an error if one of the fields listed above is missing or malformed.
+///
+/// # Examples
+///
+/// ```rust
+/// # use ruma_identifiers::RoomVersionId;
+/// # use ruma_signatures::required_keys;
+/// #
+/// let object = serde_json::from_str(
+///     r#"{
+///         "event_id": "$abc:origin.example.com",
+///         "sender": "@alice:example.com",
+///         "signatures": {
+///             "example.com": { "ed25519:1": "..." },
+///             "origin.example.com": { "ed25519:2": "..." }
+///         }
+///     }"#
+/// ).unwrap();
+///
+/// let required = required_keys(&object, &RoomVersionId::Version1).unwrap();
+///
+/// let servers: Vec<_> = required.keys().map(|server_name| server_name.as_str()).collect();
+/// assert_eq!(servers, ["example.com", "origin.example.com"]);
+/// ```
+pub fn required_keys(
+    object: &CanonicalJsonObject,
+    version: &RoomVersionId,
+) -> Result<BTreeMap<ServerNameBox, BTreeSet<ServerKeyId>>, Error> {
+    let mut servers = BTreeSet::new();
+
+    let sender: UserId = identifier_field(object, "sender")?;
+    servers.insert(ServerNameBox::from(sender.server_name()));
+
+    if room_version_rules(version).event_id_format == EventIdFormat::Opaque {
+        let event_id: EventId = identifier_field(object, "event_id")?;
+        if let Some(server_name) = event_id.server_name() {
+            servers.insert(server_name.into());
+        }
+    }
+
+    let signature_map = match object.get("signatures") {
+        Some(CanonicalJsonValue::Object(signatures)) => Some(signatures),
+        Some(_) => return Err(JsonError::invalid_type("signatures", JsonType::Object).into()),
+        None => None,
+    };
+
+    let mut required = BTreeMap::new();
+
+    for server_name in servers {
+        let key_ids =
+            match signature_map.and_then(|signatures| signatures.get(server_name.as_str())) {
+                // Signatures with malformed key IDs can't be verified, so there is no key to fetch.
+                Some(CanonicalJsonValue::Object(set)) => set
+                    .keys()
+                    .filter_map(|key_id| ServerKeyId::try_from(key_id.as_str()).ok())
+                    .collect(),
+                Some(_) => {
+                    let field = format!("signatures.{}", server_name);
+                    return Err(JsonError::invalid_type(field, JsonType::Object).into());
+                }
+                None => BTreeSet::new(),
+            };
+
+        required.insert(server_name, key_ids);
+    }
+
+    Ok(required)
+}
+
+/// Parses the identifier in the string field `field` of `object`.
+fn identifier_field<T>(object: &CanonicalJsonObject, field: &str) -> Result<T, Error>
+where
+    T: FromStr<Err = IdentifierError>,
+{
+    match object.get(field) {
+        Some(CanonicalJsonValue::String(value)) => {
+            value.parse().map_err(|error| ParseError::identifier(field, error).into())
+        }
+        Some(_) => Err(JsonError::invalid_type(field, JsonType::String).into()),
+        None => Err(JsonError::missing_field(field).into()),
+    }
+}
+
+/// Extracts the `origin_server_ts` of an event.
+///
+/// Negative timestamps are treated as the Unix epoch.
+fn origin_server_ts(object: &CanonicalJsonObject) -> Result<SystemTime, Error> {
+    match object.get("origin_server_ts") {
+        Some(CanonicalJsonValue::Integer(ts)) => {
+            let millis = u64::try_from(i64::from(*ts)).unwrap_or_default();
+            Ok(UNIX_EPOCH + Duration::from_millis(millis))
+        }
+        Some(_) => Err(JsonError::invalid_type("origin_server_ts", JsonType::Integer).into()),
+        None => Err(JsonError::missing_field("origin_server_ts").into()),
+    }
+}
+
 /// Internal implementation detail of the canonical JSON algorithm. Allows customization of the
 /// fields that will be removed before serializing.
 fn canonical_json_with_fields_to_remove(object: &CanonicalJsonObject, fields: &[&str]) -> String {
@@ -697,18 +877,20 @@ pub fn redact(
 
     let event_type_value = match event.get("type") {
         Some(event_type_value) => event_type_value,
-        None => return Err(Error::new("field `type` in JSON value must be present")),
+        None => return Err(JsonError::missing_field("type").into()),
     };
 
     let allowed_content_keys = match event_type_value {
-        CanonicalJsonValue::String(event_type) => allowed_content_keys_for(event_type, version),
-        _ => return Err(Error::new("field `type` in JSON value must be a JSON string")),
+        CanonicalJsonValue::String(event_type) => {
+            allowed_content_keys_for(event_type, &room_version_rules(version))
+        }
+        _ => return Err(JsonError::invalid_type("type", JsonType::String).into()),
     };
 
     if let Some(content_value) = event.get_mut("content") {
         let content = match content_value {
             CanonicalJsonValue::Object(map) => map,
-            _ => return Err(Error::new("field `content` in JSON value must be a JSON object")),
+            _ => return Err(JsonError::invalid_type("content", JsonType::Object).into()),
         };
 
         let mut old_content = mem::replace(content, BTreeMap::new());
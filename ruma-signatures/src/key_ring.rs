@@ -0,0 +1,791 @@
+//! Fetching, verification and caching of the signing keys of homeservers.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    error::Error as StdError,
+    fmt::{Display, Formatter, Result as FmtResult},
+    future::Future,
+    pin::Pin,
+    time::SystemTime,
+};
+
+use ruma_api::{error::IntoHttpError, exports::http, OutgoingRequest};
+use ruma_federation_api::discovery::{
+    get_remote_server_keys_batch::v2 as get_remote_server_keys_batch,
+    get_server_keys::v2 as get_server_keys, ServerKey,
+};
+use ruma_identifiers::{ServerKeyId, ServerName, ServerNameBox};
+use ruma_serde::{CanonicalJsonObject, CanonicalJsonValue};
+
+use crate::{
+    functions::verify_json,
+    keys::{PublicKeyMap, VerifyKey, VerifyKeyMap, VerifyKeySet},
+    Error, JsonError, JsonType, VerificationError,
+};
+
+/// Sends the requests of a `KeyRing` to other homeservers.
+///
+/// Implementations are responsible for resolving the address of the destination server, e.g.
+/// through `.well-known` delegation, and for sending the request over HTTPS.
+pub trait KeyTransport {
+    /// Sends `request` to the homeserver `destination` and returns a future that resolves to its
+    /// response.
+    ///
+    /// The URI of the request only consists of its path and query string, e.g.
+    /// `/_matrix/key/v2/server`.
+    #[allow(clippy::type_complexity)]
+    fn send<'a>(
+        &'a self,
+        destination: &'a ServerName,
+        request: http::Request<Vec<u8>>,
+    ) -> Pin<
+        Box<
+            dyn Future<Output = Result<http::Response<Vec<u8>>, Box<dyn StdError + Send + Sync>>>
+                + Send
+                + 'a,
+        >,
+    >;
+}
+
+/// An error produced when fetching the signing keys of homeservers fails.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum KeyRingError {
+    /// A request could not be converted to an HTTP request.
+    IntoHttp(IntoHttpError),
+
+    /// Sending a request to a homeserver or receiving its response failed.
+    Transport {
+        /// The server the request was sent to.
+        server_name: ServerNameBox,
+
+        /// The error returned by the `KeyTransport`.
+        source: Box<dyn StdError + Send + Sync>,
+    },
+
+    /// A homeserver responded with an error status code.
+    Status {
+        /// The server that responded.
+        server_name: ServerNameBox,
+
+        /// The status code of the response.
+        status: http::StatusCode,
+    },
+
+    /// A response is malformed or its signatures could not be verified.
+    InvalidResponse {
+        /// The server that responded.
+        server_name: ServerNameBox,
+
+        /// What's wrong with the response.
+        source: Error,
+    },
+
+    /// A public key differs from the one that was previously obtained for the same key ID.
+    Conflict {
+        /// The server that owns the key.
+        server_name: ServerNameBox,
+
+        /// The ID of the key.
+        key_id: ServerKeyId,
+    },
+
+    /// None of the requested keys of a server could be found, neither on the server itself nor on
+    /// any of the notary servers.
+    KeysNotFound {
+        /// The server whose keys are missing.
+        server_name: ServerNameBox,
+    },
+}
+
+impl Display for KeyRingError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Self::IntoHttp(error) => write!(f, "could not create request: {}", error),
+            Self::Transport { server_name, source } => {
+                write!(f, "request to `{}` failed: {}", server_name, source)
+            }
+            Self::Status { server_name, status } => {
+                write!(f, "`{}` responded with status {}", server_name, status)
+            }
+            Self::InvalidResponse { server_name, source } => {
+                write!(f, "invalid response from `{}`: {}", server_name, source)
+            }
+            Self::Conflict { server_name, key_id } => {
+                write!(f, "conflicting public keys for key `{}` of `{}`", key_id, server_name)
+            }
+            Self::KeysNotFound { server_name } => {
+                write!(f, "could not find any of the requested keys of `{}`", server_name)
+            }
+        }
+    }
+}
+
+impl StdError for KeyRingError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            Self::IntoHttp(error) => Some(error),
+            Self::Transport { source, .. } => Some(&**source),
+            Self::InvalidResponse { source, .. } => Some(source),
+            Self::Status { .. } | Self::Conflict { .. } | Self::KeysNotFound { .. } => None,
+        }
+    }
+}
+
+/// Fetches the signing keys of homeservers, verifies them and caches them until they expire.
+///
+/// Keys are fetched from the homeserver that owns them first, and from notary servers (also
+/// called perspectives) if that fails. Every response must be signed by the homeserver that owns
+/// the keys, with one of the keys it contains. Responses of notary servers must additionally be
+/// signed by the notary, with one of the keys it was added with.
+///
+/// A key that differs from the one that was previously obtained for the same key ID is rejected,
+/// so notary servers can't override keys obtained from other sources.
+#[derive(Debug)]
+pub struct KeyRing<T> {
+    /// The transport used to send requests to other homeservers.
+    transport: T,
+
+    /// The trusted notary servers, with their public keys.
+    notaries: BTreeMap<ServerNameBox, VerifyKeySet>,
+
+    /// The cached keys of homeservers.
+    keys: BTreeMap<ServerNameBox, BTreeMap<ServerKeyId, VerifyKey>>,
+}
+
+impl<T: KeyTransport> KeyRing<T> {
+    /// Creates an empty `KeyRing` that uses the given transport and no notary servers.
+    pub fn new(transport: T) -> Self {
+        Self { transport, notaries: BTreeMap::new(), keys: BTreeMap::new() }
+    }
+
+    /// Adds a trusted notary server, whose responses must be signed with one of the given keys.
+    pub fn add_notary(&mut self, server_name: ServerNameBox, verify_keys: VerifyKeySet) {
+        self.notaries.insert(server_name, verify_keys);
+    }
+
+    /// Adds a key to the cache, replacing any cached key with the same ID.
+    ///
+    /// This can be used to add the keys of the local homeserver, which never need to be fetched.
+    pub fn insert(&mut self, server_name: ServerNameBox, key_id: ServerKeyId, key: VerifyKey) {
+        self.keys.entry(server_name).or_default().insert(key_id, key);
+    }
+
+    /// Gets a cached key.
+    pub fn get(&self, server_name: &ServerName, key_id: &ServerKeyId) -> Option<&VerifyKey> {
+        self.keys.get(server_name).and_then(|keys| keys.get(key_id))
+    }
+
+    /// Gets the given keys of the given servers, fetching those that aren't cached or that
+    /// expired before `minimum_valid_until_ts`.
+    ///
+    /// The argument is usually the result of `required_keys`, and the returned map can be passed
+    /// to `verify_event`. It contains an entry for every server in `required`, with the keys that
+    /// could be found. Keys that expired before `minimum_valid_until_ts` are returned if they
+    /// can't be refreshed; `verify_event` decides whether they can be used.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if none of the requested keys of a server could be found, or if a key
+    /// conflicts with a cached one. Other failures to fetch keys from a server are not errors as
+    /// long as the keys can be obtained from a notary server. The keys of all servers are fetched
+    /// and cached before an error is returned, so a conflict for one server doesn't keep the keys
+    /// of the others from being cached.
+    pub async fn fetch_keys(
+        &mut self,
+        required: &BTreeMap<ServerNameBox, BTreeSet<ServerKeyId>>,
+        minimum_valid_until_ts: SystemTime,
+    ) -> Result<VerifyKeyMap, KeyRingError> {
+        let mut conflicts = BTreeMap::new();
+
+        for server_name in self.missing_keys(required, minimum_valid_until_ts).keys() {
+            // The keys may still be available from a notary server.
+            if let Err(error @ KeyRingError::Conflict { .. }) =
+                self.fetch_server_keys(server_name).await
+            {
+                conflicts.insert(server_name.clone(), error);
+            }
+        }
+
+        let notaries: Vec<_> = self.notaries.keys().cloned().collect();
+        for notary in notaries {
+            let missing = self.missing_keys(required, minimum_valid_until_ts);
+            if missing.is_empty() {
+                break;
+            }
+
+            // The keys may still be available from another notary server.
+            let _ = self
+                .fetch_notary_keys(&notary, &missing, minimum_valid_until_ts, &mut conflicts)
+                .await;
+        }
+
+        let mut verify_key_map = BTreeMap::new();
+
+        for (server_name, key_ids) in required {
+            if let Some(error) = conflicts.remove(server_name) {
+                return Err(error);
+            }
+
+            let verify_keys: VerifyKeySet = key_ids
+                .iter()
+                .filter_map(|key_id| {
+                    self.get(server_name, key_id).map(|key| (key_id.to_string(), key.clone()))
+                })
+                .collect();
+
+            if verify_keys.is_empty() && !key_ids.is_empty() {
+                return Err(KeyRingError::KeysNotFound { server_name: server_name.clone() });
+            }
+
+            verify_key_map.insert(server_name.to_string(), verify_keys);
+        }
+
+        Ok(verify_key_map)
+    }
+
+    /// Fetches all the keys of a homeserver from the homeserver itself, and adds them to the
+    /// cache.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails, the response is not signed by the homeserver or a
+    /// key conflicts with a cached one.
+    pub async fn fetch_server_keys(
+        &mut self,
+        server_name: &ServerName,
+    ) -> Result<(), KeyRingError> {
+        let body = self.send(server_name, get_server_keys::Request::new()).await?;
+        let keys = parse_json_object(&body)
+            .and_then(|object| validate_server_keys(&object, server_name, None))
+            .map_err(|source| KeyRingError::InvalidResponse {
+                server_name: server_name.into(),
+                source,
+            })?;
+
+        self.insert_verified(server_name, keys)
+    }
+
+    /// Fetches keys of several homeservers from a notary server, and adds them to the cache.
+    ///
+    /// Keys that conflict with cached ones are not added, and the conflict is recorded in
+    /// `conflicts` for their server.
+    async fn fetch_notary_keys(
+        &mut self,
+        notary: &ServerName,
+        query: &BTreeMap<ServerNameBox, BTreeSet<ServerKeyId>>,
+        minimum_valid_until_ts: SystemTime,
+        conflicts: &mut BTreeMap<ServerNameBox, KeyRingError>,
+    ) -> Result<(), KeyRingError> {
+        let criteria = query
+            .iter()
+            .map(|(server_name, key_ids)| {
+                let key_criteria = key_ids
+                    .iter()
+                    .map(|key_id| {
+                        (key_id.clone(), get_remote_server_keys_batch::QueryCriteria::new())
+                    })
+                    .collect();
+
+                (server_name.clone(), key_criteria)
+            })
+            .collect();
+        let request = get_remote_server_keys_batch::Request::new(criteria, minimum_valid_until_ts);
+
+        let body = self.send(notary, request).await?;
+        let invalid_response =
+            |source| KeyRingError::InvalidResponse { server_name: notary.into(), source };
+
+        let notary_keys = &self.notaries[notary];
+        let mut verified = Vec::new();
+        for object in parse_server_keys_list(&body).map_err(invalid_response)? {
+            let server_name = match object.get("server_name") {
+                Some(CanonicalJsonValue::String(server_name)) => {
+                    server_name.parse::<ServerNameBox>()
+                }
+                _ => continue,
+            };
+
+            // Notary servers may return keys that weren't requested.
+            let server_name = match server_name {
+                Ok(server_name) if query.contains_key(&server_name) => server_name,
+                _ => continue,
+            };
+
+            let keys = validate_server_keys(&object, &server_name, Some((notary, notary_keys)))
+                .map_err(invalid_response)?;
+            verified.push((server_name, keys));
+        }
+
+        for (server_name, keys) in verified {
+            if let Err(error) = self.insert_verified(&server_name, keys) {
+                conflicts.insert(server_name, error);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Sends a request to a homeserver and returns the body of a successful response.
+    async fn send<R: OutgoingRequest>(
+        &self,
+        server_name: &ServerName,
+        request: R,
+    ) -> Result<Vec<u8>, KeyRingError> {
+        let request = request.try_into_http_request("", None).map_err(KeyRingError::IntoHttp)?;
+
+        let response = self.transport.send(server_name, request).await.map_err(|source| {
+            KeyRingError::Transport { server_name: server_name.into(), source }
+        })?;
+
+        let status = response.status();
+        if !status.is_success() {
+            return Err(KeyRingError::Status { server_name: server_name.into(), status });
+        }
+
+        Ok(response.into_body())
+    }
+
+    /// Adds verified keys to the cache, unless one of them conflicts with a cached key.
+    fn insert_verified(
+        &mut self,
+        server_name: &ServerName,
+        keys: BTreeMap<ServerKeyId, VerifyKey>,
+    ) -> Result<(), KeyRingError> {
+        for (key_id, key) in &keys {
+            match self.get(server_name, key_id) {
+                Some(cached) if cached.key != key.key => {
+                    let server_name = server_name.into();
+                    return Err(KeyRingError::Conflict { server_name, key_id: key_id.clone() });
+                }
+                _ => {}
+            }
+        }
+
+        // Newer responses are authoritative for the validity of a key.
+        self.keys.entry(server_name.into()).or_default().extend(keys);
+
+        Ok(())
+    }
+
+    /// Returns the requested keys that are not cached or that expired before
+    /// `minimum_valid_until_ts`.
+    fn missing_keys(
+        &self,
+        required: &BTreeMap<ServerNameBox, BTreeSet<ServerKeyId>>,
+        minimum_valid_until_ts: SystemTime,
+    ) -> BTreeMap<ServerNameBox, BTreeSet<ServerKeyId>> {
+        required
+            .iter()
+            .filter_map(|(server_name, key_ids)| {
+                let missing: BTreeSet<_> = key_ids
+                    .iter()
+                    .filter(|key_id| match self.get(server_name, key_id) {
+                        Some(key) => !key.is_valid_at(minimum_valid_until_ts),
+                        None => true,
+                    })
+                    .cloned()
+                    .collect();
+
+                if missing.is_empty() {
+                    None
+                } else {
+                    Some((server_name.clone(), missing))
+                }
+            })
+            .collect()
+    }
+}
+
+/// Parses the body of a response as a JSON object.
+fn parse_json_object(body: &[u8]) -> Result<CanonicalJsonObject, Error> {
+    Ok(serde_json::from_slice(body)?)
+}
+
+/// Parses the `server_keys` list of the body of a notary server's response.
+fn parse_server_keys_list(body: &[u8]) -> Result<Vec<CanonicalJsonObject>, Error> {
+    let server_keys = match parse_json_object(body)?.remove("server_keys") {
+        Some(CanonicalJsonValue::Array(server_keys)) => server_keys,
+        Some(_) => return Err(JsonError::invalid_type("server_keys", JsonType::Array).into()),
+        None => return Err(JsonError::missing_field("server_keys").into()),
+    };
+
+    server_keys
+        .into_iter()
+        .map(|object| match object {
+            CanonicalJsonValue::Object(object) => Ok(object),
+            _ => Err(JsonError::invalid_type("server_keys", JsonType::Object).into()),
+        })
+        .collect()
+}
+
+/// Verifies the signatures on the signing keys of a homeserver and returns the keys.
+///
+/// The object must be signed by the homeserver with at least one of the keys in its
+/// `verify_keys`, and every such signature must be valid. If the object was returned by a
+/// notary server, it must also be signed by the notary with one of its known keys.
+fn validate_server_keys(
+    object: &CanonicalJsonObject,
+    server_name: &ServerName,
+    notary: Option<(&ServerName, &VerifyKeySet)>,
+) -> Result<BTreeMap<ServerKeyId, VerifyKey>, Error> {
+    let server_key: ServerKey = serde_json::from_value(serde_json::to_value(object)?)?;
+
+    let mut self_signed = false;
+    for (key_id, verify_key) in &server_key.verify_keys {
+        let mut public_keys = BTreeMap::new();
+        public_keys.insert(key_id.to_string(), verify_key.key.clone());
+        let mut public_key_map = BTreeMap::new();
+        public_key_map.insert(server_name.to_string(), public_keys);
+
+        match verify_json(&public_key_map, object) {
+            Ok(()) => self_signed = true,
+            // The response doesn't have to be signed by every key it contains.
+            Err(Error::Verification(VerificationError::SignatureNotFound { .. }))
+            | Err(Error::Verification(VerificationError::PublicKeyNotFound { .. })) => {}
+            Err(error) => return Err(error),
+        }
+    }
+
+    if !self_signed {
+        return Err(VerificationError::PublicKeyNotFound { entity: server_name.to_string() }.into());
+    }
+
+    if let Some((notary, notary_keys)) = notary {
+        let public_keys =
+            notary_keys.iter().map(|(id, key)| (id.clone(), key.key.clone())).collect();
+        let mut public_key_map = PublicKeyMap::new();
+        public_key_map.insert(notary.to_string(), public_keys);
+
+        verify_json(&public_key_map, object)?;
+    }
+
+    let valid_until_ts = Some(server_key.valid_until_ts);
+    let current_keys = server_key
+        .verify_keys
+        .into_iter()
+        .map(|(key_id, verify_key)| (key_id, VerifyKey::new(verify_key.key, valid_until_ts)));
+    let old_keys = server_key.old_verify_keys.into_iter().map(|(key_id, old_verify_key)| {
+        (key_id, VerifyKey::new(old_verify_key.key, Some(old_verify_key.expired_ts)))
+    });
+
+    Ok(old_keys.chain(current_keys).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        cell::RefCell,
+        collections::{BTreeMap, BTreeSet},
+        convert::TryFrom,
+        error::Error as StdError,
+        future::{self, Future},
+        pin::Pin,
+        time::{Duration, SystemTime, UNIX_EPOCH},
+    };
+
+    use base64::{encode_config, STANDARD_NO_PAD};
+    use ruma_api::exports::http;
+    use ruma_identifiers::{ServerKeyId, ServerName, ServerNameBox};
+    use ruma_serde::{CanonicalJsonObject, CanonicalJsonValue};
+    use serde_json::{
+        from_slice as from_json_slice, json, to_vec as to_json_vec, Value as JsonValue,
+    };
+
+    use super::{KeyRing, KeyRingError, KeyTransport};
+    use crate::{sign_json, Ed25519KeyPair, VerifyKey};
+
+    const HOUR: Duration = Duration::from_secs(60 * 60);
+
+    /// A homeserver served by `FakeTransport`.
+    struct FakeServer {
+        key_pair: Ed25519KeyPair,
+
+        /// The signed response of the server to `GET /_matrix/key/v2/server`.
+        server_keys: CanonicalJsonObject,
+
+        /// Whether the server responds to requests.
+        online: bool,
+    }
+
+    /// A transport that sends requests to in-memory homeservers.
+    #[derive(Default)]
+    struct FakeTransport {
+        servers: BTreeMap<String, FakeServer>,
+
+        /// The destination and path of every request that was sent.
+        requests: RefCell<Vec<(String, String)>>,
+    }
+
+    impl FakeTransport {
+        fn add_server(&mut self, server_name: &str, valid_until_ts: SystemTime) {
+            let key_pair = key_pair("1");
+            let server_keys = signed_server_keys(server_name, &key_pair, &key_pair, valid_until_ts);
+
+            let server = FakeServer { key_pair, server_keys, online: true };
+            self.servers.insert(server_name.to_owned(), server);
+        }
+
+        fn server_mut(&mut self, server_name: &str) -> &mut FakeServer {
+            self.servers.get_mut(server_name).unwrap()
+        }
+
+        fn request_count(&self) -> usize {
+            self.requests.borrow().len()
+        }
+
+        fn respond(&self, server: &FakeServer, request: &http::Request<Vec<u8>>) -> JsonValue {
+            if request.uri().path() == "/_matrix/key/v2/server" {
+                return to_json_value(&server.server_keys);
+            }
+
+            // A notary server: sign the keys of all requested servers it knows.
+            let query: BTreeMap<String, JsonValue> = from_json_slice(request.body()).unwrap();
+            let server_keys: Vec<_> = query
+                .keys()
+                .filter_map(|server_name| self.servers.get(server_name))
+                .map(|queried| {
+                    let mut server_keys = queried.server_keys.clone();
+                    sign_json(server.name(), &server.key_pair, &mut server_keys).unwrap();
+                    to_json_value(&server_keys)
+                })
+                .collect();
+
+            json!({ "server_keys": server_keys })
+        }
+    }
+
+    impl FakeServer {
+        fn name(&self) -> &str {
+            match self.server_keys.get("server_name") {
+                Some(CanonicalJsonValue::String(server_name)) => server_name,
+                _ => unreachable!(),
+            }
+        }
+    }
+
+    impl KeyTransport for FakeTransport {
+        fn send<'a>(
+            &'a self,
+            destination: &'a ServerName,
+            request: http::Request<Vec<u8>>,
+        ) -> Pin<
+            Box<
+                dyn Future<
+                        Output = Result<http::Response<Vec<u8>>, Box<dyn StdError + Send + Sync>>,
+                    > + Send
+                    + 'a,
+            >,
+        > {
+            let path = request.uri().path().to_owned();
+            self.requests.borrow_mut().push((destination.to_string(), path));
+
+            let response = match self.servers.get(destination.as_str()) {
+                Some(server) if server.online => {
+                    let body = to_json_vec(&self.respond(server, &request)).unwrap();
+                    Ok(http::Response::new(body))
+                }
+                _ => Err("connection refused".into()),
+            };
+
+            Box::pin(future::ready(response))
+        }
+    }
+
+    fn key_pair(version: &str) -> Ed25519KeyPair {
+        Ed25519KeyPair::new(&Ed25519KeyPair::generate().unwrap(), version.into()).unwrap()
+    }
+
+    fn verify_key(key_pair: &Ed25519KeyPair) -> VerifyKey {
+        VerifyKey::new(encode_config(key_pair.public_key(), STANDARD_NO_PAD), None)
+    }
+
+    fn to_json_value(object: &CanonicalJsonObject) -> JsonValue {
+        serde_json::to_value(object).unwrap()
+    }
+
+    fn ms_since_unix_epoch(ts: SystemTime) -> u64 {
+        ts.duration_since(UNIX_EPOCH).unwrap().as_millis() as u64
+    }
+
+    /// Creates the keys of a server that publishes `key_pair`, signed with `signing_key_pair`.
+    fn signed_server_keys(
+        server_name: &str,
+        key_pair: &Ed25519KeyPair,
+        signing_key_pair: &Ed25519KeyPair,
+        valid_until_ts: SystemTime,
+    ) -> CanonicalJsonObject {
+        let json = json!({
+            "server_name": server_name,
+            "verify_keys": {
+                format!("ed25519:{}", key_pair.version()): {
+                    "key": verify_key(key_pair).key,
+                },
+            },
+            "old_verify_keys": {},
+            "valid_until_ts": ms_since_unix_epoch(valid_until_ts),
+        });
+
+        let mut object = serde_json::from_value(json).unwrap();
+        sign_json(server_name, signing_key_pair, &mut object).unwrap();
+
+        object
+    }
+
+    fn server_name(server_name: &str) -> ServerNameBox {
+        server_name.parse().unwrap()
+    }
+
+    fn key_id(key_id: &str) -> ServerKeyId {
+        ServerKeyId::try_from(key_id).unwrap()
+    }
+
+    fn required(server_name: &str) -> BTreeMap<ServerNameBox, BTreeSet<ServerKeyId>> {
+        let mut key_ids = BTreeSet::new();
+        key_ids.insert(key_id("ed25519:1"));
+
+        let mut required = BTreeMap::new();
+        required.insert(self::server_name(server_name), key_ids);
+
+        required
+    }
+
+    #[tokio::test]
+    async fn fetch_and_cache_keys() {
+        let now = SystemTime::now();
+        let mut transport = FakeTransport::default();
+        transport.add_server("origin.example.com", now + HOUR);
+        let expected_key = verify_key(&transport.servers["origin.example.com"].key_pair).key;
+
+        let mut key_ring = KeyRing::new(transport);
+        let verify_key_map =
+            key_ring.fetch_keys(&required("origin.example.com"), now).await.unwrap();
+
+        let verify_key = &verify_key_map["origin.example.com"]["ed25519:1"];
+        assert_eq!(verify_key.key, expected_key);
+        assert!(verify_key.is_valid_at(now));
+        assert_eq!(key_ring.transport.request_count(), 1);
+
+        // The key is cached until it expires.
+        key_ring.fetch_keys(&required("origin.example.com"), now + HOUR / 2).await.unwrap();
+        assert_eq!(key_ring.transport.request_count(), 1);
+
+        key_ring.fetch_keys(&required("origin.example.com"), now + HOUR * 2).await.unwrap();
+        assert_eq!(key_ring.transport.request_count(), 2);
+    }
+
+    #[tokio::test]
+    async fn reject_invalid_self_signature() {
+        let now = SystemTime::now();
+        let mut transport = FakeTransport::default();
+        transport.add_server("origin.example.com", now + HOUR);
+
+        let server = transport.server_mut("origin.example.com");
+        server.server_keys =
+            signed_server_keys("origin.example.com", &server.key_pair, &key_pair("1"), now + HOUR);
+
+        let mut key_ring = KeyRing::new(transport);
+        let origin = server_name("origin.example.com");
+        assert!(matches!(
+            key_ring.fetch_server_keys(&origin).await,
+            Err(KeyRingError::InvalidResponse { server_name, .. }) if server_name == origin
+        ));
+        assert!(matches!(
+            key_ring.fetch_keys(&required("origin.example.com"), now).await,
+            Err(KeyRingError::KeysNotFound { server_name }) if server_name == origin
+        ));
+        assert!(key_ring.get(&origin, &key_id("ed25519:1")).is_none());
+    }
+
+    #[tokio::test]
+    async fn fetch_keys_from_notary() {
+        let now = SystemTime::now();
+        let mut transport = FakeTransport::default();
+        transport.add_server("origin.example.com", now + HOUR);
+        transport.add_server("notary.example.com", now + HOUR);
+        transport.server_mut("origin.example.com").online = false;
+
+        let expected_key = verify_key(&transport.servers["origin.example.com"].key_pair).key;
+        let notary_key = verify_key(&transport.servers["notary.example.com"].key_pair);
+
+        let mut key_ring = KeyRing::new(transport);
+        let mut notary_keys = BTreeMap::new();
+        notary_keys.insert("ed25519:1".to_owned(), notary_key);
+        key_ring.add_notary(server_name("notary.example.com"), notary_keys);
+
+        let verify_key_map =
+            key_ring.fetch_keys(&required("origin.example.com"), now).await.unwrap();
+        assert_eq!(verify_key_map["origin.example.com"]["ed25519:1"].key, expected_key);
+
+        let requests = key_ring.transport.requests.borrow();
+        assert_eq!(
+            *requests,
+            [
+                ("origin.example.com".to_owned(), "/_matrix/key/v2/server".to_owned()),
+                ("notary.example.com".to_owned(), "/_matrix/key/v2/query".to_owned()),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn reject_untrusted_notary_signature() {
+        let now = SystemTime::now();
+        let mut transport = FakeTransport::default();
+        transport.add_server("origin.example.com", now + HOUR);
+        transport.add_server("notary.example.com", now + HOUR);
+        transport.server_mut("origin.example.com").online = false;
+
+        let mut key_ring = KeyRing::new(transport);
+        let mut notary_keys = BTreeMap::new();
+        notary_keys.insert("ed25519:1".to_owned(), verify_key(&key_pair("1")));
+        key_ring.add_notary(server_name("notary.example.com"), notary_keys);
+
+        assert!(matches!(
+            key_ring.fetch_keys(&required("origin.example.com"), now).await,
+            Err(KeyRingError::KeysNotFound { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn reject_conflicting_key() {
+        let now = SystemTime::now();
+        let mut transport = FakeTransport::default();
+        transport.add_server("origin.example.com", now + HOUR);
+
+        let mut key_ring = KeyRing::new(transport);
+        let origin = server_name("origin.example.com");
+        let other_key = VerifyKey::new(verify_key(&key_pair("1")).key, Some(now));
+        key_ring.insert(origin.clone(), key_id("ed25519:1"), other_key.clone());
+
+        assert!(matches!(
+            key_ring.fetch_keys(&required("origin.example.com"), now + HOUR / 2).await,
+            Err(KeyRingError::Conflict { server_name, key_id })
+                if server_name == origin && key_id == "ed25519:1"
+        ));
+        assert_eq!(key_ring.get(&origin, &key_id("ed25519:1")), Some(&other_key));
+    }
+
+    #[tokio::test]
+    async fn conflict_does_not_abort_other_servers() {
+        let now = SystemTime::now();
+        let mut transport = FakeTransport::default();
+        transport.add_server("a.example.com", now + HOUR);
+        transport.add_server("b.example.com", now + HOUR);
+        let expected_key = verify_key(&transport.servers["b.example.com"].key_pair);
+
+        let mut key_ring = KeyRing::new(transport);
+        let a = server_name("a.example.com");
+        let other_key = VerifyKey::new(verify_key(&key_pair("1")).key, Some(now));
+        key_ring.insert(a.clone(), key_id("ed25519:1"), other_key);
+
+        let mut required = required("a.example.com");
+        required.extend(self::required("b.example.com"));
+        assert!(matches!(
+            key_ring.fetch_keys(&required, now + HOUR / 2).await,
+            Err(KeyRingError::Conflict { server_name, .. }) if server_name == a
+        ));
+
+        let b = server_name("b.example.com");
+        assert_eq!(key_ring.get(&b, &key_id("ed25519:1")).unwrap().key, expected_key.key);
+    }
+}
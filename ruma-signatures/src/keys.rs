@@ -3,6 +3,7 @@
 use std::{
     collections::BTreeMap,
     fmt::{Debug, Formatter, Result as FmtResult},
+    time::SystemTime,
 };
 
 use ring::signature::{Ed25519KeyPair as RingEd25519KeyPair, KeyPair as _};
@@ -44,7 +45,7 @@ impl Ed25519KeyPair {
     /// algorithm.
     pub fn new(document: &[u8], version: String) -> Result<Self, Error> {
         let keypair = RingEd25519KeyPair::from_pkcs8(document)
-            .map_err(|error| Error::new(error.to_string()))?;
+            .map_err(|error| Error::KeyPair(error.to_string()))?;
 
         Ok(Self { keypair, version })
     }
@@ -60,7 +61,7 @@ impl Ed25519KeyPair {
     /// Returns an error if the generation failed.
     pub fn generate() -> Result<Vec<u8>, Error> {
         let document = RingEd25519KeyPair::generate_pkcs8(&ring::rand::SystemRandom::new())
-            .map_err(|e| Error::new(e.to_string()))?;
+            .map_err(|e| Error::KeyPair(e.to_string()))?;
 
         Ok(document.as_ref().to_vec())
     }
@@ -106,6 +107,45 @@ pub type PublicKeyMap = BTreeMap<String, PublicKeySet>;
 /// This is represented as a map from key ID to Base64-encoded signature.
 pub type PublicKeySet = BTreeMap<String, String>;
 
+/// A public key of a homeserver, along with the time until which it may be used to verify
+/// signatures.
+///
+/// For a key from the `verify_keys` of a homeserver's signing keys, this is the `valid_until_ts`
+/// of the response. For a key from its `old_verify_keys`, this is the `expired_ts` of the key.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VerifyKey {
+    /// The Base64-encoded public key.
+    pub key: String,
+
+    /// The time until which the key is valid, or `None` if it never expires.
+    pub valid_until_ts: Option<SystemTime>,
+}
+
+impl VerifyKey {
+    /// Creates a new `VerifyKey` from the given Base64-encoded public key and expiry time.
+    pub fn new(key: String, valid_until_ts: Option<SystemTime>) -> Self {
+        Self { key, valid_until_ts }
+    }
+
+    /// Whether the key could be used to sign data at the given time.
+    pub fn is_valid_at(&self, ts: SystemTime) -> bool {
+        match self.valid_until_ts {
+            Some(valid_until_ts) => ts <= valid_until_ts,
+            None => true,
+        }
+    }
+}
+
+/// A map from entity names to sets of verify keys for that entity.
+///
+/// Like `PublicKeyMap`, but with the validity period of every key.
+pub type VerifyKeyMap = BTreeMap<String, VerifyKeySet>;
+
+/// A set of verify keys for a single homeserver.
+///
+/// This is represented as a map from key ID to `VerifyKey`.
+pub type VerifyKeySet = BTreeMap<String, VerifyKey>;
+
 #[cfg(test)]
 mod tests {
     use super::Ed25519KeyPair;
@@ -40,79 +40,42 @@
 //! To verify a signature on arbitrary JSON, use the `verify_json` function. To verify the
 //! signatures and hashes on an event, use the `verify_event` function. See the documentation for
 //! these respective functions for more details and full examples of use.
+//!
+//! The servers whose signatures an event must have, and the keys they signed it with, are computed
+//! by the `required_keys` function. With the `key-ring` feature, the `KeyRing` type fetches and
+//! verifies these keys, from the servers themselves or from notary servers.
 
 #![warn(rust_2018_idioms)]
 #![deny(missing_copy_implementations, missing_debug_implementations, missing_docs)]
 
-use std::{
-    error::Error as StdError,
-    fmt::{Display, Formatter, Result as FmtResult},
-};
+use std::fmt::{Display, Formatter, Result as FmtResult};
 
+pub use error::{Error, JsonError, JsonType, ParseError, VerificationError};
 pub use functions::{
-    canonical_json, content_hash, hash_and_sign_event, redact, reference_hash, sign_json,
-    verify_event, verify_json,
+    canonical_json, content_hash, hash_and_sign_event, redact, reference_hash, required_keys,
+    sign_json, verify_event, verify_json,
+};
+#[cfg(feature = "key-ring")]
+pub use key_ring::{KeyRing, KeyRingError, KeyTransport};
+pub use keys::{
+    Ed25519KeyPair, KeyPair, PublicKeyMap, PublicKeySet, VerifyKey, VerifyKeyMap, VerifyKeySet,
 };
-pub use keys::{Ed25519KeyPair, KeyPair, PublicKeyMap, PublicKeySet};
+pub use request::{sign_request, verify_request, XMatrix};
+#[cfg(feature = "ruma-api")]
+pub use request::{verify_http_request, XMatrixSigner};
 pub use ruma_serde::{CanonicalJsonError, CanonicalJsonObject, CanonicalJsonValue};
 pub use signatures::Signature;
+pub use verification::Verified;
 
+mod error;
 mod functions;
+#[cfg(feature = "key-ring")]
+mod key_ring;
 mod keys;
+mod request;
 mod signatures;
 mod verification;
 
-/// An error produced when ruma-signatures operations fail.
-#[derive(Clone, Debug, PartialEq)]
-pub struct Error {
-    /// A human-readable description of the error.
-    message: String,
-}
-
-impl Error {
-    /// Creates a new error.
-    ///
-    /// # Parameters
-    ///
-    /// * message: The error message.
-    pub(crate) fn new<T>(message: T) -> Self
-    where
-        T: Into<String>,
-    {
-        Self { message: message.into() }
-    }
-}
-
-impl StdError for Error {
-    fn description(&self) -> &str {
-        &self.message
-    }
-}
-
-impl Display for Error {
-    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
-        write!(f, "{}", self.message)
-    }
-}
-
-impl From<base64::DecodeError> for Error {
-    fn from(error: base64::DecodeError) -> Self {
-        Self::new(error.to_string())
-    }
-}
-
-impl From<serde_json::Error> for Error {
-    fn from(error: serde_json::Error) -> Self {
-        Self::new(error.to_string())
-    }
-}
-
-impl From<ruma_serde::CanonicalJsonError> for Error {
-    fn from(error: ruma_serde::CanonicalJsonError) -> Self {
-        Self::new(error.to_string())
-    }
-}
-
 /// The algorithm used for signing data.
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 pub enum Algorithm {
@@ -181,7 +144,10 @@ fn split_id(id: &str) -> Result<(Algorithm, String), SplitError<'_>> {
 
 #[cfg(test)]
 mod test {
-    use std::collections::BTreeMap;
+    use std::{
+        collections::BTreeMap,
+        time::{Duration, SystemTime, UNIX_EPOCH},
+    };
 
     use base64::{decode_config, STANDARD_NO_PAD};
     use ring::signature::{Ed25519KeyPair as RingEd25519KeyPair, KeyPair as _};
@@ -189,7 +155,9 @@ mod test {
     use serde_json::{from_str, to_string};
 
     use super::{
-        canonical_json, hash_and_sign_event, sign_json, verify_event, verify_json, Ed25519KeyPair,
+        canonical_json, hash_and_sign_event, redact, required_keys, sign_json, verify_event,
+        verify_json, CanonicalJsonObject, CanonicalJsonValue, Ed25519KeyPair, Error, JsonError,
+        JsonType, ParseError, Signature, VerificationError, VerifyKey, VerifyKeyMap,
     };
 
     const PKCS8: &str = "\
@@ -462,39 +430,285 @@ mod test {
         );
     }
 
+    #[test]
+    fn redact_aliases_event() {
+        let object = from_str(
+            r##"{
+                "content": {
+                    "aliases": ["#somewhere:domain"]
+                },
+                "event_id": "$0:domain",
+                "room_id": "!r:domain",
+                "sender": "@u:domain",
+                "state_key": "domain",
+                "type": "m.room.aliases"
+            }"##,
+        )
+        .unwrap();
+
+        let redacted = redact(&object, &RoomVersionId::Version5).unwrap();
+        assert_eq!(
+            to_string(&redacted["content"]).unwrap(),
+            r##"{"aliases":["#somewhere:domain"]}"##
+        );
+
+        let redacted = redact(&object, &RoomVersionId::Version6).unwrap();
+        assert_eq!(to_string(&redacted["content"]).unwrap(), "{}");
+    }
+
+    /// A minimal event, signed with the key pair from `PKCS8` at an `origin_server_ts` of 1000
+    /// seconds.
+    const MINIMAL_EVENT: &str = r#"{
+        "auth_events": [],
+        "content": {},
+        "depth": 3,
+        "hashes": {
+            "sha256": "5jM4wQpv6lnBo7CLIghJuHdW+s2CMBJPUOGOC89ncos"
+        },
+        "origin": "domain",
+        "origin_server_ts": 1000000,
+        "prev_events": [],
+        "room_id": "!x:domain",
+        "sender": "@a:domain",
+        "signatures": {
+            "domain": {
+                "ed25519:1": "PxOFMn6ORll8PFSQp0IRF6037MEZt3Mfzu/ROiT/gb/ccs1G+f6Ddoswez4KntLPBI3GKCGIkhctiK37JOy2Aw"
+            }
+        },
+        "type": "X",
+        "unsigned": {
+            "age_ts": 1000000
+        }
+    }"#;
+
+    /// Convenience for creating a `VerifyKeyMap` with the public key from `PKCS8`.
+    fn verify_key_map(valid_until_ts: Option<SystemTime>) -> VerifyKeyMap {
+        let mut verify_key_set = BTreeMap::new();
+        verify_key_set
+            .insert("ed25519:1".into(), VerifyKey::new(public_key_string(), valid_until_ts));
+
+        let mut verify_key_map = BTreeMap::new();
+        verify_key_map.insert("domain".into(), verify_key_set);
+
+        verify_key_map
+    }
+
     #[test]
     fn verify_minimal_event() {
+        let value = from_str(MINIMAL_EVENT).unwrap();
+
+        assert!(verify_event(&verify_key_map(None), &value, &RoomVersionId::Version5).is_ok());
+    }
+
+    #[test]
+    fn verify_event_key_validity() {
+        let value = from_str(MINIMAL_EVENT).unwrap();
+        let before = UNIX_EPOCH + Duration::from_secs(999);
+        let after = UNIX_EPOCH + Duration::from_secs(1001);
+
+        let valid = verify_key_map(Some(after));
+        assert!(verify_event(&valid, &value, &RoomVersionId::Version5).is_ok());
+
+        let expired = verify_key_map(Some(before));
+        assert!(matches!(
+            verify_event(&expired, &value, &RoomVersionId::Version5),
+            Err(Error::Verification(VerificationError::KeyExpired { entity, key_id }))
+                if entity == "domain" && key_id == "ed25519:1"
+        ));
+
+        // Room versions before 5 ignore the validity of keys.
+        assert!(verify_event(&expired, &value, &RoomVersionId::Version4).is_ok());
+
+        // A valid key is used if the event is also signed with an expired one.
+        let mut value = value;
+        if let CanonicalJsonValue::Object(signatures) = value.get_mut("signatures").unwrap() {
+            if let CanonicalJsonValue::Object(signature_set) = signatures.get_mut("domain").unwrap()
+            {
+                let signature = signature_set["ed25519:1"].clone();
+                signature_set.insert("ed25519:0".into(), signature);
+            }
+        }
+        let mut expired_and_valid = verify_key_map(Some(after));
+        let old_key = VerifyKey::new(public_key_string(), Some(before));
+        expired_and_valid.get_mut("domain").unwrap().insert("ed25519:0".into(), old_key);
+        assert!(verify_event(&expired_and_valid, &value, &RoomVersionId::Version6).is_ok());
+    }
+
+    #[test]
+    fn verify_event_without_origin_server_ts() {
+        let mut value: CanonicalJsonObject = from_str(MINIMAL_EVENT).unwrap();
+        value.remove("origin_server_ts");
+
+        assert!(matches!(
+            verify_event(&verify_key_map(None), &value, &RoomVersionId::Version5),
+            Err(Error::Json(JsonError::MissingField(field))) if field == "origin_server_ts"
+        ));
+    }
+
+    #[test]
+    fn verify_json_errors() {
         let mut signature_set = BTreeMap::new();
         signature_set.insert("ed25519:1".into(), public_key_string());
 
         let mut public_key_map = BTreeMap::new();
         public_key_map.insert("domain".into(), signature_set);
 
+        let unsigned = from_str(r#"{"one":1}"#).unwrap();
+        assert!(matches!(
+            verify_json(&public_key_map, &unsigned),
+            Err(Error::Json(JsonError::MissingField(field))) if field == "signatures"
+        ));
+
+        let other_entity = from_str(r#"{"signatures":{"other":{"ed25519:1":"abc"}}}"#).unwrap();
+        assert!(matches!(
+            verify_json(&public_key_map, &other_entity),
+            Err(Error::Verification(VerificationError::SignatureNotFound { entity }))
+                if entity == "domain"
+        ));
+
+        let other_key = from_str(r#"{"signatures":{"domain":{"ed25519:2":"abc"}}}"#).unwrap();
+        assert!(matches!(
+            verify_json(&public_key_map, &other_key),
+            Err(Error::Verification(VerificationError::PublicKeyNotFound { entity }))
+                if entity == "domain"
+        ));
+
+        let not_base64 = from_str(r#"{"signatures":{"domain":{"ed25519:1":"!!!"}}}"#).unwrap();
+        assert!(matches!(
+            verify_json(&public_key_map, &not_base64),
+            Err(Error::Parse(ParseError::Base64 { of_type: "signature", .. }))
+        ));
+
+        // The signature of `{"one":1,"two":"Two"}`.
+        let tampered = from_str(
+            r#"{"one":2,"signatures":{"domain":{"ed25519:1":"t6Ehmh6XTDz7qNWI0QI5tNPSliWLPQP/+Fzz3LpdCS7q1k2G2/5b5Embs2j4uG3ZeivejrzqSVoBcdocRpa+AQ"}},"two":"Two"}"#
+        ).unwrap();
+        assert!(matches!(
+            verify_json(&public_key_map, &tampered),
+            Err(Error::Verification(VerificationError::InvalidSignature))
+        ));
+    }
+
+    #[test]
+    fn event_shape_errors() {
+        let without_type = from_str(r#"{"content":{}}"#).unwrap();
+        assert!(matches!(
+            redact(&without_type, &RoomVersionId::Version6),
+            Err(Error::Json(JsonError::MissingField(field))) if field == "type"
+        ));
+
+        let invalid_content = from_str(r#"{"type":"X","content":[]}"#).unwrap();
+        assert!(matches!(
+            redact(&invalid_content, &RoomVersionId::Version6),
+            Err(Error::Json(JsonError::InvalidType { field, expected: JsonType::Object }))
+                if field == "content"
+        ));
+
+        let without_hash = from_str(r#"{"type":"X","content":{},"hashes":{}}"#).unwrap();
+        assert!(matches!(
+            verify_event(&BTreeMap::new(), &without_hash, &RoomVersionId::Version6),
+            Err(Error::Json(JsonError::MissingField(field))) if field == "hashes.sha256"
+        ));
+    }
+
+    #[test]
+    fn required_keys_of_v1_event() {
         let value = from_str(
             r#"{
-                "auth_events": [],
-                "content": {},
-                "depth": 3,
-                "hashes": {
-                    "sha256": "5jM4wQpv6lnBo7CLIghJuHdW+s2CMBJPUOGOC89ncos"
-                },
-                "origin": "domain",
-                "origin_server_ts": 1000000,
-                "prev_events": [],
-                "room_id": "!x:domain",
-                "sender": "@a:domain",
+                "event_id": "$abc:event.example.com",
+                "origin": "origin.example.com",
+                "sender": "@alice:example.com",
                 "signatures": {
-                    "domain": {
-                        "ed25519:1": "PxOFMn6ORll8PFSQp0IRF6037MEZt3Mfzu/ROiT/gb/ccs1G+f6Ddoswez4KntLPBI3GKCGIkhctiK37JOy2Aw"
+                    "example.com": { "ed25519:1": "abc", "ed25519:2": "def" },
+                    "origin.example.com": { "ed25519:a": "ghi", "invalid": "jkl" },
+                    "other.example.com": { "ed25519:1": "mno" }
+                },
+                "type": "X"
+            }"#,
+        )
+        .unwrap();
+
+        let required = required_keys(&value, &RoomVersionId::Version1).unwrap();
+        let required: Vec<(&str, Vec<&str>)> = required
+            .iter()
+            .map(|(server, keys)| (server.as_str(), keys.iter().map(|key| key.as_str()).collect()))
+            .collect();
+        assert_eq!(
+            required,
+            [("event.example.com", vec![]), ("example.com", vec!["ed25519:1", "ed25519:2"]),]
+        );
+
+        let required = required_keys(&value, &RoomVersionId::Version2).unwrap();
+        let servers: Vec<_> = required.keys().map(|server| server.as_str()).collect();
+        assert_eq!(servers, ["event.example.com", "example.com"]);
+
+        let required = required_keys(&value, &RoomVersionId::Version6).unwrap();
+        let servers: Vec<_> = required.keys().map(|server| server.as_str()).collect();
+        assert_eq!(servers, ["example.com"]);
+    }
+
+    #[test]
+    fn required_keys_of_third_party_invite() {
+        let value = from_str(
+            r#"{
+                "content": {
+                    "membership": "invite",
+                    "third_party_invite": {
+                        "display_name": "alice",
+                        "signed": {
+                            "mxid": "@alice:example.org",
+                            "signatures": {
+                                "magic.forest": { "ed25519:3": "abc" }
+                            },
+                            "token": "abc123"
+                        }
                     }
                 },
-                "type": "X",
-                "unsigned": {
-                    "age_ts": 1000000
-                }
-            }"#
-        ).unwrap();
+                "sender": "@bob:example.com",
+                "signatures": {
+                    "example.com": { "ed25519:1": "abc" }
+                },
+                "state_key": "@alice:example.org",
+                "type": "m.room.member"
+            }"#,
+        )
+        .unwrap();
+
+        let required = required_keys(&value, &RoomVersionId::Version6).unwrap();
+        let servers: Vec<_> = required.keys().map(|server| server.as_str()).collect();
+        assert_eq!(servers, ["example.com"]);
+    }
 
-        assert!(verify_event(&public_key_map, &value, &RoomVersionId::Version5).is_ok());
+    #[test]
+    fn required_keys_errors() {
+        let without_sender = from_str(r#"{"type":"X"}"#).unwrap();
+        assert!(matches!(
+            required_keys(&without_sender, &RoomVersionId::Version6),
+            Err(Error::Json(JsonError::MissingField(field))) if field == "sender"
+        ));
+
+        let invalid_sender = from_str(r#"{"sender":"alice","type":"X"}"#).unwrap();
+        assert!(matches!(
+            required_keys(&invalid_sender, &RoomVersionId::Version6),
+            Err(Error::Parse(ParseError::Identifier { field, .. })) if field == "sender"
+        ));
+
+        let without_event_id = from_str(r#"{"sender":"@alice:example.com","type":"X"}"#).unwrap();
+        assert!(matches!(
+            required_keys(&without_event_id, &RoomVersionId::Version2),
+            Err(Error::Json(JsonError::MissingField(field))) if field == "event_id"
+        ));
+    }
+
+    #[test]
+    fn key_id_errors() {
+        assert!(matches!(
+            Signature::new("ed25519:abc:def", &[]),
+            Err(Error::Parse(ParseError::InvalidKeyId(id))) if id == "ed25519:abc:def"
+        ));
+        assert!(matches!(
+            Signature::new("foobar:abcdef", &[]),
+            Err(Error::UnsupportedAlgorithm(algorithm)) if algorithm == "foobar"
+        ));
     }
 }
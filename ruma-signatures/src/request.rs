@@ -0,0 +1,549 @@
+//! Signing and verification of federation requests using the `X-Matrix` authorization scheme.
+
+use std::{
+    collections::BTreeMap,
+    convert::TryFrom,
+    fmt::{Display, Formatter, Result as FmtResult},
+    str::FromStr,
+};
+
+#[cfg(feature = "ruma-api")]
+use std::error::Error as StdError;
+
+#[cfg(feature = "ruma-api")]
+use ruma_api::{exports::http, RequestSigner};
+use ruma_identifiers::{ServerKeyId, ServerName, ServerNameBox};
+use ruma_serde::{CanonicalJsonObject, CanonicalJsonValue};
+
+use crate::{
+    functions::{sign_json, verify_json},
+    keys::{KeyPair, PublicKeyMap},
+    Error, ParseError, VerificationError,
+};
+
+/// The name of the authorization scheme used for federation requests.
+const SCHEME: &str = "X-Matrix";
+
+/// The credentials of a federation request, as sent in its `Authorization` header.
+///
+/// The header value can be created with `to_string` and parsed with `parse`:
+///
+/// ```rust
+/// use ruma_signatures::XMatrix;
+///
+/// let header = r#"X-Matrix origin=origin.hs.example.com,key="ed25519:key1",sig="ABCDEF...""#;
+/// let x_matrix: XMatrix = header.parse().unwrap();
+///
+/// assert_eq!(x_matrix.origin.as_str(), "origin.hs.example.com");
+/// assert_eq!(x_matrix.key.as_str(), "ed25519:key1");
+/// assert_eq!(x_matrix.to_string(), header);
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct XMatrix {
+    /// The server name of the sending server.
+    pub origin: ServerNameBox,
+
+    /// The ID of the key used to sign the request, including the algorithm name.
+    pub key: ServerKeyId,
+
+    /// The Base64-encoded signature of the request.
+    pub sig: String,
+}
+
+impl XMatrix {
+    /// Creates a new `XMatrix` with the given origin, key ID and signature.
+    pub fn new(origin: ServerNameBox, key: ServerKeyId, sig: String) -> Self {
+        Self { origin, key, sig }
+    }
+}
+
+impl Display for XMatrix {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, r#"{} origin={},key="{}",sig="{}""#, SCHEME, self.origin, self.key, self.sig)
+    }
+}
+
+impl FromStr for XMatrix {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = |reason: &str| Error::from(ParseError::XMatrix(reason.to_owned()));
+
+        let mut parts = s.trim().splitn(2, ' ');
+        let scheme = parts.next().unwrap_or_default();
+        if !scheme.eq_ignore_ascii_case(SCHEME) {
+            return Err(invalid("authorization scheme must be X-Matrix"));
+        }
+        let params = parts.next().ok_or_else(|| invalid("missing parameters"))?;
+
+        let (mut origin, mut key, mut sig) = (None, None, None);
+        for param in params.split(',') {
+            let param = param.trim();
+            if param.is_empty() {
+                continue;
+            }
+
+            let mut name_value = param.splitn(2, '=');
+            let name = name_value.next().unwrap_or_default().trim();
+            let value = name_value
+                .next()
+                .ok_or_else(|| invalid(&format!("parameter `{}` has no value", name)))?;
+            let value = unquote(value.trim());
+
+            // Unknown parameters are ignored, as recommended by the specification.
+            match name {
+                "origin" => origin = Some(value),
+                "key" => key = Some(value),
+                "sig" => sig = Some(value),
+                _ => {}
+            }
+        }
+
+        let origin = origin.ok_or_else(|| invalid("missing parameter `origin`"))?;
+        let key = key.ok_or_else(|| invalid("missing parameter `key`"))?;
+        let sig = sig.ok_or_else(|| invalid("missing parameter `sig`"))?;
+
+        let origin = ServerNameBox::try_from(origin.as_str())
+            .map_err(|_| invalid(&format!("invalid origin `{}`", origin)))?;
+        let key = ServerKeyId::try_from(key.as_str()).map_err(|_| ParseError::InvalidKeyId(key))?;
+
+        Ok(Self { origin, key, sig })
+    }
+}
+
+/// Removes the quotes around a quoted-string parameter value, and unescapes its content.
+fn unquote(value: &str) -> String {
+    if value.len() < 2 || !value.starts_with('"') || !value.ends_with('"') {
+        return value.to_owned();
+    }
+
+    let mut unquoted = String::with_capacity(value.len() - 2);
+    let mut chars = value[1..value.len() - 1].chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => unquoted.extend(chars.next()),
+            c => unquoted.push(c),
+        }
+    }
+
+    unquoted
+}
+
+/// Builds the JSON object that is signed to authenticate a federation request.
+fn request_object(
+    origin: &ServerName,
+    destination: &ServerName,
+    method: &str,
+    uri: &str,
+    content: Option<CanonicalJsonValue>,
+) -> CanonicalJsonObject {
+    let mut object = BTreeMap::new();
+    object.insert("method".to_owned(), CanonicalJsonValue::String(method.to_owned()));
+    object.insert("uri".to_owned(), CanonicalJsonValue::String(uri.to_owned()));
+    object.insert("origin".to_owned(), CanonicalJsonValue::String(origin.as_str().to_owned()));
+    object.insert(
+        "destination".to_owned(),
+        CanonicalJsonValue::String(destination.as_str().to_owned()),
+    );
+
+    if let Some(content) = content {
+        object.insert("content".to_owned(), content);
+    }
+
+    object
+}
+
+/// Signs a federation request and returns the credentials for its `Authorization` header.
+///
+/// # Parameters
+///
+/// * origin: The server name of the sending server, which owns the key pair.
+/// * key_pair: A cryptographic key pair used to sign the request.
+/// * destination: The server name of the receiving server.
+/// * method: The HTTP method of the request, in upper case, e.g. "GET".
+/// * uri: The path and query string of the request, starting with `/_matrix/`.
+/// * content: The JSON body of the request, if it has one.
+///
+/// # Errors
+///
+/// Returns an error if signing fails.
+///
+/// # Examples
+///
+/// ```rust
+/// # use std::convert::TryFrom;
+/// #
+/// # use ruma_identifiers::ServerName;
+/// #
+/// const PKCS8: &str = "MFMCAQEwBQYDK2VwBCIEINjozvdfbsGEt6DD+7Uf4PiJ/YvTNXV2mIPc/tA0T+6toSMDIQDdM+tpNzNWQM9NFpfgr4B9S7LHszOrVRp9NfKmeXS3aQ";
+///
+/// let document = base64::decode_config(&PKCS8, base64::STANDARD_NO_PAD).unwrap();
+/// let key_pair = ruma_signatures::Ed25519KeyPair::new(&document, "1".into()).unwrap();
+///
+/// let origin = <&ServerName>::try_from("origin.hs.example.com").unwrap();
+/// let destination = <&ServerName>::try_from("destination.hs.example.com").unwrap();
+///
+/// let x_matrix = ruma_signatures::sign_request(
+///     origin,
+///     &key_pair,
+///     destination,
+///     "GET",
+///     "/_matrix/federation/v1/version",
+///     None,
+/// )
+/// .unwrap();
+///
+/// assert_eq!(x_matrix.origin.as_str(), "origin.hs.example.com");
+/// assert_eq!(x_matrix.key.as_str(), "ed25519:1");
+/// ```
+pub fn sign_request<K>(
+    origin: &ServerName,
+    key_pair: &K,
+    destination: &ServerName,
+    method: &str,
+    uri: &str,
+    content: Option<CanonicalJsonValue>,
+) -> Result<XMatrix, Error>
+where
+    K: KeyPair,
+{
+    let mut object = request_object(origin, destination, method, uri, content);
+    sign_json(origin.as_str(), key_pair, &mut object)?;
+
+    // `sign_json` adds exactly one signature, from the origin, to the object without signatures.
+    let signature = match object.remove("signatures") {
+        Some(CanonicalJsonValue::Object(mut signatures)) => {
+            match signatures.remove(origin.as_str()) {
+                Some(CanonicalJsonValue::Object(signature_set)) => signature_set.into_iter().next(),
+                _ => None,
+            }
+        }
+        _ => None,
+    };
+    let (key, sig) = match signature {
+        Some((key, CanonicalJsonValue::String(sig))) => (key, sig),
+        _ => unreachable!("sign_json adds a signature from the origin"),
+    };
+
+    let key = ServerKeyId::try_from(key.as_str()).map_err(|_| ParseError::InvalidKeyId(key))?;
+
+    Ok(XMatrix { origin: origin.into(), key, sig })
+}
+
+/// Uses a set of public keys to verify the credentials of a federation request.
+///
+/// # Parameters
+///
+/// * public_key_map: A map from server names to a map from key identifiers to public keys. Only
+///   the key of the origin that is named in `authorization` is used.
+/// * authorization: The credentials from the `Authorization` header of the request.
+/// * destination: The server name of the receiving server.
+/// * method: The HTTP method of the request, in upper case, e.g. "GET".
+/// * uri: The path and query string of the request, starting with `/_matrix/`.
+/// * content: The JSON body of the request, if it has one.
+///
+/// # Errors
+///
+/// Returns an error if the public key of the origin is not in `public_key_map` or the signature
+/// doesn't match the request.
+pub fn verify_request(
+    public_key_map: &PublicKeyMap,
+    authorization: &XMatrix,
+    destination: &ServerName,
+    method: &str,
+    uri: &str,
+    content: Option<CanonicalJsonValue>,
+) -> Result<(), Error> {
+    let origin = authorization.origin.as_str();
+    let key_id = authorization.key.as_str();
+
+    let public_key = public_key_map
+        .get(origin)
+        .and_then(|keys| keys.get(key_id))
+        .ok_or_else(|| VerificationError::PublicKeyNotFound { entity: origin.to_owned() })?;
+
+    let mut signature_set = BTreeMap::new();
+    signature_set.insert(key_id.to_owned(), CanonicalJsonValue::String(authorization.sig.clone()));
+    let mut signatures = BTreeMap::new();
+    signatures.insert(origin.to_owned(), CanonicalJsonValue::Object(signature_set));
+
+    let mut object = request_object(&authorization.origin, destination, method, uri, content);
+    object.insert("signatures".to_owned(), CanonicalJsonValue::Object(signatures));
+
+    let mut public_keys = BTreeMap::new();
+    public_keys.insert(key_id.to_owned(), public_key.clone());
+    let mut origin_key_map = BTreeMap::new();
+    origin_key_map.insert(origin.to_owned(), public_keys);
+
+    verify_json(&origin_key_map, &object)
+}
+
+/// Signs outgoing federation requests using the `X-Matrix` authorization scheme.
+///
+/// Pass this to `ruma_api::OutgoingRequest::try_into_signed_http_request` to attach an
+/// `Authorization` header to requests of endpoints that require server signatures.
+#[cfg(feature = "ruma-api")]
+#[derive(Debug)]
+pub struct XMatrixSigner<K> {
+    origin: ServerNameBox,
+    key_pair: K,
+}
+
+#[cfg(feature = "ruma-api")]
+impl<K> XMatrixSigner<K> {
+    /// Creates a new `XMatrixSigner` for the homeserver `origin` and its signing key pair.
+    pub fn new(origin: ServerNameBox, key_pair: K) -> Self {
+        Self { origin, key_pair }
+    }
+}
+
+#[cfg(feature = "ruma-api")]
+impl<K: KeyPair> RequestSigner for XMatrixSigner<K> {
+    fn authorization_header(
+        &self,
+        destination: &ServerName,
+        request: &http::Request<Vec<u8>>,
+    ) -> Result<String, Box<dyn StdError + Send + Sync>> {
+        let x_matrix = sign_request(
+            &self.origin,
+            &self.key_pair,
+            destination,
+            request.method().as_str(),
+            path_and_query(request),
+            request_content(request)?,
+        )?;
+
+        Ok(x_matrix.to_string())
+    }
+}
+
+/// Verifies the `Authorization` header of an incoming federation request.
+///
+/// This is a wrapper around `verify_request` that takes the method, URI and content from
+/// `request`. On success, the parsed credentials are returned, so the caller knows which server
+/// sent the request.
+///
+/// # Errors
+///
+/// Returns an error if the `Authorization` header is missing or malformed, if the body is not
+/// JSON, or if `verify_request` fails.
+#[cfg(feature = "ruma-api")]
+pub fn verify_http_request(
+    public_key_map: &PublicKeyMap,
+    destination: &ServerName,
+    request: &http::Request<Vec<u8>>,
+) -> Result<XMatrix, Error> {
+    let authorization = request
+        .headers()
+        .get(http::header::AUTHORIZATION)
+        .ok_or_else(|| ParseError::XMatrix("missing Authorization header".to_owned()))?
+        .to_str()
+        .map_err(|_| ParseError::XMatrix("Authorization header is not valid ASCII".to_owned()))?
+        .parse::<XMatrix>()?;
+
+    verify_request(
+        public_key_map,
+        &authorization,
+        destination,
+        request.method().as_str(),
+        path_and_query(request),
+        request_content(request)?,
+    )?;
+
+    Ok(authorization)
+}
+
+/// The URI of a request as it is signed: its path and query string.
+#[cfg(feature = "ruma-api")]
+fn path_and_query(request: &http::Request<Vec<u8>>) -> &str {
+    request.uri().path_and_query().map_or("/", |path_and_query| path_and_query.as_str())
+}
+
+/// The JSON body of a request, or `None` if it has no body.
+#[cfg(feature = "ruma-api")]
+fn request_content(request: &http::Request<Vec<u8>>) -> Result<Option<CanonicalJsonValue>, Error> {
+    if request.body().is_empty() {
+        return Ok(None);
+    }
+
+    Ok(Some(serde_json::from_slice(request.body())?))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::BTreeMap, convert::TryFrom};
+
+    use base64::{decode_config, encode_config, STANDARD_NO_PAD};
+    use ring::signature::{Ed25519KeyPair as RingEd25519KeyPair, KeyPair as _};
+    use ruma_identifiers::ServerName;
+    use serde_json::{from_value as from_json_value, json};
+
+    use super::{sign_request, verify_request, XMatrix};
+    use crate::{Ed25519KeyPair, Error, ParseError, VerificationError};
+
+    const PKCS8: &str = "\
+        MFMCAQEwBQYDK2VwBCIEINjozvdfbsGEt6DD+7Uf4PiJ/YvTNXV2mIPc/\
+        tA0T+6toSMDIQDdM+tpNzNWQM9NFpfgr4B9S7LHszOrVRp9NfKmeXS3aQ\
+    ";
+
+    fn key_pair() -> Ed25519KeyPair {
+        Ed25519KeyPair::new(&decode_config(PKCS8, STANDARD_NO_PAD).unwrap(), "1".into()).unwrap()
+    }
+
+    fn public_key_map() -> BTreeMap<String, BTreeMap<String, String>> {
+        let public_key = encode_config(
+            RingEd25519KeyPair::from_pkcs8(&decode_config(PKCS8, STANDARD_NO_PAD).unwrap())
+                .unwrap()
+                .public_key(),
+            STANDARD_NO_PAD,
+        );
+
+        let mut public_keys = BTreeMap::new();
+        public_keys.insert("ed25519:1".to_owned(), public_key);
+        let mut public_key_map = BTreeMap::new();
+        public_key_map.insert("origin.hs.example.com".to_owned(), public_keys);
+
+        public_key_map
+    }
+
+    fn server_name(name: &str) -> &ServerName {
+        <&ServerName>::try_from(name).unwrap()
+    }
+
+    #[test]
+    fn parse_x_matrix() {
+        let header = r#"X-Matrix origin=origin.hs.example.com,key="ed25519:key1",sig="ABCDEF""#;
+        let x_matrix: XMatrix = header.parse().unwrap();
+
+        assert_eq!(x_matrix.origin.as_str(), "origin.hs.example.com");
+        assert_eq!(x_matrix.key.as_str(), "ed25519:key1");
+        assert_eq!(x_matrix.sig, "ABCDEF");
+        assert_eq!(x_matrix.to_string(), header);
+    }
+
+    #[test]
+    fn parse_x_matrix_lenient() {
+        let header =
+            r#"x-matrix sig="ABCDEF", origin="origin.hs.example.com", unknown=1, key=ed25519:key1"#;
+        let x_matrix: XMatrix = header.parse().unwrap();
+
+        assert_eq!(x_matrix.origin.as_str(), "origin.hs.example.com");
+        assert_eq!(x_matrix.key.as_str(), "ed25519:key1");
+        assert_eq!(x_matrix.sig, "ABCDEF");
+    }
+
+    #[test]
+    fn parse_invalid_x_matrix() {
+        for header in &[
+            r#"Bearer origin=origin.hs.example.com,key="ed25519:key1",sig="ABCDEF""#,
+            "X-Matrix",
+            r#"X-Matrix origin=origin.hs.example.com,key="ed25519:key1""#,
+            r#"X-Matrix origin="",key="ed25519:key1",sig="ABCDEF""#,
+            r#"X-Matrix origin=origin.hs.example.com,key,sig="ABCDEF""#,
+        ] {
+            assert!(matches!(header.parse::<XMatrix>(), Err(Error::Parse(ParseError::XMatrix(_)))));
+        }
+
+        assert!(matches!(
+            r#"X-Matrix origin=origin.hs.example.com,key="key1",sig="ABCDEF""#.parse::<XMatrix>(),
+            Err(Error::Parse(ParseError::InvalidKeyId(_)))
+        ));
+    }
+
+    #[test]
+    fn sign_and_verify_request() {
+        let origin = server_name("origin.hs.example.com");
+        let destination = server_name("destination.hs.example.com");
+        let uri = "/_matrix/federation/v1/send/1";
+        let content = from_json_value(json!({ "pdus": [], "edus": [] })).unwrap();
+
+        let x_matrix =
+            sign_request(origin, &key_pair(), destination, "PUT", uri, Some(content)).unwrap();
+        assert_eq!(x_matrix.origin.as_str(), "origin.hs.example.com");
+        assert_eq!(x_matrix.key.as_str(), "ed25519:1");
+
+        let x_matrix: XMatrix = x_matrix.to_string().parse().unwrap();
+        let content = from_json_value(json!({ "edus": [], "pdus": [] })).unwrap();
+        verify_request(&public_key_map(), &x_matrix, destination, "PUT", uri, Some(content))
+            .unwrap();
+    }
+
+    #[test]
+    fn verify_tampered_request() {
+        let origin = server_name("origin.hs.example.com");
+        let destination = server_name("destination.hs.example.com");
+        let uri = "/_matrix/federation/v1/query/profile?user_id=%40alice%3Aexample.com";
+
+        let x_matrix = sign_request(origin, &key_pair(), destination, "GET", uri, None).unwrap();
+
+        let other_uri = "/_matrix/federation/v1/query/profile?user_id=%40bob%3Aexample.com";
+        assert!(matches!(
+            verify_request(&public_key_map(), &x_matrix, destination, "GET", other_uri, None),
+            Err(Error::Verification(VerificationError::InvalidSignature))
+        ));
+
+        let other_destination = server_name("other.hs.example.com");
+        assert!(matches!(
+            verify_request(&public_key_map(), &x_matrix, other_destination, "GET", uri, None),
+            Err(Error::Verification(VerificationError::InvalidSignature))
+        ));
+
+        let content = from_json_value(json!({})).unwrap();
+        assert!(matches!(
+            verify_request(&public_key_map(), &x_matrix, destination, "GET", uri, Some(content)),
+            Err(Error::Verification(VerificationError::InvalidSignature))
+        ));
+    }
+
+    #[test]
+    fn verify_request_with_unknown_key() {
+        let origin = server_name("origin.hs.example.com");
+        let destination = server_name("destination.hs.example.com");
+        let uri = "/_matrix/federation/v1/version";
+
+        let mut x_matrix =
+            sign_request(origin, &key_pair(), destination, "GET", uri, None).unwrap();
+        x_matrix.key = TryFrom::try_from("ed25519:2").unwrap();
+
+        assert!(matches!(
+            verify_request(&public_key_map(), &x_matrix, destination, "GET", uri, None),
+            Err(Error::Verification(VerificationError::PublicKeyNotFound { entity }))
+                if entity == "origin.hs.example.com"
+        ));
+    }
+
+    #[cfg(feature = "ruma-api")]
+    #[test]
+    fn sign_and_verify_http_request() {
+        use ruma_api::{exports::http, RequestSigner};
+
+        use super::{verify_http_request, XMatrixSigner};
+
+        let origin = server_name("origin.hs.example.com");
+        let destination = server_name("destination.hs.example.com");
+        let signer = XMatrixSigner::new(origin.into(), key_pair());
+
+        let mut request = http::Request::builder()
+            .method("PUT")
+            .uri("https://destination.hs.example.com/_matrix/federation/v1/send/1?a=b")
+            .body(br#"{"pdus":[],"edus":[]}"#.to_vec())
+            .unwrap();
+        let authorization = signer.authorization_header(destination, &request).unwrap();
+        request.headers_mut().insert(http::header::AUTHORIZATION, authorization.parse().unwrap());
+
+        let x_matrix = verify_http_request(&public_key_map(), destination, &request).unwrap();
+        assert_eq!(x_matrix.origin.as_str(), "origin.hs.example.com");
+
+        *request.body_mut() = br#"{"pdus":[],"edus":[{}]}"#.to_vec();
+        assert!(matches!(
+            verify_http_request(&public_key_map(), destination, &request),
+            Err(Error::Verification(VerificationError::InvalidSignature))
+        ));
+
+        request.headers_mut().remove(http::header::AUTHORIZATION);
+        assert!(matches!(
+            verify_http_request(&public_key_map(), destination, &request),
+            Err(Error::Parse(ParseError::XMatrix(_)))
+        ));
+    }
+}
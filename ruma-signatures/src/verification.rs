@@ -3,7 +3,7 @@
 use ring::signature::{VerificationAlgorithm, ED25519};
 use untrusted::Input;
 
-use crate::Error;
+use crate::{Error, VerificationError};
 
 /// A digital signature verifier.
 pub trait Verifier {
@@ -35,7 +35,7 @@ impl Verifier for Ed25519Verifier {
     ) -> Result<(), Error> {
         ED25519
             .verify(Input::from(public_key), Input::from(message), Input::from(signature))
-            .map_err(|_| Error::new("signature verification failed"))
+            .map_err(|_| VerificationError::InvalidSignature.into())
     }
 }
 
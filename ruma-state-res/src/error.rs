@@ -0,0 +1,52 @@
+use std::{
+    error::Error as StdError,
+    fmt::{self, Display, Formatter},
+};
+
+use ruma_identifiers::{EventId, RoomVersionId};
+
+/// An error produced when the authorization rules can't be applied to an event, or the state of a
+/// room can't be resolved.
+///
+/// Events that are merely not allowed by the authorization rules are not errors, see
+/// [`auth_check`](fn.auth_check.html).
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// The room version is not supported.
+    UnsupportedRoomVersion(RoomVersionId),
+
+    /// An event used for authorization is malformed.
+    InvalidPdu(String),
+
+    /// An event could not be found in the event store.
+    NotFound(EventId),
+
+    /// The event store failed.
+    Store(Box<dyn StdError + Send + Sync>),
+
+    /// A JSON (de)serialization error.
+    SerdeJson(serde_json::Error),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnsupportedRoomVersion(version) => {
+                write!(f, "room version {} is not supported", version)
+            }
+            Self::InvalidPdu(message) => write!(f, "invalid PDU: {}", message),
+            Self::NotFound(event_id) => write!(f, "event {} not found", event_id),
+            Self::Store(error) => write!(f, "event store error: {}", error),
+            Self::SerdeJson(error) => write!(f, "JSON error: {}", error),
+        }
+    }
+}
+
+impl StdError for Error {}
+
+impl From<serde_json::Error> for Error {
+    fn from(error: serde_json::Error) -> Self {
+        Self::SerdeJson(error)
+    }
+}
@@ -0,0 +1,664 @@
+//! The authorization rules for room versions 1 to 6.
+
+use std::{collections::BTreeSet, convert::TryFrom};
+
+use js_int::Int;
+use ruma_events::{
+    pdu::Pdu,
+    room::{
+        create::CreateEventContent,
+        join_rules::{JoinRule, JoinRulesEventContent},
+        member::{MemberEventContent, MembershipState},
+        power_levels::PowerLevelsEventContent,
+        third_party_invite::ThirdPartyInviteEventContent,
+    },
+    EventType,
+};
+use ruma_identifiers::{RoomVersion, RoomVersionId, UserId};
+use ruma_serde::CanonicalJsonValue;
+use ruma_signatures::{verify_json, PublicKeyMap, PublicKeySet};
+use serde_json::{Map as JsonObject, Value as JsonValue};
+
+use crate::{room_version, Error, Result, StateMap};
+
+/// The top-level power levels whose changes are restricted by the sender's power level.
+const POWER_LEVEL_KEYS: &[&str] =
+    &["users_default", "events_default", "state_default", "ban", "redact", "kick", "invite"];
+
+/// Checks whether `incoming_event` is allowed by the [authorization rules] of `room_version`.
+///
+/// `prev_event` is the event referenced in the `prev_events` of `incoming_event`, if there is
+/// only one. It is needed to allow the initial join of the room creator.
+///
+/// `auth_events` is the state of the room before `incoming_event`. It needs to contain at least
+/// the events selected by the auth rules: the `m.room.create`, `m.room.power_levels` and
+/// `m.room.join_rules` events, the membership of the sender and, for `m.room.member` events,
+/// the membership of the target user and any `m.room.third_party_invite` event it references.
+///
+/// Returns `Ok(false)` if the event is not allowed, and an error if the room version is not
+/// supported or one of the `auth_events` is malformed.
+///
+/// [authorization rules]: https://matrix.org/docs/spec/server_server/r0.1.4#authorization-rules
+pub fn auth_check(
+    room_version: &RoomVersionId,
+    incoming_event: &Pdu,
+    prev_event: Option<&Pdu>,
+    auth_events: &StateMap<Pdu>,
+) -> Result<bool> {
+    let rules = room_version::rules(room_version)?;
+    let kind = incoming_event.kind();
+    let sender = incoming_event.sender();
+
+    // 1. If type is m.room.create
+    if *kind == EventType::RoomCreate {
+        return Ok(valid_create_event(incoming_event));
+    }
+
+    // 2. Reject if event has auth_events that have rejected or duplicate entries.
+    //
+    // This depends on the storage of the caller, which is expected to only pass accepted events
+    // that are unique by type and state key.
+
+    // 3. If event does not have a m.room.create in its auth_events, reject.
+    if !auth_events.contains_key(&(EventType::RoomCreate, String::new())) {
+        return Ok(false);
+    }
+
+    // 4. If type is m.room.aliases
+    if rules.special_case_aliases_auth && *kind == EventType::RoomAliases {
+        return Ok(match incoming_event.state_key() {
+            Some(state_key) => state_key == sender.server_name().as_str(),
+            None => false,
+        });
+    }
+
+    let power_levels = PowerLevels::from_auth_events(auth_events, &rules)?;
+
+    // 5. If type is m.room.member
+    if *kind == EventType::RoomMember {
+        return valid_membership_change(incoming_event, prev_event, &power_levels, auth_events);
+    }
+
+    // 6. If the sender's current membership state is not join, reject.
+    if membership(auth_events, sender)? != Some(MembershipState::Join) {
+        return Ok(false);
+    }
+
+    let sender_level = power_levels.user_level(sender);
+
+    // 7. If type is m.room.third_party_invite
+    if *kind == EventType::RoomThirdPartyInvite {
+        return Ok(sender_level >= power_levels.invite());
+    }
+
+    // 8. If the event type's required power level is greater than the sender's power level,
+    //    reject.
+    if power_levels.event_level(kind, incoming_event.state_key().is_some()) > sender_level {
+        return Ok(false);
+    }
+
+    // 9. If the event has a state_key that starts with an @ and does not match the sender,
+    //    reject.
+    if let Some(state_key) = incoming_event.state_key() {
+        if state_key.starts_with('@') && state_key != sender.as_str() {
+            return Ok(false);
+        }
+    }
+
+    // 10. If type is m.room.power_levels
+    if *kind == EventType::RoomPowerLevels {
+        let current = auth_events.get(&(EventType::RoomPowerLevels, String::new()));
+        return Ok(valid_power_levels_change(incoming_event, current, sender_level, &rules));
+    }
+
+    // 11. If type is m.room.redaction
+    if !rules.event_ids_are_hashes() && *kind == EventType::RoomRedaction {
+        if sender_level >= power_levels.redact() {
+            return Ok(true);
+        }
+
+        return Ok(match (incoming_event.event_id(), incoming_event.redacts()) {
+            (Some(event_id), Some(redacts)) => event_id.server_name() == redacts.server_name(),
+            _ => false,
+        });
+    }
+
+    // 12. Otherwise, allow.
+    Ok(true)
+}
+
+/// Returns the state keys of the events that are needed to authorize an event with the given
+/// properties.
+///
+/// When creating an event, its `auth_events` are the events at these keys in the current state
+/// of the room. The same keys select the `auth_events` argument of
+/// [`auth_check`](fn.auth_check.html).
+///
+/// # Example
+///
+/// ```
+/// # use std::convert::TryFrom;
+/// # use ruma_events::EventType;
+/// # use ruma_identifiers::UserId;
+/// # use ruma_state_res::auth_types_for_event;
+/// # use serde_json::json;
+/// let sender = UserId::try_from("@alice:example.org").unwrap();
+/// let auth_types = auth_types_for_event(
+///     &EventType::RoomMember,
+///     &sender,
+///     Some("@bob:example.org"),
+///     &json!({ "membership": "invite" }),
+/// );
+///
+/// assert_eq!(
+///     auth_types,
+///     vec![
+///         (EventType::RoomCreate, "".to_owned()),
+///         (EventType::RoomPowerLevels, "".to_owned()),
+///         (EventType::RoomMember, "@alice:example.org".to_owned()),
+///         (EventType::RoomMember, "@bob:example.org".to_owned()),
+///         (EventType::RoomJoinRules, "".to_owned()),
+///     ]
+/// );
+/// ```
+pub fn auth_types_for_event(
+    kind: &EventType,
+    sender: &UserId,
+    state_key: Option<&str>,
+    content: &JsonValue,
+) -> Vec<(EventType, String)> {
+    if *kind == EventType::RoomCreate {
+        return Vec::new();
+    }
+
+    let mut auth_types = vec![
+        (EventType::RoomCreate, String::new()),
+        (EventType::RoomPowerLevels, String::new()),
+        (EventType::RoomMember, sender.to_string()),
+    ];
+
+    if *kind == EventType::RoomMember {
+        let membership = content.get("membership").and_then(JsonValue::as_str);
+
+        if let Some(state_key) = state_key.filter(|state_key| *state_key != sender.as_str()) {
+            auth_types.push((EventType::RoomMember, state_key.to_owned()));
+        }
+
+        if membership == Some("join") || membership == Some("invite") {
+            auth_types.push((EventType::RoomJoinRules, String::new()));
+        }
+
+        if membership == Some("invite") {
+            let token = content
+                .get("third_party_invite")
+                .and_then(|invite| invite.get("signed"))
+                .and_then(|signed| signed.get("token"))
+                .and_then(JsonValue::as_str);
+
+            if let Some(token) = token {
+                auth_types.push((EventType::RoomThirdPartyInvite, token.to_owned()));
+            }
+        }
+    }
+
+    auth_types
+}
+
+/// The power levels of a room, as seen by the authorization rules.
+///
+/// If the room has no `m.room.power_levels` event, the creator of the room has power level 100
+/// and everyone else power level 0, and no power level is needed to send any event.
+#[derive(Debug)]
+pub(crate) struct PowerLevels {
+    content: Option<PowerLevelsEventContent>,
+    creator: Option<UserId>,
+}
+
+impl PowerLevels {
+    /// Gets the power levels from the `m.room.power_levels` and `m.room.create` events in
+    /// `auth_events`.
+    pub fn from_auth_events(auth_events: &StateMap<Pdu>, rules: &RoomVersion) -> Result<Self> {
+        let creator = match auth_events.get(&(EventType::RoomCreate, String::new())) {
+            Some(event) => Some(creator(event)?),
+            None => None,
+        };
+        let content = match auth_events.get(&(EventType::RoomPowerLevels, String::new())) {
+            Some(event) => Some(
+                power_levels_content(event.content(), rules)
+                    .ok_or_else(|| Error::InvalidPdu("invalid m.room.power_levels event".into()))?,
+            ),
+            None => None,
+        };
+
+        Ok(Self { content, creator })
+    }
+
+    /// The power level of the given user.
+    pub fn user_level(&self, user_id: &UserId) -> Int {
+        match &self.content {
+            Some(content) => *content.users.get(user_id).unwrap_or(&content.users_default),
+            None if self.creator.as_ref() == Some(user_id) => Int::from(100),
+            None => Int::from(0),
+        }
+    }
+
+    /// The power level required to send an event of the given type.
+    pub fn event_level(&self, kind: &EventType, is_state: bool) -> Int {
+        match &self.content {
+            Some(content) => match content.events.get(kind) {
+                Some(level) => *level,
+                None if is_state => content.state_default,
+                None => content.events_default,
+            },
+            None => Int::from(0),
+        }
+    }
+
+    /// The power level required to ban a user.
+    pub fn ban(&self) -> Int {
+        self.content.as_ref().map_or(Int::from(50), |content| content.ban)
+    }
+
+    /// The power level required to invite a user.
+    pub fn invite(&self) -> Int {
+        self.content.as_ref().map_or(Int::from(0), |content| content.invite)
+    }
+
+    /// The power level required to kick a user.
+    pub fn kick(&self) -> Int {
+        self.content.as_ref().map_or(Int::from(50), |content| content.kick)
+    }
+
+    /// The power level required to redact an event sent by another user.
+    pub fn redact(&self) -> Int {
+        self.content.as_ref().map_or(Int::from(50), |content| content.redact)
+    }
+}
+
+/// Checks rule 1: whether an `m.room.create` event is valid.
+fn valid_create_event(event: &Pdu) -> bool {
+    // a. If it has any previous events, reject.
+    if !event.prev_event_ids().is_empty() {
+        return false;
+    }
+
+    // b. If the domain of the room_id does not match the domain of the sender, reject.
+    if event.room_id().server_name() != event.sender().server_name() {
+        return false;
+    }
+
+    // c. If content.room_version is present and is not a recognised version, reject.
+    // d. If content has no creator field, reject.
+    match serde_json::from_value::<CreateEventContent>(event.content().clone()) {
+        Ok(content) => room_version::rules(&content.room_version).is_ok(),
+        Err(_) => false,
+    }
+}
+
+/// Checks rule 5: whether an `m.room.member` event is valid.
+fn valid_membership_change(
+    event: &Pdu,
+    prev_event: Option<&Pdu>,
+    power_levels: &PowerLevels,
+    auth_events: &StateMap<Pdu>,
+) -> Result<bool> {
+    let sender = event.sender();
+
+    // a. If there is no state_key property, or no membership property in content, reject.
+    let target = match event.state_key().map(UserId::try_from) {
+        Some(Ok(target)) => target,
+        _ => return Ok(false),
+    };
+    let content = match serde_json::from_value::<MemberEventContent>(event.content().clone()) {
+        Ok(content) => content,
+        Err(_) => return Ok(false),
+    };
+
+    let sender_membership = membership(auth_events, sender)?;
+    let target_membership = membership(auth_events, &target)?;
+    let sender_level = power_levels.user_level(sender);
+    let target_level = power_levels.user_level(&target);
+
+    let allowed = match content.membership {
+        // b. If membership is join
+        MembershipState::Join => {
+            // i. If the only previous event is an m.room.create and the state_key is the
+            //    creator, allow.
+            if power_levels.creator.as_ref() == Some(&target)
+                && event.prev_event_ids().len() == 1
+                && matches!(prev_event, Some(prev) if *prev.kind() == EventType::RoomCreate)
+            {
+                return Ok(true);
+            }
+
+            // ii. If the sender does not match state_key, reject.
+            // iii. If the sender is banned, reject.
+            if *sender != target || sender_membership == Some(MembershipState::Ban) {
+                return Ok(false);
+            }
+
+            // iv. If the join_rule is invite then allow if membership state is invite or join.
+            // v. If the join_rule is public, allow.
+            // vi. Otherwise, reject.
+            match join_rule(auth_events)? {
+                JoinRule::Invite => matches!(
+                    target_membership,
+                    Some(MembershipState::Invite) | Some(MembershipState::Join)
+                ),
+                JoinRule::Public => true,
+                _ => false,
+            }
+        }
+
+        // c. If membership is invite
+        MembershipState::Invite => {
+            // i. If content has third_party_invite key
+            if let Some(third_party_invite) =
+                event.content().get("third_party_invite").filter(|invite| !invite.is_null())
+            {
+                // 1. If target user is banned, reject.
+                // 2.-6. If the signature of the third party invite can't be verified, reject.
+                // 7. Otherwise, accept.
+                return Ok(target_membership != Some(MembershipState::Ban)
+                    && valid_third_party_invite(&target, sender, third_party_invite, auth_events));
+            }
+
+            // ii. If the sender's current membership state is not join, reject.
+            // iii. If target user's current membership state is join or ban, reject.
+            // iv. If the sender's power level is greater than or equal to the invite level,
+            //     allow.
+            // v. Otherwise, reject.
+            sender_membership == Some(MembershipState::Join)
+                && !matches!(
+                    target_membership,
+                    Some(MembershipState::Join) | Some(MembershipState::Ban)
+                )
+                && sender_level >= power_levels.invite()
+        }
+
+        // d. If membership is leave
+        MembershipState::Leave => {
+            // i. If the sender matches state_key, allow if and only if that user's current
+            //    membership state is invite or join.
+            if *sender == target {
+                return Ok(matches!(
+                    target_membership,
+                    Some(MembershipState::Invite) | Some(MembershipState::Join)
+                ));
+            }
+
+            // ii. If the sender's current membership state is not join, reject.
+            // iii. If the target user's current membership state is ban, and the sender's power
+            //      level is less than the ban level, reject.
+            // iv. If the sender's power level is greater than or equal to the kick level, and
+            //     the target user's power level is less than the sender's power level, allow.
+            // v. Otherwise, reject.
+            sender_membership == Some(MembershipState::Join)
+                && !(target_membership == Some(MembershipState::Ban)
+                    && sender_level < power_levels.ban())
+                && sender_level >= power_levels.kick()
+                && target_level < sender_level
+        }
+
+        // e. If membership is ban
+        MembershipState::Ban => {
+            // i. If the sender's current membership state is not join, reject.
+            // ii. If the sender's power level is greater than or equal to the ban level, and the
+            //     target user's power level is less than the sender's power level, allow.
+            // iii. Otherwise, reject.
+            sender_membership == Some(MembershipState::Join)
+                && sender_level >= power_levels.ban()
+                && target_level < sender_level
+        }
+
+        // f. Otherwise, the membership is unknown. Reject.
+        _ => false,
+    };
+
+    Ok(allowed)
+}
+
+/// Checks rules 5.c.i.2 to 5.c.i.6: whether the signed part of a third party invite is valid.
+fn valid_third_party_invite(
+    target: &UserId,
+    sender: &UserId,
+    third_party_invite: &JsonValue,
+    auth_events: &StateMap<Pdu>,
+) -> bool {
+    // 2. If content.third_party_invite does not have a signed key, reject.
+    // 3. If signed does not have mxid and token keys, reject.
+    let signed = match third_party_invite.get("signed") {
+        Some(JsonValue::Object(signed)) => signed,
+        _ => return false,
+    };
+    let (mxid, token) = match (signed.get("mxid"), signed.get("token")) {
+        (Some(JsonValue::String(mxid)), Some(JsonValue::String(token))) => (mxid, token),
+        _ => return false,
+    };
+
+    // 4. If mxid does not match state_key, reject.
+    if mxid != target.as_str() {
+        return false;
+    }
+
+    // 5. If there is no m.room.third_party_invite event in the current room state with
+    //    state_key matching token, reject.
+    // 6. If sender does not match sender of the m.room.third_party_invite, reject.
+    let invite_event = match auth_events.get(&(EventType::RoomThirdPartyInvite, token.clone())) {
+        Some(event) if event.sender() == sender => event,
+        _ => return false,
+    };
+    let invite_content = match serde_json::from_value::<ThirdPartyInviteEventContent>(
+        invite_event.content().clone(),
+    ) {
+        Ok(content) => content,
+        Err(_) => return false,
+    };
+
+    // 7. If any signature in signed matches any public key in the m.room.third_party_invite
+    //    event, allow.
+    let signed = match ruma_serde::to_canonical_value(signed) {
+        Ok(CanonicalJsonValue::Object(signed)) => signed,
+        _ => return false,
+    };
+    let signatures = match signed.get("signatures") {
+        Some(CanonicalJsonValue::Object(signatures)) => signatures,
+        _ => return false,
+    };
+
+    let public_keys = std::iter::once(&invite_content.public_key).chain(
+        invite_content.public_keys.iter().flatten().map(|public_key| &public_key.public_key),
+    );
+
+    for public_key in public_keys {
+        // Identity servers may publish their keys in unpadded URL-safe base64.
+        let public_key: String = public_key
+            .trim_end_matches('=')
+            .chars()
+            .map(|c| match c {
+                '-' => '+',
+                '_' => '/',
+                c => c,
+            })
+            .collect();
+
+        for (server, server_signatures) in signatures {
+            let key_ids = match server_signatures {
+                CanonicalJsonValue::Object(server_signatures) => server_signatures.keys(),
+                _ => continue,
+            };
+
+            for key_id in key_ids {
+                let mut key_set = PublicKeySet::new();
+                key_set.insert(key_id.clone(), public_key.clone());
+
+                let mut key_map = PublicKeyMap::new();
+                key_map.insert(server.clone(), key_set);
+
+                if verify_json(&key_map, &signed).is_ok() {
+                    return true;
+                }
+            }
+        }
+    }
+
+    false
+}
+
+/// Checks rule 10: whether an `m.room.power_levels` event is valid.
+///
+/// `current` is the `m.room.power_levels` event the new one replaces, if any.
+fn valid_power_levels_change(
+    event: &Pdu,
+    current: Option<&Pdu>,
+    sender_level: Int,
+    rules: &RoomVersion,
+) -> bool {
+    // a. If the users property in content is not an object with keys that are valid user IDs
+    //    with values that are integers (or a string that is an integer), reject.
+    if power_levels_content(event.content(), rules).is_none() {
+        return false;
+    }
+
+    // b. If there is no previous m.room.power_levels event in the room, allow.
+    let current = match current {
+        Some(current) => normalize_power_levels(current.content()),
+        None => return true,
+    };
+    let new = normalize_power_levels(event.content());
+
+    // c. For the keys users_default, events_default, state_default, ban, redact, kick, invite
+    //    check if they were added, changed or removed. For each found alteration:
+    //    i. If the current value is higher than the sender's current power level, reject.
+    //    ii. If the new value is higher than the sender's current power level, reject.
+    for key in POWER_LEVEL_KEYS {
+        let current_level = level(current.get(key));
+        let new_level = level(new.get(key));
+
+        if current_level != new_level
+            && (current_level > Some(sender_level) || new_level > Some(sender_level))
+        {
+            return false;
+        }
+    }
+
+    // d. For each entry being added, changed or removed in the events, users, and (in room
+    //    version 6) notifications keys:
+    //    i. If the current value is higher than the sender's current power level, reject.
+    //    ii. If the new value is higher than the sender's current power level, reject.
+    // e. For each entry being changed under the users key, other than the sender's own entry:
+    //    i. If the current value is equal to the sender's current power level, reject.
+    let mut maps = vec!["events", "users"];
+    if rules.limit_notifications_power_levels {
+        maps.push("notifications");
+    }
+
+    for map in maps {
+        let current_map = current.get(map).and_then(JsonValue::as_object);
+        let new_map = new.get(map).and_then(JsonValue::as_object);
+        let keys: BTreeSet<&String> =
+            current_map.into_iter().chain(new_map).flat_map(JsonObject::keys).collect();
+
+        for key in keys {
+            let current_level = level(current_map.and_then(|map| map.get(key)));
+            let new_level = level(new_map.and_then(|map| map.get(key)));
+
+            if current_level == new_level {
+                continue;
+            }
+
+            if current_level > Some(sender_level) || new_level > Some(sender_level) {
+                return false;
+            }
+
+            if map == "users"
+                && key != event.sender().as_str()
+                && current_level == Some(sender_level)
+            {
+                return false;
+            }
+        }
+    }
+
+    // f. Otherwise, allow.
+    true
+}
+
+/// The `creator` of the room, from its `m.room.create` event.
+fn creator(create_event: &Pdu) -> Result<UserId> {
+    create_event
+        .content()
+        .get("creator")
+        .and_then(JsonValue::as_str)
+        .and_then(|creator| UserId::try_from(creator).ok())
+        .ok_or_else(|| Error::InvalidPdu("m.room.create event without valid creator".into()))
+}
+
+/// The current membership of the given user, from their `m.room.member` event in `auth_events`.
+fn membership(auth_events: &StateMap<Pdu>, user_id: &UserId) -> Result<Option<MembershipState>> {
+    match auth_events.get(&(EventType::RoomMember, user_id.to_string())) {
+        Some(event) => serde_json::from_value::<MemberEventContent>(event.content().clone())
+            .map(|content| Some(content.membership))
+            .map_err(|_| Error::InvalidPdu("invalid m.room.member event".into())),
+        None => Ok(None),
+    }
+}
+
+/// The join rule of the room, from the `m.room.join_rules` event in `auth_events`.
+///
+/// Defaults to `invite` if the room has no join rules.
+fn join_rule(auth_events: &StateMap<Pdu>) -> Result<JoinRule> {
+    match auth_events.get(&(EventType::RoomJoinRules, String::new())) {
+        Some(event) => serde_json::from_value::<JoinRulesEventContent>(event.content().clone())
+            .map(|content| content.join_rule)
+            .map_err(|_| Error::InvalidPdu("invalid m.room.join_rules event".into())),
+        None => Ok(JoinRule::Invite),
+    }
+}
+
+/// Deserializes the content of an `m.room.power_levels` event.
+///
+/// Before room version 6, strings containing integers are accepted as power levels.
+fn power_levels_content(
+    content: &JsonValue,
+    rules: &RoomVersion,
+) -> Option<PowerLevelsEventContent> {
+    let content =
+        if rules.integer_power_levels { content.clone() } else { normalize_power_levels(content) };
+
+    serde_json::from_value(content).ok()
+}
+
+/// Converts strings containing integers to integers in the content of an `m.room.power_levels`
+/// event.
+fn normalize_power_levels(content: &JsonValue) -> JsonValue {
+    fn normalize_level(level: &mut JsonValue) {
+        if let JsonValue::String(s) = level {
+            if let Ok(int) = s.trim().parse::<i64>() {
+                *level = int.into();
+            }
+        }
+    }
+
+    let mut content = content.clone();
+
+    if let JsonValue::Object(fields) = &mut content {
+        for (key, value) in fields.iter_mut() {
+            match (key.as_str(), value) {
+                ("events", JsonValue::Object(levels))
+                | ("users", JsonValue::Object(levels))
+                | ("notifications", JsonValue::Object(levels)) => {
+                    levels.values_mut().for_each(normalize_level)
+                }
+                (_, value) => normalize_level(value),
+            }
+        }
+    }
+
+    content
+}
+
+/// Converts a power level from the normalized content of an `m.room.power_levels` event.
+fn level(value: Option<&JsonValue>) -> Option<Int> {
+    value.and_then(JsonValue::as_i64).and_then(Int::new)
+}
@@ -0,0 +1,51 @@
+use std::collections::BTreeSet;
+
+use ruma_events::pdu::Pdu;
+use ruma_identifiers::EventId;
+
+use crate::Result;
+
+/// Access to the events of a room, needed for state resolution.
+///
+/// This can be implemented on top of an in-memory map, as well as on a database.
+pub trait EventStore {
+    /// Returns the event with the given ID.
+    ///
+    /// Implementations should return `Error::NotFound` if the event is unknown.
+    fn get_event(&self, event_id: &EventId) -> Result<Pdu>;
+
+    /// Returns the auth chain of the given events: the events referenced in their `auth_events`,
+    /// recursively.
+    ///
+    /// The default implementation walks the `auth_events` with `get_event`, see
+    /// [`auth_chain`](fn.auth_chain.html). Stores that keep an index of auth chains should
+    /// override it.
+    fn auth_chain(&self, event_ids: &[EventId]) -> Result<BTreeSet<EventId>> {
+        auth_chain(self, event_ids)
+    }
+}
+
+/// Returns the auth chain of the given events: the events referenced in their `auth_events`,
+/// recursively.
+///
+/// The events are looked up with [`EventStore::get_event`]. The given events are only part of the
+/// result if they are in the auth chain of one of the others.
+///
+/// [`EventStore::get_event`]: trait.EventStore.html#tymethod.get_event
+pub fn auth_chain<S: EventStore + ?Sized>(
+    store: &S,
+    event_ids: &[EventId],
+) -> Result<BTreeSet<EventId>> {
+    let mut chain = BTreeSet::new();
+    let mut todo: Vec<EventId> = event_ids.to_vec();
+
+    while let Some(event_id) = todo.pop() {
+        for auth_event_id in store.get_event(&event_id)?.auth_event_ids() {
+            if chain.insert(auth_event_id.clone()) {
+                todo.push(auth_event_id.clone());
+            }
+        }
+    }
+
+    Ok(chain)
+}
@@ -0,0 +1,50 @@
+//! Crate `ruma_state_res` implements the event authorization rules and state resolution
+//! algorithms of the [Matrix](https://matrix.org/) server-server specification.
+//!
+//! # Event authorization
+//!
+//! Before an event is accepted into a room, a homeserver has to check that it is allowed by the
+//! [authorization rules] of the room version, using the state of the room before the event. Use
+//! [`auth_check`](fn.auth_check.html) with the relevant auth events (the `m.room.create`,
+//! `m.room.power_levels`, `m.room.join_rules` and `m.room.member` events that apply to the
+//! event) to run these checks. [`auth_types_for_event`](fn.auth_types_for_event.html) tells
+//! which events these are, and can also be used to select the `auth_events` of a new event.
+//!
+//! # State resolution
+//!
+//! When the state of a room diverges, e.g. because two servers sent state events at the same
+//! time, the different states have to be merged using the [state resolution] algorithm of the
+//! room version. Use [`resolve`](fn.resolve.html) with an [`EventStore`](trait.EventStore.html)
+//! that gives access to the events of the room to do that.
+//!
+//! [authorization rules]: https://matrix.org/docs/spec/server_server/r0.1.4#authorization-rules
+//! [state resolution]: https://matrix.org/docs/spec/server_server/r0.1.4#room-state-resolution
+
+#![warn(missing_debug_implementations, missing_docs, rust_2018_idioms)]
+
+use std::collections::BTreeMap;
+
+use ruma_events::EventType;
+
+mod error;
+mod event_auth;
+mod event_store;
+mod resolve;
+mod room_version;
+
+pub use self::{
+    error::Error,
+    event_auth::{auth_check, auth_types_for_event},
+    event_store::{auth_chain, EventStore},
+    resolve::resolve,
+};
+
+/// A mapping of event type and state key to some value.
+///
+/// This is how a piece of room state is addressed: `m.room.create` is found at
+/// `(EventType::RoomCreate, "")`, the membership of `@alice:example.org` at
+/// `(EventType::RoomMember, "@alice:example.org")`.
+pub type StateMap<T> = BTreeMap<(EventType, String), T>;
+
+/// The result type used by this crate.
+pub type Result<T> = std::result::Result<T, Error>;
@@ -0,0 +1,34 @@
+use ruma_identifiers::{EventId, RoomVersionId, StateResolutionVersion};
+
+use crate::{room_version, Error, EventStore, Result, StateMap};
+
+mod v1;
+mod v2;
+
+/// Resolves the conflicting `state_sets` of a room into a single state, using the state
+/// resolution algorithm of `room_version`.
+///
+/// `state_sets` are the states of the room that need to be merged, e.g. the states after each of
+/// the room's forward extremities. The events in the states and their auth chains are looked up
+/// in `store`.
+///
+/// Room version 1 uses the [original algorithm], which only looks at the conflicting events and
+/// orders them by depth. Later room versions use [state resolution version 2].
+///
+/// Fails if the room version is not supported or an event can't be fetched from the store.
+///
+/// [original algorithm]: https://matrix.org/docs/spec/rooms/v1#state-resolution
+/// [state resolution version 2]: https://matrix.org/docs/spec/rooms/v2#state-resolution
+pub fn resolve<S: EventStore>(
+    room_version: &RoomVersionId,
+    state_sets: &[StateMap<EventId>],
+    store: &S,
+) -> Result<StateMap<EventId>> {
+    let rules = room_version::rules(room_version)?;
+
+    match rules.state_res {
+        StateResolutionVersion::V1 => v1::resolve(room_version, state_sets, store),
+        StateResolutionVersion::V2 => v2::resolve(room_version, &rules, state_sets, store),
+        _ => Err(Error::UnsupportedRoomVersion(room_version.clone())),
+    }
+}
@@ -0,0 +1,215 @@
+//! The [original state resolution algorithm](https://matrix.org/docs/spec/rooms/v1#state-resolution).
+
+use std::{
+    cmp::Reverse,
+    collections::{btree_map::Entry, BTreeSet},
+};
+
+use ring::digest::{digest, SHA1_FOR_LEGACY_USE_ONLY};
+use ruma_events::{pdu::Pdu, EventType};
+use ruma_identifiers::{EventId, RoomVersionId};
+
+use crate::{auth_check, event_auth::auth_types_for_event, Error, EventStore, Result, StateMap};
+
+pub(super) fn resolve<S: EventStore>(
+    room_version: &RoomVersionId,
+    state_sets: &[StateMap<EventId>],
+    store: &S,
+) -> Result<StateMap<EventId>> {
+    let (mut unconflicted, conflicted) = separate(state_sets);
+
+    if conflicted.is_empty() {
+        return Ok(unconflicted);
+    }
+
+    let conflicted = conflicted
+        .into_iter()
+        .map(|(key, event_ids)| {
+            let events = event_ids
+                .iter()
+                .map(|event_id| get_v1_event(event_id, store))
+                .collect::<Result<Vec<_>>>()?;
+            Ok((key, events))
+        })
+        .collect::<Result<StateMap<_>>>()?;
+
+    // The auth events of the conflicted events are taken from the unconflicted state.
+    let mut auth_events = StateMap::new();
+
+    for event in conflicted.values().flatten() {
+        for key in
+            auth_types_for_event(event.kind(), event.sender(), event.state_key(), event.content())
+        {
+            if let Entry::Vacant(entry) = auth_events.entry(key) {
+                if let Some(event_id) = unconflicted.get(entry.key()) {
+                    entry.insert(get_v1_event(event_id, store)?);
+                }
+            }
+        }
+    }
+
+    // Power levels are resolved first, then join rules, then memberships. Each step uses the
+    // state resolved by the previous ones to authorize events.
+    let mut resolved = StateMap::new();
+    let auth_kinds = [EventType::RoomPowerLevels, EventType::RoomJoinRules, EventType::RoomMember];
+
+    for auth_kind in &auth_kinds {
+        for (key, events) in conflicted.iter().filter(|((kind, _), _)| kind == auth_kind) {
+            let event = resolve_auth_events(room_version, events, &auth_events);
+            resolved.insert(key.clone(), event.clone());
+        }
+
+        auth_events.extend(resolved.iter().map(|(key, event)| (key.clone(), event.clone())));
+    }
+
+    for (key, events) in &conflicted {
+        if !resolved.contains_key(key) {
+            let event = resolve_normal_events(room_version, events, &auth_events);
+            resolved.insert(key.clone(), event.clone());
+        }
+    }
+
+    for (key, event) in resolved {
+        let event_id = event.event_id().expect("room version 1 PDUs have an event ID").clone();
+        unconflicted.insert(key, event_id);
+    }
+
+    Ok(unconflicted)
+}
+
+/// Splits the given states into the state that is the same in all of them, and the state that
+/// differs.
+///
+/// A key that is missing from some of the states is not conflicted if all states that contain it
+/// map it to the same event.
+fn separate(state_sets: &[StateMap<EventId>]) -> (StateMap<EventId>, StateMap<BTreeSet<EventId>>) {
+    let mut event_ids_by_key: StateMap<BTreeSet<EventId>> = StateMap::new();
+
+    for (key, event_id) in state_sets.iter().flatten() {
+        event_ids_by_key.entry(key.clone()).or_default().insert(event_id.clone());
+    }
+
+    let mut unconflicted = StateMap::new();
+    let mut conflicted = StateMap::new();
+
+    for (key, event_ids) in event_ids_by_key {
+        if event_ids.len() == 1 {
+            unconflicted.insert(key, event_ids.into_iter().next().unwrap());
+        } else {
+            conflicted.insert(key, event_ids);
+        }
+    }
+
+    (unconflicted, conflicted)
+}
+
+/// Resolves conflicting power levels, join rules or membership events.
+///
+/// Starting from the event with the lowest depth, each event is checked against the previous
+/// one. The last event that passes is chosen.
+fn resolve_auth_events<'a>(
+    room_version: &RoomVersionId,
+    events: &'a [Pdu],
+    auth_events: &StateMap<Pdu>,
+) -> &'a Pdu {
+    let auth_keys: BTreeSet<_> = events
+        .iter()
+        .flat_map(|event| {
+            auth_types_for_event(event.kind(), event.sender(), event.state_key(), event.content())
+        })
+        .collect();
+    let mut auth_events: StateMap<Pdu> = auth_events
+        .iter()
+        .filter(|(key, _)| auth_keys.contains(key))
+        .map(|(key, event)| (key.clone(), event.clone()))
+        .collect();
+
+    let mut ordered = ordered_events(events).into_iter().rev();
+    let mut prev_event = ordered.next().expect("conflicted events are never empty");
+
+    for event in ordered {
+        let key =
+            (prev_event.kind().clone(), prev_event.state_key().unwrap_or_default().to_owned());
+        auth_events.insert(key, prev_event.clone());
+
+        if !auth_check(room_version, event, None, &auth_events).unwrap_or(false) {
+            break;
+        }
+
+        prev_event = event;
+    }
+
+    prev_event
+}
+
+/// Resolves conflicting events that are not involved in authorization.
+///
+/// The event with the highest depth that passes the auth checks is chosen, or the one with the
+/// lowest depth if none of them pass.
+fn resolve_normal_events<'a>(
+    room_version: &RoomVersionId,
+    events: &'a [Pdu],
+    auth_events: &StateMap<Pdu>,
+) -> &'a Pdu {
+    let ordered = ordered_events(events);
+
+    ordered
+        .iter()
+        .find(|event| auth_check(room_version, event, None, auth_events).unwrap_or(false))
+        .or_else(|| ordered.last())
+        .expect("conflicted events are never empty")
+}
+
+/// Orders the given events by depth, highest first, then by the SHA-1 hash of their event ID.
+fn ordered_events(events: &[Pdu]) -> Vec<&Pdu> {
+    let mut events: Vec<_> = events
+        .iter()
+        .map(|event| {
+            let event_id = event.event_id().expect("room version 1 PDUs have an event ID");
+            let hash = digest(&SHA1_FOR_LEGACY_USE_ONLY, event_id.as_str().as_bytes());
+            ((Reverse(event.depth()), hash.as_ref().to_vec()), event)
+        })
+        .collect();
+
+    events.sort_by(|(a, _), (b, _)| a.cmp(b));
+    events.into_iter().map(|(_, event)| event).collect()
+}
+
+/// Gets an event from `store`, making sure it is a room version 1 PDU.
+fn get_v1_event<S: EventStore>(event_id: &EventId, store: &S) -> Result<Pdu> {
+    match store.get_event(event_id)? {
+        event @ Pdu::RoomV1Pdu(_) => Ok(event),
+        Pdu::RoomV3Pdu(_) => {
+            Err(Error::InvalidPdu(format!("event {} is not a room version 1 PDU", event_id)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ruma_events::EventType;
+    use ruma_identifiers::event_id;
+
+    use super::separate;
+    use crate::StateMap;
+
+    #[test]
+    fn missing_keys_are_unconflicted() {
+        let topic = (EventType::RoomTopic, String::new());
+        let name = (EventType::RoomName, String::new());
+
+        let first: StateMap<_> = vec![
+            (topic.clone(), event_id!("$topic:example.org")),
+            (name.clone(), event_id!("$name1:example.org")),
+        ]
+        .into_iter()
+        .collect();
+        let second: StateMap<_> =
+            vec![(name.clone(), event_id!("$name2:example.org"))].into_iter().collect();
+
+        let (unconflicted, conflicted) = separate(&[first, second]);
+
+        assert_eq!(unconflicted.get(&topic), Some(&event_id!("$topic:example.org")));
+        assert_eq!(conflicted.get(&name).map(|event_ids| event_ids.len()), Some(2));
+    }
+}
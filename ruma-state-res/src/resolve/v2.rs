@@ -0,0 +1,483 @@
+//! [State resolution version 2](https://matrix.org/docs/spec/rooms/v2#state-resolution).
+
+use std::{
+    borrow::Cow,
+    cmp::Reverse,
+    collections::{BTreeMap, BTreeSet, BinaryHeap},
+    time::SystemTime,
+};
+
+use js_int::Int;
+use ruma_events::{pdu::Pdu, EventType};
+use ruma_identifiers::{EventId, RoomVersion, RoomVersionId};
+use serde_json::Value as JsonValue;
+
+use crate::{
+    auth_check,
+    event_auth::{auth_types_for_event, PowerLevels},
+    EventStore, Result, StateMap,
+};
+
+/// The events of the full conflicted set, by event ID.
+type EventMap = BTreeMap<EventId, Pdu>;
+
+pub(super) fn resolve<S: EventStore>(
+    room_version: &RoomVersionId,
+    rules: &RoomVersion,
+    state_sets: &[StateMap<EventId>],
+    store: &S,
+) -> Result<StateMap<EventId>> {
+    let (unconflicted, conflicted) = separate(state_sets);
+
+    if conflicted.is_empty() {
+        return Ok(unconflicted);
+    }
+
+    // The full conflicted set is the union of the conflicted state set and the auth difference.
+    let auth_chains = state_sets
+        .iter()
+        .map(|state| store.auth_chain(&state.values().cloned().collect::<Vec<_>>()))
+        .collect::<Result<Vec<_>>>()?;
+
+    let full_conflicted_set: BTreeSet<EventId> =
+        conflicted.values().flatten().cloned().chain(auth_difference(auth_chains)).collect();
+
+    let events = full_conflicted_set
+        .iter()
+        .map(|event_id| Ok((event_id.clone(), store.get_event(event_id)?)))
+        .collect::<Result<EventMap>>()?;
+
+    // Resolve the power events first, in reverse topological power ordering, starting from the
+    // unconflicted state.
+    let power_events: Vec<&EventId> = events
+        .iter()
+        .filter(|(_, event)| is_power_event(event))
+        .map(|(event_id, _)| event_id)
+        .collect();
+    let sorted_power_events = reverse_topological_power_sort(&power_events, &events, rules, store)?;
+    let resolved = iterative_auth_checks(
+        room_version,
+        &sorted_power_events,
+        unconflicted.clone(),
+        &events,
+        store,
+    )?;
+
+    // Then resolve the remaining events, ordered along the mainline of the resolved power levels.
+    let sorted_power_events: BTreeSet<&EventId> = sorted_power_events.iter().collect();
+    let other_events: Vec<&EventId> =
+        events.keys().filter(|event_id| !sorted_power_events.contains(event_id)).collect();
+    let power_levels = resolved.get(&(EventType::RoomPowerLevels, String::new()));
+    let sorted_other_events = mainline_sort(&other_events, power_levels, &events, store)?;
+    let mut resolved =
+        iterative_auth_checks(room_version, &sorted_other_events, resolved, &events, store)?;
+
+    // Finally, the unconflicted state always applies.
+    resolved.extend(unconflicted);
+
+    Ok(resolved)
+}
+
+/// Splits the given states into the state that is the same in all of them, and the state that
+/// differs.
+///
+/// A key that is missing from some of the states is conflicted, even if all states that contain
+/// it map it to the same event.
+fn separate(state_sets: &[StateMap<EventId>]) -> (StateMap<EventId>, StateMap<BTreeSet<EventId>>) {
+    let mut unconflicted = StateMap::new();
+    let mut conflicted = StateMap::new();
+
+    let keys: BTreeSet<_> = state_sets.iter().flat_map(|state| state.keys()).collect();
+
+    for key in keys {
+        let event_ids: BTreeSet<_> = state_sets.iter().map(|state| state.get(key)).collect();
+
+        match event_ids.iter().next() {
+            Some(Some(event_id)) if event_ids.len() == 1 => {
+                unconflicted.insert(key.clone(), (*event_id).clone());
+            }
+            _ => {
+                conflicted.insert(key.clone(), event_ids.into_iter().flatten().cloned().collect());
+            }
+        }
+    }
+
+    (unconflicted, conflicted)
+}
+
+/// The events that are in some, but not all of the given auth chains.
+fn auth_difference(auth_chains: Vec<BTreeSet<EventId>>) -> BTreeSet<EventId> {
+    let mut auth_chains = auth_chains.into_iter();
+    let first = match auth_chains.next() {
+        Some(first) => first,
+        None => return BTreeSet::new(),
+    };
+
+    let (union, intersection) =
+        auth_chains.fold((first.clone(), first), |(union, intersection), chain| {
+            (
+                union.union(&chain).cloned().collect(),
+                intersection.intersection(&chain).cloned().collect(),
+            )
+        });
+
+    union.difference(&intersection).cloned().collect()
+}
+
+/// Whether the given event is a power event.
+///
+/// Power events are the events that can take away the abilities of other users: power levels,
+/// join rules and kicks or bans. The `m.room.create` event is considered a power event too.
+fn is_power_event(event: &Pdu) -> bool {
+    match event.kind() {
+        EventType::RoomCreate | EventType::RoomPowerLevels | EventType::RoomJoinRules => {
+            event.state_key() == Some("")
+        }
+        EventType::RoomMember => {
+            let membership = event.content().get("membership").and_then(JsonValue::as_str);
+
+            (membership == Some("leave") || membership == Some("ban"))
+                && event.state_key() != Some(event.sender().as_str())
+        }
+        _ => false,
+    }
+}
+
+/// Sorts the given power events and the events of their auth chains that are in the full
+/// conflicted set, so that events come after their auth events.
+///
+/// Ties are broken by the power level of the sender (highest first), then by `origin_server_ts`
+/// and finally by event ID.
+fn reverse_topological_power_sort<S: EventStore>(
+    power_events: &[&EventId],
+    events: &EventMap,
+    rules: &RoomVersion,
+    store: &S,
+) -> Result<Vec<EventId>> {
+    let mut graph = BTreeMap::new();
+
+    for event_id in power_events {
+        add_event_and_auth_chain_to_graph(&mut graph, event_id, events);
+    }
+
+    let mut sort_keys = BTreeMap::new();
+
+    for event_id in graph.keys() {
+        let event = &events[event_id];
+        let power_level = sender_power_level(event, events, rules, store)?;
+        sort_keys.insert(event_id.clone(), (Reverse(power_level), event.origin_server_ts()));
+    }
+
+    Ok(lexicographical_topological_sort(&graph, |event_id| sort_keys[event_id]))
+}
+
+/// Adds the given event and its auth events that are in `events` to `graph`, recursively.
+fn add_event_and_auth_chain_to_graph(
+    graph: &mut BTreeMap<EventId, BTreeSet<EventId>>,
+    event_id: &EventId,
+    events: &EventMap,
+) {
+    let mut todo = vec![event_id.clone()];
+
+    while let Some(event_id) = todo.pop() {
+        let auth_events: BTreeSet<EventId> = events[&event_id]
+            .auth_event_ids()
+            .into_iter()
+            .filter(|auth_event_id| events.contains_key(auth_event_id))
+            .cloned()
+            .collect();
+
+        for auth_event_id in &auth_events {
+            if !graph.contains_key(auth_event_id) {
+                todo.push(auth_event_id.clone());
+            }
+        }
+
+        graph.insert(event_id, auth_events);
+    }
+}
+
+/// Sorts the nodes of `graph` topologically, so that nodes come after the nodes they have edges
+/// to.
+///
+/// Whenever several nodes could come next, the one with the smallest key comes first, with the
+/// event ID as the tie-breaker.
+fn lexicographical_topological_sort<K, F>(
+    graph: &BTreeMap<EventId, BTreeSet<EventId>>,
+    key: F,
+) -> Vec<EventId>
+where
+    K: Ord,
+    F: Fn(&EventId) -> K,
+{
+    let mut outdegrees = BTreeMap::new();
+    let mut reverse_graph: BTreeMap<&EventId, BTreeSet<&EventId>> = BTreeMap::new();
+    let mut heap = BinaryHeap::new();
+
+    for (node, edges) in graph {
+        outdegrees.insert(node, edges.len());
+        reverse_graph.entry(node).or_default();
+
+        for edge in edges {
+            reverse_graph.entry(edge).or_default().insert(node);
+        }
+
+        if edges.is_empty() {
+            heap.push(Reverse((key(node), node)));
+        }
+    }
+
+    let mut sorted = Vec::with_capacity(graph.len());
+
+    while let Some(Reverse((_, node))) = heap.pop() {
+        sorted.push(node.clone());
+
+        for &parent in &reverse_graph[node] {
+            let outdegree = outdegrees.get_mut(parent).expect("all nodes have an outdegree");
+            *outdegree -= 1;
+
+            if *outdegree == 0 {
+                heap.push(Reverse((key(parent), parent)));
+            }
+        }
+    }
+
+    sorted
+}
+
+/// The power level of the sender of `event`, according to its auth events.
+fn sender_power_level<S: EventStore>(
+    event: &Pdu,
+    events: &EventMap,
+    rules: &RoomVersion,
+    store: &S,
+) -> Result<Int> {
+    let mut auth_events = StateMap::new();
+
+    for auth_event_id in event.auth_event_ids() {
+        let auth_event = get_event(auth_event_id, events, store)?;
+
+        if let Some(state_key) = auth_event.state_key() {
+            auth_events
+                .insert((auth_event.kind().clone(), state_key.to_owned()), auth_event.into_owned());
+        }
+    }
+
+    // Malformed power levels don't give any power.
+    Ok(PowerLevels::from_auth_events(&auth_events, rules)
+        .map_or(Int::from(0), |power_levels| power_levels.user_level(event.sender())))
+}
+
+/// Sorts the given events by their position on the mainline of the `power_levels` event, then by
+/// `origin_server_ts` and finally by event ID.
+///
+/// The mainline of a power levels event is made of the event itself, the power levels event in
+/// its auth events, the power levels event in the auth events of that one, and so on.
+fn mainline_sort<S: EventStore>(
+    event_ids: &[&EventId],
+    power_levels: Option<&EventId>,
+    events: &EventMap,
+    store: &S,
+) -> Result<Vec<EventId>> {
+    let mut mainline = Vec::new();
+    let mut power_levels = power_levels.cloned();
+
+    while let Some(event_id) = power_levels {
+        let event = get_event(&event_id, events, store)?;
+        power_levels = power_levels_event_id(&event, events, store)?;
+        mainline.push(event_id);
+    }
+
+    // The oldest power levels event has depth 1, events that are not on the mainline depth 0.
+    let mainline_depths: BTreeMap<EventId, usize> = mainline
+        .into_iter()
+        .rev()
+        .enumerate()
+        .map(|(index, event_id)| (event_id, index + 1))
+        .collect();
+
+    let mut sort_keys: Vec<(usize, SystemTime, &EventId)> = Vec::with_capacity(event_ids.len());
+
+    for &event_id in event_ids {
+        let depth = mainline_depth(event_id, &mainline_depths, events, store)?;
+        let origin_server_ts = get_event(event_id, events, store)?.origin_server_ts();
+        sort_keys.push((depth, origin_server_ts, event_id));
+    }
+
+    sort_keys.sort();
+
+    Ok(sort_keys.into_iter().map(|(_, _, event_id)| event_id.clone()).collect())
+}
+
+/// The depth of the closest event on the mainline, following the power levels events in the
+/// auth events of `event_id`.
+fn mainline_depth<S: EventStore>(
+    event_id: &EventId,
+    mainline_depths: &BTreeMap<EventId, usize>,
+    events: &EventMap,
+    store: &S,
+) -> Result<usize> {
+    let mut current = Some(event_id.clone());
+
+    while let Some(event_id) = current {
+        if let Some(depth) = mainline_depths.get(&event_id) {
+            return Ok(*depth);
+        }
+
+        let event = get_event(&event_id, events, store)?;
+        current = power_levels_event_id(&event, events, store)?;
+    }
+
+    Ok(0)
+}
+
+/// The ID of the `m.room.power_levels` event in the auth events of `event`, if any.
+fn power_levels_event_id<S: EventStore>(
+    event: &Pdu,
+    events: &EventMap,
+    store: &S,
+) -> Result<Option<EventId>> {
+    for auth_event_id in event.auth_event_ids() {
+        let auth_event = get_event(auth_event_id, events, store)?;
+
+        if *auth_event.kind() == EventType::RoomPowerLevels && auth_event.state_key() == Some("") {
+            return Ok(Some(auth_event_id.clone()));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Applies the given events to `state` in order, if they are allowed by the authorization rules.
+///
+/// The auth events of each event are its own `auth_events`, replaced by the events of the state
+/// being built where they differ.
+fn iterative_auth_checks<S: EventStore>(
+    room_version: &RoomVersionId,
+    event_ids: &[EventId],
+    mut state: StateMap<EventId>,
+    events: &EventMap,
+    store: &S,
+) -> Result<StateMap<EventId>> {
+    for event_id in event_ids {
+        let event = get_event(event_id, events, store)?;
+        let state_key = match event.state_key() {
+            Some(state_key) => state_key,
+            None => continue,
+        };
+
+        let mut auth_events = StateMap::new();
+
+        for auth_event_id in event.auth_event_ids() {
+            let auth_event = get_event(auth_event_id, events, store)?;
+
+            if let Some(auth_state_key) = auth_event.state_key() {
+                auth_events.insert(
+                    (auth_event.kind().clone(), auth_state_key.to_owned()),
+                    auth_event.into_owned(),
+                );
+            }
+        }
+
+        for key in
+            auth_types_for_event(event.kind(), event.sender(), Some(state_key), event.content())
+        {
+            if let Some(state_event_id) = state.get(&key) {
+                auth_events.insert(key, get_event(state_event_id, events, store)?.into_owned());
+            }
+        }
+
+        let prev_event = match event.prev_event_ids().as_slice() {
+            [prev_event_id] => store.get_event(prev_event_id).ok(),
+            _ => None,
+        };
+
+        // Events whose auth events are malformed are rejected like any other unauthorized event.
+        if auth_check(room_version, &event, prev_event.as_ref(), &auth_events).unwrap_or(false) {
+            state.insert((event.kind().clone(), state_key.to_owned()), event_id.clone());
+        }
+    }
+
+    Ok(state)
+}
+
+/// Gets an event from `events`, or from `store` if it is not part of the full conflicted set.
+fn get_event<'a, S: EventStore>(
+    event_id: &EventId,
+    events: &'a EventMap,
+    store: &S,
+) -> Result<Cow<'a, Pdu>> {
+    match events.get(event_id) {
+        Some(event) => Ok(Cow::Borrowed(event)),
+        None => store.get_event(event_id).map(Cow::Owned),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeMap, BTreeSet};
+
+    use ruma_identifiers::{event_id, EventId};
+
+    use super::{auth_difference, lexicographical_topological_sort};
+
+    #[test]
+    fn topological_sort() {
+        let graph: BTreeMap<EventId, BTreeSet<EventId>> = vec![
+            (event_id!("$l:example.org"), vec![event_id!("$o:example.org")]),
+            (
+                event_id!("$m:example.org"),
+                vec![event_id!("$n:example.org"), event_id!("$o:example.org")],
+            ),
+            (event_id!("$n:example.org"), vec![event_id!("$o:example.org")]),
+            (event_id!("$o:example.org"), vec![]),
+            (event_id!("$p:example.org"), vec![event_id!("$o:example.org")]),
+        ]
+        .into_iter()
+        .map(|(node, edges)| (node, edges.into_iter().collect()))
+        .collect();
+
+        // Without a key, the event ID decides.
+        assert_eq!(
+            lexicographical_topological_sort(&graph, |_| ()),
+            vec![
+                event_id!("$o:example.org"),
+                event_id!("$l:example.org"),
+                event_id!("$n:example.org"),
+                event_id!("$m:example.org"),
+                event_id!("$p:example.org"),
+            ]
+        );
+
+        // The key is compared before the event ID.
+        assert_eq!(
+            lexicographical_topological_sort(&graph, |event_id| event_id.as_str()
+                != "$p:example.org"),
+            vec![
+                event_id!("$o:example.org"),
+                event_id!("$p:example.org"),
+                event_id!("$l:example.org"),
+                event_id!("$n:example.org"),
+                event_id!("$m:example.org"),
+            ]
+        );
+    }
+
+    #[test]
+    fn auth_chain_difference() {
+        let chain = |ids: &[&str]| -> BTreeSet<EventId> {
+            ids.iter().map(|id| format!("${}:example.org", id).parse().unwrap()).collect()
+        };
+
+        assert_eq!(
+            auth_difference(vec![
+                chain(&["a", "b", "c"]),
+                chain(&["a", "b", "d"]),
+                chain(&["a", "e"])
+            ]),
+            chain(&["b", "c", "d", "e"])
+        );
+        assert_eq!(auth_difference(vec![chain(&["a", "b"]), chain(&["a", "b"])]), chain(&[]));
+        assert_eq!(auth_difference(vec![]), chain(&[]));
+    }
+}
@@ -0,0 +1,10 @@
+use ruma_identifiers::{RoomVersion, RoomVersionId};
+
+use crate::{Error, Result};
+
+/// Returns the rules of the given room version.
+///
+/// Fails with `Error::UnsupportedRoomVersion` for custom room versions.
+pub(crate) fn rules(version: &RoomVersionId) -> Result<RoomVersion> {
+    version.rules().ok_or_else(|| Error::UnsupportedRoomVersion(version.clone()))
+}
@@ -0,0 +1,540 @@
+use std::convert::TryFrom;
+
+use matches::assert_matches;
+use ruma_events::{pdu::Pdu, EventType};
+use ruma_identifiers::{user_id, RoomVersionId};
+use ruma_serde::CanonicalJsonValue;
+use ruma_signatures::{sign_json, Ed25519KeyPair};
+use ruma_state_res::{auth_check, auth_types_for_event, Error, StateMap};
+use serde_json::{
+    from_value as from_json_value, json, to_value as to_json_value, Value as JsonValue,
+};
+
+const ALICE: &str = "@alice:example.org";
+const BOB: &str = "@bob:example.org";
+const CAROL: &str = "@carol:other.org";
+
+fn pdu(
+    event_id: &str,
+    sender: &str,
+    kind: &str,
+    state_key: Option<&str>,
+    content: JsonValue,
+    prev_events: &[&str],
+) -> Pdu {
+    let prev_events: Vec<_> =
+        prev_events.iter().map(|id| json!([id, { "sha256": "hash" }])).collect();
+
+    let mut pdu = json!({
+        "event_id": event_id,
+        "room_id": "!room:example.org",
+        "sender": sender,
+        "origin": "example.org",
+        "origin_server_ts": 1_600_000_000_000_u64,
+        "type": kind,
+        "content": content,
+        "prev_events": prev_events,
+        "depth": 1,
+        "auth_events": [],
+        "hashes": { "sha256": "hash" },
+        "signatures": {},
+    });
+
+    if let Some(state_key) = state_key {
+        pdu["state_key"] = state_key.into();
+    }
+
+    from_json_value(pdu).unwrap()
+}
+
+fn state_event(sender: &str, kind: &str, state_key: &str, content: JsonValue) -> Pdu {
+    pdu("$event:example.org", sender, kind, Some(state_key), content, &["$prev:example.org"])
+}
+
+fn member(sender: &str, target: &str, membership: &str) -> Pdu {
+    state_event(sender, "m.room.member", target, json!({ "membership": membership }))
+}
+
+fn power_levels(sender: &str, content: JsonValue) -> Pdu {
+    state_event(sender, "m.room.power_levels", "", content)
+}
+
+fn create() -> Pdu {
+    pdu(
+        "$create:example.org",
+        ALICE,
+        "m.room.create",
+        Some(""),
+        json!({ "creator": ALICE, "room_version": "6" }),
+        &[],
+    )
+}
+
+fn insert(state: &mut StateMap<Pdu>, event: Pdu) {
+    let key = (event.kind().clone(), event.state_key().unwrap().to_owned());
+    state.insert(key, event);
+}
+
+/// A room created by Alice, who invited Bob. Bob has joined and has power level 50.
+fn room() -> StateMap<Pdu> {
+    let mut state = StateMap::new();
+    insert(&mut state, create());
+    insert(&mut state, member(ALICE, ALICE, "join"));
+    insert(&mut state, power_levels(ALICE, json!({ "users": { ALICE: 100, BOB: 50 } })));
+    insert(
+        &mut state,
+        state_event(ALICE, "m.room.join_rules", "", json!({ "join_rule": "invite" })),
+    );
+    insert(&mut state, member(BOB, BOB, "join"));
+    state
+}
+
+fn allowed(version: RoomVersionId, event: &Pdu, state: &StateMap<Pdu>) -> bool {
+    auth_check(&version, event, None, state).unwrap()
+}
+
+#[test]
+fn create_event() {
+    let state = StateMap::new();
+    assert!(allowed(RoomVersionId::Version6, &create(), &state));
+
+    let with_prev_events = pdu(
+        "$create:example.org",
+        ALICE,
+        "m.room.create",
+        Some(""),
+        json!({ "creator": ALICE }),
+        &["$prev:example.org"],
+    );
+    assert!(!allowed(RoomVersionId::Version6, &with_prev_events, &state));
+
+    let other_server = pdu(
+        "$create:other.org",
+        CAROL,
+        "m.room.create",
+        Some(""),
+        json!({ "creator": CAROL }),
+        &[],
+    );
+    assert!(!allowed(RoomVersionId::Version6, &other_server, &state));
+
+    let unknown_version = pdu(
+        "$create:example.org",
+        ALICE,
+        "m.room.create",
+        Some(""),
+        json!({ "creator": ALICE, "room_version": "org.example.custom" }),
+        &[],
+    );
+    assert!(!allowed(RoomVersionId::Version6, &unknown_version, &state));
+
+    let no_creator = pdu("$create:example.org", ALICE, "m.room.create", Some(""), json!({}), &[]);
+    assert!(!allowed(RoomVersionId::Version6, &no_creator, &state));
+}
+
+#[test]
+fn unsupported_room_version() {
+    let version = RoomVersionId::try_from("org.example.custom").unwrap();
+    assert_matches!(
+        auth_check(&version, &create(), None, &StateMap::new()),
+        Err(Error::UnsupportedRoomVersion(_))
+    );
+}
+
+#[test]
+fn no_create_event() {
+    let mut state = room();
+    state.remove(&(EventType::RoomCreate, String::new()));
+
+    let message = pdu("$msg:example.org", ALICE, "m.room.message", None, json!({}), &[]);
+    assert!(!allowed(RoomVersionId::Version6, &message, &state));
+}
+
+#[test]
+fn creator_initial_join() {
+    let create = create();
+    let mut state = StateMap::new();
+    insert(&mut state, create.clone());
+
+    let join = pdu(
+        "$join:example.org",
+        ALICE,
+        "m.room.member",
+        Some(ALICE),
+        json!({ "membership": "join" }),
+        &["$create:example.org"],
+    );
+    assert!(auth_check(&RoomVersionId::Version6, &join, Some(&create), &state).unwrap());
+
+    // Only the creator can join right after the room was created.
+    let join = pdu(
+        "$join:example.org",
+        BOB,
+        "m.room.member",
+        Some(BOB),
+        json!({ "membership": "join" }),
+        &["$create:example.org"],
+    );
+    assert!(!auth_check(&RoomVersionId::Version6, &join, Some(&create), &state).unwrap());
+}
+
+#[test]
+fn join() {
+    let mut state = room();
+
+    // The join rule is invite.
+    assert!(!allowed(RoomVersionId::Version6, &member(CAROL, CAROL, "join"), &state));
+    insert(&mut state, member(ALICE, CAROL, "invite"));
+    assert!(allowed(RoomVersionId::Version6, &member(CAROL, CAROL, "join"), &state));
+
+    // Users can't join on behalf of others.
+    assert!(!allowed(RoomVersionId::Version6, &member(ALICE, CAROL, "join"), &state));
+
+    // Anyone who is not banned can join public rooms.
+    let mut state = room();
+    insert(
+        &mut state,
+        state_event(ALICE, "m.room.join_rules", "", json!({ "join_rule": "public" })),
+    );
+    assert!(allowed(RoomVersionId::Version6, &member(CAROL, CAROL, "join"), &state));
+    insert(&mut state, member(ALICE, CAROL, "ban"));
+    assert!(!allowed(RoomVersionId::Version6, &member(CAROL, CAROL, "join"), &state));
+}
+
+#[test]
+fn invite() {
+    let mut state = room();
+    assert!(allowed(RoomVersionId::Version6, &member(BOB, CAROL, "invite"), &state));
+
+    // Only joined members can invite.
+    assert!(!allowed(RoomVersionId::Version6, &member(CAROL, "@dan:other.org", "invite"), &state));
+
+    // Members need the invite power level.
+    insert(
+        &mut state,
+        power_levels(ALICE, json!({ "users": { ALICE: 100, BOB: 50 }, "invite": 60 })),
+    );
+    assert!(!allowed(RoomVersionId::Version6, &member(BOB, CAROL, "invite"), &state));
+    assert!(allowed(RoomVersionId::Version6, &member(ALICE, CAROL, "invite"), &state));
+
+    // Banned and joined users can't be invited.
+    insert(&mut state, member(ALICE, CAROL, "ban"));
+    assert!(!allowed(RoomVersionId::Version6, &member(ALICE, CAROL, "invite"), &state));
+    assert!(!allowed(RoomVersionId::Version6, &member(ALICE, BOB, "invite"), &state));
+}
+
+#[test]
+fn leave_and_kick() {
+    let mut state = room();
+
+    // Users can leave by themselves.
+    assert!(allowed(RoomVersionId::Version6, &member(BOB, BOB, "leave"), &state));
+    assert!(!allowed(RoomVersionId::Version6, &member(CAROL, CAROL, "leave"), &state));
+
+    // Kicking needs the kick level, and a higher level than the target.
+    assert!(allowed(RoomVersionId::Version6, &member(ALICE, BOB, "leave"), &state));
+    assert!(!allowed(RoomVersionId::Version6, &member(BOB, ALICE, "leave"), &state));
+
+    // Unbanning needs the ban level.
+    insert(&mut state, member(ALICE, CAROL, "ban"));
+    insert(&mut state, power_levels(ALICE, json!({ "users": { ALICE: 100, BOB: 50 }, "ban": 60 })));
+    assert!(!allowed(RoomVersionId::Version6, &member(BOB, CAROL, "leave"), &state));
+    assert!(allowed(RoomVersionId::Version6, &member(ALICE, CAROL, "leave"), &state));
+}
+
+#[test]
+fn ban() {
+    let mut state = room();
+    assert!(allowed(RoomVersionId::Version6, &member(ALICE, BOB, "ban"), &state));
+    assert!(allowed(RoomVersionId::Version6, &member(BOB, CAROL, "ban"), &state));
+    assert!(!allowed(RoomVersionId::Version6, &member(BOB, ALICE, "ban"), &state));
+
+    insert(&mut state, member(BOB, BOB, "leave"));
+    assert!(!allowed(RoomVersionId::Version6, &member(BOB, CAROL, "ban"), &state));
+}
+
+#[test]
+fn unknown_membership() {
+    let state = room();
+    assert!(!allowed(RoomVersionId::Version6, &member(CAROL, CAROL, "knock"), &state));
+    assert!(!allowed(RoomVersionId::Version6, &member(ALICE, CAROL, "unknown"), &state));
+
+    let no_membership = state_event(ALICE, "m.room.member", CAROL, json!({}));
+    assert!(!allowed(RoomVersionId::Version6, &no_membership, &state));
+}
+
+#[test]
+fn sender_not_joined() {
+    let state = room();
+    let message = pdu("$msg:other.org", CAROL, "m.room.message", None, json!({}), &[]);
+    assert!(!allowed(RoomVersionId::Version6, &message, &state));
+}
+
+#[test]
+fn send_level() {
+    let mut state = room();
+    insert(
+        &mut state,
+        power_levels(
+            ALICE,
+            json!({ "users": { ALICE: 100, BOB: 50 }, "events": { "m.room.name": 100 } }),
+        ),
+    );
+
+    let message = pdu("$msg:example.org", BOB, "m.room.message", None, json!({}), &[]);
+    assert!(allowed(RoomVersionId::Version6, &message, &state));
+
+    let topic = state_event(BOB, "m.room.topic", "", json!({ "topic": "Tea" }));
+    assert!(allowed(RoomVersionId::Version6, &topic, &state));
+
+    let name = state_event(BOB, "m.room.name", "", json!({ "name": "Tea" }));
+    assert!(!allowed(RoomVersionId::Version6, &name, &state));
+    let name = state_event(ALICE, "m.room.name", "", json!({ "name": "Tea" }));
+    assert!(allowed(RoomVersionId::Version6, &name, &state));
+}
+
+#[test]
+fn state_key_of_other_user() {
+    let state = room();
+
+    let own = state_event(BOB, "org.example.state", BOB, json!({}));
+    assert!(allowed(RoomVersionId::Version6, &own, &state));
+
+    let other = state_event(BOB, "org.example.state", ALICE, json!({}));
+    assert!(!allowed(RoomVersionId::Version6, &other, &state));
+}
+
+#[test]
+fn power_levels_change() {
+    let state = room();
+
+    // Alice can promote Bob up to her own level.
+    let event = power_levels(ALICE, json!({ "users": { ALICE: 100, BOB: 100 } }));
+    assert!(allowed(RoomVersionId::Version6, &event, &state));
+
+    // Bob can't promote himself above his own level.
+    let event = power_levels(BOB, json!({ "users": { ALICE: 100, BOB: 60 } }));
+    assert!(!allowed(RoomVersionId::Version6, &event, &state));
+
+    // Bob can't change levels that are higher than his own.
+    let event = power_levels(BOB, json!({ "users": { ALICE: 40, BOB: 50 } }));
+    assert!(!allowed(RoomVersionId::Version6, &event, &state));
+    let event = power_levels(BOB, json!({ "users": { ALICE: 100, BOB: 50 }, "ban": 40 }));
+    assert!(allowed(RoomVersionId::Version6, &event, &state));
+    let event = power_levels(BOB, json!({ "users": { ALICE: 100, BOB: 50 }, "ban": 60 }));
+    assert!(!allowed(RoomVersionId::Version6, &event, &state));
+
+    // Bob can demote himself, but not others with his level.
+    let event = power_levels(BOB, json!({ "users": { ALICE: 100, BOB: 10 } }));
+    assert!(allowed(RoomVersionId::Version6, &event, &state));
+    let mut state_with_carol = room();
+    insert(
+        &mut state_with_carol,
+        power_levels(ALICE, json!({ "users": { ALICE: 100, BOB: 50, CAROL: 50 } })),
+    );
+    let event = power_levels(BOB, json!({ "users": { ALICE: 100, BOB: 50, CAROL: 10 } }));
+    assert!(!allowed(RoomVersionId::Version6, &event, &state_with_carol));
+
+    // Invalid users are rejected.
+    let event = power_levels(ALICE, json!({ "users": { "alice": 100 } }));
+    assert!(!allowed(RoomVersionId::Version6, &event, &state));
+}
+
+#[test]
+fn power_levels_as_strings() {
+    let state = room();
+    let event = power_levels(ALICE, json!({ "users": { ALICE: "100", BOB: "50" }, "ban": "50" }));
+
+    assert!(allowed(RoomVersionId::Version5, &event, &state));
+    assert!(!allowed(RoomVersionId::Version6, &event, &state));
+}
+
+#[test]
+fn notifications_power_levels() {
+    let state = room();
+    let event = power_levels(
+        BOB,
+        json!({ "users": { ALICE: 100, BOB: 50 }, "notifications": { "room": 60 } }),
+    );
+
+    assert!(allowed(RoomVersionId::Version5, &event, &state));
+    assert!(!allowed(RoomVersionId::Version6, &event, &state));
+}
+
+#[test]
+fn redaction() {
+    let mut state = room();
+    insert(&mut state, member(ALICE, CAROL, "invite"));
+    insert(&mut state, member(CAROL, CAROL, "join"));
+
+    let redaction = |event_id: &str, sender: &str, redacts: &str| {
+        let mut event = pdu(event_id, sender, "m.room.redaction", None, json!({}), &[]);
+        if let Pdu::RoomV1Pdu(pdu) = &mut event {
+            pdu.redacts = Some(redacts.parse().unwrap());
+        }
+        event
+    };
+
+    // Carol doesn't have the redact level, but may redact events from her own server.
+    let own_server = redaction("$redaction:other.org", CAROL, "$event:other.org");
+    assert!(allowed(RoomVersionId::Version1, &own_server, &state));
+    let other_server = redaction("$redaction:other.org", CAROL, "$event:example.org");
+    assert!(!allowed(RoomVersionId::Version1, &other_server, &state));
+    assert!(allowed(RoomVersionId::Version3, &other_server, &state));
+
+    let with_redact_level = redaction("$redaction:example.org", BOB, "$event:other.org");
+    assert!(allowed(RoomVersionId::Version1, &with_redact_level, &state));
+}
+
+#[test]
+fn aliases() {
+    let state = room();
+
+    // Before room version 6, aliases are only checked against the sender's server.
+    let own_server = state_event(CAROL, "m.room.aliases", "other.org", json!({ "aliases": [] }));
+    assert!(allowed(RoomVersionId::Version5, &own_server, &state));
+    let other_server =
+        state_event(CAROL, "m.room.aliases", "example.org", json!({ "aliases": [] }));
+    assert!(!allowed(RoomVersionId::Version5, &other_server, &state));
+
+    // In room version 6, aliases are treated like any other state event.
+    assert!(!allowed(RoomVersionId::Version6, &own_server, &state));
+    let joined = state_event(BOB, "m.room.aliases", "example.org", json!({ "aliases": [] }));
+    assert!(allowed(RoomVersionId::Version6, &joined, &state));
+}
+
+#[test]
+fn third_party_invite() {
+    let key_pair = Ed25519KeyPair::new(&Ed25519KeyPair::generate().unwrap(), "0".into()).unwrap();
+    let public_key = base64::encode_config(key_pair.public_key(), base64::URL_SAFE_NO_PAD);
+
+    let mut state = room();
+    insert(
+        &mut state,
+        state_event(
+            BOB,
+            "m.room.third_party_invite",
+            "token",
+            json!({
+                "display_name": "carol@other.org",
+                "key_validity_url": "https://identity.example.org/isvalid",
+                "public_key": public_key,
+            }),
+        ),
+    );
+
+    let signed = |mxid: &str| {
+        let mut signed = match CanonicalJsonValue::try_from(
+            json!({ "mxid": mxid, "token": "token" }),
+        )
+        .unwrap()
+        {
+            CanonicalJsonValue::Object(signed) => signed,
+            _ => unreachable!(),
+        };
+        sign_json("identity.example.org", &key_pair, &mut signed).unwrap();
+        to_json_value(signed).unwrap()
+    };
+    let invite = |sender: &str, signed: JsonValue| {
+        state_event(
+            sender,
+            "m.room.member",
+            CAROL,
+            json!({
+                "membership": "invite",
+                "third_party_invite": { "display_name": "carol@other.org", "signed": signed },
+            }),
+        )
+    };
+
+    assert!(allowed(RoomVersionId::Version6, &invite(BOB, signed(CAROL)), &state));
+
+    // The invite has to come from the sender of the m.room.third_party_invite event.
+    assert!(!allowed(RoomVersionId::Version6, &invite(ALICE, signed(CAROL)), &state));
+
+    // The signed user ID has to match the target.
+    assert!(!allowed(RoomVersionId::Version6, &invite(BOB, signed("@dan:other.org")), &state));
+
+    // The signature has to be valid.
+    let mut tampered = signed(CAROL);
+    tampered["extra"] = "field".into();
+    assert!(!allowed(RoomVersionId::Version6, &invite(BOB, tampered), &state));
+}
+
+#[test]
+fn auth_types() {
+    let alice = user_id!("@alice:example.org");
+    let key = |kind: EventType, state_key: &str| (kind, state_key.to_owned());
+
+    assert_eq!(
+        auth_types_for_event(
+            &EventType::RoomCreate,
+            &alice,
+            Some(""),
+            &json!({ "creator": ALICE })
+        ),
+        vec![]
+    );
+
+    assert_eq!(
+        auth_types_for_event(&EventType::RoomMessage, &alice, None, &json!({ "body": "Hi" })),
+        vec![
+            key(EventType::RoomCreate, ""),
+            key(EventType::RoomPowerLevels, ""),
+            key(EventType::RoomMember, ALICE),
+        ]
+    );
+
+    assert_eq!(
+        auth_types_for_event(
+            &EventType::RoomMember,
+            &alice,
+            Some(ALICE),
+            &json!({ "membership": "join" })
+        ),
+        vec![
+            key(EventType::RoomCreate, ""),
+            key(EventType::RoomPowerLevels, ""),
+            key(EventType::RoomMember, ALICE),
+            key(EventType::RoomJoinRules, ""),
+        ]
+    );
+
+    assert_eq!(
+        auth_types_for_event(
+            &EventType::RoomMember,
+            &alice,
+            Some(BOB),
+            &json!({ "membership": "ban" })
+        ),
+        vec![
+            key(EventType::RoomCreate, ""),
+            key(EventType::RoomPowerLevels, ""),
+            key(EventType::RoomMember, ALICE),
+            key(EventType::RoomMember, BOB),
+        ]
+    );
+
+    assert_eq!(
+        auth_types_for_event(
+            &EventType::RoomMember,
+            &alice,
+            Some(CAROL),
+            &json!({
+                "membership": "invite",
+                "third_party_invite": {
+                    "display_name": "carol@other.org",
+                    "signed": { "mxid": CAROL, "token": "token", "signatures": {} },
+                },
+            })
+        ),
+        vec![
+            key(EventType::RoomCreate, ""),
+            key(EventType::RoomPowerLevels, ""),
+            key(EventType::RoomMember, ALICE),
+            key(EventType::RoomMember, CAROL),
+            key(EventType::RoomJoinRules, ""),
+            key(EventType::RoomThirdPartyInvite, "token"),
+        ]
+    );
+}
@@ -0,0 +1,490 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    convert::TryFrom,
+};
+
+use matches::assert_matches;
+use ruma_events::{pdu::Pdu, EventType};
+use ruma_identifiers::{EventId, RoomVersionId};
+use ruma_state_res::{auth_chain, resolve, Error, EventStore, Result, StateMap};
+use serde_json::{from_value as from_json_value, json, Value as JsonValue};
+
+const ALICE: &str = "@alice:example.org";
+const BOB: &str = "@bob:example.org";
+const CAROL: &str = "@carol:example.org";
+const DAN: &str = "@dan:other.org";
+
+fn id(name: &str) -> EventId {
+    EventId::try_from(format!("${}:example.org", name)).unwrap()
+}
+
+/// An in-memory event store.
+///
+/// Events get increasing timestamps and depths in the order they are added.
+#[derive(Default)]
+struct TestStore {
+    events: BTreeMap<EventId, Pdu>,
+    /// Whether to create room version 1 PDUs.
+    v1: bool,
+}
+
+impl EventStore for TestStore {
+    fn get_event(&self, event_id: &EventId) -> Result<Pdu> {
+        self.events.get(event_id).cloned().ok_or_else(|| Error::NotFound(event_id.clone()))
+    }
+}
+
+impl TestStore {
+    fn add(
+        &mut self,
+        name: &str,
+        sender: &str,
+        kind: &str,
+        state_key: &str,
+        content: JsonValue,
+        auth_events: &[&str],
+    ) {
+        let auth_events: Vec<JsonValue> =
+            auth_events
+                .iter()
+                .map(|name| {
+                    if self.v1 {
+                        json!([id(name), { "sha256": "hash" }])
+                    } else {
+                        json!(id(name))
+                    }
+                })
+                .collect();
+
+        let mut pdu = json!({
+            "room_id": "!room:example.org",
+            "sender": sender,
+            "origin": "example.org",
+            "origin_server_ts": 1_600_000_000_000_u64 + self.events.len() as u64,
+            "type": kind,
+            "state_key": state_key,
+            "content": content,
+            "prev_events": [],
+            "depth": self.events.len() + 1,
+            "auth_events": auth_events,
+            "hashes": { "sha256": "hash" },
+            "signatures": {},
+        });
+
+        if self.v1 {
+            pdu["event_id"] = json!(id(name));
+        }
+
+        self.events.insert(id(name), from_json_value(pdu).unwrap());
+    }
+
+    fn state(&self, names: &[&str]) -> StateMap<EventId> {
+        names
+            .iter()
+            .map(|name| {
+                let event = &self.events[&id(name)];
+                ((event.kind().clone(), event.state_key().unwrap().to_owned()), id(name))
+            })
+            .collect()
+    }
+}
+
+/// A public room created by Alice, with Bob (power level 50) and Carol.
+const BASE: &[&str] = &["create", "alice", "power", "join_rules", "bob", "carol"];
+
+fn base_room() -> TestStore {
+    add_base_events(TestStore::default())
+}
+
+fn base_room_v1() -> TestStore {
+    add_base_events(TestStore { v1: true, ..TestStore::default() })
+}
+
+fn add_base_events(mut store: TestStore) -> TestStore {
+    store.add("create", ALICE, "m.room.create", "", json!({ "creator": ALICE }), &[]);
+    store.add("alice", ALICE, "m.room.member", ALICE, json!({ "membership": "join" }), &["create"]);
+    store.add(
+        "power",
+        ALICE,
+        "m.room.power_levels",
+        "",
+        json!({ "users": { ALICE: 100, BOB: 50 } }),
+        &["create", "alice"],
+    );
+    store.add(
+        "join_rules",
+        ALICE,
+        "m.room.join_rules",
+        "",
+        json!({ "join_rule": "public" }),
+        &["create", "alice", "power"],
+    );
+    store.add(
+        "bob",
+        BOB,
+        "m.room.member",
+        BOB,
+        json!({ "membership": "join" }),
+        &["create", "power", "join_rules"],
+    );
+    store.add(
+        "carol",
+        CAROL,
+        "m.room.member",
+        CAROL,
+        json!({ "membership": "join" }),
+        &["create", "power", "join_rules"],
+    );
+    store
+}
+
+fn state_after(store: &TestStore, names: &[&str]) -> StateMap<EventId> {
+    let names: Vec<&str> = BASE.iter().chain(names).cloned().collect();
+    store.state(&names)
+}
+
+fn topic(state: &StateMap<EventId>) -> Option<&EventId> {
+    state.get(&(EventType::RoomTopic, String::new()))
+}
+
+fn power_levels(state: &StateMap<EventId>) -> Option<&EventId> {
+    state.get(&(EventType::RoomPowerLevels, String::new()))
+}
+
+#[test]
+fn auth_chains() {
+    let mut store = base_room();
+    store.add(
+        "topic",
+        BOB,
+        "m.room.topic",
+        "",
+        json!({ "topic": "1" }),
+        &["create", "power", "bob"],
+    );
+
+    let chain = |names: &[&str]| names.iter().map(|name| id(name)).collect::<BTreeSet<_>>();
+
+    assert_eq!(auth_chain(&store, &[id("create")]).unwrap(), chain(&[]));
+    assert_eq!(
+        auth_chain(&store, &[id("topic")]).unwrap(),
+        chain(&["create", "alice", "power", "join_rules", "bob"])
+    );
+    assert_eq!(
+        store.auth_chain(&[id("alice"), id("carol")]).unwrap(),
+        chain(&["create", "alice", "power", "join_rules"])
+    );
+    assert_matches!(
+        auth_chain(&store, &[id("unknown")]),
+        Err(Error::NotFound(event_id)) if event_id == id("unknown")
+    );
+}
+
+#[test]
+fn no_conflicts() {
+    let store = base_room();
+    let state = state_after(&store, &[]);
+
+    let resolved = resolve(&RoomVersionId::Version6, &[state.clone(), state.clone()], &store);
+    assert_eq!(resolved.unwrap(), state);
+}
+
+#[test]
+fn power_levels_before_ban() {
+    let mut store = base_room();
+    // Alice demotes Bob, while Bob bans Carol.
+    store.add(
+        "demote",
+        ALICE,
+        "m.room.power_levels",
+        "",
+        json!({ "users": { ALICE: 100 } }),
+        &["create", "alice", "power"],
+    );
+    store.add(
+        "ban",
+        BOB,
+        "m.room.member",
+        CAROL,
+        json!({ "membership": "ban" }),
+        &["create", "power", "bob", "carol"],
+    );
+
+    let state_sets = [state_after(&store, &["demote"]), state_after(&store, &["ban"])];
+    let resolved = resolve(&RoomVersionId::Version6, &state_sets, &store).unwrap();
+
+    assert_eq!(power_levels(&resolved), Some(&id("demote")));
+    assert_eq!(resolved.get(&(EventType::RoomMember, CAROL.to_owned())), Some(&id("carol")));
+}
+
+#[test]
+fn state_from_demoted_user() {
+    let mut store = base_room();
+    // Alice demotes Bob, while Bob changes the topic.
+    store.add(
+        "demote",
+        ALICE,
+        "m.room.power_levels",
+        "",
+        json!({ "users": { ALICE: 100 } }),
+        &["create", "alice", "power"],
+    );
+    store.add(
+        "topic",
+        BOB,
+        "m.room.topic",
+        "",
+        json!({ "topic": "Bob's" }),
+        &["create", "power", "bob"],
+    );
+
+    let state_sets = [state_after(&store, &["demote"]), state_after(&store, &["topic"])];
+    let resolved = resolve(&RoomVersionId::Version6, &state_sets, &store).unwrap();
+
+    assert_eq!(power_levels(&resolved), Some(&id("demote")));
+    assert_eq!(topic(&resolved), None);
+}
+
+#[test]
+fn join_rules_before_join() {
+    let mut store = base_room();
+    // Alice makes the room invite-only, while Dan joins.
+    store.add(
+        "invite_only",
+        ALICE,
+        "m.room.join_rules",
+        "",
+        json!({ "join_rule": "invite" }),
+        &["create", "alice", "power"],
+    );
+    store.add(
+        "dan",
+        DAN,
+        "m.room.member",
+        DAN,
+        json!({ "membership": "join" }),
+        &["create", "power", "join_rules"],
+    );
+
+    let state_sets = [state_after(&store, &["invite_only"]), state_after(&store, &["dan"])];
+    let resolved = resolve(&RoomVersionId::Version6, &state_sets, &store).unwrap();
+
+    assert_eq!(resolved.get(&(EventType::RoomJoinRules, String::new())), Some(&id("invite_only")));
+    assert_eq!(resolved.get(&(EventType::RoomMember, DAN.to_owned())), None);
+}
+
+#[test]
+fn mainline_ordering() {
+    let mut store = base_room();
+    // Bob changes the topic after a power levels change, while Alice changes it based on the
+    // previous power levels, but later.
+    store.add(
+        "power2",
+        ALICE,
+        "m.room.power_levels",
+        "",
+        json!({ "users": { ALICE: 100, BOB: 50 }, "events": { "m.room.name": 100 } }),
+        &["create", "alice", "power"],
+    );
+    store.add(
+        "bob_topic",
+        BOB,
+        "m.room.topic",
+        "",
+        json!({ "topic": "Bob's" }),
+        &["create", "power2", "bob"],
+    );
+    store.add(
+        "alice_topic",
+        ALICE,
+        "m.room.topic",
+        "",
+        json!({ "topic": "Alice's" }),
+        &["create", "power", "alice"],
+    );
+
+    let state_sets =
+        [state_after(&store, &["power2", "bob_topic"]), state_after(&store, &["alice_topic"])];
+    let resolved = resolve(&RoomVersionId::Version6, &state_sets, &store).unwrap();
+
+    assert_eq!(power_levels(&resolved), Some(&id("power2")));
+    assert_eq!(topic(&resolved), Some(&id("bob_topic")));
+}
+
+#[test]
+fn timestamp_ordering() {
+    let mut store = base_room();
+    store.add(
+        "topic1",
+        BOB,
+        "m.room.topic",
+        "",
+        json!({ "topic": "1" }),
+        &["create", "power", "bob"],
+    );
+    store.add(
+        "topic2",
+        ALICE,
+        "m.room.topic",
+        "",
+        json!({ "topic": "2" }),
+        &["create", "power", "alice"],
+    );
+
+    // On the same position of the mainline, the latest event wins.
+    let state_sets = [state_after(&store, &["topic1"]), state_after(&store, &["topic2"])];
+    let resolved = resolve(&RoomVersionId::Version6, &state_sets, &store).unwrap();
+    assert_eq!(topic(&resolved), Some(&id("topic2")));
+
+    let state_sets = [state_after(&store, &["topic2"]), state_after(&store, &["topic1"])];
+    let resolved = resolve(&RoomVersionId::Version6, &state_sets, &store).unwrap();
+    assert_eq!(topic(&resolved), Some(&id("topic2")));
+}
+
+#[test]
+fn missing_event() {
+    let mut store = base_room();
+    store.add(
+        "topic",
+        BOB,
+        "m.room.topic",
+        "",
+        json!({ "topic": "1" }),
+        &["create", "power", "bob"],
+    );
+
+    let mut state = state_after(&store, &["topic"]);
+    state.insert((EventType::RoomName, String::new()), id("unknown"));
+
+    assert_matches!(
+        resolve(&RoomVersionId::Version6, &[state_after(&store, &[]), state], &store),
+        Err(Error::NotFound(event_id)) if event_id == id("unknown")
+    );
+}
+
+#[test]
+fn unsupported_room_version() {
+    let store = base_room();
+    let version = RoomVersionId::try_from("org.example.custom").unwrap();
+
+    assert_matches!(resolve(&version, &[], &store), Err(Error::UnsupportedRoomVersion(_)));
+}
+
+#[test]
+fn v1_auth_events_in_depth_order() {
+    let mut store = base_room_v1();
+    // Alice restricts the room name, while Bob tries to give himself more power.
+    store.add(
+        "power2",
+        ALICE,
+        "m.room.power_levels",
+        "",
+        json!({ "users": { ALICE: 100, BOB: 50 }, "events": { "m.room.name": 100 } }),
+        &["create", "alice", "power"],
+    );
+    store.add(
+        "bob_power",
+        BOB,
+        "m.room.power_levels",
+        "",
+        json!({ "users": { ALICE: 100, BOB: 100 } }),
+        &["create", "bob", "power"],
+    );
+
+    let state_sets = [state_after(&store, &["bob_power"]), state_after(&store, &["power2"])];
+    let resolved = resolve(&RoomVersionId::Version1, &state_sets, &store).unwrap();
+
+    assert_eq!(power_levels(&resolved), Some(&id("power2")));
+}
+
+#[test]
+fn v1_normal_events_by_depth() {
+    let mut store = base_room_v1();
+    store.add(
+        "bob_topic",
+        BOB,
+        "m.room.topic",
+        "",
+        json!({ "topic": "Bob's" }),
+        &["create", "power", "bob"],
+    );
+    store.add(
+        "carol_topic",
+        CAROL,
+        "m.room.topic",
+        "",
+        json!({ "topic": "Carol's" }),
+        &["create", "power", "carol"],
+    );
+
+    // Carol's topic is newer, but she doesn't have the power level to set it.
+    let state_sets = [state_after(&store, &["bob_topic"]), state_after(&store, &["carol_topic"])];
+    let resolved = resolve(&RoomVersionId::Version1, &state_sets, &store).unwrap();
+    assert_eq!(topic(&resolved), Some(&id("bob_topic")));
+
+    store.add(
+        "alice_topic",
+        ALICE,
+        "m.room.topic",
+        "",
+        json!({ "topic": "Alice's" }),
+        &["create", "power", "alice"],
+    );
+
+    let state_sets = [state_after(&store, &["bob_topic"]), state_after(&store, &["alice_topic"])];
+    let resolved = resolve(&RoomVersionId::Version1, &state_sets, &store).unwrap();
+    assert_eq!(topic(&resolved), Some(&id("alice_topic")));
+}
+
+#[test]
+fn v1_missing_state_is_not_conflicted() {
+    let mut v1_store = base_room_v1();
+    let mut v2_store = base_room();
+
+    for store in &mut [&mut v1_store, &mut v2_store] {
+        store.add(
+            "carol_topic",
+            CAROL,
+            "m.room.topic",
+            "",
+            json!({ "topic": "Carol's" }),
+            &["create", "power", "carol"],
+        );
+    }
+
+    // Only state resolution version 2 checks Carol's topic, since it is missing from one state.
+    let state_sets = [state_after(&v1_store, &["carol_topic"]), state_after(&v1_store, &[])];
+    let resolved = resolve(&RoomVersionId::Version1, &state_sets, &v1_store).unwrap();
+    assert_eq!(topic(&resolved), Some(&id("carol_topic")));
+
+    let state_sets = [state_after(&v2_store, &["carol_topic"]), state_after(&v2_store, &[])];
+    let resolved = resolve(&RoomVersionId::Version2, &state_sets, &v2_store).unwrap();
+    assert_eq!(topic(&resolved), None);
+}
+
+#[test]
+fn v1_with_later_pdu_format() {
+    let mut store = base_room();
+    store.add(
+        "topic1",
+        BOB,
+        "m.room.topic",
+        "",
+        json!({ "topic": "1" }),
+        &["create", "power", "bob"],
+    );
+    store.add(
+        "topic2",
+        BOB,
+        "m.room.topic",
+        "",
+        json!({ "topic": "2" }),
+        &["create", "power", "bob"],
+    );
+
+    let state_sets = [state_after(&store, &["topic1"]), state_after(&store, &["topic2"])];
+    assert_matches!(
+        resolve(&RoomVersionId::Version1, &state_sets, &store),
+        Err(Error::InvalidPdu(_))
+    );
+}
@@ -36,6 +36,9 @@ pub use ruma_events as events;
 #[cfg(feature = "ruma-signatures")]
 #[doc(inline)]
 pub use ruma_signatures as signatures;
+#[cfg(feature = "ruma-state-res")]
+#[doc(inline)]
+pub use ruma_state_res as state_res;
 
 /// Rust types for various Matrix APIs requests and responses and abstractions for them.
 #[cfg(feature = "ruma-api")]